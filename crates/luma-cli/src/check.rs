@@ -1,12 +1,36 @@
 //! `check` subcommand handler
 
 use crate::utils::read_source;
-use luma_core::pipeline::Pipeline;
+use clap::ValueEnum;
+use luma_core::diagnostics::{
+    AppliedFixes, Diagnostic, FixIt, LineIndex, apply_fixits, sort_diagnostics,
+};
+use luma_core::pipeline::{Pipeline, PipelineError};
+use std::fs;
 use std::process;
 
-/// Typecheck a Luma script without executing it
-pub fn handle_check(file: &str) {
-    let source = match read_source(file) {
+/// How `check` prints diagnostics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CheckFormat {
+    /// Messages with source snippets, for people
+    Human,
+    /// One `file:line:col: severity[code]: message` line per diagnostic
+    #[value(alias = "gcc")]
+    Short,
+    /// A JSON array of diagnostics
+    Json,
+}
+
+/// Check a Luma script without executing it, optionally applying fixes first
+pub fn handle_check(
+    file: &str,
+    fix: bool,
+    parse_only: bool,
+    format: CheckFormat,
+    strict_types: bool,
+    color: bool,
+) {
+    let mut source = match read_source(file) {
         Ok(content) => content,
         Err(err) => {
             eprintln!("Error reading file '{file}': {err}");
@@ -14,19 +38,136 @@ pub fn handle_check(file: &str) {
         }
     };
 
-    let pipeline = Pipeline::new(source.clone(), file.to_string());
-
-    match pipeline.parse() {
-        Ok(ast) => match pipeline.typecheck(&ast) {
-            Ok(()) => println!("Typecheck: OK"),
-            Err(e) => {
-                eprintln!("{}", e.format_with_source(&source));
+    if fix {
+        if file == "-" {
+            eprintln!("Error: --fix needs a file to write back to, not stdin");
+            process::exit(1);
+        }
+        let fixes = fix_source(&source, file);
+        let index = LineIndex::new(&source);
+        for skipped in &fixes.skipped {
+            let ((line, col), _) = skipped.line_col_range(&index);
+            eprintln!(
+                "warning: skipped overlapping fix at {file}:{line}:{col}: {}",
+                skipped.label()
+            );
+        }
+        if fixes.applied > 0 {
+            if let Err(err) = fs::write(file, &fixes.source) {
+                eprintln!("Error writing file '{file}': {err}");
                 process::exit(1);
             }
-        },
-        Err(e) => {
-            eprintln!("{}", e.format_with_source(&source));
-            process::exit(1);
+            source = fixes.source;
         }
+        let plural = if fixes.applied == 1 { "" } else { "es" };
+        // Keep stdout clean for machine-readable reports
+        let message = format!("Applied {} fix{plural}", fixes.applied);
+        if format != CheckFormat::Human {
+            eprintln!("{message}");
+        } else {
+            println!("{message}");
+        }
+    }
+
+    let report = check_source(&source, file, parse_only, format, strict_types, color);
+    if report.ok || format != CheckFormat::Human {
+        // A clean short report is empty and prints nothing, like a compiler
+        if !report.output.is_empty() {
+            println!("{}", report.output);
+        }
+    } else {
+        eprintln!("{}", report.output);
+    }
+    if !report.ok {
+        process::exit(1);
     }
 }
+
+/// Result of checking a source: whether it passed, and what to print
+pub struct CheckReport {
+    pub ok: bool,
+    pub output: String,
+}
+
+/// Parse and, unless `parse_only`, typecheck `source`, rejecting gradual
+/// typing fallbacks when `strict_types` is set. Type warnings are reported
+/// alongside errors but do not fail the check. The `Short` and `Json` formats
+/// print only the diagnostics, one per line or as a JSON array, and ignore
+/// `color`.
+pub fn check_source(
+    source: &str,
+    file: &str,
+    parse_only: bool,
+    format: CheckFormat,
+    strict_types: bool,
+    color: bool,
+) -> CheckReport {
+    let pipeline =
+        Pipeline::new(source.to_string(), file.to_string()).with_strict_types(strict_types);
+    let mut warnings: Vec<Diagnostic> = Vec::new();
+    let result = pipeline.parse().and_then(|ast| {
+        if parse_only {
+            return Ok(());
+        }
+        let (result, type_warnings) = pipeline.typecheck_with_warnings(&ast);
+        warnings = type_warnings
+            .iter()
+            .map(|w| w.to_diagnostic(file))
+            .collect();
+        result
+    });
+
+    let ok = result.is_ok();
+    let output = match (result, format) {
+        (Ok(()), CheckFormat::Human) if parse_only => "Parse: OK".to_string(),
+        (Ok(()), CheckFormat::Human) => {
+            let mut lines: Vec<String> = warnings
+                .iter()
+                .map(|w| w.format_with_color(source, color))
+                .collect();
+            lines.push("Typecheck: OK".to_string());
+            lines.join("\n")
+        }
+        (Err(e), CheckFormat::Human) => {
+            let mut lines = vec![e.format_with_color(source, color)];
+            lines.extend(warnings.iter().map(|w| w.format_with_color(source, color)));
+            lines.join("\n")
+        }
+        (result, format) => {
+            let mut diagnostics: Vec<Diagnostic> = match result {
+                Ok(()) => Vec::new(),
+                Err(PipelineError::Parse(diagnostics)) => diagnostics,
+                Err(PipelineError::Typecheck(errors)) => {
+                    errors.iter().map(|e| e.to_diagnostic(file)).collect()
+                }
+                Err(PipelineError::Runtime(_)) => unreachable!("checking never executes"),
+            };
+            diagnostics.extend(warnings);
+            if format == CheckFormat::Short {
+                sort_diagnostics(&mut diagnostics);
+                let lines: Vec<String> =
+                    diagnostics.iter().map(|d| d.format_short(source)).collect();
+                lines.join("\n")
+            } else {
+                let values: Vec<_> = diagnostics.iter().map(|d| d.to_json(source)).collect();
+                serde_json::Value::Array(values).to_string()
+            }
+        }
+    };
+    CheckReport { ok, output }
+}
+
+/// Apply the fix-its reported for `source`. Diagnostics list alternative
+/// fixes best-first, so only the first fix of each diagnostic is used, and
+/// only if it is machine-applicable; speculative fixes are left to the user.
+pub fn fix_source(source: &str, file: &str) -> AppliedFixes {
+    let analysis = Pipeline::new(source.to_string(), file.to_string()).analyze();
+    let fixes: Vec<FixIt> = analysis
+        .diagnostics
+        .iter()
+        .filter_map(|d| d.fixits.first())
+        .filter(|fix| fix.is_machine_applicable())
+        .cloned()
+        .collect();
+    apply_fixits(source, &fixes)
+}
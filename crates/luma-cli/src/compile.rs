@@ -7,8 +7,9 @@ use luma_core::typecheck;
 use std::fs;
 use std::process;
 
-/// Compile a Luma script to bytecode
-pub fn handle_compile(file: &str, output: Option<&str>) {
+/// Compile a Luma script to bytecode. Type errors stop compilation unless
+/// `force` is set, in which case they are reported and bytecode is written anyway.
+pub fn handle_compile(file: &str, output: Option<&str>, force: bool, color: bool) {
     let source = match read_source(file) {
         Ok(content) => content,
         Err(err) => {
@@ -20,14 +21,17 @@ pub fn handle_compile(file: &str, output: Option<&str>) {
     let ast = match parser::parse(&source, file) {
         Ok(ast) => ast,
         Err(errors) => {
-            format_parse_errors(&errors, &source);
+            format_parse_errors(&errors, &source, color);
             process::exit(1);
         }
     };
 
     if let Err(errs) = typecheck::typecheck_program(&ast) {
-        format_typecheck_errors(&errs, file, &source);
-        process::exit(1);
+        format_typecheck_errors(&errs, file, &source, color);
+        if !force {
+            eprintln!("No bytecode written; pass --force to compile anyway");
+            process::exit(1);
+        }
     }
 
     let chunk = bytecode::compile::compile_program(&ast);
@@ -6,7 +6,7 @@ use luma_core::parser;
 use std::process;
 
 /// Print the parsed AST for debugging
-pub fn handle_ast(file: &str) {
+pub fn handle_ast(file: &str, color: bool) {
     let source = match read_source(file) {
         Ok(content) => content,
         Err(err) => {
@@ -18,7 +18,7 @@ pub fn handle_ast(file: &str) {
     let ast = match parser::parse(&source, file) {
         Ok(ast) => ast,
         Err(errors) => {
-            format_parse_errors(&errors, &source);
+            format_parse_errors(&errors, &source, color);
             process::exit(1);
         }
     };
@@ -26,8 +26,10 @@ pub fn handle_ast(file: &str) {
     println!("{ast:#?}");
 }
 
-/// Print the compiled bytecode for debugging
-pub fn handle_bytecode(file: &str) {
+/// Print the compiled bytecode for debugging, optionally as a listing
+/// annotated with the source lines each instruction came from, or just the
+/// captures of each closure
+pub fn handle_bytecode(file: &str, annotate_source: bool, dump_upvalues: bool, color: bool) {
     let source = match read_source(file) {
         Ok(content) => content,
         Err(err) => {
@@ -39,11 +41,20 @@ pub fn handle_bytecode(file: &str) {
     let ast = match parser::parse(&source, file) {
         Ok(ast) => ast,
         Err(errors) => {
-            format_parse_errors(&errors, &source);
+            format_parse_errors(&errors, &source, color);
             process::exit(1);
         }
     };
 
     let chunk = bytecode::compile::compile_program(&ast);
-    println!("{chunk:#?}");
+    if dump_upvalues {
+        print!("{}", bytecode::disasm::dump_upvalues(&chunk));
+    } else if annotate_source {
+        print!(
+            "{}",
+            bytecode::disasm::disassemble_with_source(&chunk, &source)
+        );
+    } else {
+        println!("{chunk:#?}");
+    }
 }
@@ -0,0 +1,161 @@
+//! `graph` subcommand handler
+
+use luma_core::parser;
+use luma_core::vm::modules::resolve_import_path;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::process;
+
+/// Import dependencies reachable from an entry module
+#[derive(Debug, Default)]
+pub struct ModuleGraph {
+    /// Resolved module paths in discovery order; the entry comes first
+    pub modules: Vec<String>,
+    /// Imports as (importer, imported) indices into `modules`
+    pub edges: Vec<(usize, usize)>,
+    /// Problems that left part of the graph undiscovered
+    pub errors: Vec<String>,
+}
+
+impl ModuleGraph {
+    /// Follow constant `import(...)` paths from `entry`, resolving them the way
+    /// the VM does. Only `.luma` modules are parsed for further imports.
+    pub fn discover(entry: &str) -> Result<Self, String> {
+        let entry = Path::new(entry)
+            .canonicalize()
+            .map_err(|e| format!("Error reading file '{entry}': {e}"))?
+            .to_string_lossy()
+            .into_owned();
+
+        let mut graph = ModuleGraph::default();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        index.insert(entry.clone(), 0);
+        graph.modules.push(entry);
+
+        let mut next = 0;
+        while next < graph.modules.len() {
+            let file = graph.modules[next].clone();
+            let from = next;
+            next += 1;
+            if Path::new(&file)
+                .extension()
+                .is_some_and(|ext| ext != "luma")
+            {
+                continue;
+            }
+
+            let source = match std::fs::read_to_string(&file) {
+                Ok(source) => source,
+                Err(e) => {
+                    graph.errors.push(format!("{file}: {e}"));
+                    continue;
+                }
+            };
+            let program = match parser::parse(&source, &file) {
+                Ok(program) => program,
+                Err(errors) => {
+                    let plural = if errors.len() == 1 { "" } else { "s" };
+                    graph
+                        .errors
+                        .push(format!("{file}: {} parse error{plural}", errors.len()));
+                    continue;
+                }
+            };
+
+            for path in program.imports() {
+                let resolved = match resolve_import_path(&path, Some(&file)) {
+                    Ok(resolved) => resolved,
+                    Err(e) => {
+                        graph.errors.push(format!("{file}: {}", e.message));
+                        continue;
+                    }
+                };
+                let to = *index.entry(resolved.clone()).or_insert_with(|| {
+                    graph.modules.push(resolved);
+                    graph.modules.len() - 1
+                });
+                if !graph.edges.contains(&(from, to)) {
+                    graph.edges.push((from, to));
+                }
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Whether the import `from -> to` is part of a cycle, i.e. `to` leads back to `from`
+    pub fn in_cycle(&self, (from, to): (usize, usize)) -> bool {
+        let mut seen = HashSet::new();
+        let mut stack = vec![to];
+        while let Some(module) = stack.pop() {
+            if module == from {
+                return true;
+            }
+            if seen.insert(module) {
+                stack.extend(
+                    self.edges
+                        .iter()
+                        .filter(|(a, _)| *a == module)
+                        .map(|(_, b)| *b),
+                );
+            }
+        }
+        false
+    }
+
+    /// Render as a Graphviz digraph. Nodes are labelled relative to the entry
+    /// module's directory; imports that form a cycle are drawn in red.
+    pub fn to_dot(&self) -> String {
+        let base = self
+            .modules
+            .first()
+            .and_then(|entry| Path::new(entry).parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let label = |module: &str| {
+            Path::new(module)
+                .strip_prefix(&base)
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_else(|_| module.to_string())
+        };
+
+        let mut out = String::from("digraph imports {\n");
+        for module in &self.modules {
+            let _ = writeln!(out, "  {:?} [label={:?}];", module, label(module));
+        }
+        for &(from, to) in &self.edges {
+            let style = if self.in_cycle((from, to)) {
+                " [color=red, label=\"cycle\"]"
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                out,
+                "  {:?} -> {:?}{style};",
+                self.modules[from], self.modules[to]
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Print the import graph of `file` in DOT format. Modules that could not be
+/// read, parsed or resolved are reported on stderr, and the command fails.
+pub fn handle_graph(file: &str) {
+    let graph = match ModuleGraph::discover(file) {
+        Ok(graph) => graph,
+        Err(err) => {
+            eprintln!("{err}");
+            process::exit(1);
+        }
+    };
+
+    print!("{}", graph.to_dot());
+    for err in &graph.errors {
+        eprintln!("Error: {err}");
+    }
+    if !graph.errors.is_empty() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,115 @@
+//! `init` subcommand handler
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Files of a new project, as paths relative to its directory and contents
+const TEMPLATE: &[(&str, &str)] = &[
+    (
+        "main.luma",
+        r#"-- Entry point of the project; run it with `luma run main.luma`
+let greet = import("./lib/greet.luma")
+
+print(greet.hello("world"))
+"#,
+    ),
+    (
+        "lib/greet.luma",
+        r#"-- An example module: importers see only the bindings it exports
+let hello = fn(name: String): String do
+  return "Hello, " + name + "!"
+end
+
+export hello
+"#,
+    ),
+];
+
+/// Lines the project's `.gitignore` must contain
+const GITIGNORE_ENTRIES: &[&str] = &["*.lumac"];
+
+/// Write the starter project into `dir`, creating it if needed, and return
+/// what was done to each path. Nothing is written if any template file already
+/// exists, unless `force` is set. An existing `.gitignore` is never replaced:
+/// only the entries it lacks are appended to it.
+pub fn init_project(dir: &Path, force: bool) -> Result<Vec<(&'static str, PathBuf)>, String> {
+    let files: Vec<(PathBuf, &str)> = TEMPLATE
+        .iter()
+        .map(|(rel, content)| (dir.join(rel), *content))
+        .collect();
+
+    if !force {
+        let existing: Vec<String> = files
+            .iter()
+            .filter(|(path, _)| path.exists())
+            .map(|(path, _)| path.display().to_string())
+            .collect();
+        if !existing.is_empty() {
+            return Err(format!(
+                "Refusing to overwrite existing files: {}; pass --force to overwrite them",
+                existing.join(", ")
+            ));
+        }
+    }
+
+    let mut written = Vec::new();
+    for (path, content) in files {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Error creating '{}': {e}", parent.display()))?;
+        }
+        fs::write(&path, content)
+            .map_err(|e| format!("Error writing '{}': {e}", path.display()))?;
+        written.push(("Created", path));
+    }
+    if let Some(action) = update_gitignore(&dir.join(".gitignore"))? {
+        written.push(action);
+    }
+    Ok(written)
+}
+
+/// Create the `.gitignore` at `path`, or append the entries an existing one is
+/// missing. Returns `None` if it already had all of them.
+fn update_gitignore(path: &Path) -> Result<Option<(&'static str, PathBuf)>, String> {
+    let error = |e: std::io::Error| format!("Error writing '{}': {e}", path.display());
+    if !path.exists() {
+        let content: String = GITIGNORE_ENTRIES.iter().map(|e| format!("{e}\n")).collect();
+        fs::write(path, content).map_err(error)?;
+        return Ok(Some(("Created", path.to_path_buf())));
+    }
+
+    let mut content = fs::read_to_string(path).map_err(error)?;
+    let missing: Vec<&str> = GITIGNORE_ENTRIES
+        .iter()
+        .copied()
+        .filter(|entry| !content.lines().any(|line| line.trim() == *entry))
+        .collect();
+    if missing.is_empty() {
+        return Ok(None);
+    }
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    for entry in missing {
+        content.push_str(entry);
+        content.push('\n');
+    }
+    fs::write(path, content).map_err(error)?;
+    Ok(Some(("Updated", path.to_path_buf())))
+}
+
+/// Scaffold a new project in `dir`
+pub fn handle_init(dir: &str, force: bool) {
+    match init_project(Path::new(dir), force) {
+        Ok(paths) => {
+            for (action, path) in paths {
+                println!("{action} {}", path.display());
+            }
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    }
+}
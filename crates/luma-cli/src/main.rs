@@ -3,6 +3,8 @@ use clap::{CommandFactory, Parser, Subcommand};
 mod check;
 mod compile;
 mod debug;
+mod graph;
+mod init;
 mod lsp;
 mod repl;
 mod run;
@@ -12,13 +14,16 @@ mod utils;
 #[cfg(test)]
 mod tests;
 
-use check::handle_check;
+use check::{CheckFormat, handle_check};
 use compile::handle_compile;
 use debug::{handle_ast, handle_bytecode};
+use graph::handle_graph;
+use init::handle_init;
 use lsp::handle_lsp;
 use repl::handle_repl;
 use run::handle_run;
 use upgrade::handle_upgrade;
+use utils::ColorMode;
 
 /// Get the version string including git revision
 fn version() -> &'static str {
@@ -38,6 +43,9 @@ struct Cli {
     command: Option<Commands>,
     /// The file to run (default if no subcommand)
     file: Option<String>,
+    /// When to color diagnostics; `auto` colors a terminal unless `NO_COLOR` is set
+    #[arg(long, value_enum, global = true, default_value_t = ColorMode::Auto)]
+    color: ColorMode,
 }
 
 #[derive(Subcommand)]
@@ -46,15 +54,43 @@ enum Commands {
     Run {
         /// The file to execute
         file: String,
+        /// Seed for the `random` module, making runs reproducible
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Print how long parsing, typechecking, compilation and execution took
+        #[arg(long)]
+        report_timings: bool,
+        /// Make `print` lay out large lists and tables over indented lines
+        #[arg(long)]
+        pretty: bool,
     },
     /// Start a REPL session with Luma
-    Repl,
+    Repl {
+        /// Run a file's top-level statements before the first prompt
+        #[arg(long)]
+        load: Option<String>,
+    },
     /// Start the Language Server Protocol server
     Lsp,
     /// Typecheck a Luma script without executing it
     Check {
         /// The file to typecheck
         file: String,
+        /// Apply suggested fixes to the file before checking it
+        #[arg(long)]
+        fix: bool,
+        /// Stop after parsing and report only syntax errors
+        #[arg(long)]
+        parse_only: bool,
+        /// How to print diagnostics: with source snippets, one line each, or as JSON
+        #[arg(long, value_enum, default_value_t = CheckFormat::Human)]
+        format: CheckFormat,
+        /// Print diagnostics as a JSON array (same as `--format=json`)
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+        /// Report `Any` parameters and values of unknown type as errors
+        #[arg(long)]
+        strict_types: bool,
     },
     /// Compile a Luma script to a .lumac bytecode file
     Compile {
@@ -63,6 +99,23 @@ enum Commands {
         /// Output file (defaults to input.lumac)
         #[arg(short, long)]
         output: Option<String>,
+        /// Write bytecode even if the script has type errors
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print a module's import dependency graph in Graphviz DOT format
+    Graph {
+        /// The entry module
+        file: String,
+    },
+    /// Create a starter Luma project
+    Init {
+        /// Directory to create the project in
+        #[arg(default_value = ".")]
+        dir: String,
+        /// Overwrite files that already exist
+        #[arg(long)]
+        force: bool,
     },
     /// Upgrade to latest version of Luma
     Upgrade {
@@ -81,36 +134,70 @@ enum Commands {
     Bytecode {
         /// The file to compile
         file: String,
+        /// Print a listing with each source line above its instructions
+        #[arg(long)]
+        annotate_source: bool,
+        /// List what each closure captures instead of the bytecode
+        #[arg(long, conflicts_with = "annotate_source")]
+        dump_upvalues: bool,
     },
 }
 
 fn main() {
     let cli = Cli::parse();
+    let color = cli.color.enabled();
 
     match &cli.command {
-        Some(Commands::Run { file }) => {
-            handle_run(file);
+        Some(Commands::Run {
+            file,
+            seed,
+            report_timings,
+            pretty,
+        }) => {
+            handle_run(file, *seed, *report_timings, *pretty, color);
         }
-        Some(Commands::Repl) => {
-            handle_repl();
+        Some(Commands::Repl { load }) => {
+            handle_repl(load.as_deref());
         }
         Some(Commands::Lsp) => {
             handle_lsp();
         }
-        Some(Commands::Check { file }) => {
-            handle_check(file);
+        Some(Commands::Check {
+            file,
+            fix,
+            parse_only,
+            format,
+            json,
+            strict_types,
+        }) => {
+            let format = if *json { CheckFormat::Json } else { *format };
+            handle_check(file, *fix, *parse_only, format, *strict_types, color);
+        }
+        Some(Commands::Compile {
+            file,
+            output,
+            force,
+        }) => {
+            handle_compile(file, output.as_deref(), *force, color);
+        }
+        Some(Commands::Graph { file }) => {
+            handle_graph(file);
         }
-        Some(Commands::Compile { file, output }) => {
-            handle_compile(file, output.as_deref());
+        Some(Commands::Init { dir, force }) => {
+            handle_init(dir, *force);
         }
         Some(Commands::Upgrade { version }) => {
             handle_upgrade(version.as_deref());
         }
         Some(Commands::Ast { file }) => {
-            handle_ast(file);
+            handle_ast(file, color);
         }
-        Some(Commands::Bytecode { file }) => {
-            handle_bytecode(file);
+        Some(Commands::Bytecode {
+            file,
+            annotate_source,
+            dump_upvalues,
+        }) => {
+            handle_bytecode(file, *annotate_source, *dump_upvalues, color);
         }
         None => {
             // Default: run the file if provided, otherwise print help
@@ -123,7 +210,7 @@ fn main() {
                     std::process::exit(0);
                 }
             };
-            handle_run(file);
+            handle_run(file, None, false, false, color);
         }
     }
 }
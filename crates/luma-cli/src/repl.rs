@@ -3,10 +3,12 @@
 use luma_core::bytecode;
 use luma_core::parser;
 use luma_core::vm;
+use std::fs;
 use std::io::{self, BufRead, Write};
 
-/// Run the interactive REPL session
-pub fn handle_repl() {
+/// Run the interactive REPL session, first running `load`'s top-level
+/// statements so its definitions are available at the prompt
+pub fn handle_repl(load: Option<&str>) {
     println!("Luma REPL v{}", env!("CARGO_PKG_VERSION"));
     println!("Type expressions and press Enter. Use Ctrl+D (Unix) or Ctrl+Z (Windows) to exit.");
     println!();
@@ -25,6 +27,14 @@ pub fn handle_repl() {
         }
     };
 
+    // A failed load still starts the session with whatever ran before the error
+    if let Some(path) = load {
+        match load_file(&mut vm, path) {
+            Ok(()) => println!("Loaded {path}"),
+            Err(message) => eprintln!("{message}"),
+        }
+    }
+
     let stdin = io::stdin();
     let mut lines = stdin.lock().lines();
 
@@ -56,36 +66,54 @@ pub fn handle_repl() {
             continue;
         }
 
-        // Try to parse the input
-        let ast = match parser::parse(&input, "<repl>") {
-            Ok(ast) => ast,
-            Err(errors) => {
-                // Report parse errors
-                for error in &errors {
-                    eprintln!("{}", error.format(&input));
-                }
-                continue;
-            }
-        };
+        match eval_input(&mut vm, &input) {
+            Ok(val) => println!("{val}"),
+            Err(message) => eprintln!("{message}"),
+        }
+    }
+}
 
-        // Skip typechecking in REPL mode since each statement is evaluated independently
-        // The typechecker doesn't have visibility into variables defined in previous REPL statements
-        // Runtime errors will still be caught during execution
+/// Evaluate one REPL input against the session's VM.
+///
+/// Only the new input is compiled and run; earlier definitions live on as
+/// globals in the VM, so they stay callable without being re-executed.
+pub fn eval_input(vm: &mut vm::VM, input: &str) -> Result<vm::value::Value, String> {
+    eval_source(vm, input, "<repl>")
+}
 
-        // Compile the AST using REPL mode (variables are globals)
-        let chunk = bytecode::compile::compile_repl_program(&ast);
+/// Run a file's top-level statements in the session's VM, as `--load` does.
+///
+/// Statements run in order, so definitions made before a runtime error
+/// stay available.
+pub fn load_file(vm: &mut vm::VM, path: &str) -> Result<(), String> {
+    let source =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read file '{path}': {e}"))?;
+    // Imports and error locations resolve against the loaded file
+    let session_file = vm.current_file.replace(path.to_string());
+    let result = eval_source(vm, &source, path).map(|_| ());
+    vm.current_file = session_file;
+    result
+}
 
-        // Set source for error reporting
-        vm.set_source(input.clone());
+fn eval_source(vm: &mut vm::VM, input: &str, file: &str) -> Result<vm::value::Value, String> {
+    let ast = parser::parse(input, file).map_err(|errors| {
+        errors
+            .iter()
+            .map(|error| error.format(input))
+            .collect::<Vec<_>>()
+            .join("\n")
+    })?;
 
-        // Execute in the existing VM context
-        match vm.eval(chunk) {
-            Ok(val) => {
-                println!("{val}");
-            }
-            Err(e) => {
-                eprintln!("{}", e.format(Some(&input)));
-            }
-        }
-    }
+    // Skip typechecking in REPL mode since each statement is evaluated independently
+    // The typechecker doesn't have visibility into variables defined in previous REPL statements
+    // Runtime errors will still be caught during execution
+
+    // Compile the AST using REPL mode (variables are globals)
+    let chunk = bytecode::compile::compile_repl_program(&ast);
+
+    // Set source for error reporting
+    vm.set_source(input.to_string());
+
+    // Execute in the existing VM context
+    vm.eval(chunk).map_err(|e| e.format(Some(input)))
 }
@@ -3,8 +3,10 @@
 use crate::utils::read_source;
 use std::process;
 
-/// Execute a Luma script file
-pub fn handle_run(file: &str) {
+/// Execute a Luma script file, optionally seeding the `random` module first.
+/// With `report_timings`, a per-phase breakdown is printed to stderr afterwards;
+/// with `pretty`, `print` lays out large lists and tables over several lines.
+pub fn handle_run(file: &str, seed: Option<u64>, report_timings: bool, pretty: bool, color: bool) {
     let source = match read_source(file) {
         Ok(content) => content,
         Err(err) => {
@@ -13,10 +15,23 @@ pub fn handle_run(file: &str) {
         }
     };
 
-    match luma_stdlib::run_program(source.clone(), file.to_string()) {
+    let mut stdlib = luma_stdlib::StdlibBuilder::new().with_pretty_print(pretty);
+    if let Some(seed) = seed {
+        stdlib = stdlib.with_seed(seed);
+    }
+
+    let result = if report_timings {
+        let (result, timings) = stdlib.run_program_timed(source.clone(), file.to_string());
+        eprint!("{}", timings.report());
+        result
+    } else {
+        stdlib.run_program(source.clone(), file.to_string())
+    };
+
+    match result {
         Ok(_val) => {}
         Err(e) => {
-            eprintln!("{}", e.format_with_source(&source));
+            eprintln!("{}", e.format_with_color(&source, color));
             process::exit(1);
         }
     }
@@ -1,40 +1,410 @@
 //! Tests for CLI utilities
 
-#[cfg(test)]
-mod tests {
-    use super::super::utils::*;
-    use std::fs;
-
-    #[test]
-    fn test_read_source_from_file() {
-        let temp_path = std::env::temp_dir().join("luma_cli_test_read.txt");
-        let content = "let x = 42;";
-        fs::write(&temp_path, content).unwrap();
-
-        let result = read_source(temp_path.to_str().unwrap());
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), content);
-
-        // Cleanup
-        let _ = fs::remove_file(temp_path);
+use crate::utils::*;
+use std::fs;
+
+#[test]
+fn test_read_source_from_file() {
+    let temp_path = std::env::temp_dir().join("luma_cli_test_read.txt");
+    let content = "let x = 42;";
+    fs::write(&temp_path, content).unwrap();
+
+    let result = read_source(temp_path.to_str().unwrap());
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), content);
+
+    // Cleanup
+    let _ = fs::remove_file(temp_path);
+}
+
+#[test]
+fn test_read_source_nonexistent_file() {
+    let result = read_source("/nonexistent/file/path.luma");
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_read_source_empty_file() {
+    let temp_path = std::env::temp_dir().join("luma_cli_test_empty.txt");
+    fs::write(&temp_path, "").unwrap();
+
+    let result = read_source(temp_path.to_str().unwrap());
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), "");
+
+    // Cleanup
+    let _ = fs::remove_file(temp_path);
+}
+
+#[test]
+fn test_timed_run_reports_every_phase() {
+    let (result, timings) =
+        luma_stdlib::run_program_timed("let x = 1 + 2\nx".to_string(), "test.luma".to_string());
+    assert!(result.is_ok());
+
+    let report = timings.report();
+    for label in ["parse", "typecheck", "compile", "execute"] {
+        let line = report
+            .lines()
+            .find(|l| l.trim_start().starts_with(label))
+            .unwrap_or_else(|| panic!("missing {label} in {report}"));
+        let ms: f64 = line
+            .trim_start_matches(|c: char| !c.is_ascii_digit())
+            .trim_end_matches("ms")
+            .parse()
+            .unwrap();
+        assert!(ms >= 0.0);
     }
+}
+
+#[test]
+fn test_render_diagnostics_in_source_order() {
+    use luma_core::ast::Span;
+    use luma_core::diagnostics::{Diagnostic, DiagnosticKind};
+
+    let source = "let a = 1\nlet b = 2\nlet c = 3";
+    let diag = |line: &str| {
+        let start = source.find(line).unwrap();
+        Diagnostic::error(
+            DiagnosticKind::Type,
+            format!("problem with {line}"),
+            Span::new(start, start + line.len()),
+            "test.luma".to_string(),
+        )
+    };
+    let out_of_order = [diag("let c"), diag("let a"), diag("let b")];
 
-    #[test]
-    fn test_read_source_nonexistent_file() {
-        let result = read_source("/nonexistent/file/path.luma");
-        assert!(result.is_err());
+    let rendered = render_diagnostics(&out_of_order, source, false);
+    let positions: Vec<usize> = ["let a", "let b", "let c"]
+        .iter()
+        .map(|l| rendered.find(&format!("problem with {l}")).unwrap())
+        .collect();
+    assert!(positions.is_sorted(), "{rendered}");
+}
+
+#[test]
+fn test_fix_source_inserts_missing_paren() {
+    use crate::check::fix_source;
+
+    let fixes = fix_source("print(1 + 2", "test.luma");
+    assert_eq!(fixes.applied, 1);
+    assert!(fixes.skipped.is_empty());
+    assert_eq!(fixes.source, "print(1 + 2)");
+    assert!(luma_core::parser::parse(&fixes.source, "test.luma").is_ok());
+}
+
+#[test]
+fn test_fix_source_leaves_speculative_fixes_alone() {
+    use crate::check::fix_source;
+
+    let source = "let count = 1\nlet x = coutn";
+    let fixes = fix_source(source, "test.luma");
+    assert_eq!(fixes.applied, 0);
+    assert_eq!(fixes.source, source);
+}
+
+#[test]
+fn test_check_parse_only_skips_typecheck() {
+    use crate::check::{CheckFormat, check_source};
+
+    let source = "let x: Number = \"not a number\"";
+    let full = check_source(source, "test.luma", false, CheckFormat::Human, false, false);
+    assert!(!full.ok);
+
+    let report = check_source(source, "test.luma", true, CheckFormat::Human, false, false);
+    assert!(report.ok, "{}", report.output);
+    assert_eq!(report.output, "Parse: OK");
+    let report = check_source(source, "test.luma", true, CheckFormat::Json, false, false);
+    assert!(report.ok);
+    assert_eq!(report.output, "[]");
+}
+
+#[test]
+fn test_check_strict_types_rejects_gradual_fallbacks() {
+    use crate::check::{CheckFormat, check_source};
+
+    let source = "let id = fn(x: Any) do x end\nlet n: Number = id(1)";
+    let lenient = check_source(source, "test.luma", false, CheckFormat::Human, false, false);
+    assert!(lenient.ok, "{}", lenient.output);
+
+    let strict = check_source(source, "test.luma", false, CheckFormat::Human, true, false);
+    assert!(!strict.ok);
+    assert!(strict.output.contains("Parameter x"), "{}", strict.output);
+
+    let annotated = "let double = fn(x: Number): Number do x * 2 end\nlet n: Number = double(2)";
+    for strict_types in [false, true] {
+        let report = check_source(
+            annotated,
+            "test.luma",
+            false,
+            CheckFormat::Human,
+            strict_types,
+            false,
+        );
+        assert!(report.ok, "{}", report.output);
     }
+}
+
+#[test]
+fn test_check_short_format_prints_one_line_per_diagnostic() {
+    use crate::check::{CheckFormat, check_source};
+
+    let source = "let a = 1\nlet b = missing\nlet c: Number = \"s\"";
+    let report = check_source(source, "test.luma", false, CheckFormat::Short, false, true);
+    assert!(!report.ok);
+    let lines: Vec<&str> = report.output.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "test.luma:2:9: error[E0002]: Undefined variable: missing",
+            "test.luma:3:1: error[E0001]: Variable c: declared type Number, got String",
+        ],
+        "{}",
+        report.output
+    );
+
+    let clean = check_source(
+        "let a = 1",
+        "test.luma",
+        false,
+        CheckFormat::Short,
+        false,
+        false,
+    );
+    assert!(clean.ok);
+    assert!(clean.output.is_empty());
+}
+
+#[test]
+fn test_check_reports_warnings_without_failing() {
+    use crate::check::{CheckFormat, check_source};
+
+    let source = "while true do\n  break\n  print(\"never\")\nend";
+    let human = check_source(source, "test.luma", false, CheckFormat::Human, false, false);
+    assert!(human.ok);
+    assert!(
+        human
+            .output
+            .starts_with("warning[W0003]: Unreachable code after `break`"),
+        "{}",
+        human.output
+    );
+    assert!(human.output.ends_with("Typecheck: OK"), "{}", human.output);
 
-    #[test]
-    fn test_read_source_empty_file() {
-        let temp_path = std::env::temp_dir().join("luma_cli_test_empty.txt");
-        fs::write(&temp_path, "").unwrap();
+    let short = check_source(source, "test.luma", false, CheckFormat::Short, false, false);
+    assert!(short.ok);
+    assert_eq!(
+        short.output,
+        "test.luma:3:3: warning[W0003]: Unreachable code after `break`"
+    );
 
-        let result = read_source(temp_path.to_str().unwrap());
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), "");
+    // Warnings are reported next to errors, and can be silenced
+    let failing = format!("{source}\nlet c: Number = \"s\"");
+    let short = check_source(
+        &failing,
+        "test.luma",
+        false,
+        CheckFormat::Short,
+        false,
+        false,
+    );
+    assert!(!short.ok);
+    assert_eq!(short.output.lines().count(), 2, "{}", short.output);
+    let ignored = "while true do\n  break\n  print(\"never\") -- luma:ignore W0003\nend";
+    let json = check_source(ignored, "test.luma", false, CheckFormat::Json, false, false);
+    assert_eq!(json.output, "[]");
+}
+
+#[test]
+fn test_check_parse_only_reports_syntax_errors() {
+    use crate::check::{CheckFormat, check_source};
+
+    let source = "let x = (1 +\n";
+    let human = check_source(source, "test.luma", true, CheckFormat::Human, false, false);
+    assert!(!human.ok);
+    assert!(human.output.contains("error:"), "{}", human.output);
+
+    let json = check_source(source, "test.luma", true, CheckFormat::Json, false, false);
+    assert!(!json.ok);
+    let diagnostics: serde_json::Value = serde_json::from_str(&json.output).unwrap();
+    let diagnostics = diagnostics.as_array().unwrap();
+    assert!(!diagnostics.is_empty());
+    assert_eq!(diagnostics[0]["kind"], "parse");
+    assert_eq!(diagnostics[0]["severity"], "error");
+    assert_eq!(diagnostics[0]["file"], "test.luma");
+}
+
+#[test]
+fn test_repl_load_file_defines_globals() {
+    use crate::repl::{eval_input, load_file};
+    use luma_core::vm::value::Value;
+
+    let dir = std::env::temp_dir();
+    let lib = dir.join(format!("luma_repl_load_{}.luma", std::process::id()));
+    std::fs::write(&lib, "let square = fn(x: Number): Number do x * x end\n").unwrap();
+    let broken = dir.join(format!("luma_repl_load_broken_{}.luma", std::process::id()));
+    std::fs::write(&broken, "let before = 1\nmissing()\nlet after = 2\n").unwrap();
+
+    let mut vm = repl_vm();
+    load_file(&mut vm, lib.to_str().unwrap()).unwrap();
+    assert_eq!(eval_input(&mut vm, "square(7)\n"), Ok(Value::Number(49.0)));
+
+    // A failing file keeps the definitions that ran before the error
+    let err = load_file(&mut vm, broken.to_str().unwrap()).unwrap_err();
+    assert!(err.contains("missing"), "{err}");
+    assert!(err.contains("luma_repl_load_broken_"), "{err}");
+    assert_eq!(eval_input(&mut vm, "before\n"), Ok(Value::Number(1.0)));
+    // Names declared after the error exist but were never assigned
+    assert_eq!(eval_input(&mut vm, "after\n"), Ok(Value::Null));
+
+    let missing = load_file(&mut vm, "does/not/exist.luma").unwrap_err();
+    assert!(missing.starts_with("Failed to read file"), "{missing}");
 
-        // Cleanup
-        let _ = fs::remove_file(temp_path);
+    std::fs::remove_file(lib).unwrap();
+    std::fs::remove_file(broken).unwrap();
+}
+
+#[test]
+fn test_graph_lists_imports_and_flags_cycles() {
+    use crate::graph::ModuleGraph;
+
+    let dir = std::env::temp_dir().join(format!("luma_graph_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("lib")).unwrap();
+    std::fs::write(
+        dir.join("main.luma"),
+        "let a = import(\"./lib/a\")\nlet f = fn() do import(\"config.json\") end",
+    )
+    .unwrap();
+    std::fs::write(dir.join("lib/a.luma"), "import(\"b.luma\")").unwrap();
+    std::fs::write(dir.join("lib/b.luma"), "import(\"./a.luma\")").unwrap();
+    std::fs::write(dir.join("config.json"), "{}").unwrap();
+
+    let graph = ModuleGraph::discover(dir.join("main.luma").to_str().unwrap()).unwrap();
+    assert!(graph.errors.is_empty(), "{:?}", graph.errors);
+    let root = dir.canonicalize().unwrap();
+    let node = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+    let dot = graph.to_dot();
+
+    assert!(dot.starts_with("digraph imports {\n"), "{dot}");
+    for rel in ["main.luma", "lib/a.luma", "lib/b.luma", "config.json"] {
+        assert!(
+            dot.contains(&format!("{:?} [label={rel:?}];", node(rel))),
+            "{dot}"
+        );
     }
+    let edge = |from: &str, to: &str| format!("{:?} -> {:?}", node(from), node(to));
+    assert!(
+        dot.contains(&format!("{};", edge("main.luma", "lib/a.luma"))),
+        "{dot}"
+    );
+    assert!(
+        dot.contains(&format!("{};", edge("main.luma", "config.json"))),
+        "{dot}"
+    );
+    for (from, to) in [("lib/a.luma", "lib/b.luma"), ("lib/b.luma", "lib/a.luma")] {
+        assert!(
+            dot.contains(&format!("{} [color=red, label=\"cycle\"];", edge(from, to))),
+            "{dot}"
+        );
+    }
+    assert_eq!(graph.edges.len(), 4);
+
+    std::fs::write(dir.join("main.luma"), "import(\"./missing\")").unwrap();
+    let graph = ModuleGraph::discover(dir.join("main.luma").to_str().unwrap()).unwrap();
+    assert_eq!(graph.modules.len(), 1);
+    assert_eq!(graph.errors.len(), 1);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+fn repl_vm() -> luma_core::vm::VM {
+    let chunk = luma_core::bytecode::ir::Chunk::new_empty("<init>".to_string());
+    let vm = luma_core::vm::VM::new_with_file(chunk, Some("<repl>".to_string()));
+    luma_stdlib::init_vm(vm).unwrap()
+}
+
+#[test]
+fn test_repl_definitions_persist_without_rerunning() {
+    use crate::repl::eval_input;
+    use luma_core::vm::value::Value;
+
+    let mut vm = repl_vm();
+    eval_input(&mut vm, "var runs = 0\n").unwrap();
+    eval_input(
+        &mut vm,
+        "runs = runs + 1\nlet double = fn(x: Number): Number do x * 2 end\n",
+    )
+    .unwrap();
+
+    assert_eq!(eval_input(&mut vm, "double(21)\n"), Ok(Value::Number(42.0)));
+    assert_eq!(eval_input(&mut vm, "double(1)\n"), Ok(Value::Number(2.0)));
+    // The defining input ran exactly once
+    assert_eq!(eval_input(&mut vm, "runs\n"), Ok(Value::Number(1.0)));
+}
+
+#[test]
+fn test_repl_echoes_structural_comparisons() {
+    use crate::repl::eval_input;
+
+    let mut vm = repl_vm();
+    let echo = |vm: &mut luma_core::vm::VM, input: &str| eval_input(vm, input).unwrap().to_string();
+    assert_eq!(echo(&mut vm, "[1, 2] == [1, 2]\n"), "true");
+    assert_eq!(echo(&mut vm, "{ a = 1 } == { a = 2 }\n"), "false");
+    assert_eq!(echo(&mut vm, "[1, 2] < [1, 3]\n"), "true");
+}
+
+#[test]
+fn test_repl_closures_see_later_global_updates() {
+    use crate::repl::eval_input;
+    use luma_core::vm::value::Value;
+
+    let mut vm = repl_vm();
+    eval_input(&mut vm, "var count = 0\n").unwrap();
+    eval_input(
+        &mut vm,
+        "let bump = fn(): Number do\n  count = count + 1\n  count\nend\n",
+    )
+    .unwrap();
+    eval_input(&mut vm, "bump()\n").unwrap();
+    eval_input(&mut vm, "count = count + 10\n").unwrap();
+    assert_eq!(eval_input(&mut vm, "bump()\n"), Ok(Value::Number(12.0)));
+
+    // Captured locals survive between inputs too
+    eval_input(
+        &mut vm,
+        "let counter = fn(): Any do\n  var n = 0\n  fn(): Number do\n    n = n + 1\n    n\n  end\nend\nlet next = counter()\n",
+    )
+    .unwrap();
+    eval_input(&mut vm, "next()\n").unwrap();
+    assert_eq!(eval_input(&mut vm, "next()\n"), Ok(Value::Number(2.0)));
+
+    let err = eval_input(&mut vm, "missing()\n").unwrap_err();
+    assert!(err.contains("missing"), "{err}");
+    // A failed input leaves earlier definitions intact
+    assert_eq!(eval_input(&mut vm, "count\n"), Ok(Value::Number(12.0)));
+}
+
+#[test]
+fn test_init_appends_to_existing_gitignore() {
+    let dir = std::env::temp_dir().join(format!("luma_init_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join(".gitignore"), "target/").unwrap();
+
+    let written = crate::init::init_project(&dir, false).unwrap();
+    assert!(written.contains(&("Updated", dir.join(".gitignore"))));
+    assert_eq!(
+        fs::read_to_string(dir.join(".gitignore")).unwrap(),
+        "target/\n*.lumac\n"
+    );
+
+    // Forcing a second run keeps the .gitignore as it is
+    let written = crate::init::init_project(&dir, true).unwrap();
+    assert!(!written.iter().any(|(_, path)| path.ends_with(".gitignore")));
+    assert_eq!(
+        fs::read_to_string(dir.join(".gitignore")).unwrap(),
+        "target/\n*.lumac\n"
+    );
+
+    let _ = fs::remove_dir_all(dir);
 }
@@ -1,10 +1,33 @@
 //! Shared CLI utilities for reading input and formatting errors
 
-use luma_core::ast;
+use clap::ValueEnum;
 use luma_core::diagnostics;
 use luma_core::typecheck;
 use std::fs;
-use std::io::{self, Read};
+use std::io::{self, IsTerminal, Read};
+
+/// When to color diagnostics, set by the global `--color` flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorMode {
+    /// Color when stderr is a terminal and `NO_COLOR` is unset
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    /// Resolve to whether diagnostics written to stderr should be colored
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                    && io::stderr().is_terminal()
+            }
+        }
+    }
+}
 
 /// Read source code from a file or stdin.
 /// If `file` is "-", reads from stdin. Otherwise reads from the specified file.
@@ -18,29 +41,33 @@ pub fn read_source(file: &str) -> io::Result<String> {
     }
 }
 
+/// Render diagnostics with source context, sorted top-to-bottom
+pub fn render_diagnostics(
+    diagnostics: &[diagnostics::Diagnostic],
+    source: &str,
+    color: bool,
+) -> String {
+    let mut sorted = diagnostics.to_vec();
+    diagnostics::sort_diagnostics(&mut sorted);
+    sorted
+        .iter()
+        .map(|d| format!("{}\n", d.format_with_color(source, color)))
+        .collect()
+}
+
 /// Format and print parse errors to stderr
-pub fn format_parse_errors(errors: &[diagnostics::Diagnostic], source: &str) {
-    for error in errors {
-        eprintln!("{}", error.format(source));
-    }
+pub fn format_parse_errors(errors: &[diagnostics::Diagnostic], source: &str, color: bool) {
+    eprint!("{}", render_diagnostics(errors, source, color));
 }
 
 /// Format and print typecheck errors to stderr
-pub fn format_typecheck_errors(errors: &[typecheck::TypeError], file: &str, source: &str) {
+pub fn format_typecheck_errors(
+    errors: &[typecheck::TypeError],
+    file: &str,
+    source: &str,
+    color: bool,
+) {
+    let diagnostics: Vec<_> = errors.iter().map(|e| e.to_diagnostic(file)).collect();
     eprintln!("Typecheck failed:");
-    for e in errors {
-        let mut diag = diagnostics::Diagnostic::error(
-            diagnostics::DiagnosticKind::Type,
-            e.message.clone(),
-            e.span.unwrap_or_else(|| ast::Span::new(0, 0)),
-            file.to_string(),
-        );
-        for s in &e.suggestions {
-            diag = diag.with_suggestion(s.clone());
-        }
-        for fix in &e.fixits {
-            diag = diag.with_fix(fix.clone());
-        }
-        eprintln!("{}", diag.format(source));
-    }
+    eprint!("{}", render_diagnostics(&diagnostics, source, color));
 }
@@ -0,0 +1,47 @@
+//! End-to-end tests for the global `--color` flag
+
+use std::fs;
+use std::process::Command;
+
+fn check_with_color(name: &str, color: &str) -> String {
+    let path = std::env::temp_dir().join(name);
+    fs::write(&path, "let x = (1 +\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_luma"))
+        .arg(format!("--color={color}"))
+        .arg("check")
+        .arg(&path)
+        .env_remove("NO_COLOR")
+        .output()
+        .unwrap();
+    let _ = fs::remove_file(path);
+    assert!(!output.status.success());
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn test_color_always_emits_ansi_codes() {
+    let stderr = check_with_color("luma_cli_test_color_always.luma", "always");
+    assert!(stderr.contains("\x1b[1;31merror"), "{stderr:?}");
+}
+
+#[test]
+fn test_color_never_emits_plain_text() {
+    let stderr = check_with_color("luma_cli_test_color_never.luma", "never");
+    assert!(stderr.starts_with("error"), "{stderr:?}");
+    assert!(!stderr.contains('\x1b'), "{stderr:?}");
+}
+
+#[test]
+fn test_color_flag_is_accepted_after_subcommand() {
+    let path = std::env::temp_dir().join("luma_cli_test_color_run.luma");
+    fs::write(&path, "let xs = [1]\nlet y = xs[3]\n").unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_luma"))
+        .arg("run")
+        .arg(&path)
+        .arg("--color=always")
+        .output()
+        .unwrap();
+    let _ = fs::remove_file(path);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("\x1b[1;34m-->\x1b[0m"), "{stderr:?}");
+}
@@ -0,0 +1,51 @@
+//! End-to-end tests for `luma compile`
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Write `source` to a fresh temp directory and compile it, returning the
+/// command output and the path the bytecode would be written to.
+fn compile(name: &str, source: &str, extra_args: &[&str]) -> (Output, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("luma_cli_compile_{name}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("script.luma");
+    fs::write(&script, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_luma"))
+        .arg("compile")
+        .arg(&script)
+        .args(extra_args)
+        .output()
+        .unwrap();
+    (output, dir.join("script.lumac"))
+}
+
+#[test]
+fn test_compile_refuses_type_errors() {
+    let (output, lumac) = compile("type_error", "let x: Number = \"text\"\n", &[]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Typecheck failed"), "{stderr}");
+    assert!(stderr.contains("--force"), "{stderr}");
+    assert!(!lumac.exists());
+    let _ = fs::remove_dir_all(lumac.parent().unwrap());
+}
+
+#[test]
+fn test_compile_force_writes_despite_type_errors() {
+    let (output, lumac) = compile("force", "let x: Number = \"text\"\n", &["--force"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Typecheck failed"));
+    assert!(lumac.exists());
+    let _ = fs::remove_dir_all(lumac.parent().unwrap());
+}
+
+#[test]
+fn test_compile_clean_file() {
+    let (output, lumac) = compile("clean", "let x = 1 + 2\n", &[]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(output.stderr.is_empty());
+    assert!(fs::read_to_string(&lumac).unwrap().contains("instructions"));
+    let _ = fs::remove_dir_all(lumac.parent().unwrap());
+}
@@ -0,0 +1,63 @@
+//! End-to-end tests for how `luma run` terminates
+
+use std::fs;
+use std::process::Command;
+
+fn run_script(name: &str, source: &str) -> std::process::Output {
+    let path = std::env::temp_dir().join(name);
+    fs::write(&path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_luma"))
+        .arg("run")
+        .arg(&path)
+        .output()
+        .unwrap();
+    let _ = fs::remove_file(path);
+    output
+}
+
+#[test]
+fn test_process_exit_flushes_output_and_runs_hooks() {
+    let output = run_script(
+        "luma_cli_test_exit.luma",
+        "at_exit(fn() do write(1, \" hook\") end)\n\
+         write(1, \"partial\")\n\
+         process.exit(3)\n\
+         write(1, \" unreachable\")",
+    );
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "partial hook");
+}
+
+#[test]
+fn test_exit_hooks_run_after_normal_completion() {
+    let output = run_script(
+        "luma_cli_test_exit_hooks.luma",
+        "at_exit(fn() do print(\"first registered\") end)\n\
+         at_exit(fn() do print(\"last registered\") end)\n\
+         print(\"body\")",
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "bodylast registeredfirst registered"
+    );
+}
+
+#[test]
+fn test_runtime_error_renders_source_snippet() {
+    let output = run_script(
+        "luma_cli_test_runtime_error.luma",
+        "let xs = [1, 2]\nlet y = xs[5]\nprint(y)\n",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("error: List index out of bounds"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("luma_cli_test_runtime_error.luma:2:1"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("2 | let y = xs[5]"), "{stderr}");
+}
@@ -0,0 +1,69 @@
+//! End-to-end tests for `luma init`
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn luma(args: &[&str], dir: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_luma"))
+        .args(args)
+        .arg(dir)
+        .output()
+        .unwrap()
+}
+
+fn fresh_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("luma_cli_init_{name}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn test_init_creates_a_runnable_project() {
+    let dir = fresh_dir("new");
+    let output = luma(&["init"], &dir);
+    assert!(output.status.success(), "{output:?}");
+    for rel in ["main.luma", "lib/greet.luma", ".gitignore"] {
+        assert!(dir.join(rel).is_file(), "missing {rel}");
+    }
+
+    let output = luma(&["run"], &dir.join("main.luma"));
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim_end(),
+        "Hello, world!"
+    );
+
+    let output = luma(&["check"], &dir.join("lib/greet.luma"));
+    assert!(output.status.success(), "{output:?}");
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_init_refuses_to_overwrite_without_force() {
+    let dir = fresh_dir("existing");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("main.luma"), "print(\"mine\")\n").unwrap();
+
+    let output = luma(&["init"], &dir);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("main.luma"), "{stderr}");
+    assert!(stderr.contains("--force"), "{stderr}");
+    // Nothing is written when any file is in the way
+    assert!(!dir.join("lib").exists());
+    assert_eq!(
+        fs::read_to_string(dir.join("main.luma")).unwrap(),
+        "print(\"mine\")\n"
+    );
+
+    let output = luma(&["init", "--force"], &dir);
+    assert!(output.status.success(), "{output:?}");
+    assert!(
+        fs::read_to_string(dir.join("main.luma"))
+            .unwrap()
+            .contains("import(\"./lib/greet.luma\")")
+    );
+    assert!(dir.join("lib/greet.luma").is_file());
+    let _ = fs::remove_dir_all(dir);
+}
@@ -0,0 +1,31 @@
+//! End-to-end tests for `luma run --seed`
+
+use std::fs;
+use std::process::Command;
+
+fn run_script(name: &str, source: &str, seed: Option<&str>) -> String {
+    let path = std::env::temp_dir().join(name);
+    fs::write(&path, source).unwrap();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_luma"));
+    command.arg("run");
+    if let Some(seed) = seed {
+        command.arg("--seed").arg(seed);
+    }
+    let output = command.arg(&path).output().unwrap();
+    let _ = fs::remove_file(path);
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn test_seed_flag_matches_random_seed_in_script() {
+    let draws = "print([random.float(), random.int(1, 1000000)])";
+    let from_flag = run_script("luma_cli_test_seed_flag.luma", draws, Some("42"));
+    let from_script = run_script(
+        "luma_cli_test_seed_script.luma",
+        &format!("random.seed(42)\n{draws}"),
+        None,
+    );
+    assert!(!from_flag.is_empty());
+    assert_eq!(from_flag, from_script);
+}
@@ -11,7 +11,7 @@ mod types;
 
 pub use patterns::{Literal, Pattern, TablePatternField};
 pub use span::{Location, Span, Spanned};
-pub use types::{Argument, Type};
+pub use types::{Argument, EnumVariant, Type};
 
 /// Argument in a function call (positional or named)
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -26,6 +26,8 @@ pub enum TableKey {
     Identifier(String),
     StringLiteral(String),
     Computed(Box<Expr>),
+    /// `...value` copies every field of the value, which is the entry's expression
+    Spread,
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -161,9 +163,50 @@ pub enum Expr {
         #[serde(default)]
         span: Option<Span>,
     },
+    /// Loop as an expression: evaluates to the value passed to the `break` that
+    /// exits it, or null when the loop finishes normally or breaks without a value
+    Loop {
+        body: Box<Stmt>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde(default)]
+        span: Option<Span>,
+    },
+    /// Numeric range `start..end`, or `start..=end` when inclusive: the numbers
+    /// from `start` counting up by 1 while below (or up to) `end`
+    Range {
+        start: Box<Expr>,
+        end: Box<Expr>,
+        inclusive: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde(default)]
+        span: Option<Span>,
+    },
+    /// `...list`, splicing a list's elements in place; only valid as a list
+    /// literal element or a positional call argument
+    Spread {
+        value: Box<Expr>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde(default)]
+        span: Option<Span>,
+    },
 }
 
 impl Expr {
+    /// The string this expression always evaluates to, if it is known at
+    /// compile time: a string literal, or literals joined with `+`.
+    pub fn constant_string(&self) -> Option<String> {
+        match self {
+            Expr::String { value, .. } => Some(value.clone()),
+            Expr::Binary {
+                left,
+                op: BinaryOp::Add,
+                right,
+                ..
+            } => Some(left.constant_string()? + &right.constant_string()?),
+            _ => None,
+        }
+    }
+
     /// Get the span of this expression, if available
     pub fn span(&self) -> Option<Span> {
         match self {
@@ -186,6 +229,9 @@ impl Expr {
             Expr::Block { span, .. } => *span,
             Expr::Import { span, .. } => *span,
             Expr::Match { span, .. } => *span,
+            Expr::Loop { span, .. } => *span,
+            Expr::Range { span, .. } => *span,
+            Expr::Spread { span, .. } => *span,
         }
     }
 }
@@ -198,6 +244,7 @@ pub enum BinaryOp {
     Mul,
     Div,
     Mod,
+    Pow,
     Eq,
     Ne,
     Lt,
@@ -221,7 +268,8 @@ pub enum LogicalOp {
 /// Assignment operators
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum AssignOp {
-    Assign, // =
+    Assign,   // =
+    Compound, // op=, whose value is kept as `target op (value)`
 }
 
 /// Statement types
@@ -240,6 +288,10 @@ pub enum Stmt {
     },
     Return {
         value: Expr,
+        /// Produced from a block's trailing expression rather than written
+        /// as `return`; it only yields the block's value
+        #[serde(default)]
+        implicit: bool,
         #[serde(skip_serializing_if = "Option::is_none")]
         #[serde(default)]
         span: Option<Span>,
@@ -295,6 +347,9 @@ pub enum Stmt {
         level: Option<u32>,
         #[serde(skip_serializing_if = "Option::is_none")]
         #[serde(default)]
+        value: Option<Expr>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde(default)]
         span: Option<Span>,
     },
     Continue {
@@ -318,6 +373,23 @@ pub enum Stmt {
         #[serde(default)]
         span: Option<Span>,
     },
+
+    /// Tagged-union declaration: type Shape = Circle { r: Number } | Square { s: Number }
+    TypeDecl {
+        name: String,
+        variants: Vec<EnumVariant>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde(default)]
+        span: Option<Span>,
+    },
+
+    /// Module export list: export name | export { a, b }
+    Export {
+        names: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde(default)]
+        span: Option<Span>,
+    },
 }
 
 impl Stmt {
@@ -336,6 +408,8 @@ impl Stmt {
             Stmt::Continue { span, .. } => *span,
             Stmt::ExprStmt { span, .. } => *span,
             Stmt::Match { span, .. } => *span,
+            Stmt::TypeDecl { span, .. } => *span,
+            Stmt::Export { span, .. } => *span,
         }
     }
 }
@@ -344,3 +418,189 @@ impl Stmt {
 pub struct Program {
     pub statements: Vec<Stmt>,
 }
+
+impl Program {
+    /// Names listed by top-level `export` statements, in source order.
+    /// Empty when the module exports its trailing value instead.
+    pub fn exports(&self) -> Vec<&str> {
+        self.statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Export { names, .. } => Some(names.iter().map(String::as_str)),
+                _ => None,
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Paths of every `import("...")` with a constant path, anywhere in the
+    /// program, in source order. Imports of computed paths are left out.
+    pub fn imports(&self) -> Vec<String> {
+        let mut paths = Vec::new();
+        for stmt in &self.statements {
+            stmt_imports(stmt, &mut paths);
+        }
+        paths
+    }
+}
+
+fn stmt_imports(stmt: &Stmt, paths: &mut Vec<String>) {
+    let block = |stmts: &[Stmt], paths: &mut Vec<String>| {
+        for stmt in stmts {
+            stmt_imports(stmt, paths);
+        }
+    };
+    match stmt {
+        Stmt::VarDecl { value, .. }
+        | Stmt::DestructuringVarDecl { value, .. }
+        | Stmt::Return { value, .. }
+        | Stmt::ExprStmt { expr: value, .. } => expr_imports(value, paths),
+        Stmt::Assignment { target, value, .. } => {
+            expr_imports(target, paths);
+            expr_imports(value, paths);
+        }
+        Stmt::If {
+            condition,
+            then_block,
+            elif_blocks,
+            else_block,
+            ..
+        } => {
+            expr_imports(condition, paths);
+            block(then_block, paths);
+            for (cond, body) in elif_blocks {
+                expr_imports(cond, paths);
+                block(body, paths);
+            }
+            if let Some(else_block) = else_block {
+                block(else_block, paths);
+            }
+        }
+        Stmt::While {
+            condition, body, ..
+        }
+        | Stmt::DoWhile {
+            condition, body, ..
+        } => {
+            expr_imports(condition, paths);
+            block(body, paths);
+        }
+        Stmt::For { iterator, body, .. } => {
+            expr_imports(iterator, paths);
+            block(body, paths);
+        }
+        Stmt::Break { value, .. } => {
+            if let Some(value) = value {
+                expr_imports(value, paths);
+            }
+        }
+        Stmt::Match { expr, arms, .. } => {
+            expr_imports(expr, paths);
+            for (_, body) in arms {
+                block(body, paths);
+            }
+        }
+        Stmt::Continue { .. } | Stmt::TypeDecl { .. } | Stmt::Export { .. } => {}
+    }
+}
+
+fn expr_imports(expr: &Expr, paths: &mut Vec<String>) {
+    let block = |stmts: &[Stmt], paths: &mut Vec<String>| {
+        for stmt in stmts {
+            stmt_imports(stmt, paths);
+        }
+    };
+    let call = |arguments: &[CallArgument], paths: &mut Vec<String>| {
+        for arg in arguments {
+            match arg {
+                CallArgument::Positional(e) | CallArgument::Named { value: e, .. } => {
+                    expr_imports(e, paths)
+                }
+            }
+        }
+    };
+    match expr {
+        Expr::Number { .. }
+        | Expr::Identifier { .. }
+        | Expr::String { .. }
+        | Expr::Boolean { .. }
+        | Expr::Null { .. } => {}
+        Expr::Import { path, .. } => match path.constant_string() {
+            Some(path) => paths.push(path),
+            None => expr_imports(path, paths),
+        },
+        Expr::Function {
+            arguments, body, ..
+        } => {
+            for arg in arguments {
+                if let Some(default) = &arg.default {
+                    expr_imports(default, paths);
+                }
+            }
+            block(body, paths);
+        }
+        Expr::List { elements, .. } => {
+            for e in elements {
+                expr_imports(e, paths);
+            }
+        }
+        Expr::Table { fields, .. } => {
+            for (key, value) in fields {
+                if let TableKey::Computed(k) = key {
+                    expr_imports(k, paths);
+                }
+                expr_imports(value, paths);
+            }
+        }
+        Expr::Binary { left, right, .. }
+        | Expr::Logical { left, right, .. }
+        | Expr::Range {
+            start: left,
+            end: right,
+            ..
+        } => {
+            expr_imports(left, paths);
+            expr_imports(right, paths);
+        }
+        Expr::Unary { operand, .. } | Expr::Spread { value: operand, .. } => {
+            expr_imports(operand, paths)
+        }
+        Expr::Call {
+            callee, arguments, ..
+        } => {
+            expr_imports(callee, paths);
+            call(arguments, paths);
+        }
+        Expr::MethodCall {
+            object, arguments, ..
+        } => {
+            expr_imports(object, paths);
+            call(arguments, paths);
+        }
+        Expr::MemberAccess { object, .. } => expr_imports(object, paths),
+        Expr::Index { object, index, .. } => {
+            expr_imports(object, paths);
+            expr_imports(index, paths);
+        }
+        Expr::If {
+            condition,
+            then_block,
+            else_block,
+            ..
+        } => {
+            expr_imports(condition, paths);
+            block(then_block, paths);
+            if let Some(else_block) = else_block {
+                block(else_block, paths);
+            }
+        }
+        Expr::Block { statements, .. } => block(statements, paths),
+        Expr::Match { expr, arms, .. } => {
+            expr_imports(expr, paths);
+            for (_, body) in arms {
+                block(body, paths);
+            }
+        }
+        Expr::Loop { body, .. } => stmt_imports(body, paths),
+    }
+}
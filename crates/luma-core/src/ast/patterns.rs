@@ -35,6 +35,14 @@ pub enum Pattern {
         #[serde(default)]
         span: Option<Span>,
     },
+    /// Variant pattern - matches a tagged-union variant by tag and binds its fields
+    Variant {
+        tag: String,
+        fields: Vec<TablePatternField>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde(default)]
+        span: Option<Span>,
+    },
     /// Literal pattern - matches a specific value
     Literal {
         value: Literal,
@@ -52,6 +60,7 @@ impl Pattern {
             Pattern::Wildcard { span, .. } => *span,
             Pattern::ListPattern { span, .. } => *span,
             Pattern::TablePattern { span, .. } => *span,
+            Pattern::Variant { span, .. } => *span,
             Pattern::Literal { span, .. } => *span,
         }
     }
@@ -61,7 +70,21 @@ impl Pattern {
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct TablePatternField {
     pub key: String,
-    pub binding: Option<String>, // None means key is also the binding name
+    /// Pattern for the field's value, as in `{key: name}` or `{pos: {x, y}}`.
+    /// None means the value binds to a variable named after the key.
+    pub pattern: Option<Pattern>,
+}
+
+impl TablePatternField {
+    /// The variable the field's value is bound to directly, if it is not
+    /// destructured further
+    pub fn binding(&self) -> Option<&str> {
+        match &self.pattern {
+            None => Some(&self.key),
+            Some(Pattern::Ident { name, .. }) => Some(name),
+            Some(_) => None,
+        }
+    }
 }
 
 /// Literal value in patterns
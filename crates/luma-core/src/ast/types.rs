@@ -61,3 +61,14 @@ pub struct Argument {
     #[serde(default)]
     pub span: Option<Span>,
 }
+
+/// A single variant of a tagged-union type declaration
+/// (`Circle { r: Number }` in `type Shape = Circle { r: Number } | Square { s: Number }`)
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct EnumVariant {
+    pub name: String,
+    pub fields: Vec<(String, Type)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub span: Option<Span>,
+}
@@ -16,8 +16,9 @@
 //! The compiler uses a stack-based virtual machine model where most operations
 //! work on values at the top of the stack.
 
+use super::hoist::Primitive;
 use super::ir::{Chunk, Constant, Instruction, UpvalueDescriptor};
-use crate::ast::{Argument, Expr, Program, Stmt};
+use crate::ast::{Argument, Expr, Program, Span, Stmt, TableKey};
 use std::collections::HashMap;
 
 pub fn compile_program(program: &Program) -> Chunk {
@@ -52,7 +53,7 @@ fn compile_program_impl(program: &Program, repl_mode: bool) -> Chunk {
 
         // Pre-register all top-level let/var declarations with null placeholders
         for stmt in &program.statements {
-            if let Stmt::VarDecl { name, .. } = stmt {
+            if let Stmt::VarDecl { name, .. } | Stmt::TypeDecl { name, .. } = stmt {
                 let null_idx = push_const(&mut c.chunk, Constant::Null);
                 c.chunk.instructions.push(Instruction::Const(null_idx));
                 let slot = c.local_count;
@@ -63,7 +64,7 @@ fn compile_program_impl(program: &Program, repl_mode: bool) -> Chunk {
     } else {
         // In REPL mode, pre-register globals with null placeholders like before
         for stmt in &program.statements {
-            if let Stmt::VarDecl { name, .. } = stmt {
+            if let Stmt::VarDecl { name, .. } | Stmt::TypeDecl { name, .. } = stmt {
                 let null_idx = push_const(&mut c.chunk, Constant::Null);
                 c.chunk.instructions.push(Instruction::Const(null_idx));
                 let name_idx = push_const(&mut c.chunk, Constant::String(name.clone()));
@@ -73,8 +74,26 @@ fn compile_program_impl(program: &Program, repl_mode: bool) -> Chunk {
     }
 
     // Compile all statements
-    for stmt in &program.statements {
-        c.emit_stmt(stmt);
+    c.emit_block(&program.statements);
+
+    // With an explicit export list, the module's value is a table of exactly
+    // those bindings rather than its trailing value
+    let exports = program.exports();
+    if !exports.is_empty() {
+        if does_block_leave_value(&program.statements) {
+            c.chunk.instructions.push(Instruction::Pop);
+        }
+        let fields = exports
+            .into_iter()
+            .map(|name| {
+                let value = Expr::Identifier {
+                    name: name.to_string(),
+                    span: None,
+                };
+                (TableKey::Identifier(name.to_string()), value)
+            })
+            .collect();
+        c.emit_expr(&Expr::Table { fields, span: None });
     }
 
     // In REPL mode, pop locals. In module mode, leave them on stack to preserve
@@ -87,6 +106,15 @@ fn compile_program_impl(program: &Program, repl_mode: bool) -> Chunk {
     }
 
     c.chunk.instructions.push(Instruction::Halt);
+    c.record_spans(c.chunk.instructions.len(), None);
+
+    if cfg!(debug_assertions)
+        && let Err(issues) = super::verify::check_stack_balance(&c.chunk)
+    {
+        let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+        panic!("Compiler error: unbalanced stack\n{}", issues.join("\n"));
+    }
+
     c.chunk.clone()
 }
 
@@ -100,12 +128,14 @@ pub(super) struct LoopContext {
     pub(super) continue_patches: Vec<usize>,
     pub(super) local_count: usize,
     pub(super) continue_target: Option<usize>,
+    /// Local slot receiving `break` values when the loop is used as an expression
+    pub(super) result_slot: Option<usize>,
 }
 
 #[derive(Debug, Clone)]
 pub(super) struct UpvalueInfo {
     pub(super) descriptor: UpvalueDescriptor,
-    pub(super) _name: String,
+    pub(super) name: String,
 }
 
 pub(super) struct Compiler {
@@ -113,10 +143,21 @@ pub(super) struct Compiler {
     pub(super) scopes: Vec<HashMap<String, usize>>,
     pub(super) local_count: usize,
     pub(super) loop_stack: Vec<LoopContext>,
+    /// Result slot for the next loop statement, set by `Expr::Loop`
+    pub(super) loop_result_slot: Option<usize>,
     pub(super) upvalues: Vec<UpvalueInfo>,
     pub(super) parent: Option<Box<Compiler>>,
     pub(super) param_scopes: Vec<HashMap<String, Vec<String>>>,
     pub(super) global_fn_params: HashMap<String, Vec<String>>,
+    /// Loop-invariant expressions already evaluated into a local, keyed by AST node
+    pub(super) hoisted: HashMap<*const Expr, usize>,
+    /// Primitive types known for immutable locals (by slot) and globals, so
+    /// loop hoisting can rule out metamethods
+    pub(super) primitive_locals: HashMap<usize, Primitive>,
+    pub(super) primitive_globals: HashMap<String, Primitive>,
+    /// Name for the function literal about to be emitted, taken from the
+    /// binding or table key it is assigned to
+    pub(super) fn_name: Option<String>,
 }
 
 impl Compiler {
@@ -129,10 +170,15 @@ impl Compiler {
             scopes: Vec::new(),
             local_count: 0,
             loop_stack: Vec::new(),
+            loop_result_slot: None,
             upvalues: Vec::new(),
             parent: None,
             param_scopes: Vec::new(),
             global_fn_params: HashMap::new(),
+            hoisted: HashMap::new(),
+            primitive_locals: HashMap::new(),
+            primitive_globals: HashMap::new(),
+            fn_name: None,
         }
     }
     fn new_with_parent(name: &str, parent: Compiler) -> Self {
@@ -144,14 +190,65 @@ impl Compiler {
             scopes: Vec::new(),
             local_count: 0,
             loop_stack: Vec::new(),
+            loop_result_slot: None,
             upvalues: Vec::new(),
             parent: Some(Box::new(parent)),
             param_scopes: Vec::new(),
             global_fn_params: HashMap::new(),
+            hoisted: HashMap::new(),
+            primitive_locals: HashMap::new(),
+            primitive_globals: HashMap::new(),
+            fn_name: None,
         }
     }
     pub(super) fn emit_stmt(&mut self, s: &Stmt) {
+        let start = self.chunk.instructions.len();
         super::emit_stmt::emit_stmt(self, s);
+        self.record_spans(start, s.span());
+    }
+
+    /// Attribute instructions emitted since `start` to `span`. Nested
+    /// statements are recorded first, so they keep their narrower spans.
+    fn record_spans(&mut self, start: usize, span: Option<Span>) {
+        let len = self.chunk.instructions.len();
+        self.chunk.spans.resize(len, None);
+        for slot in &mut self.chunk.spans[start..] {
+            if slot.is_none() {
+                *slot = span;
+            }
+        }
+    }
+
+    /// Emit a statement whose value is never used (e.g. inside a loop body),
+    /// dropping the value that if/match/return statements leave on the stack.
+    pub(super) fn emit_stmt_discarding(&mut self, s: &Stmt) {
+        self.emit_stmt(s);
+        if matches!(
+            s,
+            Stmt::If { .. } | Stmt::Match { .. } | Stmt::Return { .. }
+        ) {
+            self.chunk.instructions.push(Instruction::Pop);
+        }
+    }
+
+    /// Emit a block's statements. Only the last one may leave a value, so the
+    /// slots of locals declared after an earlier if/match stay where the
+    /// compiler thinks they are.
+    pub(super) fn emit_block(&mut self, stmts: &[Stmt]) {
+        if let Some((last, rest)) = stmts.split_last() {
+            for stmt in rest {
+                self.emit_stmt_discarding(stmt);
+            }
+            self.emit_stmt(last);
+        }
+    }
+
+    /// Emit a block used as a value, pushing null if it ends without one.
+    pub(super) fn emit_block_value(&mut self, stmts: &[Stmt]) {
+        self.emit_block(stmts);
+        if !does_block_leave_value(stmts) {
+            self.push_null();
+        }
     }
 
     // emit_expr moved to emit_expr.rs
@@ -171,7 +268,7 @@ impl Compiler {
     pub(super) fn patch_jump(&mut self, at: usize, target: usize) {
         match self.chunk.instructions.get_mut(at) {
             Some(Instruction::JumpIfFalse(addr)) => *addr = target,
-            Some(Instruction::Jump(addr)) => *addr = target,
+            Some(Instruction::Jump(addr) | Instruction::IterNext(addr)) => *addr = target,
             _ => {}
         }
     }
@@ -201,6 +298,8 @@ impl Compiler {
                 }
             }
             self.local_count = self.local_count.saturating_sub(to_pop);
+            let live = self.local_count;
+            self.primitive_locals.retain(|&slot, _| slot < live);
         }
         self.param_scopes.pop();
     }
@@ -231,6 +330,26 @@ impl Compiler {
         None
     }
 
+    /// Name the function literal `value`, if it is one, after `name` so its
+    /// chunk shows up as `name` in stack traces and disassembly
+    pub(super) fn name_function(&mut self, name: &str, value: &Expr) {
+        if matches!(value, Expr::Function { .. }) {
+            self.fn_name = Some(name.to_string());
+        }
+    }
+
+    /// Remember the callee name for the `Call` instruction just emitted so
+    /// arity errors can name the function.
+    pub(super) fn record_call_name(&mut self, callee: &Expr) {
+        let name = match callee {
+            Expr::Identifier { name, .. } => name,
+            Expr::MemberAccess { member, .. } => member,
+            _ => return,
+        };
+        let call_idx = self.chunk.instructions.len() - 1;
+        self.chunk.call_names.insert(call_idx, name.clone());
+    }
+
     /// Resolve an upvalue by searching parent scopes.
     /// Returns the upvalue index if found, None otherwise.
     pub(super) fn resolve_upvalue(&mut self, name: &str) -> Option<usize> {
@@ -271,10 +390,7 @@ impl Compiler {
 
         // Add new upvalue
         let idx = self.upvalues.len();
-        self.upvalues.push(UpvalueInfo {
-            descriptor,
-            _name: name,
-        });
+        self.upvalues.push(UpvalueInfo { descriptor, name });
         idx
     }
 
@@ -282,6 +398,7 @@ impl Compiler {
     /// This is done by temporarily moving self to become the parent of a new compiler.
     pub(super) fn compile_nested_function(
         &mut self,
+        name: &str,
         arguments: &[Argument],
         body: &[Stmt],
     ) -> (Chunk, Vec<UpvalueDescriptor>) {
@@ -290,7 +407,7 @@ impl Compiler {
         let parent = std::mem::replace(self, Compiler::new("__temp__"));
 
         // Create nested compiler with parent
-        let mut nested = Compiler::new_with_parent("<function>", parent);
+        let mut nested = Compiler::new_with_parent(name, parent);
         let arity = arguments.len();
 
         // Enter scope for function parameters
@@ -309,13 +426,12 @@ impl Compiler {
         nested.predeclare_function_locals(body);
 
         // Compile body
-        for stmt in body {
-            nested.emit_stmt(stmt);
-        }
+        nested.emit_block_value(body);
 
         // Exit scope
-        nested.exit_scope_with_preserve(does_block_leave_value(body));
+        nested.exit_scope_with_preserve(true);
         nested.chunk.instructions.push(Instruction::Return);
+        nested.record_spans(nested.chunk.instructions.len(), None);
         nested.chunk.local_count = arity as u16;
 
         // Extract upvalue descriptors and chunk
@@ -325,6 +441,7 @@ impl Compiler {
             .map(|uv| uv.descriptor.clone())
             .collect();
         nested.chunk.upvalue_descriptors = upvalue_descriptors.clone();
+        nested.chunk.upvalue_names = nested.upvalues.iter().map(|uv| uv.name.clone()).collect();
         let chunk = nested.chunk.clone();
 
         // Restore self from parent
@@ -370,10 +487,14 @@ pub(super) fn apply_implicit_return_to_arm(body: &[Stmt]) -> Vec<Stmt> {
     let mut arm_body = body.to_vec();
     if let Some(last) = arm_body.pop() {
         match last {
-            Stmt::ExprStmt { expr: e, .. } => arm_body.push(Stmt::Return {
-                value: e,
-                span: None,
-            }),
+            Stmt::ExprStmt { expr: e, .. } => {
+                let span = e.span();
+                arm_body.push(Stmt::Return {
+                    value: e,
+                    implicit: true,
+                    span,
+                })
+            }
             other => arm_body.push(other),
         }
     }
@@ -383,20 +504,11 @@ pub(super) fn apply_implicit_return_to_arm(body: &[Stmt]) -> Vec<Stmt> {
 pub(super) fn does_block_leave_value(block: &[Stmt]) -> bool {
     match block.last() {
         Some(Stmt::Return { .. }) => true,
-        Some(Stmt::If {
-            then_block,
-            elif_blocks,
-            else_block,
-            ..
-        }) => {
-            // An if-statement leaves a value if all branches leave a value
-            let then_leaves = does_block_leave_value(then_block);
-            let elif_leave = elif_blocks.iter().all(|(_, b)| does_block_leave_value(b));
-            let else_leaves = else_block
-                .as_ref()
-                .is_some_and(|b| does_block_leave_value(b));
-            then_leaves && elif_leave && else_leaves
-        }
+        // Match statements always leave the selected arm's value on the stack
+        Some(Stmt::Match { .. }) => true,
+        // If statements push null for any branch without a value (including a
+        // missing else), so they always leave exactly one value
+        Some(Stmt::If { .. }) => true,
         _ => false,
     }
 }
@@ -647,6 +759,20 @@ mod tests {
             i,
             Instruction::Jump(_)
         )));
+        assert!(has_instruction(&chunk, |i| matches!(
+            i,
+            Instruction::IterNext(_)
+        )));
+    }
+
+    #[test]
+    fn test_compile_for_over_range_counts_without_a_list() {
+        let chunk = compile_source("for x in 0..3 do let y = x end");
+        assert!(!has_instruction(&chunk, |i| matches!(
+            i,
+            Instruction::IterNext(_) | Instruction::BuildRange(_) | Instruction::Call(_)
+        )));
+        assert!(has_instruction(&chunk, |i| matches!(i, Instruction::Lt)));
     }
 
     // Function tests
@@ -662,6 +788,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_compile_folds_constant_import_paths() {
+        let chunk = compile_source("import(\"./lib/\" + \"m.luma\")");
+        assert!(matches!(
+            chunk.instructions.as_slice(),
+            [Instruction::Const(path), Instruction::Import, ..]
+                if matches!(&chunk.constants[*path], Constant::String(s) if s == "./lib/m.luma")
+        ));
+        assert!(!has_instruction(&chunk, |i| matches!(i, Instruction::Add)));
+    }
+
+    #[test]
+    fn test_compile_names_function_chunks_after_bindings() {
+        let function_names = |chunk: &Chunk| -> Vec<String> {
+            chunk
+                .constants
+                .iter()
+                .filter_map(|c| match c {
+                    Constant::Function(f) => Some(f.name.clone()),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let chunk = compile_source(
+            "let greet = fn() do return 1 end\nlet t = { area = fn() do return 2 end }\nlet xs = [fn() do return 3 end]",
+        );
+        assert_eq!(function_names(&chunk), ["greet", "area", "<anonymous>"]);
+
+        // Only the function bound directly takes the name, not one nested in it
+        let chunk = compile_source("let outer = fn() do\n  return fn() do return 1 end\nend");
+        assert_eq!(function_names(&chunk), ["outer"]);
+        let Some(Constant::Function(outer)) = chunk
+            .constants
+            .iter()
+            .find(|c| matches!(c, Constant::Function(_)))
+        else {
+            unreachable!()
+        };
+        assert_eq!(function_names(outer), ["<anonymous>"]);
+    }
+
     #[test]
     fn test_compile_function_call() {
         let chunk = compile_source("let f = fn(x: Number): Number do return x end\nf(42)");
@@ -715,6 +883,33 @@ mod tests {
         assert_eq!(prop_count, 2);
     }
 
+    #[test]
+    fn test_compile_nested_destructuring_walks_each_level() {
+        let chunk = compile_source("let { pos: { x }, tags: [t] } = shape");
+        let names: Vec<String> = chunk
+            .instructions
+            .iter()
+            .map(|i| format!("{i:?}").split('(').next().unwrap().to_string())
+            .collect();
+        // Each leaf is read by walking from the destructured value down to it
+        assert_eq!(
+            names,
+            [
+                "GetGlobal",
+                "GetLocal",
+                "GetProp",
+                "GetProp",
+                "GetLocal",
+                "GetProp",
+                "Const",
+                "GetIndex",
+                "Halt"
+            ],
+            "{:?}",
+            chunk.instructions
+        );
+    }
+
     // Halt instruction test
     #[test]
     fn test_compile_always_ends_with_halt() {
@@ -775,4 +970,175 @@ mod tests {
             .count();
         assert_eq!(func_count, 2);
     }
+
+    #[test]
+    fn test_loop_invariant_hoisted_before_loop() {
+        let chunk = compile_source(
+            "let a = 2\nlet b = 3\nvar i = 0\nvar t = 0\nwhile i < 3 do\nt = t + a * b\ni = i + 1\nend",
+        );
+        let exits: Vec<usize> = chunk
+            .instructions
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| matches!(i, Instruction::JumpIfFalse(_)))
+            .map(|(ip, _)| ip)
+            .collect();
+        let mul = chunk
+            .instructions
+            .iter()
+            .position(|i| matches!(i, Instruction::Mul))
+            .unwrap();
+        // Computed after the first condition check, before the loop proper
+        assert_eq!(exits.len(), 2);
+        assert!(
+            exits[0] < mul && mul < exits[1],
+            "a * b should be in the preheader"
+        );
+        assert_eq!(
+            chunk
+                .instructions
+                .iter()
+                .filter(|i| matches!(i, Instruction::Mul))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_loop_variant_not_hoisted() {
+        // `i * b` depends on the loop variable; loops with calls are left untouched
+        for source in [
+            "let b = 3\nvar i = 0\nvar t = 0\nwhile i < 3 do\nt = t + i * b\ni = i + 1\nend",
+            "let b = 3\nlet f = fn() do 1 end\nvar i = 0\nvar t = 0\nwhile i < 3 do\nt = t + b * b + f()\ni = i + 1\nend",
+        ] {
+            let chunk = compile_source(source);
+            let position = |f: fn(&Instruction) -> bool| chunk.instructions.iter().position(f);
+            let mul = position(|i| matches!(i, Instruction::Mul)).unwrap();
+            let loop_exit = position(|i| matches!(i, Instruction::JumpIfFalse(_))).unwrap();
+            assert!(mul > loop_exit, "{source}");
+        }
+    }
+
+    /// Number of expressions hoisted out of the program's last loop.
+    fn hoisted_in_last_loop(source: &str) -> usize {
+        let program = parse(source, "test.luma").expect("Parse failed");
+        let mut c = Compiler::new("test");
+        let (last, rest) = program.statements.split_last().unwrap();
+        for stmt in rest {
+            c.emit_stmt(stmt);
+        }
+        let (conditions, body, after) = match last {
+            Stmt::While {
+                condition, body, ..
+            } => (vec![condition], body, false),
+            Stmt::DoWhile {
+                condition, body, ..
+            } => (vec![condition], body, true),
+            other => panic!("expected a loop, got {other:?}"),
+        };
+        c.reserve_loop_invariants(&conditions, body, None, after)
+            .map_or(0, |h| h.len())
+    }
+
+    #[test]
+    fn test_hoisting_only_takes_unconditional_expressions() {
+        let prelude = "let a = 2\nlet b = 3\nvar i = 0\nvar t = 0\n";
+        for (body, expected) in [
+            ("t = t + a * b\ni = i + 1", 1),
+            // Branches and short-circuited operands may never run
+            ("if i == 1 do t = a * b end\ni = i + 1", 0),
+            ("t = i > 1 && a * b > 2\ni = i + 1", 0),
+            ("t = i > 1 ? a * b : 0\ni = i + 1", 0),
+            ("t = if i > 1 do a * b else do 0 end\ni = i + 1", 0),
+            // Nothing after a statement that may break is certain to run
+            ("if i > 1 do break end\nt = t + a * b\ni = i + 1", 0),
+        ] {
+            let source = format!("{prelude}while i < 3 do\n{body}\nend");
+            assert_eq!(hoisted_in_last_loop(&source), expected, "{body}");
+        }
+        // A do-while condition is skipped when the body may break first
+        let source = format!("{prelude}do\nif i > 1 do break end\ni = i + 1\nwhile i < a * b end");
+        assert_eq!(hoisted_in_last_loop(&source), 0);
+    }
+
+    #[test]
+    fn test_hoisting_requires_known_primitive_operands() {
+        for (decls, expected) in [
+            ("let a = 2\nlet b = a + 1", 1),
+            ("let a = \"x\"\nlet b = \"y\"", 1),
+            // Tables may overload operators; `var` and reassigned names may change type
+            ("let a = {}\nlet b = 3", 0),
+            ("var a = 2\nlet b = 3", 0),
+            ("let a = 2\nlet b = 3\na = {}", 0),
+            ("let a = 2\nlet b = 3\nlet f = fn() do a = {} end", 0),
+            ("let a = 2 / 1\nlet b = 3", 0),
+        ] {
+            let source =
+                format!("{decls}\nvar i = 0\nvar t = 0\nwhile i < 3 do\nt = a + b\ni = i + 1\nend");
+            assert_eq!(hoisted_in_last_loop(&source), expected, "{decls}");
+        }
+    }
+
+    #[test]
+    fn test_hoisted_preheader_skipped_by_zero_trip_loop() {
+        let chunk = compile_source(
+            "let a = 2\nlet b = 3\nvar t = 0\nfor x in [] do\nt = t + a * b + x\nend",
+        );
+        let first_next = chunk
+            .instructions
+            .iter()
+            .position(|i| matches!(i, Instruction::IterNext(_)))
+            .unwrap();
+        let mul = chunk
+            .instructions
+            .iter()
+            .position(|i| matches!(i, Instruction::Mul))
+            .unwrap();
+        assert!(first_next < mul, "preheader must follow the first element");
+    }
+
+    #[test]
+    #[should_panic(expected = "break outside of loop")]
+    fn test_break_does_not_cross_function_boundary() {
+        compile_source("while true do\n  let f = fn() do break end\nend");
+    }
+
+    #[test]
+    fn test_match_scrutinee_compiled_once() {
+        for source in [
+            "let f = fn(): Number do return 1 end\nmatch f() do\n  1 do 1 end\n  2 do 2 end\n  _ do 3 end\nend",
+            "let f = fn(): Number do return 1 end\nlet r = match f() do\n  1 do 1 end\n  2 do 2 end\n  _ do 3 end\nend",
+        ] {
+            let chunk = compile_source(source);
+            let calls = chunk
+                .instructions
+                .iter()
+                .filter(|i| matches!(i, Instruction::Call(_)))
+                .count();
+            assert_eq!(calls, 1, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_implicit_arm_return_keeps_expression_span() {
+        let source = "match 2 do\n  1 do \"one\" end\n  _ do 40 + 2 end\nend";
+        let program = parse(source, "test.luma").unwrap();
+        let Some(Stmt::Match { arms, .. }) = program.statements.first() else {
+            panic!("Expected match statement");
+        };
+        for (_, body) in arms {
+            let arm_body = apply_implicit_return_to_arm(body);
+            let Some(Stmt::Return { value, span, .. }) = arm_body.last() else {
+                panic!("Expected synthesized return, got {arm_body:?}");
+            };
+            assert!(span.is_some());
+            assert_eq!(*span, value.span());
+        }
+        let (_, last_arm) = &arms[1];
+        let Some(Stmt::ExprStmt { expr, .. }) = last_arm.last() else {
+            panic!("Expected expression arm body, got {last_arm:?}");
+        };
+        let span = expr.span().unwrap();
+        assert_eq!(source[span.start..span.end].trim_end(), "40 + 2");
+    }
 }
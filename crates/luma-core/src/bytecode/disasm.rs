@@ -0,0 +1,171 @@
+//! Human-readable bytecode listings.
+//!
+//! `disassemble_with_source` interleaves the source line each group of
+//! instructions was compiled from, using the chunk's span table.
+//! `dump_upvalues` lists what each function captures and from where.
+
+use super::ir::{Chunk, Constant, Instruction, UpvalueDescriptor};
+use crate::diagnostics::LineIndex;
+use std::fmt::Write;
+
+/// Disassemble a chunk and every nested function chunk.
+pub fn disassemble(chunk: &Chunk) -> String {
+    let mut out = String::new();
+    write_chunk(&mut out, chunk, None);
+    out
+}
+
+/// Disassemble a chunk, printing each source line above the instructions
+/// compiled from it (like `objdump -S`).
+pub fn disassemble_with_source(chunk: &Chunk, source: &str) -> String {
+    let lines = SourceLines {
+        source,
+        index: LineIndex::new(source),
+    };
+    let mut out = String::new();
+    write_chunk(&mut out, chunk, Some(&lines));
+    out
+}
+
+/// List the captures of every function chunk: each upvalue's name and whether
+/// it comes from a local slot or an upvalue of the enclosing function.
+pub fn dump_upvalues(chunk: &Chunk) -> String {
+    let mut out = String::new();
+    write_upvalues(&mut out, chunk);
+    out
+}
+
+fn write_upvalues(out: &mut String, chunk: &Chunk) {
+    for constant in &chunk.constants {
+        let Constant::Function(nested) = constant else {
+            continue;
+        };
+        let _ = writeln!(out, "== {} ==", nested.name);
+        if nested.upvalue_descriptors.is_empty() {
+            out.push_str("  (no captures)\n");
+        }
+        for (i, descriptor) in nested.upvalue_descriptors.iter().enumerate() {
+            let name = nested.upvalue_names.get(i).map_or("?", String::as_str);
+            let source = match descriptor {
+                UpvalueDescriptor::Local(slot) => format!("local {slot}"),
+                UpvalueDescriptor::Upvalue(idx) => format!("upvalue {idx}"),
+            };
+            let _ = writeln!(out, "  {i:>4}  {name}  <- {source}");
+        }
+        write_upvalues(out, nested);
+    }
+}
+
+struct SourceLines<'a> {
+    source: &'a str,
+    index: LineIndex,
+}
+
+impl SourceLines<'_> {
+    fn text(&self, line: usize) -> &str {
+        let Some((start, end)) = self.index.line_range(line) else {
+            return "";
+        };
+        let end = end.min(self.source.len());
+        self.source[start..end].trim_end()
+    }
+}
+
+fn write_chunk(out: &mut String, chunk: &Chunk, lines: Option<&SourceLines>) {
+    let _ = writeln!(out, "== {} ==", chunk.name);
+    let mut current_line = None;
+    for (ip, instr) in chunk.instructions.iter().enumerate() {
+        if let Some(lines) = lines
+            && let Some(span) = chunk.get_span(ip)
+        {
+            let (line, _) = lines.index.line_col(span.start);
+            if current_line != Some(line) {
+                let _ = writeln!(out, "{line:>4} | {}", lines.text(line));
+                current_line = Some(line);
+            }
+        }
+        let _ = write!(out, "  {ip:04}  {instr:?}");
+        if let Some(name) = chunk.get_call_name(ip) {
+            let _ = write!(out, "  ; {name}");
+        }
+        if let Instruction::MatchJump(idx) = instr
+            && let Some(table) = chunk.jump_tables.get(*idx)
+        {
+            let high = table.low + table.targets.len() as i64 - 1;
+            let targets: Vec<String> = table.targets.iter().map(|t| format!("{t:04}")).collect();
+            let _ = write!(
+                out,
+                "  ; {}..={high} -> [{}], else {:04}",
+                table.low,
+                targets.join(", "),
+                table.default
+            );
+        }
+        out.push('\n');
+    }
+
+    for constant in &chunk.constants {
+        if let Constant::Function(nested) = constant {
+            out.push('\n');
+            write_chunk(out, nested, lines);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::compile::compile_program;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_disassemble_lists_instructions() {
+        let program = parse("let x = 1 + 2", "test.luma").unwrap();
+        let listing = disassemble(&compile_program(&program));
+        assert!(listing.starts_with("== <program> =="));
+        assert!(listing.contains("Add"));
+        assert!(!listing.contains(" | "));
+    }
+
+    #[test]
+    fn test_disassemble_with_source_annotates_lines() {
+        let source = "let x = 1 + 2\nlet y = x * 3";
+        let program = parse(source, "test.luma").unwrap();
+        let listing = disassemble_with_source(&compile_program(&program), source);
+        let lines: Vec<&str> = listing.lines().collect();
+
+        let first = lines
+            .iter()
+            .position(|l| *l == "   1 | let x = 1 + 2")
+            .expect(&listing);
+        let second = lines
+            .iter()
+            .position(|l| *l == "   2 | let y = x * 3")
+            .expect(&listing);
+        assert!(first < second);
+        assert!(lines[first + 1..second].iter().any(|l| l.contains("Add")));
+        assert!(lines[second + 1..].iter().any(|l| l.contains("Mul")));
+    }
+
+    #[test]
+    fn test_dump_upvalues_names_captures() {
+        let source = "let outer = fn() do\n  let count = 1\n  let middle = fn() do\n    return fn() do\n      return count\n    end\n  end\n  return middle\nend";
+        let program = parse(source, "test.luma").unwrap();
+        let dump = dump_upvalues(&compile_program(&program));
+        let lines: Vec<&str> = dump.lines().collect();
+        // `middle` captures `count` from its slot (after the predeclared
+        // `middle` itself); the innermost closure goes through `middle`'s upvalue
+        assert_eq!(
+            lines,
+            [
+                "== outer ==",
+                "  (no captures)",
+                "== middle ==",
+                "     0  count  <- local 1",
+                "== <anonymous> ==",
+                "     0  count  <- upvalue 0",
+            ],
+            "{dump}"
+        );
+    }
+}
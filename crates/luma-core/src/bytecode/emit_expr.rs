@@ -1,10 +1,15 @@
 use super::compile::Compiler;
-use super::helpers::HIDDEN_MATCH_VAL;
+use super::helpers::HIDDEN_LOOP_RESULT;
 use super::ir::{Constant, Instruction};
 use crate::ast::{BinaryOp, CallArgument, Expr, LogicalOp, TableKey, UnaryOp};
 
 impl Compiler {
     pub(super) fn emit_expr(&mut self, e: &Expr) {
+        // Only the expression right after `name_function` may use the name
+        let fn_name = self.fn_name.take();
+        if self.emit_hoisted(e) {
+            return;
+        }
         match e {
             Expr::Number { value: n, .. } => {
                 let idx = self.push_const(Constant::Number(*n));
@@ -24,30 +29,35 @@ impl Compiler {
             }
             Expr::List {
                 elements: items, ..
-            } => {
-                for item in items {
-                    self.emit_expr(item);
-                }
-                self.chunk
-                    .instructions
-                    .push(Instruction::BuildList(items.len()));
-            }
+            } => self.emit_list_items(0, items),
             Expr::Table { fields, .. } => {
+                // Spreads split the literal into runs of plain entries; each run
+                // is built on its own and merged in order, so later entries win
+                let mut pending = 0;
+                let mut started = false;
                 for (key, value) in fields {
                     match key {
                         TableKey::Identifier(s) | TableKey::StringLiteral(s) => {
                             let k_idx = self.push_const(Constant::String(s.clone()));
                             self.chunk.instructions.push(Instruction::Const(k_idx));
+                            self.name_function(s, value);
                         }
                         TableKey::Computed(expr) => {
                             self.emit_expr(expr);
                         }
+                        TableKey::Spread => {
+                            self.emit_table_run(pending, started);
+                            started = true;
+                            pending = 0;
+                            self.emit_expr(value);
+                            self.chunk.instructions.push(Instruction::MergeTable);
+                            continue;
+                        }
                     }
                     self.emit_expr(value);
+                    pending += 1;
                 }
-                self.chunk
-                    .instructions
-                    .push(Instruction::BuildTable(fields.len()));
+                self.emit_table_run(pending, started);
             }
             Expr::MemberAccess { object, member, .. } => {
                 self.emit_expr(object);
@@ -113,25 +123,15 @@ impl Compiler {
             } => {
                 self.emit_expr(left);
                 self.emit_expr(right);
-                match op {
-                    BinaryOp::Add => self.chunk.instructions.push(Instruction::Add),
-                    BinaryOp::Sub => self.chunk.instructions.push(Instruction::Sub),
-                    BinaryOp::Mul => self.chunk.instructions.push(Instruction::Mul),
-                    BinaryOp::Div => self.chunk.instructions.push(Instruction::Div),
-                    BinaryOp::Mod => self.chunk.instructions.push(Instruction::Mod),
-                    BinaryOp::Eq => self.chunk.instructions.push(Instruction::Eq),
-                    BinaryOp::Ne => self.chunk.instructions.push(Instruction::Ne),
-                    BinaryOp::Lt => self.chunk.instructions.push(Instruction::Lt),
-                    BinaryOp::Le => self.chunk.instructions.push(Instruction::Le),
-                    BinaryOp::Gt => self.chunk.instructions.push(Instruction::Gt),
-                    BinaryOp::Ge => self.chunk.instructions.push(Instruction::Ge),
-                }
+                self.emit_binary_op(op);
             }
             Expr::Function {
                 arguments, body, ..
             } => {
-                let (fn_chunk, upvalue_descriptors) = self.compile_nested_function(arguments, body);
-                let idx = self.push_const(Constant::Function(fn_chunk));
+                let name = fn_name.as_deref().unwrap_or("<anonymous>");
+                let (fn_chunk, upvalue_descriptors) =
+                    self.compile_nested_function(name, arguments, body);
+                let idx = self.push_const(Constant::Function(Box::new(fn_chunk)));
                 if upvalue_descriptors.is_empty() {
                     self.chunk.instructions.push(Instruction::MakeFunction(idx));
                 } else {
@@ -144,7 +144,12 @@ impl Compiler {
                 let has_named = arguments
                     .iter()
                     .any(|a| matches!(a, CallArgument::Named { .. }));
-                if !has_named {
+                if !has_named && arguments.iter().any(is_spread_argument) {
+                    self.emit_expr(callee);
+                    self.emit_list_items(0, arguments.iter().map(argument_value));
+                    self.chunk.instructions.push(Instruction::CallList);
+                    self.record_call_name(callee);
+                } else if !has_named {
                     self.emit_expr(callee);
                     for arg in arguments {
                         match arg {
@@ -155,11 +160,15 @@ impl Compiler {
                     self.chunk
                         .instructions
                         .push(Instruction::Call(arguments.len()));
+                    self.record_call_name(callee);
                 } else {
                     let mut seen_named = false;
                     for arg in arguments {
                         match arg {
                             CallArgument::Named { .. } => seen_named = true,
+                            CallArgument::Positional(Expr::Spread { .. }) => {
+                                self.error("Named arguments cannot be combined with a spread");
+                            }
                             CallArgument::Positional(_) if seen_named => {
                                 self.error("Positional arguments cannot follow named arguments");
                             }
@@ -222,6 +231,7 @@ impl Compiler {
                     self.chunk
                         .instructions
                         .push(Instruction::Call(param_names.len()));
+                    self.record_call_name(callee);
                 }
             }
             Expr::MethodCall {
@@ -237,24 +247,26 @@ impl Compiler {
                     .instructions
                     .push(Instruction::GetProp(method_idx));
                 self.emit_expr(object); // Insert object as first argument
-                for arg in arguments {
-                    match arg {
-                        CallArgument::Positional(expr) => self.emit_expr(expr),
-                        CallArgument::Named { value, .. } => self.emit_expr(value),
+                if arguments.iter().any(is_spread_argument) {
+                    self.emit_list_items(1, arguments.iter().map(argument_value));
+                    self.chunk.instructions.push(Instruction::CallList);
+                } else {
+                    for arg in arguments {
+                        self.emit_expr(argument_value(arg));
                     }
+                    self.chunk
+                        .instructions
+                        .push(Instruction::Call(arguments.len() + 1));
                 }
-                self.chunk
-                    .instructions
-                    .push(Instruction::Call(arguments.len() + 1));
+                let call_idx = self.chunk.instructions.len() - 1;
+                self.chunk.call_names.insert(call_idx, method.clone());
             }
             Expr::Block {
                 statements: stmts, ..
             } => {
                 self.enter_scope();
                 self.predeclare_function_locals(stmts);
-                for stmt in stmts {
-                    self.emit_stmt(stmt);
-                }
+                self.emit_block_value(stmts);
                 self.exit_scope_with_preserve(true);
             }
             Expr::If {
@@ -267,9 +279,7 @@ impl Compiler {
                 let jf = self.emit_jump_if_false();
                 self.enter_scope();
                 self.predeclare_function_locals(then_block);
-                for stmt in then_block {
-                    self.emit_stmt(stmt);
-                }
+                self.emit_block_value(then_block);
                 self.exit_scope_with_preserve(true);
 
                 if let Some(else_stmts) = else_block {
@@ -279,9 +289,7 @@ impl Compiler {
 
                     self.enter_scope();
                     self.predeclare_function_locals(else_stmts);
-                    for stmt in else_stmts {
-                        self.emit_stmt(stmt);
-                    }
+                    self.emit_block_value(else_stmts);
                     self.exit_scope_with_preserve(true);
 
                     let end = self.current_ip();
@@ -297,96 +305,126 @@ impl Compiler {
                 }
             }
             Expr::Import { path, .. } => {
-                self.emit_expr(path);
+                // Constant paths are joined here so the VM sees a single literal
+                match path.constant_string() {
+                    Some(path) => {
+                        let idx = self.push_const(Constant::String(path));
+                        self.chunk.instructions.push(Instruction::Const(idx));
+                    }
+                    None => self.emit_expr(path),
+                }
                 self.chunk.instructions.push(Instruction::Import);
             }
-            Expr::Match { expr, arms, .. } => {
+            Expr::Match { expr, arms, .. } => self.emit_match(expr, arms),
+            Expr::Range {
+                start,
+                end,
+                inclusive,
+                ..
+            } => {
+                self.emit_expr(start);
+                self.emit_expr(end);
+                self.chunk
+                    .instructions
+                    .push(Instruction::BuildRange(*inclusive));
+            }
+            Expr::Spread { .. } => {
+                self.error("Spread is only allowed in list literals and call arguments")
+            }
+            Expr::Loop { body, .. } => {
+                // The result lives in a hidden local below the loop's own locals so
+                // `break value` can store into it from any nesting depth
                 self.enter_scope();
-                self.emit_expr(expr);
-                let match_val_slot = self.local_count;
-                self.bind_hidden_local(HIDDEN_MATCH_VAL.to_string(), match_val_slot);
+                self.push_null();
+                let result_slot = self.local_count;
+                self.bind_hidden_local(HIDDEN_LOOP_RESULT.to_string(), result_slot);
                 self.local_count += 1;
+                self.loop_result_slot = Some(result_slot);
+                self.emit_stmt(body);
+                self.chunk
+                    .instructions
+                    .push(Instruction::GetLocal(result_slot));
+                self.exit_scope_with_preserve(true);
+            }
+        }
+    }
 
-                let mut end_jumps = Vec::new();
-                for (i, (pattern, body)) in arms.iter().enumerate() {
-                    let is_wildcard = matches!(pattern, crate::ast::Pattern::Wildcard { .. });
-                    let is_tag_pattern = matches!(pattern, crate::ast::Pattern::Ident { name, .. } if matches!(name.as_str(), "ok" | "err" | "some" | "none"));
-                    let is_catch_all = is_wildcard
-                        || (matches!(pattern, crate::ast::Pattern::Ident { name: _, .. })
-                            && !is_tag_pattern);
+    /// Combine the two values on top of the stack with `op`
+    pub(super) fn emit_binary_op(&mut self, op: &BinaryOp) {
+        let instruction = match op {
+            BinaryOp::Add => Instruction::Add,
+            BinaryOp::Sub => Instruction::Sub,
+            BinaryOp::Mul => Instruction::Mul,
+            BinaryOp::Div => Instruction::Div,
+            BinaryOp::Mod => Instruction::Mod,
+            BinaryOp::Pow => Instruction::Pow,
+            BinaryOp::Eq => Instruction::Eq,
+            BinaryOp::Ne => Instruction::Ne,
+            BinaryOp::Lt => Instruction::Lt,
+            BinaryOp::Le => Instruction::Le,
+            BinaryOp::Gt => Instruction::Gt,
+            BinaryOp::Ge => Instruction::Ge,
+        };
+        self.chunk.instructions.push(instruction);
+    }
 
-                    if !is_catch_all {
-                        match pattern {
-                            crate::ast::Pattern::Ident { name, .. } => {
-                                self.emit_get_local(match_val_slot);
-                                let name_idx = self.push_const(Constant::String(name.clone()));
-                                self.chunk.instructions.push(Instruction::GetProp(name_idx));
-                                self.push_null();
-                                self.chunk.instructions.push(Instruction::Ne);
-                                let jf_next = self.emit_jump_if_false();
+    /// Build a list of `items` on top of `leading` values already on the stack.
+    /// Spreads split it into runs of plain elements; each run is built on its
+    /// own and appended in order.
+    fn emit_list_items<'e>(&mut self, leading: usize, items: impl IntoIterator<Item = &'e Expr>) {
+        let mut pending = leading;
+        let mut started = false;
+        for item in items {
+            if let Expr::Spread { value, .. } = item {
+                self.emit_list_run(pending, started);
+                started = true;
+                pending = 0;
+                self.emit_expr(value);
+                self.chunk.instructions.push(Instruction::SpreadList);
+            } else {
+                self.emit_expr(item);
+                pending += 1;
+            }
+        }
+        self.emit_list_run(pending, started);
+    }
 
-                                let arm_body = super::compile::apply_implicit_return_to_arm(body);
-                                for st in &arm_body {
-                                    self.emit_stmt(st);
-                                }
-                                if !super::compile::does_block_leave_value(&arm_body) {
-                                    self.push_null();
-                                }
-                                end_jumps.push(self.emit_jump());
-                                let next_ip = self.current_ip();
-                                self.patch_jump(jf_next, next_ip);
-                            }
-                            crate::ast::Pattern::Literal { value: lit, .. } => {
-                                self.emit_get_local(match_val_slot);
-                                match lit {
-                                    crate::ast::Literal::Number(n) => {
-                                        self.push_number(*n);
-                                    }
-                                    crate::ast::Literal::String(s) => {
-                                        self.push_string(s.clone());
-                                    }
-                                    crate::ast::Literal::Boolean(b) => {
-                                        self.push_boolean(*b);
-                                    }
-                                    crate::ast::Literal::Null => {
-                                        self.push_null();
-                                    }
-                                }
-                                self.chunk.instructions.push(Instruction::Eq);
-                                let jf_next = self.emit_jump_if_false();
+    /// Build the `pending` values on the stack into a list, appending it to
+    /// the list below when an earlier part of the literal started one.
+    fn emit_list_run(&mut self, pending: usize, started: bool) {
+        if started && pending == 0 {
+            return;
+        }
+        self.chunk
+            .instructions
+            .push(Instruction::BuildList(pending));
+        if started {
+            self.chunk.instructions.push(Instruction::SpreadList);
+        }
+    }
 
-                                let arm_body = super::compile::apply_implicit_return_to_arm(body);
-                                for st in &arm_body {
-                                    self.emit_stmt(st);
-                                }
-                                if !super::compile::does_block_leave_value(&arm_body) {
-                                    self.push_null();
-                                }
-                                end_jumps.push(self.emit_jump());
-                                let next_ip = self.current_ip();
-                                self.patch_jump(jf_next, next_ip);
-                            }
-                            _ => self.error("structural patterns for match expr not yet supported"),
-                        }
-                    } else {
-                        let arm_body = super::compile::apply_implicit_return_to_arm(body);
-                        for st in &arm_body {
-                            self.emit_stmt(st);
-                        }
-                        if !super::compile::does_block_leave_value(&arm_body) {
-                            self.push_null();
-                        }
-                        if i < arms.len() - 1 {
-                            end_jumps.push(self.emit_jump());
-                        }
-                    }
-                }
-                let end_ip = self.current_ip();
-                for j in end_jumps {
-                    self.patch_jump(j, end_ip);
-                }
-                self.exit_scope_with_preserve(true);
-            }
+    /// Build the `pending` key/value pairs on the stack into a table, merging
+    /// it into the table below when an earlier part of the literal started one.
+    fn emit_table_run(&mut self, pending: usize, started: bool) {
+        if started && pending == 0 {
+            return;
         }
+        self.chunk
+            .instructions
+            .push(Instruction::BuildTable(pending));
+        if started {
+            self.chunk.instructions.push(Instruction::MergeTable);
+        }
+    }
+}
+
+fn is_spread_argument(arg: &CallArgument) -> bool {
+    matches!(arg, CallArgument::Positional(Expr::Spread { .. }))
+}
+
+fn argument_value(arg: &CallArgument) -> &Expr {
+    match arg {
+        CallArgument::Positional(expr) => expr,
+        CallArgument::Named { value, .. } => value,
     }
 }
@@ -1,9 +1,10 @@
 use super::compile::{Compiler, does_block_leave_value};
 use super::helpers::{
-    GLOBAL_ITER_FN, HIDDEN_DESTRUCTURE_VAL, HIDDEN_I, HIDDEN_ITER, HIDDEN_MATCH_VAL,
+    GLOBAL_ITER_FN, GLOBAL_ITER_KEYS_FN, HIDDEN_DESTRUCTURE_VAL, HIDDEN_I, HIDDEN_ITER, HIDDEN_LEN,
+    enum_constructor_table,
 };
 use super::ir::{Constant, Instruction};
-use crate::ast::{Expr, Stmt};
+use crate::ast::{AssignOp, Expr, Pattern, Stmt};
 
 pub(super) fn emit_stmt(c: &mut Compiler, s: &Stmt) {
     match s {
@@ -24,9 +25,7 @@ pub(super) fn emit_stmt(c: &mut Compiler, s: &Stmt) {
             c.enter_scope();
             c.predeclare_function_locals(then_block);
             c.chunk.instructions.push(Instruction::Pop);
-            for st in then_block {
-                c.emit_stmt(st);
-            }
+            c.emit_block(then_block);
             let then_preserve = does_block_leave_value(then_block);
             c.exit_scope_with_preserve(then_preserve);
             if !then_preserve {
@@ -44,9 +43,7 @@ pub(super) fn emit_stmt(c: &mut Compiler, s: &Stmt) {
                 c.enter_scope();
                 c.predeclare_function_locals(elif_body);
                 c.chunk.instructions.push(Instruction::Pop);
-                for st in elif_body {
-                    c.emit_stmt(st);
-                }
+                c.emit_block(elif_body);
                 let elif_preserve = does_block_leave_value(elif_body);
                 c.exit_scope_with_preserve(elif_preserve);
                 if !elif_preserve {
@@ -61,9 +58,7 @@ pub(super) fn emit_stmt(c: &mut Compiler, s: &Stmt) {
                 c.enter_scope();
                 c.predeclare_function_locals(else_body);
                 c.chunk.instructions.push(Instruction::Pop);
-                for st in else_body {
-                    c.emit_stmt(st);
-                }
+                c.emit_block(else_body);
                 let else_preserve = does_block_leave_value(else_body);
                 c.exit_scope_with_preserve(else_preserve);
                 if !else_preserve {
@@ -79,43 +74,72 @@ pub(super) fn emit_stmt(c: &mut Compiler, s: &Stmt) {
         Stmt::While {
             condition, body, ..
         } => {
+            let result_slot = c.loop_result_slot.take();
+            let hoisted = c.reserve_loop_invariants(&[condition], body, None, false);
+            // With invariants, the first condition check is peeled off so the
+            // preheader only runs when the loop is entered
+            let mut exit_jumps = Vec::new();
+            let mut enter_body = None;
+            if let Some(hoisted) = &hoisted {
+                c.emit_expr(condition);
+                exit_jumps.push(c.emit_jump_if_false());
+                c.emit_loop_preheader(hoisted);
+                enter_body = Some(c.emit_jump());
+            }
             let loop_start = c.current_ip();
             c.loop_stack.push(super::compile::LoopContext {
                 break_patches: Vec::new(),
                 continue_patches: Vec::new(),
                 local_count: c.local_count,
                 continue_target: Some(loop_start),
+                result_slot,
             });
             c.emit_expr(condition);
-            let jf_end = c.emit_jump_if_false();
+            exit_jumps.push(c.emit_jump_if_false());
+            if let Some(jump) = enter_body {
+                let body_start = c.current_ip();
+                c.patch_jump(jump, body_start);
+            }
             c.enter_scope();
             c.predeclare_function_locals(body);
             for st in body {
-                c.emit_stmt(st);
+                c.emit_stmt_discarding(st);
             }
             c.exit_scope_with_preserve(false);
             c.chunk.instructions.push(Instruction::Jump(loop_start));
             let end_ip = c.current_ip();
-            c.patch_jump(jf_end, end_ip);
+            for jump in exit_jumps {
+                c.patch_jump(jump, end_ip);
+            }
             let loop_ctx = c.loop_stack.pop().unwrap();
             for break_ip in loop_ctx.break_patches {
                 c.patch_jump(break_ip, end_ip);
             }
+            if hoisted.is_some() {
+                c.exit_hoisted_scope();
+            }
         }
         Stmt::DoWhile {
             body, condition, ..
         } => {
+            let result_slot = c.loop_result_slot.take();
+            // The body always runs once, so the preheader needs no guard
+            let hoisted = c.reserve_loop_invariants(&[condition], body, None, true);
+            if let Some(hoisted) = &hoisted {
+                c.emit_loop_preheader(hoisted);
+            }
             let loop_start = c.current_ip();
             c.loop_stack.push(super::compile::LoopContext {
                 break_patches: Vec::new(),
                 continue_patches: Vec::new(),
                 local_count: c.local_count,
                 continue_target: Some(loop_start),
+                result_slot,
             });
             c.enter_scope();
             c.predeclare_function_locals(body);
             for st in body {
-                c.emit_stmt(st);
+                c.emit_stmt_discarding(st);
             }
             c.exit_scope_with_preserve(false);
             c.emit_expr(condition);
@@ -127,28 +151,36 @@ pub(super) fn emit_stmt(c: &mut Compiler, s: &Stmt) {
             for break_ip in loop_ctx.break_patches {
                 c.patch_jump(break_ip, end_ip);
             }
-        }
-        Stmt::Match { expr, arms, .. } => {
-            c.enter_scope();
-            c.emit_expr(expr);
-            let match_val_slot = c.local_count;
-            c.bind_hidden_local(HIDDEN_MATCH_VAL.to_string(), match_val_slot);
-            c.local_count += 1;
-            let mut end_jumps = Vec::new();
-            for (i, (pattern, body)) in arms.iter().enumerate() {
-                if let Some(j) =
-                    c.emit_match_arm(match_val_slot, pattern, body, i == arms.len() - 1)
-                {
-                    end_jumps.push(j);
-                }
-            }
-            let end_ip = c.current_ip();
-            for jump_pos in end_jumps {
-                c.patch_jump(jump_pos, end_ip);
+            if hoisted.is_some() {
+                c.exit_hoisted_scope();
             }
-            c.exit_scope_with_preserve(true);
         }
-        Stmt::VarDecl { name, value, .. } => {
+        Stmt::TypeDecl {
+            name,
+            variants,
+            span,
+        } => {
+            // A tagged-union declaration binds its name to a table of constructors
+            let decl = Stmt::VarDecl {
+                mutable: false,
+                name: name.clone(),
+                r#type: None,
+                value: enum_constructor_table(variants),
+                span: *span,
+            };
+            emit_stmt(c, &decl);
+        }
+        Stmt::Match { expr, arms, .. } => c.emit_match(expr, arms),
+        // The export table is built once the whole module has run
+        Stmt::Export { .. } => {}
+        Stmt::VarDecl {
+            mutable,
+            name,
+            value,
+            ..
+        } => {
+            c.name_function(name, value);
+            let primitive = c.binding_primitive(*mutable, value);
             if c.scopes.is_empty() {
                 c.emit_expr(value);
                 let name_idx =
@@ -175,6 +207,7 @@ pub(super) fn emit_stmt(c: &mut Compiler, s: &Stmt) {
                     }
                 }
             }
+            c.record_binding(name, primitive);
         }
         Stmt::DestructuringVarDecl {
             mutable: _,
@@ -191,15 +224,45 @@ pub(super) fn emit_stmt(c: &mut Compiler, s: &Stmt) {
                 c.local_count += 1;
                 c.emit_destructure_local(pattern, value_slot);
             }
+            c.forget_pattern_primitives(pattern);
         }
+        // Targets are evaluated left to right (object, then index), then the
+        // value, each exactly once; the store comes last
+        // A compound assignment reads the element or field through the object
+        // and index it already evaluated for the store, so each runs once
         Stmt::Assignment {
-            target,
-            op: _,
-            value,
+            target: Expr::Index { object, index, .. },
+            op: AssignOp::Compound,
+            value: Expr::Binary { op, right, .. },
+            ..
+        } => {
+            c.emit_expr(object);
+            c.emit_expr(index);
+            c.chunk.instructions.push(Instruction::Dup2);
+            c.chunk.instructions.push(Instruction::GetIndex);
+            c.emit_expr(right);
+            c.emit_binary_op(op);
+            c.chunk.instructions.push(Instruction::SetIndex);
+        }
+        Stmt::Assignment {
+            target: Expr::MemberAccess { object, member, .. },
+            op: AssignOp::Compound,
+            value: Expr::Binary { op, right, .. },
             ..
-        } => match target {
+        } => {
+            let name_idx =
+                super::compile::push_const(&mut c.chunk, Constant::String(member.clone()));
+            c.emit_expr(object);
+            c.chunk.instructions.push(Instruction::Dup);
+            c.chunk.instructions.push(Instruction::GetProp(name_idx));
+            c.emit_expr(right);
+            c.emit_binary_op(op);
+            c.chunk.instructions.push(Instruction::SetProp(name_idx));
+        }
+        Stmt::Assignment { target, value, .. } => match target {
             Expr::Identifier { name, .. } => {
                 c.emit_expr(value);
+                c.forget_primitive(name);
                 if let Some(slot) = c.lookup_local(name) {
                     c.chunk.instructions.push(Instruction::SetLocal(slot));
                 } else if let Some(upvalue_idx) = c.resolve_upvalue(name) {
@@ -233,27 +296,104 @@ pub(super) fn emit_stmt(c: &mut Compiler, s: &Stmt) {
             body,
             ..
         } => {
+            let result_slot = c.loop_result_slot.take();
+            let hoisted = c.reserve_loop_invariants(&[], body, Some(pattern), false);
             c.enter_scope();
-            let iter_name_idx = super::compile::push_const(
-                &mut c.chunk,
-                Constant::String(GLOBAL_ITER_FN.to_string()),
-            );
-            c.chunk
-                .instructions
-                .push(Instruction::GetGlobal(iter_name_idx));
-            c.emit_expr(iterator);
-            c.chunk.instructions.push(Instruction::Call(1));
-            let iter_slot = c.local_count;
-            c.bind_hidden_local(HIDDEN_ITER.to_string(), iter_slot);
-            c.local_count += 1;
-            let zero_idx = super::compile::push_const(&mut c.chunk, Constant::Number(0.0));
-            c.chunk.instructions.push(Instruction::Const(zero_idx));
-            let i_slot = c.local_count;
-            c.bind_hidden_local(HIDDEN_I.to_string(), i_slot);
-            c.local_count += 1;
+            // A literal range is counted directly rather than built as a list:
+            // `__iter` holds the start, `__len` how many numbers the range
+            // holds and `__i` the index of the current one
+            let range = match iterator {
+                Expr::Range {
+                    start,
+                    end,
+                    inclusive,
+                    ..
+                } => Some((start, end, *inclusive)),
+                _ => None,
+            };
+            let (iter_slot, i_slot, len_slot) = if let Some((start, end, inclusive)) = range {
+                c.emit_expr(start);
+                c.emit_expr(end);
+                c.chunk
+                    .instructions
+                    .push(Instruction::RangeBounds(inclusive));
+                let iter_slot = c.local_count;
+                c.bind_hidden_local(HIDDEN_ITER.to_string(), iter_slot);
+                let len_slot = iter_slot + 1;
+                c.bind_hidden_local(HIDDEN_LEN.to_string(), len_slot);
+                c.local_count += 2;
+                c.push_number(0.0);
+                let i_slot = c.local_count;
+                c.bind_hidden_local(HIDDEN_I.to_string(), i_slot);
+                c.local_count += 1;
+                (iter_slot, i_slot, len_slot)
+            } else {
+                // `for k in table` walks keys; destructuring forms walk [key, value] pairs
+                let iter_fn = match pattern {
+                    Pattern::Ident { .. } | Pattern::Wildcard { .. } => GLOBAL_ITER_KEYS_FN,
+                    _ => GLOBAL_ITER_FN,
+                };
+                let iter_name_idx =
+                    super::compile::push_const(&mut c.chunk, Constant::String(iter_fn.to_string()));
+                c.chunk
+                    .instructions
+                    .push(Instruction::GetGlobal(iter_name_idx));
+                c.emit_expr(iterator);
+                c.chunk.instructions.push(Instruction::Call(1));
+                let iter_slot = c.local_count;
+                c.bind_hidden_local(HIDDEN_ITER.to_string(), iter_slot);
+                c.local_count += 1;
+                // A list is walked up to the length it had when the loop began,
+                // so elements pushed by the body are not visited
+                c.chunk.instructions.push(Instruction::GetLocal(iter_slot));
+                c.chunk.instructions.push(Instruction::GetLen);
+                let len_slot = c.local_count;
+                c.bind_hidden_local(HIDDEN_LEN.to_string(), len_slot);
+                c.local_count += 1;
+                let zero_idx = super::compile::push_const(&mut c.chunk, Constant::Number(0.0));
+                c.chunk.instructions.push(Instruction::Const(zero_idx));
+                let i_slot = c.local_count;
+                c.bind_hidden_local(HIDDEN_I.to_string(), i_slot);
+                c.local_count += 1;
+                (iter_slot, i_slot, len_slot)
+            };
 
             let loop_pat = c.prepare_loop_pattern(pattern);
 
+            // Fetch the next element into the pattern, returning the jump
+            // taken once the iterator is exhausted
+            let emit_next = |c: &mut Compiler| {
+                let next_end = if range.is_some() {
+                    c.chunk.instructions.push(Instruction::GetLocal(i_slot));
+                    c.chunk.instructions.push(Instruction::GetLocal(len_slot));
+                    c.chunk.instructions.push(Instruction::Lt);
+                    let next_end = c.current_ip();
+                    c.chunk.instructions.push(Instruction::JumpIfFalse(0));
+                    c.chunk.instructions.push(Instruction::GetLocal(iter_slot));
+                    c.chunk.instructions.push(Instruction::GetLocal(i_slot));
+                    c.chunk.instructions.push(Instruction::Add);
+                    next_end
+                } else {
+                    c.chunk.instructions.push(Instruction::GetLocal(iter_slot));
+                    c.chunk.instructions.push(Instruction::GetLocal(i_slot));
+                    c.chunk.instructions.push(Instruction::GetLocal(len_slot));
+                    let next_end = c.current_ip();
+                    c.chunk.instructions.push(Instruction::IterNext(0));
+                    next_end
+                };
+                c.assign_loop_pattern_value(&loop_pat);
+                next_end
+            };
+
+            // With invariants, the first fetch is peeled off so the preheader
+            // only runs once there is an element
+            let mut exit_jumps = Vec::new();
+            let mut enter_body = None;
+            if let Some(hoisted) = &hoisted {
+                exit_jumps.push(emit_next(c));
+                c.emit_loop_preheader(hoisted);
+                enter_body = Some(c.emit_jump());
+            }
             let loop_start = c.current_ip();
             let loop_ctx_idx = c.loop_stack.len();
             c.loop_stack.push(super::compile::LoopContext {
@@ -261,17 +401,20 @@ pub(super) fn emit_stmt(c: &mut Compiler, s: &Stmt) {
                 continue_patches: Vec::new(),
                 local_count: c.local_count,
                 continue_target: None,
+                result_slot,
             });
-            c.chunk.instructions.push(Instruction::GetLocal(i_slot));
-            c.chunk.instructions.push(Instruction::GetLocal(iter_slot));
-            c.chunk.instructions.push(Instruction::GetLen);
-            c.chunk.instructions.push(Instruction::Lt);
-            let jf_end = c.emit_jump_if_false();
-            c.assign_loop_pattern_value(&loop_pat, iter_slot, i_slot);
+            exit_jumps.push(emit_next(c));
+            if let Some(jump) = enter_body {
+                let body_start = c.current_ip();
+                c.patch_jump(jump, body_start);
+            }
+            // Body locals get their own scope so each iteration pops them
+            c.enter_scope();
             c.predeclare_function_locals(body);
             for stmt in body {
-                c.emit_stmt(stmt);
+                c.emit_stmt_discarding(stmt);
             }
+            c.exit_scope_with_preserve(false);
             let continue_target = c.current_ip();
             let loop_ctx = &c.loop_stack[loop_ctx_idx];
             let continue_ips = loop_ctx.continue_patches.clone();
@@ -285,20 +428,32 @@ pub(super) fn emit_stmt(c: &mut Compiler, s: &Stmt) {
             c.chunk.instructions.push(Instruction::SetLocal(i_slot));
             c.chunk.instructions.push(Instruction::Jump(loop_start));
             let exit_ip = c.current_ip();
-            c.patch_jump(jf_end, exit_ip);
+            for jump in exit_jumps {
+                c.patch_jump(jump, exit_ip);
+            }
             let loop_ctx = c.loop_stack.pop().unwrap();
             for break_ip in loop_ctx.break_patches {
                 c.patch_jump(break_ip, exit_ip);
             }
             c.exit_scope_with_preserve(false);
+            if hoisted.is_some() {
+                c.exit_hoisted_scope();
+            }
         }
         Stmt::Break {
-            level: level_opt, ..
+            level: level_opt,
+            value,
+            ..
         } => {
             let level = level_opt.unwrap_or(1) as usize;
+            // Function bodies start with an empty loop stack, so this also
+            // rejects a `break` in a function nested inside a loop
+            if c.loop_stack.is_empty() {
+                c.error("break outside of loop");
+            }
             if level > c.loop_stack.len() {
                 c.error(&format!(
-                    "break {} exceeds loop nesting depth of {}",
+                    "break level {} exceeds loop nesting depth of {}",
                     level,
                     c.loop_stack.len()
                 ));
@@ -307,6 +462,14 @@ pub(super) fn emit_stmt(c: &mut Compiler, s: &Stmt) {
                 c.error("break level must be at least 1");
             }
             let target_loop_idx = c.loop_stack.len() - level;
+            if let Some(value) = value {
+                c.emit_expr(value);
+                match c.loop_stack[target_loop_idx].result_slot {
+                    Some(slot) => c.chunk.instructions.push(Instruction::SetLocal(slot)),
+                    // Statement loops have no result; the value is evaluated and discarded
+                    None => c.chunk.instructions.push(Instruction::Pop),
+                }
+            }
             let target_loop = &c.loop_stack[target_loop_idx];
             let locals_to_pop = c.local_count - target_loop.local_count;
             for _ in 0..locals_to_pop {
@@ -319,9 +482,14 @@ pub(super) fn emit_stmt(c: &mut Compiler, s: &Stmt) {
             level: level_opt, ..
         } => {
             let level = level_opt.unwrap_or(1) as usize;
+            // Function bodies start with an empty loop stack, so this also
+            // rejects a `continue` in a function nested inside a loop
+            if c.loop_stack.is_empty() {
+                c.error("continue outside of loop");
+            }
             if level > c.loop_stack.len() {
                 c.error(&format!(
-                    "continue {} exceeds loop nesting depth of {}",
+                    "continue level {} exceeds loop nesting depth of {}",
                     level,
                     c.loop_stack.len()
                 ));
@@ -1,12 +1,31 @@
 use super::compile::Compiler;
-use super::ir::{Constant, Instruction};
+use super::ir::{Constant, Instruction, JumpTable};
+use crate::ast::{Literal, Pattern, Stmt, TablePatternField};
 
 // Hidden names and global helper identifiers
 pub(super) const HIDDEN_MATCH_VAL: &str = "__match_val";
 pub(super) const HIDDEN_DESTRUCTURE_VAL: &str = "__destructure_val";
 pub(super) const HIDDEN_ITER: &str = "__iter";
 pub(super) const HIDDEN_I: &str = "__i";
+/// Length of the list a `for` loop walks, taken when the loop starts
+pub(super) const HIDDEN_LEN: &str = "__len";
+pub(super) const HIDDEN_LOOP_RESULT: &str = "__loop_result";
+/// Prefix for locals holding loop-invariant values; suffixed with the slot
+pub(super) const HIDDEN_HOISTED: &str = "__hoisted";
 pub(super) const GLOBAL_ITER_FN: &str = "iter";
+/// Iterator used when a `for` loop binds a single name: yields keys for tables
+pub(super) const GLOBAL_ITER_KEYS_FN: &str = "iter_keys";
+/// Field holding the variant name on tables built by tagged-union constructors
+pub(super) const ENUM_TAG_FIELD: &str = "__tag";
+/// Fewest integer literal arms worth dispatching through a jump table
+const MIN_JUMP_TABLE_ARMS: usize = 4;
+
+/// One step from a destructured value to a part of it
+#[derive(Clone)]
+enum PathStep {
+    Index(usize),
+    Key(String),
+}
 
 impl Compiler {
     // Stack/const helpers
@@ -40,6 +59,83 @@ impl Compiler {
         panic!("Compiler error: {msg}")
     }
 
+    // Shared: emit a match, leaving the selected arm's value (null if none matched)
+    pub(super) fn emit_match(
+        &mut self,
+        expr: &crate::ast::Expr,
+        arms: &[(crate::ast::Pattern, Vec<crate::ast::Stmt>)],
+    ) {
+        self.enter_scope();
+        // Evaluate the scrutinee once; every arm reads it from the hidden local
+        self.emit_expr(expr);
+        let match_val_slot = self.local_count;
+        self.bind_hidden_local(HIDDEN_MATCH_VAL.to_string(), match_val_slot);
+        self.local_count += 1;
+        // Leading dense integer literal arms dispatch in one step; any arms
+        // after them are tested as usual from the table's default target
+        let (mut end_jumps, rest) = match dense_number_arms(arms) {
+            Some(values) => {
+                let (table_arms, rest) = arms.split_at(values.len());
+                let end_jumps = self.emit_match_jump_table(match_val_slot, table_arms, &values);
+                (end_jumps, rest)
+            }
+            None => (Vec::new(), arms),
+        };
+        let mut exhaustive = false;
+        for (i, (pattern, body)) in rest.iter().enumerate() {
+            match self.emit_match_arm(match_val_slot, pattern, body, i == rest.len() - 1) {
+                Some(j) => end_jumps.push(j),
+                None => exhaustive = true,
+            }
+        }
+        if !exhaustive {
+            self.push_null();
+        }
+        let end_ip = self.current_ip();
+        for jump_pos in end_jumps {
+            self.patch_jump(jump_pos, end_ip);
+        }
+        self.exit_scope_with_preserve(true);
+    }
+
+    /// Emit a `MatchJump` over integer literal arms, followed by their bodies.
+    /// Values without an arm fall through to whatever is emitted next.
+    /// Returns the jumps to patch to the end of the match.
+    fn emit_match_jump_table(
+        &mut self,
+        match_val_slot: usize,
+        arms: &[(Pattern, Vec<Stmt>)],
+        values: &[i64],
+    ) -> Vec<usize> {
+        let low = *values.iter().min().unwrap();
+        let high = *values.iter().max().unwrap();
+        let table_idx = self.chunk.jump_tables.len();
+        self.chunk.jump_tables.push(JumpTable {
+            low,
+            targets: Vec::new(),
+            default: usize::MAX,
+        });
+        self.emit_get_local(match_val_slot);
+        self.chunk
+            .instructions
+            .push(Instruction::MatchJump(table_idx));
+
+        let mut targets = vec![None; (high - low + 1) as usize];
+        let mut end_jumps = Vec::new();
+        for ((_, body), value) in arms.iter().zip(values) {
+            // As with sequential tests, the first arm for a value wins
+            targets[(value - low) as usize].get_or_insert(self.current_ip());
+            self.emit_arm_body(body);
+            end_jumps.push(self.emit_jump());
+        }
+
+        let default = self.current_ip();
+        let table = &mut self.chunk.jump_tables[table_idx];
+        table.targets = targets.into_iter().map(|t| t.unwrap_or(default)).collect();
+        table.default = default;
+        end_jumps
+    }
+
     // Shared: emit a single match arm; returns optional jump index to patch at end
     pub(super) fn emit_match_arm(
         &mut self,
@@ -66,15 +162,7 @@ impl Compiler {
                     self.push_null();
                     self.chunk.instructions.push(Instruction::Ne);
                     let jf_next_arm = self.emit_jump_if_false();
-                    let arm_body = super::compile::apply_implicit_return_to_arm(body);
-                    for stmt in &arm_body {
-                        self.emit_stmt(stmt);
-                    }
-                    let arm_preserves = super::compile::does_block_leave_value(&arm_body);
-                    if !arm_preserves {
-                        let null_idx = super::compile::push_const(&mut self.chunk, Constant::Null);
-                        self.chunk.instructions.push(Instruction::Const(null_idx));
-                    }
+                    self.emit_arm_body(body);
                     let j = self.emit_jump();
                     let next_arm_ip = self.current_ip();
                     self.patch_jump(jf_next_arm, next_arm_ip);
@@ -90,32 +178,71 @@ impl Compiler {
                     }
                     self.chunk.instructions.push(Instruction::Eq);
                     let jf_next_arm = self.emit_jump_if_false();
-                    let arm_body = super::compile::apply_implicit_return_to_arm(body);
-                    for stmt in &arm_body {
-                        self.emit_stmt(stmt);
-                    }
-                    let arm_preserves = super::compile::does_block_leave_value(&arm_body);
-                    if !arm_preserves {
-                        self.push_null();
-                    }
+                    self.emit_arm_body(body);
                     let j = self.emit_jump();
                     let next_arm_ip = self.current_ip();
                     self.patch_jump(jf_next_arm, next_arm_ip);
                     Some(j)
                 }
+                Pattern::Variant { tag, fields, .. } => {
+                    // Dispatch on the tagged table's __tag field
+                    self.emit_get_local(match_val_slot);
+                    let tag_key_idx = super::compile::push_const(
+                        &mut self.chunk,
+                        Constant::String(ENUM_TAG_FIELD.to_string()),
+                    );
+                    self.chunk
+                        .instructions
+                        .push(Instruction::GetProp(tag_key_idx));
+                    self.push_string(tag.clone());
+                    self.chunk.instructions.push(Instruction::Eq);
+                    let jf_next_arm = self.emit_jump_if_false();
+                    let mut fails = vec![jf_next_arm];
+                    self.emit_field_tests(match_val_slot, &mut Vec::new(), fields, &mut fails);
+
+                    // Bind variant fields in an arm-local scope
+                    self.enter_scope();
+                    self.bind_pattern_locals(match_val_slot, &mut Vec::new(), pattern);
+                    self.emit_arm_body(body);
+                    self.exit_scope_with_preserve(true);
+                    let j = self.emit_jump();
+                    let next_arm_ip = self.current_ip();
+                    for fail in fails {
+                        self.patch_jump(fail, next_arm_ip);
+                    }
+                    Some(j)
+                }
                 Pattern::ListPattern { .. } | Pattern::TablePattern { .. } => {
-                    self.error("Structural patterns in match statements not yet fully supported");
+                    let mut fails = Vec::new();
+                    self.emit_pattern_tests(match_val_slot, &mut Vec::new(), pattern, &mut fails);
+                    self.enter_scope();
+                    self.bind_pattern_locals(match_val_slot, &mut Vec::new(), pattern);
+                    self.emit_arm_body(body);
+                    self.exit_scope_with_preserve(true);
+                    let j = self.emit_jump();
+                    let next_arm_ip = self.current_ip();
+                    for fail in fails {
+                        self.patch_jump(fail, next_arm_ip);
+                    }
+                    Some(j)
                 }
                 _ => None,
             }
         } else {
-            let arm_body = super::compile::apply_implicit_return_to_arm(body);
-            for stmt in &arm_body {
-                self.emit_stmt(stmt);
+            // A catch-all identifier (including `_ as name`) binds the scrutinee
+            let binding = match pattern {
+                Pattern::Ident { name, .. } => Some(name),
+                _ => None,
+            };
+            if let Some(name) = binding {
+                self.enter_scope();
+                self.emit_get_local(match_val_slot);
+                self.bind_hidden_local(name.clone(), self.local_count);
+                self.local_count += 1;
             }
-            let arm_preserves = super::compile::does_block_leave_value(&arm_body);
-            if !arm_preserves {
-                self.push_null();
+            self.emit_arm_body(body);
+            if binding.is_some() {
+                self.exit_scope_with_preserve(true);
             }
             if !is_last {
                 Some(self.emit_jump())
@@ -125,7 +252,16 @@ impl Compiler {
         }
     }
 
-    // Shared: destructuring for globals
+    // Shared: emit a match arm's body in its own scope, leaving its value
+    fn emit_arm_body(&mut self, body: &[crate::ast::Stmt]) {
+        let arm_body = super::compile::apply_implicit_return_to_arm(body);
+        self.enter_scope();
+        self.predeclare_function_locals(&arm_body);
+        self.emit_block_value(&arm_body);
+        self.exit_scope_with_preserve(true);
+    }
+
+    // Shared: destructuring for globals; consumes the value on top of the stack
     pub(super) fn emit_destructure_global(&mut self, pattern: &crate::ast::Pattern) {
         use crate::ast::Pattern;
         use crate::bytecode::ir::{Constant, Instruction};
@@ -133,28 +269,16 @@ impl Compiler {
         match pattern {
             Pattern::ListPattern { elements, rest, .. } => {
                 for (i, elem_pattern) in elements.iter().enumerate() {
-                    match elem_pattern {
-                        Pattern::Ident { name, .. } => {
-                            self.chunk.instructions.push(Instruction::Dup);
-                            self.push_number(i as f64);
-                            self.chunk.instructions.push(Instruction::GetIndex);
-                            let name_idx = super::compile::push_const(
-                                &mut self.chunk,
-                                Constant::String(name.clone()),
-                            );
-                            self.chunk
-                                .instructions
-                                .push(Instruction::SetGlobal(name_idx));
-                        }
-                        Pattern::Wildcard { .. } => {}
-                        _ => {
-                            self.error("Nested destructuring patterns not yet supported");
-                        }
+                    if matches!(elem_pattern, Pattern::Wildcard { .. }) {
+                        continue;
                     }
+                    self.chunk.instructions.push(Instruction::Dup);
+                    self.push_number(i as f64);
+                    self.chunk.instructions.push(Instruction::GetIndex);
+                    self.emit_destructure_global(elem_pattern);
                 }
                 if let Some(rest_name) = rest {
                     let start_index = elements.len();
-                    self.chunk.instructions.push(Instruction::Dup);
                     self.chunk
                         .instructions
                         .push(Instruction::SliceList(start_index));
@@ -169,7 +293,7 @@ impl Compiler {
                     self.chunk.instructions.push(Instruction::Pop);
                 }
             }
-            Pattern::TablePattern { fields, .. } => {
+            Pattern::TablePattern { fields, .. } | Pattern::Variant { fields, .. } => {
                 for field in fields {
                     self.chunk.instructions.push(Instruction::Dup);
                     let key_idx = super::compile::push_const(
@@ -177,14 +301,18 @@ impl Compiler {
                         Constant::String(field.key.clone()),
                     );
                     self.chunk.instructions.push(Instruction::GetProp(key_idx));
-                    let binding_name = field.binding.as_ref().unwrap_or(&field.key);
-                    let name_idx = super::compile::push_const(
-                        &mut self.chunk,
-                        Constant::String(binding_name.clone()),
-                    );
-                    self.chunk
-                        .instructions
-                        .push(Instruction::SetGlobal(name_idx));
+                    match &field.pattern {
+                        Some(nested) => self.emit_destructure_global(nested),
+                        None => {
+                            let name_idx = super::compile::push_const(
+                                &mut self.chunk,
+                                Constant::String(field.key.clone()),
+                            );
+                            self.chunk
+                                .instructions
+                                .push(Instruction::SetGlobal(name_idx));
+                        }
+                    }
                 }
                 self.chunk.instructions.push(Instruction::Pop);
             }
@@ -208,73 +336,166 @@ impl Compiler {
         value_slot: usize,
     ) {
         use crate::ast::Pattern;
-        use crate::bytecode::ir::{Constant, Instruction};
 
         match pattern {
+            Pattern::ListPattern { .. }
+            | Pattern::TablePattern { .. }
+            | Pattern::Variant { .. } => {
+                self.bind_pattern_locals(value_slot, &mut Vec::new(), pattern);
+            }
+            Pattern::Ident { name, .. } => {
+                let slot = self.local_count;
+                self.scopes.last_mut().unwrap().insert(name.clone(), slot);
+                self.local_count += 1;
+            }
+            Pattern::Wildcard { .. } | Pattern::Literal { .. } => {
+                self.local_count += 1;
+            }
+        }
+    }
+
+    /// Push the part of the value in `slot` that `path` leads to
+    fn emit_load_path(&mut self, slot: usize, path: &[PathStep]) {
+        self.emit_get_local(slot);
+        self.emit_path_steps(path);
+    }
+
+    /// Replace the value on top of the stack with the part `path` leads to
+    fn emit_path_steps(&mut self, path: &[PathStep]) {
+        for step in path {
+            match step {
+                PathStep::Index(i) => {
+                    self.push_number(*i as f64);
+                    self.chunk.instructions.push(Instruction::GetIndex);
+                }
+                PathStep::Key(key) => {
+                    let key_idx = self.push_const(Constant::String(key.clone()));
+                    self.chunk.instructions.push(Instruction::GetProp(key_idx));
+                }
+            }
+        }
+    }
+
+    /// Bind every variable in `pattern`, applied to the part of the value in
+    /// `slot` at `path`, as a new local in the current scope
+    fn bind_pattern_locals(&mut self, slot: usize, path: &mut Vec<PathStep>, pattern: &Pattern) {
+        match pattern {
+            Pattern::Ident { name, .. } => {
+                self.emit_load_path(slot, path);
+                self.bind_hidden_local(name.clone(), self.local_count);
+                self.local_count += 1;
+            }
             Pattern::ListPattern { elements, rest, .. } => {
-                for (i, elem_pattern) in elements.iter().enumerate() {
-                    match elem_pattern {
-                        Pattern::Ident { name, .. } => {
-                            self.chunk
-                                .instructions
-                                .push(Instruction::GetLocal(value_slot));
-                            self.push_number(i as f64);
-                            self.chunk.instructions.push(Instruction::GetIndex);
-                            let elem_slot = self.local_count;
-                            self.scopes
-                                .last_mut()
-                                .unwrap()
-                                .insert(name.clone(), elem_slot);
-                            self.local_count += 1;
-                        }
-                        Pattern::Wildcard { .. } => {}
-                        _ => {
-                            self.error("Nested destructuring patterns not yet supported");
-                        }
-                    }
+                for (i, element) in elements.iter().enumerate() {
+                    path.push(PathStep::Index(i));
+                    self.bind_pattern_locals(slot, path, element);
+                    path.pop();
                 }
                 if let Some(rest_name) = rest {
-                    let start_index = elements.len();
-                    self.chunk
-                        .instructions
-                        .push(Instruction::GetLocal(value_slot));
+                    self.emit_load_path(slot, path);
                     self.chunk
                         .instructions
-                        .push(Instruction::SliceList(start_index));
-                    let rest_slot = self.local_count;
-                    self.scopes
-                        .last_mut()
-                        .unwrap()
-                        .insert(rest_name.clone(), rest_slot);
+                        .push(Instruction::SliceList(elements.len()));
+                    self.bind_hidden_local(rest_name.clone(), self.local_count);
                     self.local_count += 1;
                 }
             }
+            Pattern::TablePattern { fields, .. } | Pattern::Variant { fields, .. } => {
+                for field in fields {
+                    path.push(PathStep::Key(field.key.clone()));
+                    match &field.pattern {
+                        Some(nested) => self.bind_pattern_locals(slot, path, nested),
+                        None => {
+                            self.emit_load_path(slot, path);
+                            self.bind_hidden_local(field.key.clone(), self.local_count);
+                            self.local_count += 1;
+                        }
+                    }
+                    path.pop();
+                }
+            }
+            Pattern::Wildcard { .. } | Pattern::Literal { .. } => {}
+        }
+    }
+
+    /// Emit the checks a match arm's `pattern` makes on the part of the value
+    /// in `slot` at `path`. Each check jumps away when it fails; those jumps
+    /// are added to `fails`. Names always match, so they emit nothing.
+    fn emit_pattern_tests(
+        &mut self,
+        slot: usize,
+        path: &mut Vec<PathStep>,
+        pattern: &Pattern,
+        fails: &mut Vec<usize>,
+    ) {
+        match pattern {
+            Pattern::Ident { .. } | Pattern::Wildcard { .. } => {}
+            Pattern::Literal { value, .. } => {
+                self.emit_load_path(slot, path);
+                match value {
+                    Literal::Number(n) => self.push_number(*n),
+                    Literal::String(s) => self.push_string(s.clone()),
+                    Literal::Boolean(b) => self.push_boolean(*b),
+                    Literal::Null => self.push_null(),
+                }
+                self.chunk.instructions.push(Instruction::Eq);
+                fails.push(self.emit_jump_if_false());
+            }
+            Pattern::ListPattern { elements, rest, .. } => {
+                self.emit_load_path(slot, path);
+                self.chunk
+                    .instructions
+                    .push(Instruction::MatchList(elements.len(), rest.is_some()));
+                fails.push(self.emit_jump_if_false());
+                for (i, element) in elements.iter().enumerate() {
+                    path.push(PathStep::Index(i));
+                    self.emit_pattern_tests(slot, path, element, fails);
+                    path.pop();
+                }
+            }
             Pattern::TablePattern { fields, .. } => {
                 for field in fields {
+                    self.emit_load_path(slot, path);
+                    let key_idx = self.push_const(Constant::String(field.key.clone()));
                     self.chunk
                         .instructions
-                        .push(Instruction::GetLocal(value_slot));
-                    let key_idx = super::compile::push_const(
-                        &mut self.chunk,
-                        Constant::String(field.key.clone()),
-                    );
-                    self.chunk.instructions.push(Instruction::GetProp(key_idx));
-                    let binding_name = field.binding.as_ref().unwrap_or(&field.key);
-                    let key_slot = self.local_count;
-                    self.scopes
-                        .last_mut()
-                        .unwrap()
-                        .insert(binding_name.clone(), key_slot);
-                    self.local_count += 1;
+                        .push(Instruction::MatchField(key_idx));
+                    fails.push(self.emit_jump_if_false());
                 }
+                self.emit_field_tests(slot, path, fields, fails);
             }
-            Pattern::Ident { name, .. } => {
-                let slot = self.local_count;
-                self.scopes.last_mut().unwrap().insert(name.clone(), slot);
-                self.local_count += 1;
+            Pattern::Variant { tag, fields, .. } => {
+                let tag_key_idx = self.push_const(Constant::String(ENUM_TAG_FIELD.to_string()));
+                self.emit_load_path(slot, path);
+                self.chunk
+                    .instructions
+                    .push(Instruction::MatchField(tag_key_idx));
+                fails.push(self.emit_jump_if_false());
+                self.emit_load_path(slot, path);
+                self.chunk
+                    .instructions
+                    .push(Instruction::GetProp(tag_key_idx));
+                self.push_string(tag.clone());
+                self.chunk.instructions.push(Instruction::Eq);
+                fails.push(self.emit_jump_if_false());
+                self.emit_field_tests(slot, path, fields, fails);
             }
-            Pattern::Wildcard { .. } | Pattern::Literal { .. } => {
-                self.local_count += 1;
+        }
+    }
+
+    /// Emit the checks made by fields that destructure their value further
+    fn emit_field_tests(
+        &mut self,
+        slot: usize,
+        path: &mut Vec<PathStep>,
+        fields: &[TablePatternField],
+        fails: &mut Vec<usize>,
+    ) {
+        for field in fields {
+            if let Some(nested) = &field.pattern {
+                path.push(PathStep::Key(field.key.clone()));
+                self.emit_pattern_tests(slot, path, nested, fails);
+                path.pop();
             }
         }
     }
@@ -292,117 +513,203 @@ pub(super) enum LoopPatDesc {
     Ident {
         slot: usize,
     },
-    List {
-        elem_slots: Vec<Option<usize>>,
-        rest_slot: Option<usize>,
+    /// Destructuring, to any depth: where in the element each variable's
+    /// value is found
+    Destructure {
+        bindings: Vec<LoopBinding>,
     },
 }
 
+/// A variable bound by a destructuring `for` pattern
+pub(super) struct LoopBinding {
+    path: Vec<PathStep>,
+    /// For a rest name, how many leading list elements to drop
+    rest_from: Option<usize>,
+    slot: usize,
+}
+
 impl Compiler {
     pub(super) fn prepare_loop_pattern(&mut self, pattern: &crate::ast::Pattern) -> LoopPatDesc {
-        use crate::ast::Pattern;
-        use crate::bytecode::ir::{Constant, Instruction};
-
         match pattern {
-            Pattern::Ident { name: var_name, .. } => {
-                let null_idx = super::compile::push_const(&mut self.chunk, Constant::Null);
-                self.chunk.instructions.push(Instruction::Const(null_idx));
-                let slot = self.local_count;
-                self.scopes
-                    .last_mut()
-                    .unwrap()
-                    .insert(var_name.clone(), slot);
-                self.local_count += 1;
-                LoopPatDesc::Ident { slot }
+            Pattern::Ident { name: var_name, .. } => LoopPatDesc::Ident {
+                slot: self.declare_loop_local(var_name),
+            },
+            _ => {
+                let mut bindings = Vec::new();
+                self.collect_loop_bindings(pattern, &mut Vec::new(), &mut bindings);
+                LoopPatDesc::Destructure { bindings }
             }
+        }
+    }
+
+    /// Declare a loop variable as a local initialized to null, returning its slot
+    fn declare_loop_local(&mut self, name: &str) -> usize {
+        self.push_null();
+        let slot = self.local_count;
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), slot);
+        self.local_count += 1;
+        slot
+    }
+
+    /// Declare a local for every variable in `pattern`, recording where in
+    /// the loop element each one's value is found
+    fn collect_loop_bindings(
+        &mut self,
+        pattern: &Pattern,
+        path: &mut Vec<PathStep>,
+        bindings: &mut Vec<LoopBinding>,
+    ) {
+        match pattern {
+            Pattern::Ident { name, .. } => bindings.push(LoopBinding {
+                path: path.clone(),
+                rest_from: None,
+                slot: self.declare_loop_local(name),
+            }),
             Pattern::ListPattern { elements, rest, .. } => {
-                let mut elem_slots: Vec<Option<usize>> = Vec::with_capacity(elements.len());
-                for elem in elements {
-                    match elem {
-                        Pattern::Ident { name, .. } => {
-                            self.push_null();
-                            let slot = self.local_count;
-                            self.scopes.last_mut().unwrap().insert(name.clone(), slot);
-                            self.local_count += 1;
-                            elem_slots.push(Some(slot));
-                        }
-                        Pattern::Wildcard { .. } => {
-                            elem_slots.push(None);
-                        }
-                        _ => {
-                            self.error("Nested patterns not yet supported in for destructuring");
-                        }
-                    }
+                for (i, element) in elements.iter().enumerate() {
+                    path.push(PathStep::Index(i));
+                    self.collect_loop_bindings(element, path, bindings);
+                    path.pop();
                 }
-                let rest_slot = if let Some(rest_name) = rest {
-                    self.push_null();
-                    let slot = self.local_count;
-                    self.scopes
-                        .last_mut()
-                        .unwrap()
-                        .insert(rest_name.clone(), slot);
-                    self.local_count += 1;
-                    Some(slot)
-                } else {
-                    None
-                };
-                LoopPatDesc::List {
-                    elem_slots,
-                    rest_slot,
+                if let Some(rest_name) = rest {
+                    bindings.push(LoopBinding {
+                        path: path.clone(),
+                        rest_from: Some(elements.len()),
+                        slot: self.declare_loop_local(rest_name),
+                    });
                 }
             }
-            _ => {
-                self.error("Unsupported pattern in for loop");
+            Pattern::TablePattern { fields, .. } | Pattern::Variant { fields, .. } => {
+                for field in fields {
+                    path.push(PathStep::Key(field.key.clone()));
+                    match &field.pattern {
+                        Some(nested) => self.collect_loop_bindings(nested, path, bindings),
+                        None => bindings.push(LoopBinding {
+                            path: path.clone(),
+                            rest_from: None,
+                            slot: self.declare_loop_local(&field.key),
+                        }),
+                    }
+                    path.pop();
+                }
             }
+            // Literals are rejected by the type checker outside `match`
+            Pattern::Wildcard { .. } | Pattern::Literal { .. } => {}
         }
     }
 
-    // Shared: assign current iter value to loop pattern bindings
-    pub(super) fn assign_loop_pattern_value(
-        &mut self,
-        desc: &LoopPatDesc,
-        iter_slot: usize,
-        i_slot: usize,
-    ) {
-        use crate::bytecode::ir::Instruction;
+    // Shared: bind the element `IterNext` left on the stack to the loop pattern
+    pub(super) fn assign_loop_pattern_value(&mut self, desc: &LoopPatDesc) {
         match desc {
             LoopPatDesc::Ident { slot } => {
-                self.chunk
-                    .instructions
-                    .push(Instruction::GetLocal(iter_slot));
-                self.chunk.instructions.push(Instruction::GetLocal(i_slot));
-                self.chunk.instructions.push(Instruction::GetIndex);
                 self.chunk.instructions.push(Instruction::SetLocal(*slot));
             }
-            LoopPatDesc::List {
-                elem_slots,
-                rest_slot,
-            } => {
-                self.chunk
-                    .instructions
-                    .push(Instruction::GetLocal(iter_slot));
-                self.chunk.instructions.push(Instruction::GetLocal(i_slot));
-                self.chunk.instructions.push(Instruction::GetIndex);
-                for (idx, slot_opt) in elem_slots.iter().enumerate() {
-                    if let Some(slot) = slot_opt {
-                        self.chunk.instructions.push(Instruction::Dup);
-                        self.push_number(idx as f64);
-                        self.chunk.instructions.push(Instruction::GetIndex);
-                        self.chunk.instructions.push(Instruction::SetLocal(*slot));
+            LoopPatDesc::Destructure { bindings } => {
+                for binding in bindings {
+                    self.chunk.instructions.push(Instruction::Dup);
+                    self.emit_path_steps(&binding.path);
+                    if let Some(start_index) = binding.rest_from {
+                        self.chunk
+                            .instructions
+                            .push(Instruction::SliceList(start_index));
                     }
-                }
-                if let Some(rest_slot) = rest_slot {
-                    let start_index = elem_slots.len();
-                    self.chunk
-                        .instructions
-                        .push(Instruction::SliceList(start_index));
                     self.chunk
                         .instructions
-                        .push(Instruction::SetLocal(*rest_slot));
-                } else {
-                    self.chunk.instructions.push(Instruction::Pop);
+                        .push(Instruction::SetLocal(binding.slot));
                 }
+                self.chunk.instructions.push(Instruction::Pop);
             }
         }
     }
 }
+
+/// Build the constructor table for a tagged-union declaration.
+///
+/// `type Shape = Circle { r: Number } | Square { s: Number }` becomes
+/// `{ Circle = fn(r: Number) do { __tag = "Circle", r = r } end, Square = ... }`,
+/// so constructing a variant is a plain call: `Shape.Circle(2)`.
+pub(super) fn enum_constructor_table(variants: &[crate::ast::EnumVariant]) -> crate::ast::Expr {
+    use crate::ast::{Argument, Expr, Stmt, TableKey};
+
+    let constructors = variants
+        .iter()
+        .map(|variant| {
+            let arguments = variant
+                .fields
+                .iter()
+                .map(|(name, ty)| Argument {
+                    name: name.clone(),
+                    r#type: ty.clone(),
+                    default: None,
+                    span: variant.span,
+                })
+                .collect();
+
+            let mut fields = vec![(
+                TableKey::Identifier(ENUM_TAG_FIELD.to_string()),
+                Expr::String {
+                    value: variant.name.clone(),
+                    span: variant.span,
+                },
+            )];
+            fields.extend(variant.fields.iter().map(|(name, _)| {
+                (
+                    TableKey::Identifier(name.clone()),
+                    Expr::Identifier {
+                        name: name.clone(),
+                        span: variant.span,
+                    },
+                )
+            }));
+
+            let constructor = Expr::Function {
+                arguments,
+                return_type: None,
+                body: vec![Stmt::Return {
+                    value: Expr::Table {
+                        fields,
+                        span: variant.span,
+                    },
+                    implicit: true,
+                    span: variant.span,
+                }],
+                span: variant.span,
+            };
+            (TableKey::Identifier(variant.name.clone()), constructor)
+        })
+        .collect();
+
+    let span = match (variants.first(), variants.last()) {
+        (Some(first), Some(last)) => first.span.zip(last.span).map(|(f, l)| f.merge(&l)),
+        _ => None,
+    };
+    Expr::Table {
+        fields: constructors,
+        span,
+    }
+}
+
+/// Values of a match's leading integer literal arms, when there are enough of
+/// them and they cover at least half of their range. Later arms are still
+/// tested in order, so they may use any pattern.
+fn dense_number_arms(arms: &[(Pattern, Vec<Stmt>)]) -> Option<Vec<i64>> {
+    let values: Vec<i64> = arms
+        .iter()
+        .map_while(|(pattern, _)| match pattern {
+            // Beyond 2^53 integers are no longer exact, so leave those alone
+            Pattern::Literal {
+                value: Literal::Number(n),
+                ..
+            } if n.fract() == 0.0 && n.abs() <= 9_007_199_254_740_992.0 => Some(*n as i64),
+            _ => None,
+        })
+        .collect();
+    let mut distinct = values.clone();
+    distinct.sort_unstable();
+    distinct.dedup();
+    let span = distinct.last()? - distinct.first()? + 1;
+    (distinct.len() >= MIN_JUMP_TABLE_ARMS && span <= 2 * distinct.len() as i64).then_some(values)
+}
@@ -0,0 +1,564 @@
+//! Loop-invariant code motion.
+//!
+//! Before compiling a loop, the compiler looks for subexpressions in its
+//! condition and body whose operands cannot change between iterations. It
+//! computes them once into hidden locals and reads the local inside the loop
+//! instead.
+//!
+//! This is deliberately conservative:
+//! - Only operators whose operands are known to be numbers, strings or
+//!   booleans qualify, so an operator can never dispatch to a user
+//!   `__add`/`__lt`/... metamethod or raise an error. The compiler has no
+//!   types, so a name is only known when it is bound by `let` to such an
+//!   expression and never reassigned. `/` and `%` are skipped entirely.
+//! - Only expressions evaluated on every iteration are considered: the
+//!   condition outside short-circuit right operands and branches, and the
+//!   leading body statements up to the first one that may branch.
+//! - Loops containing calls, function literals or imports are left alone,
+//!   because those could reassign captured variables behind our back.
+//! - Any name bound or assigned anywhere in the loop is treated as variant.
+//!
+//! Hoisted values are computed in a preheader that only runs once the loop is
+//! known to iterate: after the first condition check of a `while` and the
+//! first element of a `for`. A `do ... while` body always runs once, so its
+//! preheader sits directly before the loop.
+
+use std::collections::HashSet;
+
+use super::compile::Compiler;
+use super::helpers::HIDDEN_HOISTED;
+use super::ir::Instruction;
+use crate::ast::{BinaryOp, Expr, Pattern, Stmt, TableKey, UnaryOp};
+
+/// The primitive type an expression is statically known to produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Primitive {
+    Number,
+    String,
+    Boolean,
+}
+
+/// Hidden locals reserved for a loop's invariants, filled in by
+/// `emit_loop_preheader`.
+pub(super) struct Hoisted<'a> {
+    exprs: Vec<(&'a Expr, usize)>,
+}
+
+impl Hoisted<'_> {
+    #[cfg(test)]
+    pub(super) fn len(&self) -> usize {
+        self.exprs.len()
+    }
+}
+
+impl Compiler {
+    /// Reserve hidden locals for the loop's invariant subexpressions.
+    /// Returns None when nothing qualifies; otherwise a scope is opened that
+    /// the caller closes with `exit_hoisted_scope` after the loop.
+    ///
+    /// `conditions_after_body` is set for `do ... while`: its condition only
+    /// runs on every iteration when nothing in the body can skip it.
+    pub(super) fn reserve_loop_invariants<'a>(
+        &mut self,
+        conditions: &[&'a Expr],
+        body: &'a [Stmt],
+        pattern: Option<&Pattern>,
+        conditions_after_body: bool,
+    ) -> Option<Hoisted<'a>> {
+        let known = |name: &str| self.known_primitive(name);
+        let invariants: Vec<&Expr> =
+            find_invariants(conditions, body, pattern, conditions_after_body, &known)
+                .into_iter()
+                .filter(|e| !self.hoisted.contains_key(&(*e as *const Expr)))
+                .collect();
+        if invariants.is_empty() {
+            return None;
+        }
+
+        self.enter_scope();
+        let mut exprs = Vec::new();
+        for expr in invariants {
+            self.push_null();
+            let slot = self.local_count;
+            self.bind_hidden_local(format!("{HIDDEN_HOISTED}{slot}"), slot);
+            self.local_count += 1;
+            exprs.push((expr, slot));
+        }
+        Some(Hoisted { exprs })
+    }
+
+    /// Compute the reserved invariants. Reads inside the loop emitted after
+    /// this point use the hidden locals.
+    pub(super) fn emit_loop_preheader(&mut self, hoisted: &Hoisted) {
+        for &(expr, slot) in &hoisted.exprs {
+            self.emit_expr(expr);
+            self.chunk.instructions.push(Instruction::SetLocal(slot));
+            self.hoisted.insert(expr as *const Expr, slot);
+        }
+    }
+
+    /// Drop the hidden locals created by `reserve_loop_invariants`.
+    pub(super) fn exit_hoisted_scope(&mut self) {
+        if let Some(scope) = self.scopes.last() {
+            let slots: HashSet<usize> = scope.values().copied().collect();
+            self.hoisted.retain(|_, slot| !slots.contains(slot));
+        }
+        self.exit_scope_with_preserve(false);
+    }
+
+    /// Emit a read of a hoisted value if `expr` was computed ahead of its loop.
+    pub(super) fn emit_hoisted(&mut self, expr: &Expr) -> bool {
+        match self.hoisted.get(&(expr as *const Expr)) {
+            Some(&slot) => {
+                self.chunk.instructions.push(Instruction::GetLocal(slot));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The primitive type a binding of `value` is known to keep. Only
+    /// immutable bindings qualify.
+    pub(super) fn binding_primitive(&self, mutable: bool, value: &Expr) -> Option<Primitive> {
+        if mutable {
+            return None;
+        }
+        let known = |name: &str| self.known_primitive(name);
+        primitive_of(value, &HashSet::new(), &known)
+    }
+
+    /// Remember the primitive type of the binding just made for `name`.
+    pub(super) fn record_binding(&mut self, name: &str, primitive: Option<Primitive>) {
+        let Some(primitive) = primitive else {
+            self.forget_primitive(name);
+            return;
+        };
+        match self.lookup_local(name) {
+            Some(slot) => {
+                self.primitive_locals.insert(slot, primitive);
+            }
+            None => {
+                self.primitive_globals.insert(name.to_string(), primitive);
+            }
+        }
+    }
+
+    /// Forget what is known about every name bound by `pattern`.
+    pub(super) fn forget_pattern_primitives(&mut self, pattern: &Pattern) {
+        let mut names = HashSet::new();
+        bind_pattern(pattern, &mut names);
+        for name in names {
+            self.forget_primitive(&name);
+        }
+    }
+
+    /// Forget what is known about `name`, wherever it resolves.
+    pub(super) fn forget_primitive(&mut self, name: &str) {
+        if let Some(slot) = self.lookup_local(name) {
+            self.primitive_locals.remove(&slot);
+            return;
+        }
+        match self.parent.as_mut() {
+            Some(parent) => parent.forget_primitive(name),
+            None => {
+                self.primitive_globals.remove(name);
+            }
+        }
+    }
+
+    /// The primitive type `name` is known to hold here, if any. Captured
+    /// variables are never known, since a closure only sees the binding.
+    fn known_primitive(&self, name: &str) -> Option<Primitive> {
+        if let Some(slot) = self.lookup_local(name) {
+            return self.primitive_locals.get(&slot).copied();
+        }
+        let mut root = self;
+        while let Some(parent) = root.parent.as_deref() {
+            if parent.lookup_local(name).is_some() {
+                return None;
+            }
+            root = parent;
+        }
+        root.primitive_globals.get(name).copied()
+    }
+}
+
+/// Find the maximal invariant subexpressions of a loop, in evaluation order.
+pub(super) fn find_invariants<'a>(
+    conditions: &[&'a Expr],
+    body: &'a [Stmt],
+    pattern: Option<&Pattern>,
+    conditions_after_body: bool,
+    known: &dyn Fn(&str) -> Option<Primitive>,
+) -> Vec<&'a Expr> {
+    let mut scan = Scan::default();
+    if let Some(pattern) = pattern {
+        bind_pattern(pattern, &mut scan.written);
+    }
+    for cond in conditions {
+        scan.visit_expr(cond);
+    }
+    scan.visit_block(body);
+    if scan.impure {
+        return Vec::new();
+    }
+
+    let ctx = Collect {
+        written: &scan.written,
+        known,
+    };
+    let mut found = Vec::new();
+    if !conditions_after_body {
+        for cond in conditions {
+            ctx.expr(cond, &mut found);
+        }
+    }
+    let straight = ctx.block(body, &mut found);
+    if conditions_after_body && straight {
+        for cond in conditions {
+            ctx.expr(cond, &mut found);
+        }
+    }
+    found
+}
+/// Names written inside the loop, and whether it contains anything that
+/// could change state we cannot see.
+#[derive(Default)]
+struct Scan {
+    written: HashSet<String>,
+    impure: bool,
+}
+
+impl Scan {
+    fn visit_block(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.visit_stmt(stmt);
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VarDecl { name, value, .. } => {
+                self.written.insert(name.clone());
+                self.visit_expr(value);
+            }
+            Stmt::DestructuringVarDecl { pattern, value, .. } => {
+                bind_pattern(pattern, &mut self.written);
+                self.visit_expr(value);
+            }
+            Stmt::Assignment { target, value, .. } => {
+                if let Expr::Identifier { name, .. } = target {
+                    self.written.insert(name.clone());
+                }
+                self.visit_expr(target);
+                self.visit_expr(value);
+            }
+            Stmt::If {
+                condition,
+                then_block,
+                elif_blocks,
+                else_block,
+                ..
+            } => {
+                self.visit_expr(condition);
+                self.visit_block(then_block);
+                for (cond, block) in elif_blocks {
+                    self.visit_expr(cond);
+                    self.visit_block(block);
+                }
+                if let Some(block) = else_block {
+                    self.visit_block(block);
+                }
+            }
+            Stmt::While {
+                condition, body, ..
+            }
+            | Stmt::DoWhile {
+                condition, body, ..
+            } => {
+                self.visit_expr(condition);
+                self.visit_block(body);
+            }
+            Stmt::For {
+                pattern,
+                iterator,
+                body,
+                ..
+            } => {
+                bind_pattern(pattern, &mut self.written);
+                self.visit_expr(iterator);
+                self.visit_block(body);
+            }
+            Stmt::Return { value, .. } | Stmt::ExprStmt { expr: value, .. } => {
+                self.visit_expr(value)
+            }
+            Stmt::Break { value, .. } => {
+                if let Some(value) = value {
+                    self.visit_expr(value);
+                }
+            }
+            Stmt::Match { expr, arms, .. } => {
+                self.visit_expr(expr);
+                self.visit_arms(arms);
+            }
+            Stmt::TypeDecl { name, .. } => {
+                self.written.insert(name.clone());
+            }
+            Stmt::Continue { .. } | Stmt::Export { .. } => {}
+        }
+    }
+
+    fn visit_arms(&mut self, arms: &[(Pattern, Vec<Stmt>)]) {
+        for (pattern, body) in arms {
+            bind_pattern(pattern, &mut self.written);
+            self.visit_block(body);
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Call { .. }
+            | Expr::MethodCall { .. }
+            | Expr::Function { .. }
+            | Expr::Import { .. } => self.impure = true,
+            Expr::Number { .. }
+            | Expr::String { .. }
+            | Expr::Boolean { .. }
+            | Expr::Null { .. }
+            | Expr::Identifier { .. } => {}
+            Expr::List { elements, .. } => elements.iter().for_each(|e| self.visit_expr(e)),
+            Expr::Table { fields, .. } => {
+                for (key, value) in fields {
+                    if let TableKey::Computed(k) = key {
+                        self.visit_expr(k);
+                    }
+                    self.visit_expr(value);
+                }
+            }
+            Expr::Binary { left, right, .. }
+            | Expr::Logical { left, right, .. }
+            | Expr::Range {
+                start: left,
+                end: right,
+                ..
+            } => {
+                self.visit_expr(left);
+                self.visit_expr(right);
+            }
+            Expr::Unary { operand, .. } | Expr::Spread { value: operand, .. } => {
+                self.visit_expr(operand)
+            }
+            Expr::MemberAccess { object, .. } => self.visit_expr(object),
+            Expr::Index { object, index, .. } => {
+                self.visit_expr(object);
+                self.visit_expr(index);
+            }
+            Expr::If {
+                condition,
+                then_block,
+                else_block,
+                ..
+            } => {
+                self.visit_expr(condition);
+                self.visit_block(then_block);
+                if let Some(block) = else_block {
+                    self.visit_block(block);
+                }
+            }
+            Expr::Block { statements, .. } => self.visit_block(statements),
+            Expr::Match { expr, arms, .. } => {
+                self.visit_expr(expr);
+                self.visit_arms(arms);
+            }
+            Expr::Loop { body, .. } => self.visit_stmt(body),
+        }
+    }
+}
+
+fn bind_pattern(pattern: &Pattern, names: &mut HashSet<String>) {
+    match pattern {
+        Pattern::Ident { name, .. } => {
+            names.insert(name.clone());
+        }
+        Pattern::ListPattern { elements, rest, .. } => {
+            for element in elements {
+                bind_pattern(element, names);
+            }
+            if let Some(rest) = rest {
+                names.insert(rest.clone());
+            }
+        }
+        Pattern::TablePattern { fields, .. } | Pattern::Variant { fields, .. } => {
+            for field in fields {
+                match &field.pattern {
+                    Some(pattern) => bind_pattern(pattern, names),
+                    None => {
+                        names.insert(field.key.clone());
+                    }
+                }
+            }
+        }
+        Pattern::Wildcard { .. } | Pattern::Literal { .. } => {}
+    }
+}
+
+/// The primitive type `expr` produces when it is invariant and cannot fail or
+/// dispatch to a metamethod, or None.
+fn primitive_of(
+    expr: &Expr,
+    written: &HashSet<String>,
+    known: &dyn Fn(&str) -> Option<Primitive>,
+) -> Option<Primitive> {
+    use Primitive::*;
+    match expr {
+        Expr::Number { .. } => Some(Number),
+        Expr::String { .. } => Some(String),
+        Expr::Boolean { .. } => Some(Boolean),
+        Expr::Identifier { name, .. } if !written.contains(name) => known(name),
+        Expr::Binary {
+            left, op, right, ..
+        } => {
+            let left = primitive_of(left, written, known)?;
+            let right = primitive_of(right, written, known)?;
+            match (op, left, right) {
+                (BinaryOp::Add, Number, Number) | (BinaryOp::Add, String, String) => Some(left),
+                (BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Pow, Number, Number) => Some(Number),
+                (BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge, Number, Number) => {
+                    Some(Boolean)
+                }
+                (BinaryOp::Eq | BinaryOp::Ne, _, _) if left == right => Some(Boolean),
+                _ => None,
+            }
+        }
+        Expr::Logical { left, right, .. } => {
+            let left = primitive_of(left, written, known)?;
+            let right = primitive_of(right, written, known)?;
+            (left == Boolean && right == Boolean).then_some(Boolean)
+        }
+        Expr::Unary { op, operand, .. } => match (op, primitive_of(operand, written, known)?) {
+            (UnaryOp::Neg, Number) => Some(Number),
+            (UnaryOp::Not, Boolean) => Some(Boolean),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Only operator expressions are worth a local; literals and names are already cheap.
+fn worth_hoisting(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Binary { .. } | Expr::Logical { .. } | Expr::Unary { .. }
+    )
+}
+
+/// Collects invariants from the parts of a loop that run on every iteration.
+struct Collect<'s> {
+    written: &'s HashSet<String>,
+    known: &'s dyn Fn(&str) -> Option<Primitive>,
+}
+
+impl Collect<'_> {
+    /// Collect from the leading statements that always run in order, stopping
+    /// at the first one that may branch. Returns true when the whole block
+    /// is straight-line.
+    fn block<'a>(&self, stmts: &'a [Stmt], found: &mut Vec<&'a Expr>) -> bool {
+        for stmt in stmts {
+            let value = match stmt {
+                Stmt::VarDecl { value, .. }
+                | Stmt::DestructuringVarDecl { value, .. }
+                | Stmt::Assignment { value, .. }
+                | Stmt::ExprStmt { expr: value, .. } => value,
+                _ => return false,
+            };
+            if !straight_line(value) {
+                return false;
+            }
+            self.expr(value, found);
+        }
+        true
+    }
+
+    /// Collect from the parts of `expr` that are evaluated whenever it is.
+    fn expr<'a>(&self, expr: &'a Expr, found: &mut Vec<&'a Expr>) {
+        if worth_hoisting(expr) && primitive_of(expr, self.written, self.known).is_some() {
+            found.push(expr);
+            return;
+        }
+        match expr {
+            Expr::List { elements, .. } => {
+                for e in elements {
+                    self.expr(e, found);
+                }
+            }
+            Expr::Table { fields, .. } => {
+                for (key, value) in fields {
+                    if let TableKey::Computed(k) = key {
+                        self.expr(k, found);
+                    }
+                    self.expr(value, found);
+                }
+            }
+            Expr::Binary { left, right, .. }
+            | Expr::Range {
+                start: left,
+                end: right,
+                ..
+            } => {
+                self.expr(left, found);
+                self.expr(right, found);
+            }
+            // The right operand is skipped by short-circuiting
+            Expr::Logical { left, .. } => self.expr(left, found),
+            Expr::Unary { operand, .. } | Expr::Spread { value: operand, .. } => {
+                self.expr(operand, found)
+            }
+            Expr::MemberAccess { object, .. } => self.expr(object, found),
+            Expr::Index { object, index, .. } => {
+                self.expr(object, found);
+                self.expr(index, found);
+            }
+            // Only the condition or scrutinee is certain to run
+            Expr::If { condition, .. } => self.expr(condition, found),
+            Expr::Match { expr, .. } => self.expr(expr, found),
+            Expr::Block { .. }
+            | Expr::Loop { .. }
+            | Expr::Call { .. }
+            | Expr::MethodCall { .. }
+            | Expr::Number { .. }
+            | Expr::String { .. }
+            | Expr::Boolean { .. }
+            | Expr::Null { .. }
+            | Expr::Identifier { .. }
+            | Expr::Function { .. }
+            | Expr::Import { .. } => {}
+        }
+    }
+}
+
+/// Whether evaluating `expr` always runs to completion without branching
+/// past the statements that follow it, e.g. through a `break` in an `if`.
+fn straight_line(expr: &Expr) -> bool {
+    match expr {
+        Expr::If { .. } | Expr::Match { .. } | Expr::Block { .. } | Expr::Loop { .. } => false,
+        Expr::List { elements, .. } => elements.iter().all(straight_line),
+        Expr::Table { fields, .. } => fields.iter().all(|(key, value)| {
+            let key_ok = match key {
+                TableKey::Computed(k) => straight_line(k),
+                _ => true,
+            };
+            key_ok && straight_line(value)
+        }),
+        Expr::Binary { left, right, .. }
+        | Expr::Logical { left, right, .. }
+        | Expr::Range {
+            start: left,
+            end: right,
+            ..
+        } => straight_line(left) && straight_line(right),
+        Expr::Unary { operand, .. } | Expr::Spread { value: operand, .. } => straight_line(operand),
+        Expr::MemberAccess { object, .. } => straight_line(object),
+        Expr::Index { object, index, .. } => straight_line(object) && straight_line(index),
+        _ => true,
+    }
+}
@@ -1,5 +1,6 @@
 use crate::ast::Span;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Describes where an upvalue is captured from
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,7 +17,7 @@ pub enum Constant {
     String(String),
     Boolean(bool),
     Null,
-    Function(Chunk),
+    Function(Box<Chunk>),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +28,7 @@ pub enum Instruction {
     Mul,
     Div,
     Mod,
+    Pow,
     Neg, // Unary negation
     Eq,
     Ne,
@@ -39,12 +41,17 @@ pub enum Instruction {
     // Pop N items but preserve the previous top-of-stack value
     PopNPreserve(usize),
     Dup,
+    Dup2, // duplicates the top two values, keeping their order
     Jump(usize),
     JumpIfFalse(usize),
     GetGlobal(usize),  // const string name index
     SetGlobal(usize),  // const string name index, pops value
     BuildList(usize),  // n
+    BuildRange(bool), // pops end and start, pushes the list of numbers between (end included if set)
+    RangeBounds(bool), // pops end and start, checked as for BuildRange, pushes start and the element count
     BuildTable(usize), // n pairs
+    MergeTable,        // pops source table, copies its fields into the table below
+    SpreadList,        // pops source list, appends its elements to the list below
     GetIndex,          // pops index and object, pushes value
     GetProp(usize),    // const string name index
     GetLen,            // pops list or table, pushes Number (length)
@@ -58,9 +65,38 @@ pub enum Instruction {
     GetUpvalue(usize),   // get upvalue at index
     SetUpvalue(usize),   // set upvalue at index
     Call(usize),         // arity (number of arguments)
+    CallList,            // pops argument list, calls the function below with its elements
     Return,              // return top of stack
     Halt,
-    Import, // pops path string, pushes module value
+    Import,                 // pops path string, pushes module value
+    MatchJump(usize),       // index into the chunk's jump tables, pops the value to dispatch on
+    IterNext(usize),        // pops length, index and iterable, pushes next element or jumps at end
+    MatchField(usize), // const string name index; pops value, pushes whether it is a table with that field
+    MatchList(usize, bool), // pops value, pushes whether it is a list of that length (or longer, if set)
+}
+
+/// Targets of a `MatchJump`: an integer `n` in `low..low + targets.len()`
+/// jumps to `targets[n - low]`, and any other value jumps to `default`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JumpTable {
+    pub low: i64,
+    pub targets: Vec<usize>,
+    pub default: usize,
+}
+
+impl JumpTable {
+    /// Where a value dispatches to
+    pub fn target(&self, value: f64) -> usize {
+        if value.fract() != 0.0 || !value.is_finite() {
+            return self.default;
+        }
+        (value as i64)
+            .checked_sub(self.low)
+            .and_then(|offset| usize::try_from(offset).ok())
+            .and_then(|offset| self.targets.get(offset))
+            .copied()
+            .unwrap_or(self.default)
+    }
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -72,11 +108,29 @@ pub struct Chunk {
     /// Describes which upvalues this chunk needs, in order
     /// Each upvalue descriptor tells us how to capture the value when creating a closure
     pub upvalue_descriptors: Vec<UpvalueDescriptor>,
+    /// Source names of the captured variables, parallel to `upvalue_descriptors`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub upvalue_names: Vec<String>,
     /// Maps instruction index to source span (parallel to instructions)
     /// None indicates no source location available
     #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde(default)]
     pub spans: Vec<Option<Span>>,
+    /// Maps the index of a `Call` instruction to the callee's source name,
+    /// when the callee is an identifier, member access, or method
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default)]
+    pub call_names: HashMap<usize, String>,
+    /// Tables referenced by `MatchJump` instructions
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub jump_tables: Vec<JumpTable>,
+    /// Source file the chunk was compiled from, when it differs from the
+    /// file the VM is running (functions of an imported module)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub file: Option<String>,
 }
 
 impl Chunk {
@@ -88,7 +142,21 @@ impl Chunk {
             local_count: 0,
             name,
             upvalue_descriptors: vec![],
+            upvalue_names: vec![],
             spans: vec![None], // One span for the Halt instruction
+            call_names: HashMap::new(),
+            jump_tables: vec![],
+            file: None,
+        }
+    }
+
+    /// Mark this chunk and every function nested in it as compiled from `file`
+    pub fn set_file(&mut self, file: &str) {
+        self.file = Some(file.to_string());
+        for constant in &mut self.constants {
+            if let Constant::Function(chunk) = constant {
+                chunk.set_file(file);
+            }
         }
     }
 
@@ -97,6 +165,11 @@ impl Chunk {
         self.spans.get(ip).and_then(|&s| s)
     }
 
+    /// Get the callee name recorded for the `Call` instruction at `ip`
+    pub fn get_call_name(&self, ip: usize) -> Option<&str> {
+        self.call_names.get(&ip).map(String::as_str)
+    }
+
     /// Push an instruction with an optional span
     pub fn push_instruction(&mut self, instr: Instruction, span: Option<Span>) {
         self.instructions.push(instr);
@@ -1,5 +1,8 @@
 pub mod compile;
+pub mod disasm;
 pub mod emit_expr;
 pub mod emit_stmt;
 pub mod helpers;
+pub mod hoist;
 pub mod ir;
+pub mod verify;
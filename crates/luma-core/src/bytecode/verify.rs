@@ -0,0 +1,243 @@
+//! Static stack-depth checking for compiled bytecode.
+//!
+//! `check_stack_balance` walks every path through a chunk, tracking how many
+//! values each instruction leaves on the stack. A miscompilation shows up as an
+//! instruction that would pop more than is there, two paths reaching the same
+//! instruction with different depths, or a path that runs off the end of the
+//! chunk. Depths are relative to the frame: a function chunk starts with its
+//! parameters on the stack, the program chunk with nothing.
+
+use super::ir::{Chunk, Constant, Instruction};
+use std::fmt;
+
+/// A stack-balance problem found in a chunk
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackIssue {
+    /// Name of the chunk containing the instruction
+    pub chunk: String,
+    /// Index of the offending instruction
+    pub ip: usize,
+    pub message: String,
+}
+
+impl fmt::Display for StackIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} @ {:04}: {}", self.chunk, self.ip, self.message)
+    }
+}
+
+/// Check a chunk and every nested function chunk for stack imbalances.
+pub fn check_stack_balance(chunk: &Chunk) -> Result<(), Vec<StackIssue>> {
+    let mut issues = Vec::new();
+    check_chunk(chunk, 0, &mut issues);
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(issues)
+    }
+}
+
+fn check_chunk(chunk: &Chunk, entry_depth: usize, issues: &mut Vec<StackIssue>) {
+    let len = chunk.instructions.len();
+    let mut depth_at: Vec<Option<usize>> = vec![None; len];
+    let mut pending = vec![(0, entry_depth)];
+    let issue = |ip: usize, message: String| StackIssue {
+        chunk: chunk.name.clone(),
+        ip,
+        message,
+    };
+
+    while let Some((ip, depth)) = pending.pop() {
+        if ip >= len {
+            issues.push(issue(ip, "execution runs past the end of the chunk".into()));
+            continue;
+        }
+        match depth_at[ip] {
+            Some(seen) if seen == depth => continue,
+            Some(seen) => {
+                issues.push(issue(
+                    ip,
+                    format!("reached with stack depth {depth} and {seen} on different paths"),
+                ));
+                continue;
+            }
+            None => depth_at[ip] = Some(depth),
+        }
+
+        let instr = &chunk.instructions[ip];
+        let (pops, pushes) = stack_effect(instr);
+        if depth < pops {
+            issues.push(issue(
+                ip,
+                format!("{instr:?} needs {pops} values but the stack holds {depth}"),
+            ));
+            continue;
+        }
+        if let Instruction::GetLocal(slot) | Instruction::SetLocal(slot) = instr
+            && *slot >= depth - pops
+        {
+            issues.push(issue(
+                ip,
+                format!(
+                    "{instr:?} refers past the {} values on the stack",
+                    depth - pops
+                ),
+            ));
+        }
+        let next = depth - pops + pushes;
+
+        match instr {
+            Instruction::Jump(target) => pending.push((*target, next)),
+            Instruction::JumpIfFalse(target) => {
+                pending.push((*target, next));
+                pending.push((ip + 1, next));
+            }
+            // The element is only pushed when the loop continues
+            Instruction::IterNext(target) => {
+                pending.push((*target, next - 1));
+                pending.push((ip + 1, next));
+            }
+            Instruction::MatchJump(idx) => match chunk.jump_tables.get(*idx) {
+                Some(table) => {
+                    for &target in table.targets.iter().chain([&table.default]) {
+                        pending.push((target, next));
+                    }
+                }
+                None => issues.push(issue(ip, format!("no jump table {idx}"))),
+            },
+            Instruction::Return | Instruction::Halt => {}
+            _ => pending.push((ip + 1, next)),
+        }
+    }
+
+    for constant in &chunk.constants {
+        if let Constant::Function(nested) = constant {
+            check_chunk(nested, nested.local_count as usize, issues);
+        }
+    }
+}
+
+/// Number of values an instruction pops and pushes
+fn stack_effect(instr: &Instruction) -> (usize, usize) {
+    use Instruction::*;
+    match instr {
+        Const(_) | GetGlobal(_) | GetLocal(_) | GetUpvalue(_) | MakeFunction(_) | Closure(_) => {
+            (0, 1)
+        }
+        Add | Sub | Mul | Div | Mod | Pow | Eq | Ne | Lt | Le | Gt | Ge | GetIndex => (2, 1),
+        IterNext(_) => (3, 1),
+        // `.into` also leaves the receiver under its bound native, but the
+        // following `Call` pops that extra value, so it nets out like any prop
+        Neg | Not | GetLen | SliceList(_) | Import | GetProp(_) | MatchField(_) | MatchList(..) => {
+            (1, 1)
+        }
+        Pop | SetGlobal(_) | SetLocal(_) | SetUpvalue(_) | JumpIfFalse(_) | MatchJump(_) => (1, 0),
+        PopNPreserve(n) => (n + 1, 1),
+        Dup => (1, 2),
+        Dup2 => (2, 4),
+        RangeBounds(_) => (2, 2),
+        Jump(_) | Halt => (0, 0),
+        BuildList(n) => (*n, 1),
+        BuildTable(n) => (2 * n, 1),
+        MergeTable | SpreadList | BuildRange(_) | CallList => (2, 1),
+        SetIndex => (3, 0),
+        SetProp(_) => (2, 0),
+        Call(arity) => (arity + 1, 1),
+        Return => (1, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::compile::compile_program;
+    use crate::parser::parse;
+    use Instruction::*;
+
+    fn chunk(instructions: Vec<Instruction>) -> Chunk {
+        Chunk {
+            name: "test".to_string(),
+            instructions,
+            constants: vec![Constant::Number(1.0)],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_balanced_chunk_passes() {
+        let ok = chunk(vec![Const(0), Const(0), Add, Halt]);
+        assert_eq!(check_stack_balance(&ok), Ok(()));
+    }
+
+    #[test]
+    fn test_underflow_detected() {
+        let bad = chunk(vec![Const(0), Add, Halt]);
+        let issues = check_stack_balance(&bad).unwrap_err();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].ip, 1);
+        assert_eq!(
+            issues[0].message,
+            "Add needs 2 values but the stack holds 1"
+        );
+    }
+
+    #[test]
+    fn test_mismatched_branch_depths_detected() {
+        // Both arms push one value before meeting at the Halt
+        let ok = chunk(vec![
+            Const(0),
+            JumpIfFalse(4),
+            Const(0),
+            Jump(5),
+            Const(0),
+            Halt,
+        ]);
+        assert_eq!(check_stack_balance(&ok), Ok(()));
+
+        // The fall-through path pushes a value the jump skips
+        let bad = chunk(vec![Const(0), JumpIfFalse(3), Const(0), Const(0), Halt]);
+        let issues = check_stack_balance(&bad).unwrap_err();
+        assert_eq!(issues[0].ip, 3);
+        assert!(issues[0].message.contains("different paths"), "{issues:?}");
+    }
+
+    #[test]
+    fn test_running_off_the_end_and_bad_locals_detected() {
+        let issues = check_stack_balance(&chunk(vec![Const(0)])).unwrap_err();
+        assert!(issues[0].message.contains("past the end"), "{issues:?}");
+
+        let issues = check_stack_balance(&chunk(vec![GetLocal(0), Halt])).unwrap_err();
+        assert!(issues[0].message.contains("refers past"), "{issues:?}");
+    }
+
+    #[test]
+    fn test_nested_function_chunks_checked() {
+        let mut inner = chunk(vec![Pop, Return]);
+        inner.name = "f".to_string();
+        inner.local_count = 0;
+        let mut outer = chunk(vec![MakeFunction(1), Halt]);
+        outer.constants.push(Constant::Function(Box::new(inner)));
+        let issues = check_stack_balance(&outer).unwrap_err();
+        assert_eq!(issues[0].chunk, "f");
+        assert_eq!(issues[0].ip, 0);
+    }
+
+    #[test]
+    fn test_compiled_programs_are_balanced() {
+        let sources = [
+            "let x = 1 + 2\nlet y = if x > 2 do x else do 0 end",
+            "var i = 0\nwhile i < 10 do\n  i = i + 1\n  if i == 5 do continue end\n  if i == 8 do break end\nend",
+            "let f = fn(a: Number, b: Number): Number do\n  let c = a * b\n  return c\nend\nf(2, 3)",
+            "let total = for x in [1, 2, 3] do if x == 2 do break x end end",
+            "let r = match 3 do\n  1 do \"one\" end\n  _ as n do n.into(String) end\nend",
+            "let make = fn() do\n  var n = 0\n  return fn() do\n    n = n + 1\n    return n\n  end\nend\nlet c = make()\nc()",
+            "let [a, b] = [1, 2]\nlet { x, y } = { x = 1, y = 2 }\nlet t = { a = 1 }\nt.a = a + b",
+        ];
+        for source in sources {
+            let program = parse(source, "test.luma").unwrap();
+            if let Err(issues) = check_stack_balance(&compile_program(&program)) {
+                panic!("{source}\n{issues:#?}");
+            }
+        }
+    }
+}
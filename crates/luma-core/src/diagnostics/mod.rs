@@ -1,6 +1,10 @@
 use crate::ast::Span;
 use std::fmt;
 
+mod suppress;
+
+pub use suppress::Suppressions;
+
 /// Diagnostic severity level (matches LSP DiagnosticSeverity)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Severity {
@@ -41,6 +45,27 @@ impl DiagnosticKind {
     }
 }
 
+/// How confident a fix-it is, which decides whether tools may apply it unattended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Applicability {
+    /// The edit is certainly what was meant and is safe to apply automatically
+    MachineApplicable,
+    /// The edit is a plausible guess that the user should review
+    MaybeIncorrect,
+    /// The edit contains placeholder text the user has to fill in
+    HasPlaceholders,
+}
+
+impl Applicability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Applicability::MachineApplicable => "machine-applicable",
+            Applicability::MaybeIncorrect => "maybe-incorrect",
+            Applicability::HasPlaceholders => "has-placeholders",
+        }
+    }
+}
+
 /// A single text edit suggestion that can be applied to the source
 /// Represented using byte spans; mapping to line/column is done by frontends (CLI/LSP)
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -51,24 +76,45 @@ pub enum FixIt {
         replacement: String,
         /// Short label for UI like "Change to 'foo'" or "Insert 'end'"
         label: String,
+        applicability: Applicability,
     },
 }
 
 impl FixIt {
+    /// A machine-applicable replacement; see [`FixIt::with_applicability`]
     pub fn replace(span: Span, replacement: impl Into<String>, label: impl Into<String>) -> Self {
         FixIt::Replace {
             span,
             replacement: replacement.into(),
             label: label.into(),
+            applicability: Applicability::MachineApplicable,
         }
     }
 
+    pub fn with_applicability(mut self, level: Applicability) -> Self {
+        match &mut self {
+            FixIt::Replace { applicability, .. } => *applicability = level,
+        }
+        self
+    }
+
     pub fn label(&self) -> &str {
         match self {
             FixIt::Replace { label, .. } => label,
         }
     }
 
+    pub fn applicability(&self) -> Applicability {
+        match self {
+            FixIt::Replace { applicability, .. } => *applicability,
+        }
+    }
+
+    /// Whether the edit is safe to apply without the user reviewing it
+    pub fn is_machine_applicable(&self) -> bool {
+        self.applicability() == Applicability::MachineApplicable
+    }
+
     pub fn span(&self) -> Span {
         match self {
             FixIt::Replace { span, .. } => *span,
@@ -80,6 +126,63 @@ impl FixIt {
             FixIt::Replace { replacement, .. } => replacement,
         }
     }
+
+    /// Start and end of the edited range as 1-indexed (line, column) pairs
+    pub fn line_col_range(&self, index: &LineIndex) -> ((usize, usize), (usize, usize)) {
+        let span = self.span();
+        (index.line_col(span.start), index.line_col(span.end))
+    }
+
+    /// Whether applying both edits would touch the same text. Two insertions
+    /// at the same offset conflict too, since their order is ambiguous.
+    pub fn overlaps(&self, other: &FixIt) -> bool {
+        let (a, b) = (self.span(), other.span());
+        (a.start < b.end && b.start < a.end) || a.start == b.start
+    }
+}
+
+/// Outcome of [`apply_fixits`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedFixes {
+    /// The source with every accepted edit applied
+    pub source: String,
+    /// Number of edits applied
+    pub applied: usize,
+    /// Edits dropped because they overlap an earlier one
+    pub skipped: Vec<FixIt>,
+}
+
+/// Apply non-overlapping fix-its to `source`. When edits overlap, the one
+/// listed first wins and the rest are returned in `skipped`. Edits are
+/// applied from the end of the file backward so earlier spans stay valid.
+pub fn apply_fixits(source: &str, fixits: &[FixIt]) -> AppliedFixes {
+    let mut accepted: Vec<&FixIt> = Vec::new();
+    let mut skipped = Vec::new();
+    for fix in fixits {
+        let span = fix.span();
+        let in_bounds = span.start <= span.end
+            && span.end <= source.len()
+            && source.is_char_boundary(span.start)
+            && source.is_char_boundary(span.end);
+        if !in_bounds || accepted.iter().any(|a| a.overlaps(fix)) {
+            skipped.push(fix.clone());
+        } else {
+            accepted.push(fix);
+        }
+    }
+
+    accepted.sort_by_key(|f| std::cmp::Reverse(f.span().start));
+    let mut fixed = source.to_string();
+    for fix in &accepted {
+        let span = fix.span();
+        fixed.replace_range(span.start..span.end, fix.replacement());
+    }
+
+    AppliedFixes {
+        source: fixed,
+        applied: accepted.len(),
+        skipped,
+    }
 }
 
 /// Diagnostic error code for categorization (e.g., "E0001")
@@ -219,14 +322,58 @@ impl Diagnostic {
 
     /// Format the diagnostic with source code snippet
     pub fn format(&self, source: &str) -> String {
+        self.format_with_color(source, false)
+    }
+
+    /// Format the diagnostic with source code snippet, highlighting the
+    /// severity, location and underline with ANSI colors when `color` is set
+    pub fn format_with_color(&self, source: &str, color: bool) -> String {
         let line_index = LineIndex::new(source);
         let formatter = DiagnosticFormatter {
             diagnostic: self,
             source,
             line_index: &line_index,
+            color,
         };
         formatter.format()
     }
+
+    /// One-line `file:line:col: severity[code]: message` form, as printed by
+    /// GCC and understood by most editors, with a 1-indexed position
+    pub fn format_short(&self, source: &str) -> String {
+        let (line, column) = LineIndex::new(source).line_col(self.span.start);
+        let code = self
+            .code
+            .as_ref()
+            .map(|c| format!("[{}]", c.code))
+            .unwrap_or_default();
+        format!(
+            "{}:{line}:{column}: {}{code}: {}",
+            self.filename,
+            self.severity.as_str(),
+            self.message
+        )
+    }
+
+    /// Machine-readable form for tooling, with 1-indexed line/column positions
+    pub fn to_json(&self, source: &str) -> serde_json::Value {
+        let line_index = LineIndex::new(source);
+        let (line, column) = line_index.line_col(self.span.start);
+        let (end_line, end_column) = line_index.line_col(self.span.end);
+        serde_json::json!({
+            "file": self.filename,
+            "kind": self.kind.as_str(),
+            "severity": self.severity.as_str(),
+            "code": self.code.as_ref().map(|c| c.code.as_str()),
+            "message": self.message,
+            "line": line,
+            "column": column,
+            "end_line": end_line,
+            "end_column": end_column,
+            "notes": self.notes,
+            "help": self.help,
+        })
+    }
 }
 
 impl fmt::Display for Diagnostic {
@@ -243,6 +390,12 @@ impl fmt::Display for Diagnostic {
     }
 }
 
+/// Sort diagnostics into source order. At the same position, errors come
+/// before warnings (and warnings before info and hints).
+pub fn sort_diagnostics(diagnostics: &mut [Diagnostic]) {
+    diagnostics.sort_by_key(|d| (d.span.start, d.severity as u8));
+}
+
 /// Line index for efficient offset-to-line/column conversion
 #[derive(Debug)]
 pub struct LineIndex {
@@ -275,6 +428,39 @@ impl LineIndex {
         (line + 1, col + 1)
     }
 
+    /// Convert a byte offset in `source` to (line, column) with the column
+    /// counted in UTF-16 code units (both 1-indexed), as LSP clients expect.
+    /// An offset inside a multibyte character counts from that character's start.
+    pub fn line_col_utf16(&self, source: &str, offset: usize) -> (usize, usize) {
+        let (line, _) = self.line_col(offset);
+        let line_start = self.line_starts[line - 1];
+        let mut end = offset.min(source.len());
+        while !source.is_char_boundary(end) {
+            end -= 1;
+        }
+        let col = source[line_start.min(end)..end]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>();
+        (line, col + 1)
+    }
+
+    /// Byte offset of a 1-indexed line and a 0-based UTF-16 column, the
+    /// inverse of [`LineIndex::line_col_utf16`]. Columns past the end of the
+    /// line clamp to it, and columns inside a surrogate pair round down.
+    pub fn offset_utf16(&self, source: &str, line: usize, utf16_col: usize) -> Option<usize> {
+        let (start, end) = self.line_range(line)?;
+        let end = end.min(source.len());
+        let mut units = 0;
+        for (i, ch) in source[start..end].char_indices() {
+            units += ch.len_utf16();
+            if units > utf16_col {
+                return Some(start + i);
+            }
+        }
+        Some(end)
+    }
+
     /// Get the byte range for a given line (1-indexed)
     pub fn line_range(&self, line: usize) -> Option<(usize, usize)> {
         if line == 0 || line > self.line_starts.len() {
@@ -299,9 +485,45 @@ struct DiagnosticFormatter<'a> {
     diagnostic: &'a Diagnostic,
     source: &'a str,
     line_index: &'a LineIndex,
+    color: bool,
+}
+
+// ANSI styles used when color is enabled
+const BOLD: &str = "1";
+pub(crate) const BOLD_RED: &str = "1;31";
+const BOLD_YELLOW: &str = "1;33";
+const BOLD_CYAN: &str = "1;36";
+const BOLD_BLUE: &str = "1;34";
+
+/// Wrap `text` in an ANSI style when `color` is set, or return it unchanged
+pub(crate) fn paint(style: &str, text: &str, color: bool) -> String {
+    if color {
+        format!("\x1b[{style}m{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
 }
 
 impl<'a> DiagnosticFormatter<'a> {
+    /// Wrap `text` in an ANSI style, or return it unchanged without color
+    fn paint(&self, style: &str, text: &str) -> String {
+        paint(style, text, self.color)
+    }
+
+    fn severity_style(&self) -> &'static str {
+        match self.diagnostic.severity {
+            Severity::Error => BOLD_RED,
+            Severity::Warning => BOLD_YELLOW,
+            Severity::Info | Severity::Hint => BOLD_CYAN,
+        }
+    }
+
+    /// Line-number gutter, e.g. ` 12 | `, or blank when `line` is `None`
+    fn gutter(&self, line: Option<usize>, width: usize) -> String {
+        let number = line.map_or_else(String::new, |n| n.to_string());
+        self.paint(BOLD_BLUE, &format!("{number:>width$} |"))
+    }
+
     fn format(&self) -> String {
         let mut output = String::new();
 
@@ -312,38 +534,72 @@ impl<'a> DiagnosticFormatter<'a> {
             Severity::Info => "info",
             Severity::Hint => "hint",
         };
-        output.push_str(&format!("{}: {}\n", severity_str, self.diagnostic.message));
+        let label = match &self.diagnostic.code {
+            Some(code) => format!("{}[{}]", severity_str, code.code),
+            None => severity_str.to_string(),
+        };
+        output.push_str(&format!(
+            "{}{}\n",
+            self.paint(self.severity_style(), &label),
+            self.paint(BOLD, &format!(": {}", self.diagnostic.message))
+        ));
 
-        // Location
-        let (start_line, start_col) = self.line_index.line_col(self.diagnostic.span.start);
-        let (end_line, end_col) = self.line_index.line_col(self.diagnostic.span.end);
+        // Statement spans include trailing whitespace; underline only the code
+        let span = self.diagnostic.span;
+        let end = self
+            .source
+            .get(span.start..span.end)
+            .map_or(span.end, |text| span.start + text.trim_end().len());
+        let (start_line, start_col) = self.line_index.line_col(span.start);
+        let (end_line, end_col) = self.line_index.line_col(end);
 
+        // Location
         output.push_str(&format!(
-            "  --> {}:{}:{}\n",
-            self.diagnostic.filename, start_line, start_col
+            "  {} {}:{}:{}\n",
+            self.paint(BOLD_BLUE, "-->"),
+            self.diagnostic.filename,
+            start_line,
+            start_col
         ));
 
         // Source snippet
         output.push_str(&self.format_snippet(start_line, start_col, end_line, end_col));
 
+        // Related locations
+        for related in &self.diagnostic.related_info {
+            let (line, col) = self.line_index.line_col(related.span.start);
+            output.push_str(&format!(
+                "{}: {}\n  {} {}:{}:{}\n",
+                self.paint(BOLD, "note"),
+                related.message,
+                self.paint(BOLD_BLUE, "-->"),
+                related.filename,
+                line,
+                col
+            ));
+        }
+
         // Notes
         for note in &self.diagnostic.notes {
-            output.push_str(&format!("note: {note}\n"));
+            output.push_str(&format!("{}: {note}\n", self.paint(BOLD, "note")));
         }
 
         // Help
         if let Some(help) = &self.diagnostic.help {
-            output.push_str(&format!("help: {help}\n"));
+            output.push_str(&format!("{}: {help}\n", self.paint(BOLD, "help")));
         }
 
         // Suggestions
         for suggestion in &self.diagnostic.suggestions {
-            output.push_str(&format!("suggestion: {suggestion}\n"));
+            output.push_str(&format!(
+                "{}: {suggestion}\n",
+                self.paint(BOLD, "suggestion")
+            ));
         }
 
         // Fix-its (labels only in CLI formatter)
         for fix in &self.diagnostic.fixits {
-            output.push_str(&format!("fix: {}\n", fix.label()));
+            output.push_str(&format!("{}: {}\n", self.paint(BOLD, "fix"), fix.label()));
         }
 
         output
@@ -366,7 +622,7 @@ impl<'a> DiagnosticFormatter<'a> {
         let context_start = start_line.saturating_sub(1).max(1);
         let context_end = (end_line + 1).min(self.line_index.line_count());
 
-        output.push_str(&format!("{:width$} |\n", "", width = line_num_width));
+        output.push_str(&format!("{}\n", self.gutter(None, line_num_width)));
 
         for line_num in context_start..=context_end {
             if let Some((line_start, line_end)) = self.line_index.line_range(line_num) {
@@ -374,12 +630,13 @@ impl<'a> DiagnosticFormatter<'a> {
                 let line_text = &self.source[line_start..line_end];
 
                 // Print line number and source
-                output.push_str(&format!("{line_num:line_num_width$} | {line_text}\n"));
+                output.push_str(&format!(
+                    "{} {line_text}\n",
+                    self.gutter(Some(line_num), line_num_width)
+                ));
 
                 // Print underline/caret for error span
                 if line_num >= start_line && line_num <= end_line {
-                    output.push_str(&format!("{:width$} | ", "", width = line_num_width));
-
                     let line_span_start = if line_num == start_line {
                         start_col - 1
                     } else {
@@ -391,31 +648,20 @@ impl<'a> DiagnosticFormatter<'a> {
                         line_text.chars().count()
                     };
 
-                    // Add spaces up to start of error
-                    for _ in 0..line_span_start {
-                        output.push(' ');
-                    }
-
-                    // Add carets/tildes for error span
+                    // Caret at the start of the error, tildes for the rest of the span
                     let span_width = (line_span_end.saturating_sub(line_span_start)).max(1);
-                    if span_width == 1 {
-                        output.push('^');
-                    } else {
-                        for i in 0..span_width {
-                            if i == 0 {
-                                output.push('^');
-                            } else {
-                                output.push('~');
-                            }
-                        }
-                    }
-
-                    output.push('\n');
+                    let underline = format!("^{}", "~".repeat(span_width - 1));
+                    output.push_str(&format!(
+                        "{} {}{}\n",
+                        self.gutter(None, line_num_width),
+                        " ".repeat(line_span_start),
+                        self.paint(self.severity_style(), &underline)
+                    ));
                 }
             }
         }
 
-        output.push_str(&format!("{:width$} |\n", "", width = line_num_width));
+        output.push_str(&format!("{}\n", self.gutter(None, line_num_width)));
 
         output
     }
@@ -444,6 +690,58 @@ mod tests {
         assert_eq!(index.line_col(12), (3, 1)); // 'l' in line3
     }
 
+    #[test]
+    fn test_apply_fixits_back_to_front() {
+        let source = "print(1\nlet x = 2,";
+        let fixes = [
+            FixIt::replace(Span::new(7, 7), ")", "Insert ')'"),
+            FixIt::replace(Span::new(17, 18), "", "Remove ','"),
+        ];
+        let result = apply_fixits(source, &fixes);
+        assert_eq!(result.source, "print(1)\nlet x = 2");
+        assert_eq!(result.applied, 2);
+        assert!(result.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_apply_fixits_skips_overlapping() {
+        let source = "let valeu = 1";
+        let fixes = [
+            FixIt::replace(Span::new(4, 9), "value", "Change to 'value'"),
+            FixIt::replace(Span::new(6, 9), "lue", "Change to 'lue'"),
+            FixIt::replace(Span::new(12, 12), "0", "Insert '0'"),
+            FixIt::replace(Span::new(12, 12), "2", "Insert '2'"),
+        ];
+        let result = apply_fixits(source, &fixes);
+        assert_eq!(result.source, "let value = 01");
+        assert_eq!(result.applied, 2);
+        assert_eq!(result.skipped, vec![fixes[1].clone(), fixes[3].clone()]);
+
+        let index = LineIndex::new(source);
+        assert_eq!(result.skipped[0].line_col_range(&index), ((1, 7), (1, 10)));
+    }
+
+    #[test]
+    fn test_line_col_utf16_counts_code_units() {
+        let source = "x = 1\n\"café\" = 🎉 + y";
+        let index = LineIndex::new(source);
+        let after_e = source.find("\" =").unwrap();
+        // Bytes count `é` as two, UTF-16 as one
+        assert_eq!(index.line_col(after_e), (2, 7));
+        assert_eq!(index.line_col_utf16(source, after_e), (2, 6));
+        // The emoji is two UTF-16 units
+        let y = source.find('y').unwrap();
+        assert_eq!(index.line_col_utf16(source, y), (2, 15));
+        // Inside a multibyte character counts from its start
+        let inside_e = source.find('é').unwrap() + 1;
+        assert_eq!(index.line_col_utf16(source, inside_e), (2, 5));
+
+        assert_eq!(index.offset_utf16(source, 2, 5), Some(after_e));
+        assert_eq!(index.offset_utf16(source, 2, 14), Some(y));
+        assert_eq!(index.offset_utf16(source, 2, 99), Some(source.len()));
+        assert_eq!(index.offset_utf16(source, 3, 0), None);
+    }
+
     #[test]
     fn test_line_range() {
         let source = "line1\nline2\nline3";
@@ -511,6 +809,46 @@ mod tests {
         assert_eq!(diag.code.as_ref().unwrap().code, "E0001");
     }
 
+    #[test]
+    fn test_diagnostic_to_json() {
+        let source = "let a = 1\nlet b = c";
+        let diag = Diagnostic::error(
+            DiagnosticKind::Type,
+            "Undefined variable 'c'".to_string(),
+            Span::new(18, 19),
+            "test.luma".to_string(),
+        )
+        .with_code(DiagnosticCode::new("E0002", "Undefined variable"));
+
+        let json = diag.to_json(source);
+        assert_eq!(json["severity"], "error");
+        assert_eq!(json["kind"], "type");
+        assert_eq!(json["code"], "E0002");
+        assert_eq!(json["line"], 2);
+        assert_eq!(json["column"], 9);
+        assert_eq!(json["end_column"], 10);
+    }
+
+    #[test]
+    fn test_diagnostic_format_short() {
+        let source = "let a = 1\nlet b = c";
+        let diag = Diagnostic::error(
+            DiagnosticKind::Type,
+            "Undefined variable: c".to_string(),
+            Span::new(18, 19),
+            "test.luma".to_string(),
+        );
+        assert_eq!(
+            diag.format_short(source),
+            "test.luma:2:9: error: Undefined variable: c"
+        );
+        let diag = diag.with_code(DiagnosticCode::new("E0002", "Undefined variable"));
+        assert_eq!(
+            diag.format_short(source),
+            "test.luma:2:9: error[E0002]: Undefined variable: c"
+        );
+    }
+
     #[test]
     fn test_diagnostic_with_related_info() {
         let diag = Diagnostic::error(
@@ -607,4 +945,27 @@ mod tests {
         assert!(diag.help.is_some());
         assert_eq!(diag.related_info.len(), 1);
     }
+
+    #[test]
+    fn test_sort_diagnostics_by_position_then_severity() {
+        let diag = |start: usize, severity: Severity, message: &str| {
+            let mut d = Diagnostic::error(
+                DiagnosticKind::Type,
+                message.to_string(),
+                Span::new(start, start + 1),
+                "test.luma".to_string(),
+            );
+            d.severity = severity;
+            d
+        };
+        let mut diagnostics = vec![
+            diag(20, Severity::Error, "third"),
+            diag(5, Severity::Warning, "second"),
+            diag(5, Severity::Error, "first"),
+            diag(40, Severity::Hint, "fourth"),
+        ];
+        sort_diagnostics(&mut diagnostics);
+        let order: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(order, ["first", "second", "third", "fourth"]);
+    }
 }
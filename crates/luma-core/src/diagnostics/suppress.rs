@@ -0,0 +1,119 @@
+//! Inline suppression comments
+//!
+//! A comment `-- luma:ignore E0002` silences diagnostics with that code on
+//! its own line and on the line after it, so it can trail the offending code
+//! or sit directly above it. Several codes may be listed, separated by spaces
+//! or commas; `-- luma:ignore` without codes silences everything there.
+
+use super::{Diagnostic, LineIndex};
+use crate::parser::{skip_identifier, skip_literal};
+use std::collections::HashMap;
+
+const DIRECTIVE: &str = "luma:ignore";
+
+/// Suppression directives found in a source file
+#[derive(Debug)]
+pub struct Suppressions {
+    index: LineIndex,
+    /// Codes ignored per 1-indexed line; an empty list ignores every code
+    lines: HashMap<usize, Vec<String>>,
+}
+
+impl Suppressions {
+    pub fn parse(source: &str) -> Self {
+        let index = LineIndex::new(source);
+        let mut lines: HashMap<usize, Vec<String>> = HashMap::new();
+        for (offset, comment) in line_comments(source) {
+            let comment = comment.trim_start();
+            // Also accept `luma: ignore`
+            let Some(rest) = comment
+                .strip_prefix(DIRECTIVE)
+                .or_else(|| comment.strip_prefix("luma: ignore"))
+            else {
+                continue;
+            };
+            if rest.starts_with(|c: char| c.is_alphanumeric()) {
+                continue;
+            }
+            let codes = rest
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|code| !code.is_empty())
+                .map(str::to_string)
+                .collect();
+            lines.insert(index.line_col(offset).0, codes);
+        }
+        Self { index, lines }
+    }
+
+    /// Whether a diagnostic starting on `line` with `code` is silenced
+    pub fn suppresses(&self, line: usize, code: Option<&str>) -> bool {
+        [line, line.saturating_sub(1)].iter().any(|l| {
+            self.lines.get(l).is_some_and(|codes| {
+                codes.is_empty() || code.is_some_and(|c| codes.iter().any(|x| x == c))
+            })
+        })
+    }
+
+    pub fn suppresses_diagnostic(&self, diagnostic: &Diagnostic) -> bool {
+        let (line, _) = self.index.line_col(diagnostic.span.start);
+        self.suppresses(line, diagnostic.code.as_ref().map(|c| c.code.as_str()))
+    }
+
+    /// Line of a byte offset, for callers filtering values other than `Diagnostic`
+    pub fn line_of(&self, offset: usize) -> usize {
+        self.index.line_col(offset).0
+    }
+}
+
+/// Each `--` line comment in `source`, as the offset of its marker and the
+/// text after it. Strings and block comments are skipped by the same rules
+/// the parser's nesting pre-pass uses, so markers inside them don't count.
+fn line_comments(source: &str) -> Vec<(usize, &str)> {
+    let bytes = source.as_bytes();
+    let mut comments = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(end) = skip_literal(source, i) {
+            i = end;
+            continue;
+        }
+        match bytes[i] {
+            b'-' if source[i..].starts_with("--") => {
+                let end = source[i..].find('\n').map_or(bytes.len(), |end| i + end);
+                comments.push((i, &source[i + 2..end]));
+                i = end;
+            }
+            b if b.is_ascii_alphabetic() || b == b'_' => i = skip_identifier(bytes, i),
+            _ => i += 1,
+        }
+    }
+    comments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_directives() {
+        let source = "let a = 1 -- luma:ignore E0002\n-- luma:ignore E0001, E0003\nlet b = 2\n-- luma:ignore\nlet s = \"-- luma:ignore E0009\"";
+        let s = Suppressions::parse(source);
+        assert!(s.suppresses(1, Some("E0002")));
+        assert!(!s.suppresses(1, Some("E0001")));
+        assert!(s.suppresses(3, Some("E0003")));
+        assert!(!s.suppresses(3, None));
+        assert!(s.suppresses(5, None));
+        assert!(!s.suppresses(6, Some("E0009")));
+    }
+
+    #[test]
+    fn test_markers_inside_strings_are_ignored() {
+        let source = "let a = \"\"\"\n-- luma:ignore E0001\n\"\"\"\nlet b = `-- luma:ignore E0002`\nlet c = r\"\\\" -- luma:ignore E0003\nlet d = 1 -- luma:ignore E0004\n--[[ -- luma:ignore E0005 ]]";
+        let s = Suppressions::parse(source);
+        assert!(!s.suppresses(2, Some("E0001")));
+        assert!(!s.suppresses(4, Some("E0002")));
+        assert!(s.suppresses(5, Some("E0003")));
+        assert!(s.suppresses(6, Some("E0004")));
+        assert!(!s.suppresses(7, Some("E0005")));
+    }
+}
@@ -3,6 +3,7 @@ pub mod bytecode;
 pub mod diagnostics;
 pub mod parser;
 pub mod pipeline;
+pub mod resolve;
 pub mod typecheck;
 pub mod vm;
 
@@ -0,0 +1,129 @@
+//! Nesting-depth pre-pass
+//!
+//! The grammar is recursive, so pathological inputs (thousands of nested
+//! parentheses or blocks) would overflow the stack inside the parser. Before
+//! parsing, this scan measures how deeply brackets and `do ... end` blocks
+//! nest and rejects the source with a diagnostic past a configurable limit.
+
+use crate::ast::Span;
+
+/// Find the first opener that nests deeper than `max_depth`. Counts `(`, `[`,
+/// `{` and the `do` keyword, skipping strings and comments. An `if` takes one
+/// level however many branches it has: the `do` of an `else do` or
+/// `else if ... do` continues the block its `end` closes.
+pub(super) fn find_too_deep(source: &str, max_depth: usize) -> Option<Span> {
+    let bytes = source.as_bytes();
+    let mut depth = 0usize;
+    // Depth at which the last `else` was seen, until its `do` arrives
+    let mut else_depth = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(end) = skip_literal(source, i) {
+            i = end;
+            continue;
+        }
+        let b = bytes[i];
+        match b {
+            b'-' if source[i..].starts_with("--") => {
+                i = source[i..].find('\n').map_or(bytes.len(), |end| i + end);
+                continue;
+            }
+            b'(' | b'[' | b'{' => {
+                depth += 1;
+                if depth > max_depth {
+                    return Some(Span::new(i, i + 1));
+                }
+            }
+            b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+            _ if b.is_ascii_alphabetic() || b == b'_' => {
+                let start = i;
+                i = skip_identifier(bytes, i);
+                match &source[start..i] {
+                    "else" => else_depth = Some(depth),
+                    "do" if else_depth == Some(depth) => else_depth = None,
+                    "do" => {
+                        depth += 1;
+                        if depth > max_depth {
+                            return Some(Span::new(start, i));
+                        }
+                    }
+                    "end" => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// End of the string literal or `--[[ ]]` block comment starting at byte `i`,
+/// if one starts there. Covers `"..."` with escapes, `"""..."""`, backtick
+/// strings and `r"..."` raw strings; `i` must not be inside an identifier, or
+/// its trailing `r` would read as a raw string prefix. Line comments are left
+/// to the caller.
+pub(crate) fn skip_literal(source: &str, i: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let rest = &source[i..];
+    let until = |open: usize, close: &str| {
+        source[i + open..]
+            .find(close)
+            .map_or(bytes.len(), |end| i + open + end + close.len())
+    };
+    match bytes.get(i)? {
+        b'-' if rest.starts_with("--[[") => Some(until(4, "]]")),
+        b'"' if rest.starts_with("\"\"\"") => Some(until(3, "\"\"\"")),
+        b'r' if bytes.get(i + 1) == Some(&b'"') => Some(until(2, "\"")),
+        b'"' => {
+            let mut j = i + 1;
+            while j < bytes.len() && bytes[j] != b'"' {
+                j += if bytes[j] == b'\\' { 2 } else { 1 };
+            }
+            Some((j + 1).min(bytes.len()))
+        }
+        b'`' => Some(until(1, "`")),
+        _ => None,
+    }
+}
+
+/// End of the identifier starting at byte `i`
+pub(crate) fn skip_identifier(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+        i += 1;
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_depth_counts_brackets_and_blocks() {
+        assert_eq!(find_too_deep("f((1))", 2), None);
+        assert_eq!(find_too_deep("f((1))", 1), Some(Span::new(2, 3)));
+        assert_eq!(
+            find_too_deep("if a do\nwhile b do x end\nend", 1),
+            Some(Span::new(16, 18))
+        );
+        let branches = "if a do\nx\nelse if b do\ny\nelse do\nz\nend";
+        assert_eq!(find_too_deep(branches, 1), None);
+        // A block opened inside the condition still counts
+        assert_eq!(
+            find_too_deep("if a do x else if f(g do y end) do z end", 2),
+            Some(Span::new(22, 24))
+        );
+    }
+
+    #[test]
+    fn test_depth_skips_strings_and_comments() {
+        let source = "let s = \"(((\\\"(\" -- (((\n--[[ ((( ]] f(1)";
+        assert_eq!(find_too_deep(source, 1), None);
+        assert_eq!(find_too_deep("doubled(1)", 1), None);
+        assert_eq!(find_too_deep("let r = `((\\`", 1), None);
+        assert_eq!(find_too_deep("let t = \"\"\"(\"(\"\"(\"\"\"", 1), None);
+        assert_eq!(find_too_deep("let r = r\"((\\\" + f(1)", 1), None);
+    }
+}
@@ -3,7 +3,7 @@
 //! Converts Chumsky parser errors into user-friendly diagnostic messages
 
 use crate::ast::Span;
-use crate::diagnostics::{Diagnostic, DiagnosticKind, FixIt};
+use crate::diagnostics::{Applicability, Diagnostic, DiagnosticKind, FixIt};
 use chumsky::error::{Rich, RichReason};
 
 /// Convert Chumsky error reason to readable message
@@ -77,11 +77,11 @@ fn augment_with_fixits(diag: &mut Diagnostic, reason: &RichReason<char>, span: S
         } else {
             diag.suggestions
                 .push("Did you forget to close a block with 'end'?".to_string());
-            diag.fixits.push(FixIt::replace(
-                Span::new(span.end, span.end),
-                "\nend",
-                "Insert 'end'",
-            ));
+            // Nothing is visibly unclosed, so the missing 'end' is a guess
+            diag.fixits.push(
+                FixIt::replace(Span::new(span.end, span.end), "\nend", "Insert 'end'")
+                    .with_applicability(Applicability::MaybeIncorrect),
+            );
         }
         return;
     }
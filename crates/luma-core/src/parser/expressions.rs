@@ -88,12 +88,12 @@ where
                 .ignore_then(expr.clone())
                 .or_not(),
         )
-        .map(
-            |((name, t), default): ((&str, Type), Option<Expr>)| Argument {
+        .map_with(
+            |((name, t), default): ((&str, Type), Option<Expr>), e| Argument {
                 name: name.to_string(),
                 r#type: t,
                 default,
-                span: None,
+                span: Some(Span::from_chumsky(e.span())),
             },
         );
 
@@ -195,3 +195,18 @@ where
         })
         .boxed()
 }
+
+/// Creates a parser for loops used as expressions (while, do-while, for)
+pub fn loop_expr<'a, L>(loop_stmt: L) -> Boxed<'a, 'a, &'a str, Expr, extra::Err<Rich<'a, char>>>
+where
+    L: Parser<'a, &'a str, Stmt, extra::Err<Rich<'a, char>>> + Clone + 'a,
+{
+    loop_stmt
+        .try_map(|body, span| {
+            Ok(Expr::Loop {
+                body: Box::new(body),
+                span: Some(Span::from_chumsky(span)),
+            })
+        })
+        .boxed()
+}
@@ -23,5 +23,5 @@ pub fn ws<'a>() -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Cl
 /// List of reserved keywords
 pub const KEYWORDS: &[&str] = &[
     "let", "var", "fn", "do", "end", "return", "true", "false", "null", "if", "else", "while",
-    "for", "in", "break", "continue", "match", "await",
+    "for", "in", "break", "continue", "match", "await", "unless",
 ];
@@ -1,56 +1,151 @@
 use crate::ast::{Expr, Span, TableKey};
 use chumsky::prelude::*;
+use std::ops::Range;
 
-/// Creates a parser for number literals (integers, floats, hex, binary, scientific)
+/// Creates a parser for number literals (integers, floats, hex, octal, binary, scientific)
 pub fn number<'a, WS>(ws: WS) -> impl Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>>
 where
     WS: Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
 {
-    let hex = just("0x")
-        .or(just("0X"))
-        .ignore_then(
-            one_of("0123456789abcdefABCDEF")
+    let hex = radix_literal('x');
+    let octal = radix_literal('o');
+    let binary = radix_literal('b');
+
+    choice((hex, octal, binary, decimal_literal()))
+        .validate(|text: &str, e, emitter| {
+            let span: SimpleSpan = e.span();
+            let value = number_value(text).unwrap_or_else(|(msg, at)| {
+                let at = SimpleSpan::from(span.start + at.start..span.start + at.end);
+                emitter.emit(Rich::custom(at, msg));
+                0.0
+            });
+            Expr::Number {
+                value,
+                span: Some(Span::from_chumsky(span)),
+            }
+        })
+        .padded_by(ws)
+        .boxed()
+}
+
+/// Parser for the text of a decimal literal, e.g. `1_000`, `3.141_59` or
+/// `1e-3`. Misplaced `_` separators are accepted here and rejected by
+/// `parse_number`, so `1_` is one bad literal rather than `1` and `_`.
+pub(super) fn decimal_literal<'a>()
+-> impl Parser<'a, &'a str, &'a str, extra::Err<Rich<'a, char>>> + Clone {
+    let digits = || {
+        text::digits(10).then(
+            just('_')
                 .repeated()
                 .at_least(1)
-                .collect::<String>(),
+                .then(text::digits(10))
+                .repeated(),
         )
-        .try_map(|s: String, span| {
-            let val = i64::from_str_radix(&s, 16).unwrap_or(0);
-            Ok(Expr::Number {
-                value: val as f64,
-                span: Some(Span::from_chumsky(span)),
-            })
-        });
-
-    let binary = just("0b")
-        .or(just("0B"))
-        .ignore_then(one_of("01").repeated().at_least(1).collect::<String>())
-        .try_map(|s: String, span| {
-            let val = i64::from_str_radix(&s, 2).unwrap_or(0);
-            Ok(Expr::Number {
-                value: val as f64,
-                span: Some(Span::from_chumsky(span)),
-            })
-        });
-
-    let decimal = text::int(10)
-        .then(just('.').then(text::digits(10)).or_not())
+    };
+    text::int(10)
+        .then(
+            just('_')
+                .repeated()
+                .at_least(1)
+                .then(text::digits(10))
+                .repeated(),
+        )
+        .then(just('.').then(digits()).or_not())
         .then(
             one_of("eE")
                 .then(one_of("+-").or_not())
-                .then(text::digits(10))
+                .then(digits())
                 .or_not(),
         )
+        .then(just('_').repeated())
         .to_slice()
-        .try_map(|s: &str, span| {
-            let val = s.parse::<f64>().unwrap_or(0.0);
-            Ok(Expr::Number {
-                value: val,
-                span: Some(Span::from_chumsky(span)),
-            })
-        });
+}
+
+/// Parser for the text of a `0x`/`0o`/`0b`-style literal. Every alphanumeric
+/// character or `_` after the prefix belongs to the literal, so `0xZZ` is
+/// reported as a bad digit rather than parsed as `0` followed by the
+/// identifier `xZZ`.
+fn radix_literal<'a>(
+    marker: char,
+) -> impl Parser<'a, &'a str, &'a str, extra::Err<Rich<'a, char>>> + Clone {
+    just('0')
+        .then(one_of([marker, marker.to_ascii_uppercase()]))
+        .then(
+            any()
+                .filter(|c: &char| c.is_ascii_alphanumeric() || *c == '_')
+                .repeated(),
+        )
+        .to_slice()
+}
+
+/// Convert the text of a number literal to its value, the way the parser
+/// does: decimal (with optional fraction and exponent), `0x` hexadecimal,
+/// `0o` octal or `0b` binary. A `_` may separate two digits, as in
+/// `1_000_000`, and is otherwise an error.
+pub fn parse_number(text: &str) -> Result<f64, String> {
+    number_value(text).map_err(|(msg, _)| msg)
+}
+
+/// `parse_number`, also returning the byte range within `text` that an
+/// error is about: the first invalid digit, or else the whole literal.
+fn number_value(text: &str) -> Result<f64, (String, Range<usize>)> {
+    let (prefix, radix, kind) = match text.get(..2) {
+        Some("0x" | "0X") => (2, 16, "hexadecimal"),
+        Some("0o" | "0O") => (2, 8, "octal"),
+        Some("0b" | "0B") => (2, 2, "binary"),
+        _ => (0, 10, "number"),
+    };
+    let digits = &text[prefix..];
 
-    choice((hex, binary, decimal)).padded_by(ws).boxed()
+    let cleaned: String = digits.chars().filter(|c| *c != '_').collect();
+    if radix != 10 {
+        if cleaned.is_empty() {
+            return Err((format!("{kind} literal has no digits"), 0..text.len()));
+        }
+        if let Some((i, bad)) = digits
+            .char_indices()
+            .find(|(_, c)| *c != '_' && !c.is_digit(radix))
+        {
+            return Err((
+                format!("invalid digit '{bad}' in {kind} literal"),
+                prefix + i..prefix + i + bad.len_utf8(),
+            ));
+        }
+    }
+
+    let chars: Vec<char> = digits.chars().collect();
+    let is_digit = |i: Option<usize>| {
+        i.and_then(|i| chars.get(i))
+            .is_some_and(|c| c.is_digit(radix))
+    };
+    for (i, c) in chars.iter().enumerate() {
+        if *c == '_' && !(is_digit(i.checked_sub(1)) && is_digit(Some(i + 1))) {
+            // Reported on the whole literal: which `_` is misplaced is
+            // obvious, and the fix may be to drop the underscores entirely
+            return Err((
+                format!("'_' in a {kind} literal must be between two digits"),
+                0..text.len(),
+            ));
+        }
+    }
+
+    if radix == 10 {
+        cleaned
+            .parse::<f64>()
+            .map_err(|_| (format!("invalid number literal '{text}'"), 0..text.len()))
+    } else {
+        // Convert once at the end so integers up to 2^53 are exact; only
+        // literals too long for a u128 accumulate rounding instead
+        Ok(u128::from_str_radix(&cleaned, radix).map_or_else(
+            |_| {
+                cleaned
+                    .chars()
+                    .filter_map(|c| c.to_digit(radix))
+                    .fold(0.0, |acc, d| acc * f64::from(radix) + f64::from(d))
+            },
+            |n| n as f64,
+        ))
+    }
 }
 
 /// Creates a parser for boolean literals (true/false)
@@ -97,7 +192,8 @@ where
     WS: Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
     E: Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone + 'a,
 {
-    expr.separated_by(just(',').padded_by(ws.clone()))
+    choice((spread(ws.clone(), expr.clone()), expr))
+        .separated_by(just(',').padded_by(ws.clone()))
         .allow_trailing()
         .collect::<Vec<Expr>>()
         .delimited_by(
@@ -113,6 +209,26 @@ where
         .boxed()
 }
 
+/// Creates a parser for `...expression`, splicing a list into a list literal
+/// or a call's arguments
+pub fn spread<'a, WS, E>(
+    ws: WS,
+    expr: E,
+) -> Boxed<'a, 'a, &'a str, Expr, extra::Err<Rich<'a, char>>>
+where
+    WS: Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
+    E: Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone + 'a,
+{
+    just("...")
+        .padded_by(ws)
+        .ignore_then(expr)
+        .map_with(|value, e| Expr::Spread {
+            value: Box::new(value),
+            span: Some(Span::from_chumsky(e.span())),
+        })
+        .boxed()
+}
+
 /// Creates a parser for table literals {key = value, ...}
 /// Supports:
 /// - Identifier keys: key = value
@@ -175,7 +291,13 @@ where
         ))
     });
 
-    let table_entry = choice((kv_entry, shorthand_entry));
+    // Spread entry: ...expression copies the fields of another table
+    let spread_entry = just("...")
+        .padded_by(ws.clone())
+        .ignore_then(expr.clone())
+        .map(|e| (TableKey::Spread, e));
+
+    let table_entry = choice((spread_entry, kv_entry, shorthand_entry));
 
     table_entry
         .separated_by(just(',').padded_by(ws.clone()))
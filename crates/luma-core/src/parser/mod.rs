@@ -1,7 +1,8 @@
 use crate::ast::{CallArgument, Expr, Program, Stmt};
-use crate::diagnostics::Diagnostic;
+use crate::diagnostics::{Diagnostic, DiagnosticKind};
 use chumsky::prelude::*;
 
+mod depth;
 mod errors;
 mod expressions;
 mod lexer;
@@ -14,8 +15,14 @@ mod string;
 mod types;
 mod utils;
 
+pub(crate) use depth::{skip_identifier, skip_literal};
+pub use literals::parse_number;
 use string::string_parser;
 
+/// Parser extra for the operator grammar, whose context says whether `:`
+/// may start a method call
+type OperatorExtra<'a> = extra::Full<Rich<'a, char>, (), bool>;
+
 pub fn parser<'a>() -> impl Parser<'a, &'a str, Program, extra::Err<Rich<'a, char>>> {
     // Comments and whitespace
     let ws = lexer::ws();
@@ -76,6 +83,21 @@ pub fn parser<'a>() -> impl Parser<'a, &'a str, Program, extra::Err<Rich<'a, cha
         pattern.clone(),
     );
 
+    // Loops double as expressions so `break value` can yield a result
+    let while_stmt = statements::while_stmt(ws.clone(), expr_ref.clone(), stmt_ref.clone());
+    let do_while_stmt = statements::do_while_stmt(ws.clone(), expr_ref.clone(), stmt_ref.clone());
+    let for_stmt = statements::for_stmt(
+        ws.clone(),
+        pattern.clone(),
+        expr_ref.clone(),
+        stmt_ref.clone(),
+    );
+    let loop_expr = expressions::loop_expr(choice((
+        do_while_stmt.clone(),
+        while_stmt.clone(),
+        for_stmt.clone(),
+    )));
+
     // Parenthesized expressions - allows precedence override
     let paren_expr = expr_ref
         .clone()
@@ -94,6 +116,7 @@ pub fn parser<'a>() -> impl Parser<'a, &'a str, Program, extra::Err<Rich<'a, cha
         list,
         table,
         if_expr,
+        loop_expr, // Must come before block_expr to avoid ambiguity with do-while's "do"
         block_expr,
         match_expression,
         function,
@@ -123,7 +146,11 @@ pub fn parser<'a>() -> impl Parser<'a, &'a str, Program, extra::Err<Rich<'a, cha
                 value,
             });
 
-        let positional = expr_ref.clone().map(CallArgument::Positional);
+        let positional = choice((
+            literals::spread(ws.clone(), expr_ref.clone()),
+            expr_ref.clone(),
+        ))
+        .map(CallArgument::Positional);
 
         choice((named, positional))
     };
@@ -157,188 +184,354 @@ pub fn parser<'a>() -> impl Parser<'a, &'a str, Program, extra::Err<Rich<'a, cha
         Index(Box<Expr>),
     }
 
-    let postfix_op = choice((
-        method_call.map(|(method, args): (&str, Vec<CallArgument>)| {
-            PostfixOp::MethodCall(method.to_string(), args)
-        }),
-        call_args.map(PostfixOp::Call),
+    let plain_postfix_op = choice((
+        call_args.clone().map(PostfixOp::Call),
         member_access.map(|m: &str| PostfixOp::Member(m.to_string())),
         index.map(|e| PostfixOp::Index(Box::new(e))),
-    ));
+    ))
+    .boxed();
 
-    let postfix = primary
-        .clone()
-        .then(postfix_op.repeated().collect::<Vec<_>>())
-        .try_map(|(mut expr, ops), span| {
-            let start = span.start;
-            for op in ops {
-                let expr_span = expr.span().map(|s| s.start).unwrap_or(start);
-                expr = match op {
-                    PostfixOp::Call(arguments) => Expr::Call {
-                        callee: Box::new(expr),
-                        arguments,
-                        span: Some(crate::ast::Span::new(expr_span, span.end)),
-                    },
-                    PostfixOp::Member(member) => Expr::MemberAccess {
-                        object: Box::new(expr),
-                        member,
-                        span: Some(crate::ast::Span::new(expr_span, span.end)),
-                    },
-                    PostfixOp::MethodCall(method, arguments) => Expr::MethodCall {
-                        object: Box::new(expr),
-                        method,
-                        arguments,
-                        span: Some(crate::ast::Span::new(expr_span, span.end)),
-                    },
-                    PostfixOp::Index(index) => Expr::Index {
-                        object: Box::new(expr),
-                        index,
-                        span: Some(crate::ast::Span::new(expr_span, span.end)),
-                    },
-                };
-            }
-            Ok(expr)
+    // Method calls are left out where the context says so, which lets the
+    // ternary's then-branch end at a `:`
+    let method_call_op: Boxed<'a, 'a, &'a str, PostfixOp, OperatorExtra<'a>> = method_call
+        .map(|(method, args): (&str, Vec<CallArgument>)| {
+            PostfixOp::MethodCall(method.to_string(), args)
         })
+        .with_ctx(())
         .boxed();
+    let postfix_op = choice((
+        method_call_op
+            .contextual()
+            .configure(|_, allows_method_calls: &bool| *allows_method_calls),
+        plain_postfix_op.with_ctx(()),
+    ))
+    .boxed();
 
-    // Unary operators (not, -)
-    // These have lower precedence than postfix, so they operate on postfix expressions
-    let unary_op = operators::unary_op(ws.clone());
-    let unary_expr = unary_op
-        .repeated()
-        .collect::<Vec<_>>()
-        .then(postfix.clone())
-        .try_map(|(ops, mut operand), span| {
-            // Apply operators right-to-left
-            for op in ops.into_iter().rev() {
-                let operand_span = operand.span().map(|s| s.end).unwrap_or(span.end);
-                operand = Expr::Unary {
-                    op,
-                    operand: Box::new(operand),
-                    span: Some(crate::ast::Span::new(span.start, operand_span)),
-                };
-            }
-            Ok(operand)
-        })
-        .boxed();
+    // Everything from postfix operators up to `|>`, built once and run with a
+    // context saying whether method calls are allowed
+    let operator_expr: Boxed<'a, 'a, &'a str, Expr, OperatorExtra<'a>> = {
+        let postfix = primary
+            .clone()
+            .with_ctx(())
+            .then(postfix_op.repeated().collect::<Vec<_>>())
+            .try_map(|(mut expr, ops), span| {
+                let start = span.start;
+                for op in ops {
+                    let expr_span = expr.span().map(|s| s.start).unwrap_or(start);
+                    expr = match op {
+                        PostfixOp::Call(arguments) => Expr::Call {
+                            callee: Box::new(expr),
+                            arguments,
+                            span: Some(crate::ast::Span::new(expr_span, span.end)),
+                        },
+                        PostfixOp::Member(member) => Expr::MemberAccess {
+                            object: Box::new(expr),
+                            member,
+                            span: Some(crate::ast::Span::new(expr_span, span.end)),
+                        },
+                        PostfixOp::MethodCall(method, arguments) => Expr::MethodCall {
+                            object: Box::new(expr),
+                            method,
+                            arguments,
+                            span: Some(crate::ast::Span::new(expr_span, span.end)),
+                        },
+                        PostfixOp::Index(index) => Expr::Index {
+                            object: Box::new(expr),
+                            index,
+                            span: Some(crate::ast::Span::new(expr_span, span.end)),
+                        },
+                    };
+                }
+                Ok(expr)
+            })
+            .boxed();
 
-    // Binary operators with precedence
-    let mul_op = operators::mul_op(ws.clone());
-    let add_op = operators::add_op(ws.clone());
-    let cmp_op = operators::cmp_op(ws.clone());
-    let eq_op = operators::eq_op(ws.clone());
-    let and_op = operators::and_op(ws.clone());
-    let or_op = operators::or_op(ws.clone());
+        // Unary operators (not, -)
+        // These have lower precedence than postfix, so they operate on postfix expressions
+        let unary_op = operators::unary_op(ws.clone()).with_ctx(());
+        let unary_expr = unary_op
+            .repeated()
+            .collect::<Vec<_>>()
+            .then(postfix.clone())
+            .try_map(|(ops, mut operand), span| {
+                // Apply operators right-to-left
+                for op in ops.into_iter().rev() {
+                    let operand_span = operand.span().map(|s| s.end).unwrap_or(span.end);
+                    operand = Expr::Unary {
+                        op,
+                        operand: Box::new(operand),
+                        span: Some(crate::ast::Span::new(span.start, operand_span)),
+                    };
+                }
+                Ok(operand)
+            })
+            .boxed();
+
+        // Binary operators with precedence
+        let pow_op = operators::pow_op(ws.clone()).with_ctx(());
+        let mul_op = operators::mul_op(ws.clone()).with_ctx(());
+        let add_op = operators::add_op(ws.clone()).with_ctx(());
+        let range_op = operators::range_op(ws.clone()).with_ctx(());
+        let cmp_op = operators::cmp_op(ws.clone()).with_ctx(());
+        let eq_op = operators::eq_op(ws.clone()).with_ctx(());
+        let and_op = operators::and_op(ws.clone()).with_ctx(());
+        let or_op = operators::or_op(ws.clone()).with_ctx(());
+        let pipe_op = operators::pipe_op(ws.clone()).with_ctx(());
+
+        // Build expression with precedence: |> > || > && > == != > < <= > >= > .. ..= > + - > * / % > ** > unary > postfix
+        let pow_expr = unary_expr
+            .clone()
+            .then(
+                pow_op
+                    .then(unary_expr.clone())
+                    .repeated()
+                    .collect::<Vec<_>>(),
+            )
+            .try_map(|(first, rest), span| {
+                // `**` is right-associative, so fold from the last operand back
+                let (ops, mut operands): (Vec<_>, Vec<_>) = rest.into_iter().unzip();
+                operands.insert(0, first);
+                let mut right = operands.pop().expect("at least one operand");
+                for (op, left) in ops.into_iter().rev().zip(operands.into_iter().rev()) {
+                    let left_span = left.span().map(|s| s.start).unwrap_or(span.start);
+                    let right_span = right.span().map(|s| s.end).unwrap_or(span.end);
+                    right = Expr::Binary {
+                        left: Box::new(left),
+                        op,
+                        right: Box::new(right),
+                        span: Some(crate::ast::Span::new(left_span, right_span)),
+                    };
+                }
+                Ok(right)
+            })
+            .boxed();
 
-    // Build expression with precedence: || > && > == != > < <= > >= > + - > * / % > unary > postfix
-    let mul_expr = unary_expr
-        .clone()
-        .then(
-            mul_op
-                .then(unary_expr.clone())
-                .repeated()
-                .collect::<Vec<_>>(),
-        )
-        .try_map(|(mut left, ops), span| {
-            for (op, right) in ops {
-                let left_span = left.span().map(|s| s.start).unwrap_or(span.start);
-                left = Expr::Binary {
-                    left: Box::new(left),
-                    op,
-                    right: Box::new(right),
-                    span: Some(crate::ast::Span::new(left_span, span.end)),
-                };
-            }
-            Ok(left)
-        })
-        .boxed();
+        let mul_expr = pow_expr
+            .clone()
+            .then(mul_op.then(pow_expr.clone()).repeated().collect::<Vec<_>>())
+            .try_map(|(mut left, ops), span| {
+                for (op, right) in ops {
+                    let left_span = left.span().map(|s| s.start).unwrap_or(span.start);
+                    left = Expr::Binary {
+                        left: Box::new(left),
+                        op,
+                        right: Box::new(right),
+                        span: Some(crate::ast::Span::new(left_span, span.end)),
+                    };
+                }
+                Ok(left)
+            })
+            .boxed();
 
-    let add_expr = mul_expr
-        .clone()
-        .then(add_op.then(mul_expr.clone()).repeated().collect::<Vec<_>>())
-        .try_map(|(mut left, ops), span| {
-            for (op, right) in ops {
-                let left_span = left.span().map(|s| s.start).unwrap_or(span.start);
-                left = Expr::Binary {
-                    left: Box::new(left),
-                    op,
-                    right: Box::new(right),
-                    span: Some(crate::ast::Span::new(left_span, span.end)),
-                };
-            }
-            Ok(left)
-        })
-        .boxed();
+        let add_expr = mul_expr
+            .clone()
+            .then(add_op.then(mul_expr.clone()).repeated().collect::<Vec<_>>())
+            .try_map(|(mut left, ops), span| {
+                for (op, right) in ops {
+                    let left_span = left.span().map(|s| s.start).unwrap_or(span.start);
+                    left = Expr::Binary {
+                        left: Box::new(left),
+                        op,
+                        right: Box::new(right),
+                        span: Some(crate::ast::Span::new(left_span, span.end)),
+                    };
+                }
+                Ok(left)
+            })
+            .boxed();
 
-    let cmp_expr = add_expr
-        .clone()
-        .then(cmp_op.then(add_expr.clone()).repeated().collect::<Vec<_>>())
-        .try_map(|(mut left, ops), span| {
-            for (op, right) in ops {
-                let left_span = left.span().map(|s| s.start).unwrap_or(span.start);
-                left = Expr::Binary {
-                    left: Box::new(left),
-                    op,
-                    right: Box::new(right),
-                    span: Some(crate::ast::Span::new(left_span, span.end)),
+        // Ranges don't chain: `a..b..c` is a syntax error
+        let range_expr = add_expr
+            .clone()
+            .then(range_op.then(add_expr.clone()).or_not())
+            .try_map(|(start, end), span| {
+                let Some((inclusive, end)) = end else {
+                    return Ok(start);
                 };
-            }
-            Ok(left)
-        })
-        .boxed();
+                let start_pos = start.span().map_or(span.start, |s| s.start);
+                Ok(Expr::Range {
+                    start: Box::new(start),
+                    end: Box::new(end),
+                    inclusive,
+                    span: Some(crate::ast::Span::new(start_pos, span.end)),
+                })
+            })
+            .boxed();
 
-    let eq_expr = cmp_expr
-        .clone()
-        .then(eq_op.then(cmp_expr.clone()).repeated().collect::<Vec<_>>())
-        .try_map(|(mut left, ops), span| {
-            for (op, right) in ops {
-                let left_span = left.span().map(|s| s.start).unwrap_or(span.start);
-                left = Expr::Binary {
-                    left: Box::new(left),
-                    op,
-                    right: Box::new(right),
-                    span: Some(crate::ast::Span::new(left_span, span.end)),
-                };
-            }
-            Ok(left)
-        })
-        .boxed();
+        let cmp_expr = range_expr
+            .clone()
+            .then(
+                cmp_op
+                    .then(range_expr.clone())
+                    .repeated()
+                    .collect::<Vec<_>>(),
+            )
+            .try_map(|(mut left, ops), span| {
+                for (op, right) in ops {
+                    let left_span = left.span().map(|s| s.start).unwrap_or(span.start);
+                    left = Expr::Binary {
+                        left: Box::new(left),
+                        op,
+                        right: Box::new(right),
+                        span: Some(crate::ast::Span::new(left_span, span.end)),
+                    };
+                }
+                Ok(left)
+            })
+            .boxed();
 
-    let and_expr = eq_expr
-        .clone()
-        .then(and_op.then(eq_expr.clone()).repeated().collect::<Vec<_>>())
-        .try_map(|(mut left, ops), span| {
-            for (op, right) in ops {
-                let left_span = left.span().map(|s| s.start).unwrap_or(span.start);
-                left = Expr::Logical {
-                    left: Box::new(left),
-                    op,
-                    right: Box::new(right),
-                    span: Some(crate::ast::Span::new(left_span, span.end)),
-                };
-            }
-            Ok(left)
-        })
-        .boxed();
+        let eq_expr = cmp_expr
+            .clone()
+            .then(eq_op.then(cmp_expr.clone()).repeated().collect::<Vec<_>>())
+            .try_map(|(mut left, ops), span| {
+                for (op, right) in ops {
+                    let left_span = left.span().map(|s| s.start).unwrap_or(span.start);
+                    left = Expr::Binary {
+                        left: Box::new(left),
+                        op,
+                        right: Box::new(right),
+                        span: Some(crate::ast::Span::new(left_span, span.end)),
+                    };
+                }
+                Ok(left)
+            })
+            .boxed();
 
-    let or_expr = and_expr
-        .clone()
-        .then(or_op.then(and_expr.clone()).repeated().collect::<Vec<_>>())
-        .try_map(|(mut left, ops), span| {
-            for (op, right) in ops {
-                let left_span = left.span().map(|s| s.start).unwrap_or(span.start);
-                left = Expr::Logical {
-                    left: Box::new(left),
-                    op,
-                    right: Box::new(right),
-                    span: Some(crate::ast::Span::new(left_span, span.end)),
-                };
-            }
-            Ok(left)
-        })
-        .boxed();
+        let and_expr = eq_expr
+            .clone()
+            .then(and_op.then(eq_expr.clone()).repeated().collect::<Vec<_>>())
+            .try_map(|(mut left, ops), span| {
+                for (op, right) in ops {
+                    let left_span = left.span().map(|s| s.start).unwrap_or(span.start);
+                    left = Expr::Logical {
+                        left: Box::new(left),
+                        op,
+                        right: Box::new(right),
+                        span: Some(crate::ast::Span::new(left_span, span.end)),
+                    };
+                }
+                Ok(left)
+            })
+            .boxed();
 
-    expr_ref.define(or_expr);
+        let or_expr = and_expr
+            .clone()
+            .then(or_op.then(and_expr.clone()).repeated().collect::<Vec<_>>())
+            .try_map(|(mut left, ops), span| {
+                for (op, right) in ops {
+                    let left_span = left.span().map(|s| s.start).unwrap_or(span.start);
+                    left = Expr::Logical {
+                        left: Box::new(left),
+                        op,
+                        right: Box::new(right),
+                        span: Some(crate::ast::Span::new(left_span, span.end)),
+                    };
+                }
+                Ok(left)
+            })
+            .boxed();
+
+        // `x |> f(a)` is sugar for `f(x, a)`, and `x |> f` for `f(x)`: the left
+        // side becomes the first argument of the call on the right
+        or_expr
+            .clone()
+            .then(
+                pipe_op
+                    .ignore_then(or_expr.clone().validate(|stage, e, emitter| {
+                        // Reported at the stage, keeping the parse going
+                        if matches!(stage, Expr::MethodCall { .. }) {
+                            emitter.emit(Rich::custom(
+                                e.span(),
+                                "Cannot pipe into a method call; pipe into a function instead",
+                            ));
+                        }
+                        stage
+                    }))
+                    .repeated()
+                    .collect::<Vec<_>>(),
+            )
+            .map_with(|(mut left, stages), e| {
+                let span: SimpleSpan = e.span();
+                for stage in stages {
+                    let start = left.span().map_or(span.start, |s| s.start);
+                    let end = stage.span().map_or(span.end, |s| s.end);
+                    let call_span = Some(crate::ast::Span::new(start, end));
+                    left = match stage {
+                        Expr::Call {
+                            callee,
+                            mut arguments,
+                            ..
+                        } => {
+                            arguments.insert(0, CallArgument::Positional(left));
+                            Expr::Call {
+                                callee,
+                                arguments,
+                                span: call_span,
+                            }
+                        }
+                        callee => Expr::Call {
+                            callee: Box::new(callee),
+                            arguments: vec![CallArgument::Positional(left)],
+                            span: call_span,
+                        },
+                    };
+                }
+                left
+            })
+            .boxed()
+    };
+
+    // `cond ? a : b` is shorthand for `if cond do a else do b end`. Nested
+    // ternaries associate right. Inside the then-branch a `:` always ends
+    // the branch rather than starting a method call, so `c ? f :g(x)` reads
+    // as `c ? f : g(x)`; parenthesize to call a method there.
+    let ternary =
+        |operand: Boxed<'a, 'a, &'a str, Expr, extra::Err<Rich<'a, char>>>,
+         then_branch: Boxed<'a, 'a, &'a str, Expr, extra::Err<Rich<'a, char>>>,
+         else_branch: Boxed<'a, 'a, &'a str, Expr, extra::Err<Rich<'a, char>>>| {
+            operand
+                .then(
+                    just('?')
+                        .padded_by(ws.clone())
+                        .ignore_then(then_branch)
+                        .then_ignore(just(':').padded_by(ws.clone()))
+                        .then(else_branch)
+                        .or_not(),
+                )
+                .try_map(|(condition, branches), span| {
+                    let Some((then_expr, else_expr)) = branches else {
+                        return Ok(condition);
+                    };
+                    let branch = |value: Expr| {
+                        let span = value.span();
+                        vec![Stmt::Return {
+                            value,
+                            implicit: true,
+                            span,
+                        }]
+                    };
+                    let start = condition.span().map_or(span.start, |s| s.start);
+                    Ok(Expr::If {
+                        condition: Box::new(condition),
+                        then_block: branch(then_expr),
+                        else_block: Some(branch(else_expr)),
+                        span: Some(crate::ast::Span::new(start, span.end)),
+                    })
+                })
+                .boxed()
+        };
+
+    let mut then_branch = Recursive::declare();
+    then_branch.define(ternary(
+        operator_expr.clone().with_ctx(false).boxed(),
+        then_branch.clone().boxed(),
+        then_branch.clone().boxed(),
+    ));
+    expr_ref.define(ternary(
+        operator_expr.with_ctx(true).boxed(),
+        then_branch.boxed(),
+        expr_ref.clone().boxed(),
+    ));
 
     // Statement parsers
 
@@ -350,18 +543,13 @@ pub fn parser<'a>() -> impl Parser<'a, &'a str, Program, extra::Err<Rich<'a, cha
     );
     let return_stmt = statements::return_stmt(ws.clone(), expr_ref.clone());
     let if_stmt = statements::if_stmt(ws.clone(), expr_ref.clone(), stmt_ref.clone());
-    let while_stmt = statements::while_stmt(ws.clone(), expr_ref.clone(), stmt_ref.clone());
-    let do_while_stmt = statements::do_while_stmt(ws.clone(), expr_ref.clone(), stmt_ref.clone());
-    let for_stmt = statements::for_stmt(
-        ws.clone(),
-        pattern.clone(),
-        expr_ref.clone(),
-        stmt_ref.clone(),
-    );
-    let break_stmt = statements::break_stmt(ws.clone());
+    let unless_stmt = statements::unless_stmt(ws.clone(), expr_ref.clone(), stmt_ref.clone());
+    let break_stmt = statements::break_stmt(ws.clone(), expr_ref.clone());
     let continue_stmt = statements::continue_stmt(ws.clone());
     let assignment = statements::assignment(ws.clone(), expr_ref.clone());
     let expr_stmt = statements::expr_stmt(expr_ref.clone());
+    let type_decl = statements::type_decl(ws.clone(), ident.clone(), type_parser.clone());
+    let export_stmt = statements::export_stmt(ws.clone(), ident.clone());
 
     let match_stmt = statements::match_stmt(
         ws.clone(),
@@ -377,9 +565,12 @@ pub fn parser<'a>() -> impl Parser<'a, &'a str, Program, extra::Err<Rich<'a, cha
         continue_stmt,
         var_decl,
         if_stmt,
+        unless_stmt,
         do_while_stmt, // Must come before while_stmt to avoid ambiguity with "do"
         while_stmt,
         for_stmt,
+        type_decl,   // Contextual keyword: must come before assignment/expr_stmt
+        export_stmt, // Contextual keyword, like `type`
         assignment,
         expr_stmt,
     ))
@@ -411,7 +602,38 @@ pub fn parser<'a>() -> impl Parser<'a, &'a str, Program, extra::Err<Rich<'a, cha
     )
 }
 
+/// Deepest nesting of brackets and `do ... end` blocks `parse` accepts.
+/// Chumsky grows the stack for recursive parsers as needed, so parsing runs
+/// on the caller's thread; this keeps the passes that later walk the tree
+/// within an ordinary thread stack.
+pub const MAX_NESTING_DEPTH: usize = 128;
+
 pub fn parse(source: &str, filename: &str) -> Result<Program, Vec<Diagnostic>> {
+    parse_with_max_depth(source, filename, MAX_NESTING_DEPTH)
+}
+
+/// Parse with a custom nesting limit. Sources nesting deeper are rejected
+/// with a diagnostic instead of risking a stack overflow in the parser.
+pub fn parse_with_max_depth(
+    source: &str,
+    filename: &str,
+    max_depth: usize,
+) -> Result<Program, Vec<Diagnostic>> {
+    if let Some(span) = depth::find_too_deep(source, max_depth) {
+        let diag = Diagnostic::error(
+            DiagnosticKind::Parse,
+            "expression nesting too deep".to_string(),
+            span,
+            filename.to_string(),
+        )
+        .with_note(format!("nesting is limited to {max_depth} levels"));
+        return Err(vec![diag]);
+    }
+
+    parse_unchecked(source, filename)
+}
+
+fn parse_unchecked(source: &str, filename: &str) -> Result<Program, Vec<Diagnostic>> {
     let (output, errs) = parser().parse(source).into_output_errors();
 
     if errs.is_empty() {
@@ -426,7 +648,8 @@ pub fn parse(source: &str, filename: &str) -> Result<Program, Vec<Diagnostic>> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ast::{BinaryOp, Expr, LogicalOp, Stmt, UnaryOp};
+    use crate::ast::{AssignOp, BinaryOp, Expr, LogicalOp, Pattern, Span, Stmt, TableKey, UnaryOp};
+    use crate::test_utils::{missing_spans, strip_spans_stmt};
 
     fn parse_expr(source: &str) -> Expr {
         let program = parse(source, "test.luma").expect("Parse failed");
@@ -443,6 +666,51 @@ mod tests {
         program.statements[0].clone()
     }
 
+    #[test]
+    fn test_parse_rejects_deep_nesting() {
+        let source = format!("let x = {}1{}", "(".repeat(5000), ")".repeat(5000));
+        let errors = parse(&source, "test.luma").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "expression nesting too deep");
+        assert_eq!(errors[0].span.start, 8 + MAX_NESTING_DEPTH);
+    }
+
+    #[test]
+    fn test_parse_accepts_nesting_at_limit() {
+        let depth = MAX_NESTING_DEPTH;
+        let source = format!("let x = {}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(parse(&source, "test.luma").is_ok());
+
+        let blocks = format!(
+            "{}x = 1{}",
+            "if true do\n".repeat(depth),
+            "\nend".repeat(depth)
+        );
+        assert!(parse_with_max_depth(&blocks, "test.luma", depth - 1).is_err());
+    }
+
+    #[test]
+    fn test_flat_if_else_chains_do_not_add_up() {
+        let source =
+            "var x = 0\nif x > 0 do\n  x = 1\nelse if x < 0 do\n  x = 2\nelse do\n  x = 3\nend\n"
+                .repeat(MAX_NESTING_DEPTH + 10);
+        assert!(parse(&source, "test.luma").is_ok());
+    }
+
+    #[test]
+    fn test_nesting_at_limit_fits_on_the_callers_stack() {
+        // Runs on the test harness's ordinary thread, with no stack of its own
+        let depth = MAX_NESTING_DEPTH;
+        let source = format!(
+            "{}1{}",
+            "let f = fn() do\n".repeat(depth),
+            "\nend".repeat(depth)
+        );
+        let program = parse(&source, "test.luma").expect("Parse failed");
+        crate::typecheck::typecheck_program(&program).expect("Typecheck failed");
+        crate::bytecode::compile::compile_program(&program);
+    }
+
     // ===== Literal Tests =====
 
     #[test]
@@ -459,12 +727,236 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_hex_and_binary_literals() {
+        assert!(matches!(parse_expr("0xFF"), Expr::Number { value, .. } if value == 255.0));
+        assert!(matches!(parse_expr("0Xff"), Expr::Number { value, .. } if value == 255.0));
+        assert!(matches!(parse_expr("0b1010"), Expr::Number { value, .. } if value == 10.0));
+        assert!(matches!(parse_expr("0o777"), Expr::Number { value, .. } if value == 511.0));
+        assert!(matches!(parse_expr("0O17"), Expr::Number { value, .. } if value == 15.0));
+    }
+
+    #[test]
+    fn test_parse_radix_literals_keep_integer_precision() {
+        let max_safe = (1u64 << 53) - 1;
+        let source = format!("0x{max_safe:X}");
+        assert!(
+            matches!(parse_expr(&source), Expr::Number { value, .. } if value == max_safe as f64)
+        );
+        let source = format!("0b{max_safe:b}");
+        assert!(
+            matches!(parse_expr(&source), Expr::Number { value, .. } if value == max_safe as f64)
+        );
+    }
+
+    #[test]
+    fn test_parse_digit_separators() {
+        assert!(matches!(parse_expr("1_000"), Expr::Number { value, .. } if value == 1000.0));
+        assert!(matches!(parse_expr("1.234_5"), Expr::Number { value, .. } if value == 1.2345));
+        assert!(matches!(parse_expr("1_0e1_0"), Expr::Number { value, .. } if value == 1e11));
+        assert!(matches!(parse_expr("0xFF_FF"), Expr::Number { value, .. } if value == 65535.0));
+        // A leading underscore makes an identifier instead
+        assert!(matches!(parse_expr("_1"), Expr::Identifier { .. }));
+
+        for (source, end) in [
+            ("let a = 1_", 10),
+            ("let a = 1__0", 12),
+            ("let a = 0x_FF", 13),
+            ("let a = 1_.5", 10),
+        ] {
+            let errors = parse(source, "test.luma").unwrap_err();
+            assert!(
+                errors[0].message.contains("must be between two digits"),
+                "{source}: {}",
+                errors[0].message
+            );
+            // The error covers the whole literal
+            assert_eq!(errors[0].span, Span::new(8, end), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_parse_number_matches_literals() {
+        assert_eq!(parse_number("1_000"), Ok(1000.0));
+        assert_eq!(parse_number("2.5E-3"), Ok(0.0025));
+        assert_eq!(parse_number("0b1010_1010"), Ok(170.0));
+        assert!(parse_number("_1").is_err());
+        assert!(parse_number("1_").is_err());
+        assert!(parse_number("1__0").is_err());
+        assert_eq!(
+            parse_number("0xZZ"),
+            Err("invalid digit 'Z' in hexadecimal literal".to_string())
+        );
+        for source in ["1_000", "3.141_59", "0xFF_FF", "0b1_1"] {
+            let Expr::Number { value, .. } = parse_expr(source) else {
+                panic!("{source} is not a number");
+            };
+            assert_eq!(parse_number(source), Ok(value), "{source}");
+        }
+    }
+
+    #[test]
+    fn test_parse_error_invalid_radix_digits() {
+        let errors = parse("let a = 0xZZ", "test.luma").unwrap_err();
+        assert_eq!(
+            errors[0].message,
+            "invalid digit 'Z' in hexadecimal literal"
+        );
+        assert_eq!(errors[0].span, Span::new(10, 11));
+
+        let errors = parse("let a = 0b102", "test.luma").unwrap_err();
+        assert_eq!(errors[0].message, "invalid digit '2' in binary literal");
+        assert_eq!(errors[0].span, Span::new(12, 13));
+
+        let errors = parse("let a = 0o78", "test.luma").unwrap_err();
+        assert_eq!(errors[0].message, "invalid digit '8' in octal literal");
+        assert_eq!(errors[0].span, Span::new(11, 12));
+
+        let errors = parse("let a = 0x", "test.luma").unwrap_err();
+        assert_eq!(errors[0].message, "hexadecimal literal has no digits");
+    }
+
     #[test]
     fn test_parse_string_literal() {
         let expr = parse_expr(r#""hello world""#);
         assert!(matches!(expr, Expr::String { value, .. } if value == "hello world"));
     }
 
+    #[test]
+    fn test_parse_string_interpolation() {
+        let Expr::Binary {
+            left,
+            op: BinaryOp::Add,
+            right,
+            span,
+        } = parse_expr(r#""n = ${count + 1}""#)
+        else {
+            panic!("Expected concatenation");
+        };
+        assert_eq!(span, Some(Span::new(0, 18)));
+        assert!(matches!(*left, Expr::String { ref value, .. } if value == "n = "));
+        // Non-string interpolants are converted with `.into(String)`
+        let Expr::Call { callee, .. } = *right else {
+            panic!("Expected into(String) call");
+        };
+        assert!(matches!(
+            *callee,
+            Expr::MemberAccess { ref object, ref member, .. }
+                if member == "into" && matches!(**object, Expr::Binary { op: BinaryOp::Add, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_string_interpolation_with_nested_braces() {
+        let expr = parse_expr(r#""a ${f({ k = { v = 1 } }).k} b""#);
+        let Expr::Binary { left, right, .. } = expr else {
+            panic!("Expected concatenation");
+        };
+        assert!(matches!(*right, Expr::String { ref value, .. } if value == " b"));
+        let Expr::Binary { right: call, .. } = *left else {
+            panic!("Expected concatenation");
+        };
+        let Expr::Call { callee, .. } = *call else {
+            panic!("Expected into(String) call");
+        };
+        let Expr::MemberAccess { object, .. } = *callee else {
+            panic!("Expected member access");
+        };
+        assert!(matches!(*object, Expr::MemberAccess { ref member, .. } if member == "k"));
+
+        // A string inside an interpolation may itself interpolate
+        assert!(parse("let s = \"x${\"in${y}\"}\"", "test.luma").is_ok());
+    }
+
+    #[test]
+    fn test_parse_escaped_interpolation_is_literal() {
+        let expr = parse_expr(r#""cost: \${price}\r""#);
+        assert!(matches!(expr, Expr::String { value, .. } if value == "cost: ${price}\r"));
+    }
+
+    #[test]
+    fn test_parse_raw_string_keeps_backslashes() {
+        let expr = parse_expr(r"`a\nb ${x}`");
+        assert!(matches!(expr, Expr::String { value, .. } if value == r"a\nb ${x}"));
+    }
+
+    #[test]
+    fn test_parse_raw_string_spans_lines() {
+        let source = "let s = `{\n  \"k\": 1\n}`";
+        let program = parse(source, "test.luma").unwrap();
+        let Stmt::VarDecl { value, .. } = &program.statements[0] else {
+            panic!("Expected VarDecl");
+        };
+        assert!(matches!(value, Expr::String { value, .. } if value == "{\n  \"k\": 1\n}"));
+        assert_eq!(value.span(), Some(Span::new(8, source.len())));
+    }
+
+    #[test]
+    fn test_parse_triple_quoted_string_keeps_newlines() {
+        let source = "let s = \"\"\"line 1\n  line 2\n\"\"\"";
+        let program = parse(source, "test.luma").unwrap();
+        let Stmt::VarDecl { value, .. } = &program.statements[0] else {
+            panic!("Expected VarDecl");
+        };
+        assert!(matches!(value, Expr::String { value, .. } if value == "line 1\n  line 2\n"));
+        assert_eq!(value.span(), Some(Span::new(8, source.len())));
+    }
+
+    #[test]
+    fn test_parse_triple_quoted_string_with_inner_quotes() {
+        let expr = parse_expr(r#""""say "hi" or ""bye"" \t""""#);
+        assert!(
+            matches!(expr, Expr::String { ref value, .. } if value == "say \"hi\" or \"\"bye\"\" \t"),
+            "{expr:?}"
+        );
+        // Interpolation works as in ordinary strings
+        assert!(matches!(
+            parse_expr(r#""""a ${x} b""""#),
+            Expr::Binary {
+                op: BinaryOp::Add,
+                ..
+            }
+        ));
+        assert!(matches!(parse_expr(r#""""""""#), Expr::String { value, .. } if value.is_empty()));
+    }
+
+    #[test]
+    fn test_parse_triple_quoted_string_ending_in_a_quote() {
+        // The closing delimiter is the last three quotes of a run
+        let expr = parse_expr(r#""""say "hi"""""#);
+        assert!(
+            matches!(expr, Expr::String { ref value, .. } if value == "say \"hi\""),
+            "{expr:?}"
+        );
+        let expr = parse_expr(r#""""a """"""#);
+        assert!(
+            matches!(expr, Expr::String { ref value, .. } if value == "a \"\""),
+            "{expr:?}"
+        );
+    }
+
+    #[test]
+    fn test_parse_r_prefixed_raw_string() {
+        let expr = parse_expr(r#"r"\n""#);
+        assert!(
+            matches!(expr, Expr::String { ref value, .. } if value.len() == 2 && value == r"\n")
+        );
+        let expr = parse_expr(r#"r"C:\dir ${x} `q`""#);
+        assert!(matches!(expr, Expr::String { value, .. } if value == r"C:\dir ${x} `q`"));
+        // `r` on its own is still an identifier
+        assert!(matches!(parse_expr("r"), Expr::Identifier { name, .. } if name == "r"));
+    }
+
+    #[test]
+    fn test_parse_raw_and_quoted_strings_together() {
+        let expr = parse_expr("`raw\\` + \"q\\n\"");
+        let Expr::Binary { left, right, .. } = expr else {
+            panic!("Expected concatenation");
+        };
+        assert!(matches!(*left, Expr::String { ref value, .. } if value == "raw\\"));
+        assert!(matches!(*right, Expr::String { ref value, .. } if value == "q\n"));
+    }
+
     #[test]
     fn test_parse_boolean_true() {
         let expr = parse_expr("true");
@@ -601,6 +1093,276 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_power_is_right_associative() {
+        let number = |e: &Expr, n: f64| matches!(e, Expr::Number { value, .. } if *value == n);
+        let Expr::Binary {
+            left,
+            op: BinaryOp::Pow,
+            right,
+            ..
+        } = parse_expr("2 ** 3 ** 2")
+        else {
+            panic!("Expected exponentiation");
+        };
+        assert!(number(&left, 2.0));
+        match *right {
+            Expr::Binary {
+                left,
+                op: BinaryOp::Pow,
+                right,
+                span,
+            } => {
+                assert!(number(&left, 3.0) && number(&right, 2.0));
+                assert_eq!(span, Some(Span::new(5, 11)));
+            }
+            other => panic!("Expected 3 ** 2 on the right, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_power_binds_tighter_than_mul() {
+        let Expr::Binary {
+            left,
+            op: BinaryOp::Mul,
+            right,
+            ..
+        } = parse_expr("2 * 3 ** 2 * 4")
+        else {
+            panic!("Expected multiplication");
+        };
+        assert!(matches!(
+            *left,
+            Expr::Binary {
+                op: BinaryOp::Mul,
+                right: ref inner,
+                ..
+            } if matches!(**inner, Expr::Binary { op: BinaryOp::Pow, .. })
+        ));
+        assert!(matches!(*right, Expr::Number { value, .. } if value == 4.0));
+
+        // Unary minus applies to the base
+        assert!(matches!(
+            parse_expr("-2 ** 2"),
+            Expr::Binary { op: BinaryOp::Pow, left, .. } if matches!(*left, Expr::Unary { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_range() {
+        let Expr::Range {
+            start,
+            end,
+            inclusive: false,
+            span,
+        } = parse_expr("0..n + 1")
+        else {
+            panic!("Expected an exclusive range");
+        };
+        assert!(matches!(*start, Expr::Number { value, .. } if value == 0.0));
+        assert!(matches!(
+            *end,
+            Expr::Binary {
+                op: BinaryOp::Add,
+                ..
+            }
+        ));
+        assert_eq!(span.map(|s| (s.start, s.end)), Some((0, 8)));
+        assert!(matches!(
+            parse_expr("1..=3"),
+            Expr::Range {
+                inclusive: true,
+                ..
+            }
+        ));
+        // Ranges bind looser than arithmetic but tighter than comparison
+        assert!(matches!(
+            parse_expr("0..3 == x"),
+            Expr::Binary { op: BinaryOp::Eq, left, .. } if matches!(*left, Expr::Range { .. })
+        ));
+        assert!(parse("let r = 0..1..2", "test.luma").is_err());
+    }
+
+    fn callee_name(expr: &Expr) -> &str {
+        match expr {
+            Expr::Identifier { name, .. } => name,
+            other => panic!("Expected an identifier, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_pipe_desugars_to_calls() {
+        // x |> f |> g(2) is g(f(x), 2)
+        let Expr::Call {
+            callee,
+            arguments,
+            span,
+        } = parse_expr("x |> f |> g(2)")
+        else {
+            panic!("Expected a call");
+        };
+        assert_eq!(callee_name(&callee), "g");
+        assert_eq!(span.map(|s| (s.start, s.end)), Some((0, 14)));
+        let [
+            CallArgument::Positional(inner),
+            CallArgument::Positional(Expr::Number { value: 2.0, .. }),
+        ] = arguments.as_slice()
+        else {
+            panic!("Expected f(x) then 2, got {arguments:?}");
+        };
+        let Expr::Call {
+            callee, arguments, ..
+        } = inner
+        else {
+            panic!("Expected f(x), got {inner:?}");
+        };
+        assert_eq!(callee_name(callee), "f");
+        assert!(matches!(
+            arguments.as_slice(),
+            [CallArgument::Positional(x)] if callee_name(x) == "x"
+        ));
+
+        // Pipes bind looser than arithmetic and logic
+        let Expr::Call { arguments, .. } = parse_expr("a + 1 |> f") else {
+            panic!("Expected a call");
+        };
+        assert!(matches!(
+            &arguments[0],
+            CallArgument::Positional(Expr::Binary {
+                op: BinaryOp::Add,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_parse_pipe_into_method_call_is_an_error() {
+        let errors = parse("let y = x |> obj:method(1)", "test.luma").unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.message.contains("Cannot pipe into a method call")),
+            "{errors:?}"
+        );
+
+        // The error points at the method call, not the start of the pipe
+        let source = "let t = {}\nlet y = 3 |> t:m(1)";
+        let errors = parse(source, "test.luma").unwrap_err();
+        let error = errors
+            .iter()
+            .find(|e| e.message.contains("Cannot pipe into a method call"))
+            .expect("pipe error");
+        let span = error.span;
+        assert_eq!(&source[span.start..span.end], "t:m(1)", "{errors:?}");
+    }
+
+    /// The single expression a ternary branch returns
+    fn ternary_branch(block: &[Stmt]) -> &Expr {
+        match block {
+            [Stmt::Return { value, .. }] => value,
+            other => panic!("Expected a single-expression branch, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_ternary_desugars_to_if() {
+        let Expr::If {
+            condition,
+            then_block,
+            else_block: Some(else_block),
+            span,
+        } = parse_expr("x > 0 ? f(x) : g(x, 1)")
+        else {
+            panic!("Expected an if expression");
+        };
+        assert!(matches!(
+            *condition,
+            Expr::Binary {
+                op: BinaryOp::Gt,
+                ..
+            }
+        ));
+        assert!(matches!(ternary_branch(&then_block), Expr::Call { .. }));
+        assert!(matches!(ternary_branch(&else_block), Expr::Call { .. }));
+        assert_eq!(span.map(|s| s.start), Some(0));
+    }
+
+    #[test]
+    fn test_parse_ternary_is_right_associative() {
+        let Expr::If {
+            condition,
+            else_block: Some(else_block),
+            ..
+        } = parse_expr("a ? b : c ? d : e")
+        else {
+            panic!("Expected an if expression");
+        };
+        assert!(matches!(*condition, Expr::Identifier { ref name, .. } if name == "a"));
+        assert!(matches!(
+            ternary_branch(&else_block),
+            Expr::If { condition, .. } if matches!(**condition, Expr::Identifier { ref name, .. } if name == "c")
+        ));
+    }
+
+    #[test]
+    fn test_parse_method_call_allows_space_after_colon() {
+        assert!(matches!(parse_expr("obj:m(1)"), Expr::MethodCall { .. }));
+        assert!(matches!(parse_expr("obj : m(1)"), Expr::MethodCall { .. }));
+        let Expr::Call { arguments, .. } = parse_expr("print(t: m(3))") else {
+            panic!("Expected a call");
+        };
+        assert!(matches!(
+            arguments.as_slice(),
+            [CallArgument::Positional(Expr::MethodCall { .. })]
+        ));
+    }
+
+    #[test]
+    fn test_parse_ternary_then_branch_ends_at_colon() {
+        for source in [
+            "ok ? obj : m(1)",
+            "ok ? obj :m(1)",
+            "ok ? a ? b :c(1) :d(2)",
+        ] {
+            let Expr::If {
+                then_block,
+                else_block: Some(else_block),
+                ..
+            } = parse_expr(source)
+            else {
+                panic!("Expected an if expression for {source}");
+            };
+            assert!(!matches!(
+                ternary_branch(&then_block),
+                Expr::MethodCall { .. }
+            ));
+            assert!(
+                matches!(ternary_branch(&else_block), Expr::Call { .. }),
+                "{source}"
+            );
+        }
+        // Method calls still work in the condition, the else-branch and
+        // anywhere nested in brackets
+        let Expr::If {
+            condition,
+            then_block,
+            else_block: Some(else_block),
+            ..
+        } = parse_expr("a:ok() ? (o:m()) : o:n()")
+        else {
+            panic!("Expected an if expression");
+        };
+        assert!(matches!(*condition, Expr::MethodCall { .. }));
+        assert!(matches!(
+            ternary_branch(&then_block),
+            Expr::MethodCall { .. }
+        ));
+        assert!(matches!(
+            ternary_branch(&else_block),
+            Expr::MethodCall { .. }
+        ));
+    }
+
     // ===== Comparison Operator Tests =====
 
     #[test]
@@ -852,6 +1614,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_table_spread() {
+        let expr = parse_expr("{ ...p, x = 10 }");
+        match expr {
+            Expr::Table { fields, .. } => {
+                assert_eq!(fields.len(), 2);
+                assert_eq!(fields[0].0, TableKey::Spread);
+                assert!(matches!(&fields[0].1, Expr::Identifier { name, .. } if name == "p"));
+                assert_eq!(fields[1].0, TableKey::Identifier("x".to_string()));
+            }
+            _ => panic!("Expected table"),
+        }
+    }
+
+    #[test]
+    fn test_parse_list_and_call_spread() {
+        let expr = parse_expr("[...a, 4, ...b]");
+        let Expr::List { elements, .. } = expr else {
+            panic!("Expected list, got {expr:?}");
+        };
+        assert_eq!(elements.len(), 3);
+        assert!(matches!(&elements[0], Expr::Spread { value, .. }
+            if matches!(&**value, Expr::Identifier { name, .. } if name == "a")));
+        assert!(matches!(&elements[1], Expr::Number { .. }));
+        assert!(matches!(&elements[2], Expr::Spread { .. }));
+
+        let expr = parse_expr("f(1, ...args)");
+        let Expr::Call { arguments, .. } = expr else {
+            panic!("Expected call, got {expr:?}");
+        };
+        assert!(matches!(
+            &arguments[0],
+            CallArgument::Positional(Expr::Number { .. })
+        ));
+        assert!(matches!(
+            &arguments[1],
+            CallArgument::Positional(Expr::Spread { .. })
+        ));
+    }
+
     // ===== Function Call Tests =====
 
     #[test]
@@ -945,6 +1747,69 @@ mod tests {
         assert!(matches!(stmt, Stmt::VarDecl { mutable: true, .. }));
     }
 
+    #[test]
+    fn test_parse_let_mut_is_var() {
+        for source in ["var x = 1", "let mut x = 1"] {
+            let Stmt::VarDecl {
+                mutable,
+                name,
+                r#type,
+                value,
+                ..
+            } = parse_stmt(source)
+            else {
+                panic!("Expected var decl for {source}");
+            };
+            assert!(mutable, "{source}");
+            assert_eq!(name, "x");
+            assert!(r#type.is_none());
+            assert!(matches!(value, Expr::Number { value, .. } if value == 1.0));
+        }
+        assert!(matches!(
+            parse_stmt("let mut [a, b] = [1, 2]"),
+            Stmt::DestructuringVarDecl { mutable: true, .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_nested_table_pattern() {
+        let Stmt::DestructuringVarDecl { pattern, .. } =
+            parse_stmt("let { pos: { x, y: py }, tags: [first, ...more] } = shape")
+        else {
+            panic!("Expected destructuring declaration");
+        };
+        let Pattern::TablePattern { fields, .. } = pattern else {
+            panic!("Expected a table pattern");
+        };
+        assert_eq!(fields[0].key, "pos");
+        assert_eq!(fields[0].binding(), None);
+        let Some(Pattern::TablePattern { fields: inner, .. }) = &fields[0].pattern else {
+            panic!("Expected a nested table pattern");
+        };
+        assert_eq!(inner[0].binding(), Some("x"));
+        assert_eq!(inner[1].binding(), Some("py"));
+        assert!(matches!(
+            &fields[1].pattern,
+            Some(Pattern::ListPattern { elements, .. }) if elements.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_parse_mut_stays_an_identifier() {
+        assert!(matches!(
+            parse_stmt("let x = 1"),
+            Stmt::VarDecl { mutable: false, .. }
+        ));
+        assert!(matches!(
+            parse_stmt("let mut = 1"),
+            Stmt::VarDecl { mutable: false, ref name, .. } if name == "mut"
+        ));
+        assert!(matches!(
+            parse_stmt("let mutable = 1"),
+            Stmt::VarDecl { mutable: false, ref name, .. } if name == "mutable"
+        ));
+    }
+
     #[test]
     fn test_parse_var_decl_with_type() {
         let stmt = parse_stmt("let x: Number = 5");
@@ -957,6 +1822,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_arrow_function_type() {
+        let arrow = parse_stmt("let f: (Number, String) -> Boolean = g");
+        let fn_form = parse_stmt("let f: fn(Number, String): Boolean = g");
+        assert_eq!(strip_spans_stmt(arrow), strip_spans_stmt(fn_form));
+
+        let nested = parse_stmt("let f: () -> (Number) -> Number = g");
+        let Stmt::VarDecl {
+            r#type:
+                Some(crate::ast::Type::FunctionType {
+                    param_types,
+                    return_type,
+                    ..
+                }),
+            ..
+        } = nested
+        else {
+            panic!("Expected function-typed var decl");
+        };
+        assert!(param_types.is_empty());
+        assert!(matches!(
+            *return_type,
+            crate::ast::Type::FunctionType { .. }
+        ));
+    }
+
     // ===== If Statement Tests =====
 
     #[test]
@@ -976,6 +1867,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_every_node_has_a_span() {
+        let source = r#"
+type Shape = Circle { r: Number } | Square { s: Number }
+let area = fn(shape: Any, scale: Number): Number do
+  match shape do
+    { r } do r * r * scale end
+    _ do 0 end
+  end
+end
+var total = 0
+for [i, x] in indexed([1, 2, 3]) do
+  total = total + x
+  unless x > 1 do continue end
+end
+let label = "total: ${total}, first: ${area(Shape.Circle(2), 1)}!"
+let p = { ...{ x = 1 }, ["y"] = -2, z = label }
+let [head, ...rest] = [1, 2, 3]
+while total > 0 do total = total - 1 end
+if p.x == 1 && !false do print(label) else do print(p) end
+"#;
+        let program = parse(source, "test.luma").unwrap();
+        let missing = missing_spans(&program);
+        assert!(missing.is_empty(), "nodes without spans: {missing:#?}");
+    }
+
+    #[test]
+    fn test_parse_unless_desugars_to_negated_if() {
+        let unless = parse_stmt("unless x do y end");
+        let negated_if = parse_stmt("if !x do y end");
+        assert_eq!(strip_spans_stmt(unless), strip_spans_stmt(negated_if));
+    }
+
+    #[test]
+    fn test_parse_unless_else_stmt() {
+        let unless = parse_stmt("unless x do y else do z end");
+        let negated_if = parse_stmt("if !x do y else do z end");
+        assert_eq!(strip_spans_stmt(unless), strip_spans_stmt(negated_if));
+    }
+
+    #[test]
+    fn test_parse_unless_rejects_else_if() {
+        assert!(parse("unless x do y else if z do w end", "test.luma").is_err());
+    }
+
     // ===== While Loop Tests =====
 
     #[test]
@@ -1032,7 +1968,24 @@ mod tests {
     #[test]
     fn test_parse_return_stmt() {
         let stmt = parse_stmt("return 42");
-        assert!(matches!(stmt, Stmt::Return { .. }));
+        assert!(matches!(
+            stmt,
+            Stmt::Return {
+                implicit: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_trailing_expression_is_implicit_return() {
+        let Expr::Function { body, .. } = parse_expr("fn() do\n  let x = 1\n  x\nend") else {
+            panic!("Expected a function");
+        };
+        assert!(matches!(
+            body.last(),
+            Some(Stmt::Return { implicit: true, .. })
+        ));
     }
 
     // ===== Break/Continue Tests =====
@@ -1045,8 +1998,109 @@ mod tests {
 
     #[test]
     fn test_parse_break_with_level() {
-        let stmt = parse_stmt("break 2");
-        assert!(matches!(stmt, Stmt::Break { level: Some(2), .. }));
+        let stmt = parse_stmt("break level 2");
+        assert!(matches!(
+            stmt,
+            Stmt::Break {
+                level: Some(2),
+                value: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_break_number_is_a_level() {
+        for source in ["break 2", "break level 2"] {
+            let stmt = parse_stmt(source);
+            assert!(
+                matches!(
+                    stmt,
+                    Stmt::Break {
+                        level: Some(2),
+                        value: None,
+                        ..
+                    }
+                ),
+                "{source}: {stmt:?}"
+            );
+        }
+        // Other numbers, or a parenthesized one, are values
+        for source in ["break 2.5", "break 0x10", "break (5)"] {
+            let stmt = parse_stmt(source);
+            assert!(
+                matches!(
+                    stmt,
+                    Stmt::Break {
+                        level: None,
+                        value: Some(_),
+                        ..
+                    }
+                ),
+                "{source}: {stmt:?}"
+            );
+        }
+        // `level` only introduces a level when a number follows
+        let stmt = parse_stmt("break level");
+        assert!(matches!(
+            stmt,
+            Stmt::Break {
+                level: None,
+                value: Some(Expr::Identifier { .. }),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_break_with_value() {
+        let stmt = parse_stmt("break x + 1");
+        assert!(matches!(
+            stmt,
+            Stmt::Break {
+                level: None,
+                value: Some(Expr::Binary { .. }),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_break_with_level_and_value() {
+        let stmt = parse_stmt("break level 2 \"found\"");
+        assert!(matches!(
+            stmt,
+            Stmt::Break {
+                level: Some(2),
+                value: Some(Expr::String { .. }),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_break_value_must_share_line() {
+        let program = parse("while true do\n  break\n  x\nend", "test.luma").unwrap();
+        match &program.statements[0] {
+            Stmt::While { body, .. } => {
+                assert!(matches!(body[0], Stmt::Break { value: None, .. }));
+                assert_eq!(body.len(), 2);
+            }
+            other => panic!("Expected while, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_while_loop_as_expression() {
+        let stmt = parse_stmt("let x = while true do break (42) end");
+        match stmt {
+            Stmt::VarDecl { value, .. } => {
+                assert!(
+                    matches!(value, Expr::Loop { body, .. } if matches!(*body, Stmt::While { .. }))
+                );
+            }
+            other => panic!("Expected VarDecl, got {other:?}"),
+        }
     }
 
     #[test]
@@ -1059,6 +2113,8 @@ mod tests {
     fn test_parse_continue_with_level() {
         let stmt = parse_stmt("continue 3");
         assert!(matches!(stmt, Stmt::Continue { level: Some(3), .. }));
+        let stmt = parse_stmt("continue level 3");
+        assert!(matches!(stmt, Stmt::Continue { level: Some(3), .. }));
     }
 
     // ===== Assignment Tests =====
@@ -1069,6 +2125,75 @@ mod tests {
         assert!(matches!(stmt, Stmt::Assignment { .. }));
     }
 
+    #[test]
+    fn test_parse_compound_assignment_desugars() {
+        for (source, expected) in [
+            ("x += 2", BinaryOp::Add),
+            ("x -= 2", BinaryOp::Sub),
+            ("x *= 2", BinaryOp::Mul),
+            ("x /= 2", BinaryOp::Div),
+            ("x %= 2", BinaryOp::Mod),
+        ] {
+            let Stmt::Assignment {
+                target: Expr::Identifier { name, .. },
+                op: AssignOp::Compound,
+                value: Expr::Binary {
+                    left, op, right, ..
+                },
+                span,
+            } = parse_stmt(source)
+            else {
+                panic!("{source} should desugar to an assignment");
+            };
+            assert_eq!(name, "x");
+            assert_eq!(op, expected, "{source}");
+            assert!(matches!(*left, Expr::Identifier { ref name, .. } if name == "x"));
+            assert!(matches!(*right, Expr::Number { value, .. } if value == 2.0));
+            assert_eq!(span.map(|s| (s.start, s.end)), Some((0, 6)));
+        }
+    }
+
+    #[test]
+    fn test_parse_compound_assignment_keeps_operand_grouped() {
+        // `x *= 1 + 2` means `x = x * (1 + 2)`
+        let Stmt::Assignment { value, .. } = parse_stmt("x *= 1 + 2") else {
+            panic!("expected an assignment");
+        };
+        let Expr::Binary { op, right, .. } = value else {
+            panic!("expected a binary value");
+        };
+        assert_eq!(op, BinaryOp::Mul);
+        assert!(matches!(
+            *right,
+            Expr::Binary {
+                op: BinaryOp::Add,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_compound_assignment_to_index_and_member() {
+        let stmt = parse_stmt("arr[i] += 1");
+        assert!(matches!(
+            stmt,
+            Stmt::Assignment {
+                target: Expr::Index { .. },
+                value: Expr::Binary { ref left, .. },
+                ..
+            } if matches!(**left, Expr::Index { .. })
+        ));
+        let stmt = parse_stmt("obj.n += 1");
+        assert!(matches!(
+            stmt,
+            Stmt::Assignment {
+                target: Expr::MemberAccess { .. },
+                value: Expr::Binary { ref left, .. },
+                ..
+            } if matches!(**left, Expr::MemberAccess { .. })
+        ));
+    }
+
     // ===== Error Tests =====
 
     #[test]
@@ -1083,6 +2208,15 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_parse_error_wildcard_as_tag_name() {
+        let errors = parse("match x do\n  _ as ok do 1 end\nend", "test.luma").unwrap_err();
+        assert!(
+            errors.iter().any(|e| e.message.contains("tag pattern")),
+            "{errors:?}"
+        );
+    }
+
     // ===== Complex Expression Tests =====
 
     #[test]
@@ -1104,6 +2238,13 @@ mod tests {
         assert!(matches!(expr, Expr::Import { .. }));
     }
 
+    #[test]
+    fn test_program_imports_finds_literal_paths() {
+        let source = "let a = import(\"a\")\nlet f = fn() do\n  if true do import(\"./b.luma\") end\nend\nlet c = import(\"c\" + \".luma\")\nlet d = import(name)";
+        let program = parse(source, "test.luma").unwrap();
+        assert_eq!(program.imports(), vec!["a", "./b.luma", "c.luma"]);
+    }
+
     #[test]
     fn test_parse_unexpected_eof_suggestion() {
         let source = "if true do";
@@ -1123,10 +2264,12 @@ mod tests {
         let result = parse(source, "test.luma");
         assert!(result.is_err());
         let diags = result.unwrap_err();
-        let has_paren_insert = diags
+        let paren_insert = diags
             .iter()
-            .any(|d| d.fixits.iter().any(|f| f.replacement().contains(')')));
-        assert!(has_paren_insert, "expected a fix-it to insert ')'");
+            .flat_map(|d| &d.fixits)
+            .find(|f| f.replacement().contains(')'))
+            .expect("expected a fix-it to insert ')'");
+        assert!(paren_insert.is_machine_applicable());
     }
 
     #[test]
@@ -1140,4 +2283,34 @@ mod tests {
             .any(|d| d.fixits.iter().any(|f| f.replacement().is_empty()));
         assert!(has_delete, "expected a fix-it to remove an extra closer");
     }
+
+    #[test]
+    fn test_parse_type_decl_variants() {
+        let stmt = parse_stmt("type Shape = Circle { r: Number } | Square { s: Number } | Empty");
+        match stmt {
+            Stmt::TypeDecl { name, variants, .. } => {
+                assert_eq!(name, "Shape");
+                let names: Vec<&str> = variants.iter().map(|v| v.name.as_str()).collect();
+                assert_eq!(names, ["Circle", "Square", "Empty"]);
+                assert_eq!(variants[0].fields[0].0, "r");
+                assert!(variants[2].fields.is_empty());
+            }
+            other => panic!("Expected TypeDecl, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_export_forms() {
+        for (source, expected) in [
+            ("export add", vec!["add"]),
+            ("export { add, pi, }", vec!["add", "pi"]),
+        ] {
+            match parse_stmt(source) {
+                Stmt::Export { names, .. } => assert_eq!(names, expected),
+                other => panic!("Expected Export, got {other:?}"),
+            }
+        }
+        // Still an ordinary identifier outside that position
+        assert!(matches!(parse_stmt("export = 1"), Stmt::Assignment { .. }));
+    }
 }
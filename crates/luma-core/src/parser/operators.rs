@@ -44,6 +44,53 @@ where
     just('=').padded_by(ws).to(AssignOp::Assign)
 }
 
+/// Creates a parser for compound assignment operators, yielding the
+/// arithmetic operator they apply (`+=` yields `Add`)
+pub fn compound_assign_op<'a, WS>(
+    ws: WS,
+) -> impl Parser<'a, &'a str, BinaryOp, extra::Err<Rich<'a, char>>> + Clone
+where
+    WS: Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
+{
+    choice((
+        just("+=").to(BinaryOp::Add),
+        just("-=").to(BinaryOp::Sub),
+        just("*=").to(BinaryOp::Mul),
+        just("/=").to(BinaryOp::Div),
+        just("%=").to(BinaryOp::Mod),
+    ))
+    .padded_by(ws)
+}
+
+/// Creates a parser for the pipe operator
+pub fn pipe_op<'a, WS>(ws: WS) -> impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone
+where
+    WS: Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
+{
+    just("|>").padded_by(ws).ignored()
+}
+
+/// Creates a parser for range operators, yielding whether the range
+/// includes its end (`..=`) or not (`..`)
+pub fn range_op<'a, WS>(
+    ws: WS,
+) -> impl Parser<'a, &'a str, bool, extra::Err<Rich<'a, char>>> + Clone
+where
+    WS: Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
+{
+    choice((just("..=").to(true), just("..").to(false))).padded_by(ws)
+}
+
+/// Creates a parser for the exponent operator
+pub fn pow_op<'a, WS>(
+    ws: WS,
+) -> impl Parser<'a, &'a str, BinaryOp, extra::Err<Rich<'a, char>>> + Clone
+where
+    WS: Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
+{
+    just("**").padded_by(ws).to(BinaryOp::Pow)
+}
+
 /// Creates a parser for multiplication/division/modulo operators
 pub fn mul_op<'a, WS>(
     ws: WS,
@@ -53,7 +100,10 @@ where
 {
     let op = |c| just(c).padded_by(ws.clone());
     choice((
-        op('*').to(BinaryOp::Mul),
+        just('*')
+            .then_ignore(just('*').not())
+            .padded_by(ws.clone())
+            .to(BinaryOp::Mul),
         op('/').to(BinaryOp::Div),
         op('%').to(BinaryOp::Mod),
     ))
@@ -10,12 +10,11 @@ where
     WS: Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
 {
     // Number literal
-    let number = text::int(10)
-        .then(just('.').then(text::digits(10)).or_not())
-        .to_slice()
+    let number = super::literals::decimal_literal()
         .try_map(|s: &str, span| {
+            let value = super::literals::parse_number(s).map_err(|msg| Rich::custom(span, msg))?;
             Ok(Pattern::Literal {
-                value: Literal::Number(s.parse::<f64>().unwrap()),
+                value: Literal::Number(value),
                 span: Some(Span::from_chumsky(span)),
             })
         })
@@ -64,7 +63,7 @@ where
     choice((number, string_literal, bool_true, bool_false, null)).boxed()
 }
 
-/// Creates a parser for all pattern types (ident, list, table, wildcard, literal)
+/// Creates a parser for all pattern types (ident, list, table, variant, wildcard, literal)
 /// Note: Tag patterns are semantically the same as Ident patterns in parsing,
 /// but are distinguished during type checking in match contexts
 pub fn pattern<'a, WS, I>(
@@ -85,6 +84,26 @@ where
             })
             .boxed();
 
+        // `_ as name` is a catch-all that binds the matched value
+        let wildcard_as = just('_')
+            .padded_by(ws.clone())
+            .ignore_then(text::keyword("as"))
+            .padded_by(ws.clone())
+            .ignore_then(ident.clone())
+            .validate(|name: &str, e, emitter| {
+                if matches!(name, "ok" | "err" | "some" | "none") {
+                    emitter.emit(Rich::custom(
+                        e.span(),
+                        format!("cannot bind to '{name}' with `_ as`; it names a tag pattern"),
+                    ));
+                }
+                Pattern::Ident {
+                    name: name.to_string(),
+                    span: Some(Span::from_chumsky(e.span())),
+                }
+            })
+            .boxed();
+
         // List patterns support nested patterns
         let list_pattern = pattern_ref
             .clone()
@@ -111,21 +130,24 @@ where
             })
             .boxed();
 
-        // Table patterns with field renames: {key}, {key: binding}
+        // Table patterns: {key}, {key: binding}, or {key: pattern} to destructure further
         let table_field = ident
             .clone()
             .then(
                 just(':')
                     .padded_by(ws.clone())
-                    .ignore_then(ident.clone())
+                    .ignore_then(pattern_ref.clone())
                     .or_not(),
             )
-            .map(|(key, binding): (&str, Option<&str>)| TablePatternField {
-                key: key.to_string(),
-                binding: binding.map(|s| s.to_string()),
-            });
+            .map(
+                |(key, pattern): (&str, Option<Pattern>)| TablePatternField {
+                    key: key.to_string(),
+                    pattern,
+                },
+            );
 
         let table_pattern = table_field
+            .clone()
             .separated_by(just(',').padded_by(ws.clone()))
             .at_least(1)
             .collect::<Vec<TablePatternField>>()
@@ -141,6 +163,29 @@ where
             })
             .boxed();
 
+        // Variant patterns: Tag { field, field: pattern } (fields may be empty)
+        let variant_field = table_field.clone();
+
+        let variant_pattern = ident
+            .clone()
+            .then(
+                variant_field
+                    .separated_by(just(',').padded_by(ws.clone()))
+                    .collect::<Vec<TablePatternField>>()
+                    .delimited_by(
+                        just('{').padded_by(ws.clone()),
+                        just('}').padded_by(ws.clone()),
+                    ),
+            )
+            .try_map(|(tag, fields): (&str, _), span| {
+                Ok(Pattern::Variant {
+                    tag: tag.to_string(),
+                    fields,
+                    span: Some(Span::from_chumsky(span)),
+                })
+            })
+            .boxed();
+
         // Identifier pattern (default)
         let ident_pattern = ident
             .clone()
@@ -159,8 +204,10 @@ where
             list_pattern,
             table_pattern,
             literal,
+            wildcard_as, // Must come before wildcard: both start with `_`
             wildcard,
-            ident_pattern, // Identifiers become Ident patterns (can be treated as Tag in match)
+            variant_pattern, // Must come before ident_pattern: `Tag { .. }` starts with an ident
+            ident_pattern,   // Identifiers become Ident patterns (can be treated as Tag in match)
         ))
     })
     .boxed()
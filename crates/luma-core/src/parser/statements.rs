@@ -35,7 +35,7 @@ where
         .boxed()
 }
 use super::utils::apply_implicit_return_stmts;
-use crate::ast::{Expr, Pattern, Span, Stmt, Type};
+use crate::ast::{AssignOp, EnumVariant, Expr, Pattern, Span, Stmt, Type, UnaryOp};
 use crate::parser::operators;
 use chumsky::prelude::*;
 
@@ -54,6 +54,7 @@ where
         .try_map(|value, span| {
             Ok(Stmt::Return {
                 value,
+                implicit: false,
                 span: Some(Span::from_chumsky(span)),
             })
         })
@@ -73,7 +74,15 @@ where
     T: Parser<'a, &'a str, Type, extra::Err<Rich<'a, char>>> + Clone + 'a,
     E: Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone + 'a,
 {
-    let var_decl_token = choice((just("let").to(false), just("var").to(true)))
+    // `let mut` is an alias for `var`. `mut` is only special when a pattern
+    // follows it, so `let mut = 1` still declares a variable named `mut`.
+    let let_mut = just("let")
+        .padded_by(ws.clone())
+        .then(text::keyword("mut"))
+        .padded_by(ws.clone())
+        .then(pattern.clone().rewind())
+        .to(true);
+    let var_decl_token = choice((let_mut, just("let").to(false), just("var").to(true)))
         .padded_by(ws.clone())
         .then(choice((pattern.map(|p| match p {
             Pattern::Ident { name, .. } => (None, Some(name)),
@@ -111,6 +120,10 @@ where
 }
 
 /// Creates a parser for assignment statements (x = value, x += value, etc.)
+///
+/// A compound assignment `x op= value` is kept as `x = x op (value)` with
+/// `AssignOp::Compound`, so the compiler evaluates the target's parts once
+/// for both the read and the store.
 pub fn assignment<'a, WS, E>(
     ws: WS,
     expr: E,
@@ -119,17 +132,33 @@ where
     WS: Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
     E: Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone + 'a,
 {
-    let assign_op = operators::assign_op(ws.clone());
+    let assign_op = choice((
+        operators::compound_assign_op(ws.clone()).map(Some),
+        operators::assign_op(ws.clone()).to(None),
+    ));
 
     expr.clone()
         .then(assign_op)
         .then(expr)
-        .try_map(|((target, op), value), span| {
+        .try_map(|((target, compound), value), span| {
+            let span = Some(Span::from_chumsky(span));
+            let (op, value) = match compound {
+                Some(op) => (
+                    AssignOp::Compound,
+                    Expr::Binary {
+                        left: Box::new(target.clone()),
+                        op,
+                        right: Box::new(value),
+                        span,
+                    },
+                ),
+                None => (AssignOp::Assign, value),
+            };
             Ok(Stmt::Assignment {
                 target,
                 op,
                 value,
-                span: Some(Span::from_chumsky(span)),
+                span,
             })
         })
         .boxed()
@@ -182,6 +211,48 @@ where
         .boxed()
 }
 
+/// Creates a parser for `unless` statements, sugar for `if !cond`.
+/// An optional `else` block runs when the condition holds; `else if` is not allowed.
+pub fn unless_stmt<'a, WS, E, S>(
+    ws: WS,
+    expr: E,
+    stmt: S,
+) -> Boxed<'a, 'a, &'a str, Stmt, extra::Err<Rich<'a, char>>>
+where
+    WS: Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
+    E: Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone + 'a,
+    S: Parser<'a, &'a str, Stmt, extra::Err<Rich<'a, char>>> + Clone + 'a,
+{
+    let else_block = just("else")
+        .padded_by(ws.clone())
+        .then_ignore(just("do").padded_by(ws.clone()))
+        .ignore_then(stmt.clone().repeated().collect::<Vec<Stmt>>())
+        .map(apply_implicit_return_stmts);
+
+    just("unless")
+        .padded_by(ws.clone())
+        .ignore_then(expr)
+        .then_ignore(just("do").padded_by(ws.clone()))
+        .then(stmt.repeated().collect::<Vec<Stmt>>())
+        .then(else_block.or_not())
+        .then_ignore(just("end").padded_by(ws))
+        .map_with(|((condition, then_block), else_block), e| {
+            let cond_span = condition.span();
+            Stmt::If {
+                condition: Expr::Unary {
+                    op: UnaryOp::Not,
+                    operand: Box::new(condition),
+                    span: cond_span,
+                },
+                then_block: apply_implicit_return_stmts(then_block),
+                elif_blocks: Vec::new(),
+                else_block,
+                span: Some(Span::from_chumsky(e.span())),
+            }
+        })
+        .boxed()
+}
+
 /// Creates a parser for while loop statements
 pub fn while_stmt<'a, WS, E, S>(
     ws: WS,
@@ -268,32 +339,55 @@ where
         .boxed()
 }
 
-/// Creates a parser for break statements
-pub fn break_stmt<'a, WS>(ws: WS) -> Boxed<'a, 'a, &'a str, Stmt, extra::Err<Rich<'a, char>>>
+/// Creates a parser for break statements: `break`, `break 2`, `break value`,
+/// `break 2 value`. A bare integer is a loop level, as it is for `continue`;
+/// `break level 2` spells the same level, and `break level 1 5` or `break (5)`
+/// break with the number 5. The value must start on the same line as the
+/// `break` keyword so that a following statement is never swallowed as a value.
+pub fn break_stmt<'a, WS, E>(
+    ws: WS,
+    expr: E,
+) -> Boxed<'a, 'a, &'a str, Stmt, extra::Err<Rich<'a, char>>>
 where
     WS: Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
+    E: Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone + 'a,
 {
-    just("break")
-        .padded_by(ws.clone())
+    let inline_ws = one_of(" \t").repeated();
+
+    ws.clone()
+        .ignore_then(just("break"))
+        .ignore_then(
+            inline_ws
+                .ignore_then(text::keyword("level").then(inline_ws).or_not())
+                // A level is a whole integer, not the start of `2.5` or `0x10`
+                .ignore_then(loop_level("break"))
+                .then_ignore(
+                    any()
+                        .filter(|c: &char| c.is_alphanumeric() || matches!(c, '.' | '_'))
+                        .not(),
+                )
+                .or_not(),
+        )
         .then(
-            text::int(10)
-                .padded_by(ws.clone())
-                .try_map(|s: &str, span| {
-                    s.parse::<u32>()
-                        .map_err(|e| Rich::custom(span, format!("Invalid break level: {e}")))
-                })
+            inline_ws
+                .ignore_then(one_of("\r\n").not())
+                .ignore_then(just("--").not())
+                .ignore_then(expr)
                 .or_not(),
         )
-        .try_map(|(_, level), span| {
+        .then_ignore(ws)
+        .try_map(|(level, value), span| {
             Ok(Stmt::Break {
                 level,
+                value,
                 span: Some(Span::from_chumsky(span)),
             })
         })
         .boxed()
 }
 
-/// Creates a parser for continue statements
+/// Creates a parser for continue statements: `continue`, `continue 2` or
+/// `continue level 2`, the latter matching how `break` spells levels
 pub fn continue_stmt<'a, WS>(ws: WS) -> Boxed<'a, 'a, &'a str, Stmt, extra::Err<Rich<'a, char>>>
 where
     WS: Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
@@ -301,12 +395,10 @@ where
     just("continue")
         .padded_by(ws.clone())
         .then(
-            text::int(10)
+            text::keyword("level")
                 .padded_by(ws.clone())
-                .try_map(|s: &str, span| {
-                    s.parse::<u32>()
-                        .map_err(|e| Rich::custom(span, format!("Invalid continue level: {e}")))
-                })
+                .or_not()
+                .ignore_then(loop_level("continue").padded_by(ws.clone()))
                 .or_not(),
         )
         .try_map(|(_, level), span| {
@@ -318,6 +410,16 @@ where
         .boxed()
 }
 
+/// The loop count after `break` or `continue`
+fn loop_level<'a>(
+    keyword: &'static str,
+) -> impl Parser<'a, &'a str, u32, extra::Err<Rich<'a, char>>> + Clone {
+    text::int(10).try_map(move |s: &str, span| {
+        s.parse::<u32>()
+            .map_err(|e| Rich::custom(span, format!("Invalid {keyword} level: {e}")))
+    })
+}
+
 /// Creates a parser for expression statements
 pub fn expr_stmt<'a, E>(expr: E) -> Boxed<'a, 'a, &'a str, Stmt, extra::Err<Rich<'a, char>>>
 where
@@ -331,3 +433,92 @@ where
     })
     .boxed()
 }
+
+/// Creates a parser for tagged-union type declarations
+/// (`type Shape = Circle { r: Number } | Square { s: Number }`)
+pub fn type_decl<'a, WS, I, T>(
+    ws: WS,
+    ident: I,
+    type_parser: T,
+) -> Boxed<'a, 'a, &'a str, Stmt, extra::Err<Rich<'a, char>>>
+where
+    WS: Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
+    I: Parser<'a, &'a str, &'a str, extra::Err<Rich<'a, char>>> + Clone + 'a,
+    T: Parser<'a, &'a str, Type, extra::Err<Rich<'a, char>>> + Clone + 'a,
+{
+    let field = ident
+        .clone()
+        .then_ignore(just(':').padded_by(ws.clone()))
+        .then(type_parser)
+        .map(|(name, ty): (&str, Type)| (name.to_string(), ty));
+
+    let fields = field
+        .separated_by(just(',').padded_by(ws.clone()))
+        .allow_trailing()
+        .collect::<Vec<(String, Type)>>()
+        .delimited_by(
+            just('{').padded_by(ws.clone()),
+            just('}').padded_by(ws.clone()),
+        );
+
+    let variant = ident
+        .clone()
+        .then(fields.or_not())
+        .try_map(|(name, fields): (&str, _), span| {
+            Ok(EnumVariant {
+                name: name.to_string(),
+                fields: fields.unwrap_or_default(),
+                span: Some(Span::from_chumsky(span)),
+            })
+        });
+
+    text::keyword("type")
+        .padded_by(ws.clone())
+        .ignore_then(ident)
+        .then_ignore(just('=').padded_by(ws.clone()))
+        .then(
+            variant
+                .separated_by(just('|').padded_by(ws))
+                .at_least(1)
+                .collect::<Vec<EnumVariant>>(),
+        )
+        .try_map(|(name, variants): (&str, _), span| {
+            Ok(Stmt::TypeDecl {
+                name: name.to_string(),
+                variants,
+                span: Some(Span::from_chumsky(span)),
+            })
+        })
+        .boxed()
+}
+
+/// Creates a parser for export statements (`export name` or `export { a, b }`).
+/// `export` is a contextual keyword, so it stays usable as an identifier.
+pub fn export_stmt<'a, WS, I>(
+    ws: WS,
+    ident: I,
+) -> Boxed<'a, 'a, &'a str, Stmt, extra::Err<Rich<'a, char>>>
+where
+    WS: Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
+    I: Parser<'a, &'a str, &'a str, extra::Err<Rich<'a, char>>> + Clone + 'a,
+{
+    let list = ident
+        .clone()
+        .separated_by(just(',').padded_by(ws.clone()))
+        .allow_trailing()
+        .at_least(1)
+        .collect::<Vec<&str>>()
+        .delimited_by(
+            just('{').padded_by(ws.clone()),
+            just('}').padded_by(ws.clone()),
+        );
+
+    text::keyword("export")
+        .padded_by(ws)
+        .ignore_then(choice((list, ident.map(|name| vec![name]))))
+        .map_with(|names: Vec<&str>, e| Stmt::Export {
+            names: names.into_iter().map(str::to_string).collect(),
+            span: Some(Span::from_chumsky(e.span())),
+        })
+        .boxed()
+}
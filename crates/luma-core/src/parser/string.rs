@@ -10,9 +10,10 @@ where
     WS: Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
     E: Parser<'a, &'a str, Expr, extra::Err<Rich<'a, char>>> + Clone + 'a,
 {
-    // Escape sequences: \n, \t, \", \\ and \$ (treated literally)
+    // Escape sequences: \n, \r, \t, \", \\ and \$ (treated literally)
     let escape = just('\\').ignore_then(any()).map(|c| match c {
         'n' => '\n',
+        'r' => '\r',
         't' => '\t',
         '"' => '"',
         '\\' => '\\',
@@ -28,93 +29,157 @@ where
 
     // Plain character (any char except quote and backslash, handled separately)
     let plain_char = any().filter(|c| *c != '"' && *c != '\\');
+    // Inside `"""`, a quote is plain unless it starts the closing `"""`,
+    // which is the last three quotes of a run, so text may end with a quote
+    let closing = just("\"\"\"").then(just('"').not());
+    let triple_char = any().filter(|c| *c != '\\').and_is(closing.not());
 
-    // Segment enum (local) to accumulate pieces
-    #[derive(Clone)]
-    enum Segment {
-        Text(char),
-        Expr(Expr),
-    }
-
+    let text = |chars: Boxed<'a, 'a, &'a str, char, extra::Err<Rich<'a, char>>>| {
+        chars.map_with(|c, e| Segment::Text(c, Span::from_chumsky(e.span())))
+    };
     let segment = choice((
+        interpolation.clone().map(Segment::Expr).boxed(),
+        text(escape.boxed()).boxed(),
+        text(plain_char.boxed()).boxed(),
+    ))
+    .boxed();
+    let triple_segment = choice((
         interpolation.map(Segment::Expr).boxed(),
-        escape.map(Segment::Text).boxed(),
-        plain_char.map(Segment::Text).boxed(),
+        text(escape.boxed()).boxed(),
+        text(triple_char.boxed()).boxed(),
     ))
     .boxed();
 
-    let body = segment.repeated().collect::<Vec<Segment>>();
-    just('"')
-        .ignore_then(body)
+    // Raw string: `...` keeps its content verbatim, including newlines,
+    // backslashes and `${`, so it has no escapes and no interpolation
+    let raw = just('`')
+        .ignore_then(none_of('`').repeated().to_slice())
+        .then_ignore(just('`'))
+        .map_with(|content: &str, e| Expr::String {
+            value: content.to_string(),
+            span: Some(Span::from_chumsky(e.span())),
+        });
+    // `r"..."` is the same, for text that contains backticks but no quotes
+    let raw_quoted = just("r\"")
+        .ignore_then(none_of('"').repeated().to_slice())
+        .then_ignore(just('"'))
+        .map_with(|content: &str, e| Expr::String {
+            value: content.to_string(),
+            span: Some(Span::from_chumsky(e.span())),
+        });
+
+    // `"""..."""` may hold lone `"` and `""`; like any string it keeps its
+    // newlines and indentation as written
+    let triple = just("\"\"\"")
+        .ignore_then(triple_segment.repeated().collect::<Vec<Segment>>())
+        .then_ignore(just("\"\"\""))
+        .map_with(|segments, e| join_segments(segments, Span::from_chumsky(e.span())));
+    let quoted = just('"')
+        .ignore_then(segment.repeated().collect::<Vec<Segment>>())
         .then_ignore(just('"'))
-        .try_map(|segments, span| {
-            let mut parts: Vec<Expr> = Vec::new();
-            let mut buf = String::new();
-            for seg in segments {
-                match seg {
-                    Segment::Text(c) => buf.push(c),
-                    Segment::Expr(e) => {
-                        if !buf.is_empty() {
-                            parts.push(Expr::String {
-                                value: buf.clone(),
-                                span: None, // Inner string fragments don't need spans
-                            });
-                            buf.clear();
+        .map_with(|segments, e| join_segments(segments, Span::from_chumsky(e.span())));
+
+    choice((triple, raw_quoted, raw, quoted)).padded_by(ws)
+}
+
+/// A piece of a quoted string literal
+#[derive(Clone)]
+enum Segment {
+    Text(char, Span),
+    Expr(Expr),
+}
+
+/// Join a literal's text and interpolations into one expression spanning the
+/// whole literal: a plain `Expr::String`, or a chain of `+` concatenations
+fn join_segments(segments: Vec<Segment>, span: Span) -> Expr {
+    let mut parts: Vec<Expr> = Vec::new();
+    let mut buf = String::new();
+    let mut buf_span: Option<Span> = None;
+    for seg in segments {
+        match seg {
+            Segment::Text(c, char_span) => {
+                buf.push(c);
+                buf_span = Some(buf_span.map_or(char_span, |s| s.merge(&char_span)));
+            }
+            Segment::Expr(e) => {
+                if !buf.is_empty() {
+                    parts.push(Expr::String {
+                        value: std::mem::take(&mut buf),
+                        span: buf_span.take(),
+                    });
+                }
+                // Wrap non-string expressions with .into(String) method call,
+                // attributed to the interpolated expression
+                let expr_to_add = match &e {
+                    Expr::String { .. } => e,
+                    _ => {
+                        let inner_span = e.span();
+                        Expr::Call {
+                            callee: Box::new(Expr::MemberAccess {
+                                object: Box::new(e),
+                                member: "into".to_string(),
+                                span: inner_span,
+                            }),
+                            arguments: vec![crate::ast::CallArgument::Positional(
+                                Expr::Identifier {
+                                    name: "String".to_string(),
+                                    span: inner_span,
+                                },
+                            )],
+                            span: inner_span,
                         }
-                        // Wrap non-string expressions with .into(String) method call
-                        let expr_to_add = match &e {
-                            Expr::String { .. } => e,
-                            _ => Expr::Call {
-                                callee: Box::new(Expr::MemberAccess {
-                                    object: Box::new(e),
-                                    member: "into".to_string(),
-                                    span: None,
-                                }),
-                                arguments: vec![crate::ast::CallArgument::Positional(
-                                    Expr::Identifier {
-                                        name: "String".to_string(),
-                                        span: None,
-                                    },
-                                )],
-                                span: None,
-                            },
-                        };
-                        parts.push(expr_to_add);
                     }
-                }
+                };
+                parts.push(expr_to_add);
             }
-            if !buf.is_empty() {
-                parts.push(Expr::String {
-                    value: buf,
-                    span: None,
-                });
+        }
+    }
+    if !buf.is_empty() {
+        parts.push(Expr::String {
+            value: buf,
+            span: buf_span,
+        });
+    }
+    match parts.len() {
+        0 => Expr::String {
+            value: String::new(),
+            span: Some(span),
+        },
+        1 => {
+            // Single part - return it but update span
+            let mut expr = parts.remove(0);
+            if let Expr::String {
+                span: ref mut s, ..
+            } = expr
+            {
+                *s = Some(span);
             }
-            Ok(match parts.len() {
-                0 => Expr::String {
-                    value: String::new(),
-                    span: Some(Span::from_chumsky(span)),
-                },
-                1 => {
-                    // Single part - return it but update span
-                    let mut expr = parts.remove(0);
-                    if let Expr::String {
-                        span: ref mut s, ..
-                    } = expr
-                    {
-                        *s = Some(Span::from_chumsky(span));
-                    }
-                    expr
-                }
-                _ => parts
-                    .into_iter()
-                    .reduce(|left, right| Expr::Binary {
+            expr
+        }
+        _ => {
+            let mut expr = parts
+                .into_iter()
+                .reduce(|left, right| {
+                    let span = match (left.span(), right.span()) {
+                        (Some(l), Some(r)) => Some(l.merge(&r)),
+                        (l, r) => l.or(r),
+                    };
+                    Expr::Binary {
                         left: Box::new(left),
                         op: BinaryOp::Add,
                         right: Box::new(right),
-                        span: None,
-                    })
-                    .unwrap(),
-            })
-        })
-        .padded_by(ws)
+                        span,
+                    }
+                })
+                .unwrap();
+            // The outermost concatenation stands for the whole literal
+            if let Expr::Binary {
+                span: ref mut s, ..
+            } = expr
+            {
+                *s = Some(span);
+            }
+            expr
+        }
+    }
 }
@@ -3,7 +3,7 @@
 //! Handles parsing of type annotations including:
 //! - Simple type identifiers (Number, String, Boolean, etc.)
 //! - Generic types (List(String), Result(Number, String))
-//! - Function types (fn(Number, String): Boolean)
+//! - Function types (fn(Number, String): Boolean or (Number, String) -> Boolean)
 //! - The Any type for dynamic typing
 
 use crate::ast::{Span, Type};
@@ -16,7 +16,7 @@ use chumsky::prelude::*;
 /// - `Any` - dynamic type
 /// - `TypeIdent` - simple type names
 /// - `GenericType` - parameterized types like List(String)
-/// - `FunctionType` - function signatures like fn(Number): String
+/// - `FunctionType` - function signatures like fn(Number): String or (Number) -> String
 pub fn type_parser<'a>(
     ws: impl Parser<'a, &'a str, (), extra::Err<Rich<'a, char>>> + Clone + 'a,
 ) -> impl Parser<'a, &'a str, Type, extra::Err<Rich<'a, char>>> + Clone {
@@ -72,6 +72,26 @@ pub fn type_parser<'a>(
                 })
             });
 
+        // Parse arrow function type: (Type, Type, ...) -> Type
+        let arrow_type = type_ref
+            .clone()
+            .separated_by(just(',').padded_by(ws.clone()))
+            .allow_trailing()
+            .collect::<Vec<Type>>()
+            .delimited_by(
+                just('(').padded_by(ws.clone()),
+                just(')').padded_by(ws.clone()),
+            )
+            .then_ignore(just("->").padded_by(ws.clone()))
+            .then(type_ref.clone())
+            .try_map(|(param_types, return_type), span| {
+                Ok(Type::FunctionType {
+                    param_types,
+                    return_type: Box::new(return_type),
+                    span: Some(Span::from_chumsky(span)),
+                })
+            });
+
         // Parse generic type: TypeIdent(Type, Type, ...)
         let generic_type = text::ident()
             .try_map(move |s: &str, span| {
@@ -105,9 +125,10 @@ pub fn type_parser<'a>(
                 })
             });
 
-        // Combine all type parsers, with priority: function > generic > any > ident
+        // Combine all type parsers, with priority: function > arrow > generic > any > ident
         choice((
             function_type.boxed(),
+            arrow_type.boxed(),
             generic_type.boxed(),
             any_type.boxed(),
             type_ident.boxed(),
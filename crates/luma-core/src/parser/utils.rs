@@ -13,13 +13,21 @@ pub fn apply_implicit_return(mut stmts: Vec<Stmt>, ret: Option<Expr>) -> Vec<Stm
     if let Some(expr) = ret {
         // Explicit trailing expression captured separately
         let span = expr.span();
-        stmts.push(Stmt::Return { value: expr, span });
+        stmts.push(Stmt::Return {
+            value: expr,
+            implicit: true,
+            span,
+        });
     } else if let Some(last) = stmts.pop() {
         // No separate trailing expression; convert last ExprStmt into implicit return
         match last {
             Stmt::ExprStmt { expr, .. } => {
                 let span = expr.span();
-                stmts.push(Stmt::Return { value: expr, span });
+                stmts.push(Stmt::Return {
+                    value: expr,
+                    implicit: true,
+                    span,
+                });
             }
             other => stmts.push(other),
         }
@@ -6,7 +6,7 @@
 //! ## Usage
 //!
 //! ```no_run
-//! # use luma::pipeline::Pipeline;
+//! # use luma_core::pipeline::Pipeline;
 //! let pipeline = Pipeline::new("let x = 1 + 2".to_string(), "example.luma".to_string());
 //!
 //! match pipeline.run_all() {
@@ -20,23 +20,26 @@
 //! You can also run individual stages:
 //!
 //! ```no_run
-//! # use luma::pipeline::Pipeline;
+//! # use luma_core::pipeline::Pipeline;
 //! let pipeline = Pipeline::new("let x = 1".to_string(), "example.luma".to_string());
 //!
 //! let ast = pipeline.parse()?;
 //! pipeline.typecheck(&ast)?;
 //! let chunk = pipeline.compile(&ast);
-//! # Ok::<(), luma::pipeline::PipelineError>(())
+//! # Ok::<(), luma_core::pipeline::PipelineError>(())
 //! ```
 
-use crate::ast::Program;
+use crate::ast::{Program, Span};
 use crate::bytecode::ir::Chunk;
-use crate::diagnostics::Diagnostic;
-use crate::typecheck::{self, TypeError};
+use crate::diagnostics::{self, BOLD_RED, Diagnostic, Suppressions, paint};
+use crate::resolve::{self, Occurrence};
+use crate::typecheck::{self, TcType, TypeError, TypecheckOptions};
 use crate::vm::value::Value;
 use crate::vm::{self, VmError};
+use std::collections::HashMap;
 use std::fmt;
 use std::path::Path;
+use std::time::Duration;
 
 /// Errors that can occur during pipeline execution
 #[derive(Debug)]
@@ -65,11 +68,14 @@ impl PipelineError {
                         if let Some(span) = &e.span {
                             // Create a dummy source to calculate line/col, or use byte range
                             format!(
-                                "Type error at bytes {}..{}: {}",
-                                span.start, span.end, e.message
+                                "Type error{} at bytes {}..{}: {}",
+                                code_suffix(e),
+                                span.start,
+                                span.end,
+                                e.message
                             )
                         } else {
-                            format!("Type error: {}", e.message)
+                            format!("Type error{}: {}", code_suffix(e), e.message)
                         }
                     })
                     .collect::<Vec<_>>()
@@ -81,29 +87,50 @@ impl PipelineError {
 
     /// Format error with source code context
     pub fn format_with_source(&self, source: &str) -> String {
+        self.format_with_color(source, false)
+    }
+
+    /// Format error with source code context, using ANSI colors when `color` is set
+    pub fn format_with_color(&self, source: &str, color: bool) -> String {
         match self {
             PipelineError::Parse(diagnostics) => diagnostics
                 .iter()
-                .map(|d| d.format(source))
+                .map(|d| d.format_with_color(source, color))
                 .collect::<Vec<_>>()
                 .join("\n"),
             PipelineError::Typecheck(errors) => errors
                 .iter()
                 .map(|e| {
+                    let label = paint(BOLD_RED, &format!("Type error{}", code_suffix(e)), color);
                     if let Some(span) = &e.span {
                         let loc = span.location(source);
-                        format!("Type error at {}:{}: {}", loc.line, loc.col, e.message)
+                        format!("{label} at {}:{}: {}", loc.line, loc.col, e.message)
                     } else {
-                        format!("Type error: {}", e.message)
+                        format!("{label}: {}", e.message)
                     }
                 })
                 .collect::<Vec<_>>()
                 .join("\n"),
-            PipelineError::Runtime(err) => err.format(Some(source)),
+            PipelineError::Runtime(err) => {
+                let formatted = match err.to_diagnostic() {
+                    Some(diagnostic) => diagnostic
+                        .format_with_color(err.source_for(err.file.as_deref(), source), color),
+                    None => err.format_display(),
+                };
+                err.append_trace(formatted, source)
+            }
         }
     }
 }
 
+/// `[E0001]`-style tag for a type error's code, or nothing if it has none
+fn code_suffix(error: &TypeError) -> String {
+    error
+        .code()
+        .map(|c| format!("[{}]", c.code))
+        .unwrap_or_default()
+}
+
 impl fmt::Display for PipelineError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.format_display())
@@ -128,6 +155,17 @@ impl From<VmError> for PipelineError {
     }
 }
 
+fn is_suppressed(suppressions: &Suppressions, error: &TypeError) -> bool {
+    let Some(span) = error.span else {
+        return false;
+    };
+    let code = error.code();
+    suppressions.suppresses(
+        suppressions.line_of(span.start),
+        code.as_ref().map(|c| c.code.as_str()),
+    )
+}
+
 /// Result type for pipeline operations
 pub type PipelineResult<T> = Result<T, PipelineError>;
 
@@ -137,12 +175,24 @@ pub struct Pipeline {
     source: String,
     /// Filename for error reporting
     filename: String,
+    /// Options used by `typecheck` and `analyze`
+    typecheck_options: TypecheckOptions,
 }
 
 impl Pipeline {
     /// Create a new pipeline with source code and filename
     pub fn new(source: String, filename: String) -> Self {
-        Pipeline { source, filename }
+        Pipeline {
+            source,
+            filename,
+            typecheck_options: TypecheckOptions::default(),
+        }
+    }
+
+    /// Turn the type checker's gradual `Any`/`Unknown` fallbacks into errors
+    pub fn with_strict_types(mut self, strict: bool) -> Self {
+        self.typecheck_options.strict_types = strict;
+        self
     }
 
     /// Parse the source code into an AST
@@ -158,9 +208,66 @@ impl Pipeline {
     ///
     /// # Errors
     ///
-    /// Returns `PipelineError::Typecheck` if type checking fails
+    /// Returns `PipelineError::Typecheck` if type checking fails. Errors
+    /// silenced by `-- luma:ignore` comments don't count.
     pub fn typecheck(&self, ast: &Program) -> PipelineResult<()> {
-        typecheck::typecheck_program(ast).map_err(PipelineError::Typecheck)
+        self.typecheck_with_warnings(ast).0
+    }
+
+    /// Type-check the AST like [`Pipeline::typecheck`], also returning the
+    /// warnings that are not silenced by `-- luma:ignore` comments
+    pub fn typecheck_with_warnings(&self, ast: &Program) -> (PipelineResult<()>, Vec<TypeError>) {
+        let report = typecheck::typecheck_report_with_options(ast, self.typecheck_options);
+        let suppressions = Suppressions::parse(&self.source);
+        let unsuppressed = |diagnostics: Vec<TypeError>| -> Vec<TypeError> {
+            diagnostics
+                .into_iter()
+                .filter(|e| !is_suppressed(&suppressions, e))
+                .collect()
+        };
+        let errors = unsuppressed(report.errors);
+        let warnings = unsuppressed(report.warnings);
+        if errors.is_empty() {
+            (Ok(()), warnings)
+        } else {
+            (Err(PipelineError::Typecheck(errors)), warnings)
+        }
+    }
+
+    /// Parse and type-check without failing, collecting every diagnostic
+    ///
+    /// Intended for editor tooling: the result holds the AST (when parsing
+    /// succeeded), all parse/type diagnostics including warnings, and the
+    /// inferred type of each expression.
+    pub fn analyze(&self) -> Analysis {
+        match crate::parser::parse(&self.source, &self.filename) {
+            Ok(program) => {
+                let report =
+                    typecheck::typecheck_report_with_options(&program, self.typecheck_options);
+                let suppressions = Suppressions::parse(&self.source);
+                let mut diagnostics: Vec<Diagnostic> = report
+                    .errors
+                    .iter()
+                    .chain(&report.warnings)
+                    .map(|e| e.to_diagnostic(&self.filename))
+                    .filter(|d| !suppressions.suppresses_diagnostic(d))
+                    .collect();
+                diagnostics::sort_diagnostics(&mut diagnostics);
+                let occurrences = resolve::resolve(&program, &self.source);
+                Analysis {
+                    program: Some(program),
+                    diagnostics,
+                    expr_types: report.expr_types,
+                    occurrences,
+                }
+            }
+            Err(diagnostics) => Analysis {
+                program: None,
+                diagnostics,
+                expr_types: HashMap::new(),
+                occurrences: Vec::new(),
+            },
+        }
     }
 
     /// Compile the AST to bytecode
@@ -252,9 +359,82 @@ impl Pipeline {
     }
 }
 
+/// Wall-clock duration of each pipeline phase, as reported by `luma run --report-timings`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PhaseTimings {
+    pub parse: Duration,
+    pub typecheck: Duration,
+    pub compile: Duration,
+    pub execute: Duration,
+}
+
+impl PhaseTimings {
+    /// Format a per-phase summary, one phase per line, durations in milliseconds
+    pub fn report(&self) -> String {
+        let phases = [
+            ("parse", self.parse),
+            ("typecheck", self.typecheck),
+            ("compile", self.compile),
+            ("execute", self.execute),
+        ];
+        let total: Duration = phases.iter().map(|(_, d)| *d).sum();
+        let mut out = String::from("Timings:\n");
+        for (label, duration) in phases.into_iter().chain([("total", total)]) {
+            out.push_str(&format!(
+                "  {label:<10} {:>10.3}ms\n",
+                duration.as_secs_f64() * 1000.0
+            ));
+        }
+        out
+    }
+}
+
+/// Result of [`Pipeline::analyze`]: everything known about a source file
+/// short of running it.
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    /// The parsed program, or `None` if parsing failed
+    pub program: Option<Program>,
+    /// Parse errors, or type errors followed by type warnings
+    pub diagnostics: Vec<Diagnostic>,
+    /// Inferred type of every checked expression, keyed by its span
+    pub expr_types: HashMap<Span, TcType>,
+    /// Every mention of every variable, in source order
+    pub occurrences: Vec<Occurrence>,
+}
+
+impl Analysis {
+    /// Type of the innermost expression whose span contains `offset`
+    pub fn type_at(&self, offset: usize) -> Option<&TcType> {
+        self.expr_types
+            .iter()
+            .filter(|(span, _)| span.start <= offset && offset < span.end)
+            .min_by_key(|(span, _)| span.end - span.start)
+            .map(|(_, ty)| ty)
+    }
+
+    /// Every mention of the variable named at `offset`, including its
+    /// declaration; empty if `offset` is not on a variable
+    pub fn occurrences_at(&self, offset: usize) -> Vec<&Occurrence> {
+        let Some(symbol) = self
+            .occurrences
+            .iter()
+            .find(|o| o.span.start <= offset && offset <= o.span.end)
+            .map(|o| o.symbol)
+        else {
+            return Vec::new();
+        };
+        self.occurrences
+            .iter()
+            .filter(|o| o.symbol == symbol)
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::diagnostics::Severity;
 
     #[test]
     fn test_pipeline_simple_execution() {
@@ -280,6 +460,34 @@ mod tests {
         assert!(matches!(result, Err(PipelineError::Typecheck(_))));
     }
 
+    #[test]
+    fn test_pipeline_ignore_comment_suppresses_matching_code() {
+        let typecheck = |source: &str| {
+            let pipeline = Pipeline::new(source.to_string(), "test.luma".to_string());
+            let ast = pipeline.parse().unwrap();
+            pipeline.typecheck(&ast)
+        };
+
+        assert!(typecheck("let x: Number = \"s\" -- luma:ignore E0001").is_ok());
+        assert!(typecheck("-- luma:ignore\nlet x: Number = \"s\"").is_ok());
+
+        let Err(PipelineError::Typecheck(errors)) =
+            typecheck("-- luma:ignore E0002\nlet x: Number = \"s\"")
+        else {
+            panic!("mismatched code should still report");
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code().unwrap().code, "E0001");
+
+        let analysis = Pipeline::new(
+            "let a = missing -- luma:ignore E0002\nlet b = 1\nlet c = also_missing".to_string(),
+            "test.luma".to_string(),
+        )
+        .analyze();
+        assert_eq!(analysis.diagnostics.len(), 1, "{:?}", analysis.diagnostics);
+        assert!(analysis.diagnostics[0].message.contains("also_missing"));
+    }
+
     #[test]
     fn test_pipeline_individual_stages() {
         let pipeline = Pipeline::new("1 + 2".to_string(), "test.luma".to_string());
@@ -306,4 +514,31 @@ mod tests {
         let formatted = error.format_display();
         assert!(!formatted.is_empty());
     }
+
+    #[test]
+    fn test_pipeline_analyze_collects_types_and_diagnostics() {
+        let source = "let x = 1\nlet x = \"two\"";
+        let pipeline = Pipeline::new(source.to_string(), "test.luma".to_string());
+        let analysis = pipeline.analyze();
+
+        assert!(analysis.program.is_some());
+        assert_eq!(analysis.diagnostics.len(), 1);
+        let offset = source.find("\"two\"").unwrap() + 1;
+        assert_eq!(analysis.type_at(offset), Some(&TcType::String));
+
+        let broken = Pipeline::new("1 +".to_string(), "test.luma".to_string()).analyze();
+        assert!(broken.program.is_none());
+        assert!(!broken.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_pipeline_analyze_orders_diagnostics_by_position() {
+        // The shadowing warning (line 2) precedes the type error (line 3)
+        let source = "let x = 1\nlet x = 2\nlet y: String = 5";
+        let analysis = Pipeline::new(source.to_string(), "test.luma".to_string()).analyze();
+        let starts: Vec<usize> = analysis.diagnostics.iter().map(|d| d.span.start).collect();
+        assert_eq!(starts.len(), 2, "{:?}", analysis.diagnostics);
+        assert!(starts.is_sorted(), "{starts:?}");
+        assert_eq!(analysis.diagnostics[0].severity, Severity::Warning);
+    }
 }
@@ -0,0 +1,439 @@
+//! Scope-aware name resolution for editor tooling.
+//!
+//! Links every mention of a variable to the binding it refers to, following
+//! the type checker's scoping rules: blocks, function bodies, loop bodies and
+//! match arms open scopes, a `let` is visible from the next statement on, and
+//! functions bound in a block are visible throughout it. Names with no binding
+//! in the program, such as standard library globals, share one symbol per name.
+
+use std::collections::HashMap;
+
+use crate::ast::{CallArgument, Expr, Pattern, Program, Span, Stmt, TableKey};
+
+/// How a mention of a variable uses it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OccurrenceKind {
+    /// The binding itself: a `let`/`var`, parameter or pattern variable
+    Declaration,
+    /// The value is read
+    Read,
+    /// The variable is the target of an assignment
+    Write,
+}
+
+/// One mention of a variable's name in the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    /// Span of the name itself
+    pub span: Span,
+    /// Identifies the binding; equal for every mention of the same variable
+    pub symbol: usize,
+    pub kind: OccurrenceKind,
+}
+
+/// Find every mention of every variable in `program`, in source order.
+/// `source` is the text the program was parsed from; it locates names whose
+/// AST node has no span of its own, such as the name in `let x = ...`.
+pub fn resolve(program: &Program, source: &str) -> Vec<Occurrence> {
+    let mut resolver = Resolver {
+        source,
+        scopes: vec![HashMap::new()],
+        globals: HashMap::new(),
+        symbols: 0,
+        occurrences: Vec::new(),
+    };
+    resolver.stmts(&program.statements);
+    resolver.occurrences.sort_by_key(|o| o.span.start);
+    resolver.occurrences
+}
+
+struct Resolver<'a> {
+    source: &'a str,
+    scopes: Vec<HashMap<String, usize>>,
+    /// Symbols for names used without a binding in the program
+    globals: HashMap<String, usize>,
+    symbols: usize,
+    occurrences: Vec<Occurrence>,
+}
+
+impl Resolver<'_> {
+    fn declare(&mut self, name: &str, span: Option<Span>) {
+        let symbol = self.symbols;
+        self.symbols += 1;
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), symbol);
+        }
+        if let Some(span) = span {
+            self.occurrences.push(Occurrence {
+                span,
+                symbol,
+                kind: OccurrenceKind::Declaration,
+            });
+        }
+    }
+
+    fn mention(&mut self, name: &str, span: Option<Span>, kind: OccurrenceKind) {
+        let Some(span) = span else {
+            return;
+        };
+        // Expression spans can run on over trailing whitespace
+        let span = Span::new(span.start, span.start + name.len());
+        let symbol = match self.scopes.iter().rev().find_map(|s| s.get(name)) {
+            Some(symbol) => *symbol,
+            None => *self.globals.entry(name.to_string()).or_insert_with(|| {
+                self.symbols += 1;
+                self.symbols - 1
+            }),
+        };
+        self.occurrences.push(Occurrence { span, symbol, kind });
+    }
+
+    /// Span of the first whole-word `name` in `within` at or after `from`
+    fn find_name(&self, within: Option<Span>, from: usize, name: &str) -> Option<Span> {
+        let within = within?;
+        let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+        let text = self.source.get(from..within.end)?;
+        text.match_indices(name).find_map(|(i, _)| {
+            let start = from + i;
+            let end = start + name.len();
+            let before = self.source[..start].chars().next_back();
+            let after = self.source[end..].chars().next();
+            (!before.is_some_and(is_ident) && !after.is_some_and(is_ident))
+                .then(|| Span::new(start, end))
+        })
+    }
+
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        f(self);
+        self.scopes.pop();
+    }
+
+    fn block(&mut self, stmts: &[Stmt]) {
+        self.scoped(|r| r.stmts(stmts));
+    }
+
+    /// Statements sharing the current scope. Functions are bound up front so
+    /// they can be called before their definition, as the type checker allows.
+    fn stmts(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            if let Stmt::VarDecl {
+                name,
+                value: Expr::Function { .. },
+                span,
+                ..
+            } = stmt
+            {
+                let from = span.map_or(0, |s| s.start);
+                self.declare(name, self.find_name(*span, from, name));
+            }
+        }
+        for stmt in stmts {
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::VarDecl {
+                name, value, span, ..
+            } => {
+                self.expr(value);
+                if !matches!(value, Expr::Function { .. }) {
+                    let from = span.map_or(0, |s| s.start);
+                    self.declare(name, self.find_name(*span, from, name));
+                }
+            }
+            Stmt::DestructuringVarDecl { pattern, value, .. } => {
+                self.expr(value);
+                self.pattern(pattern);
+            }
+            Stmt::Assignment { target, value, .. } => {
+                match target {
+                    Expr::Identifier { name, span } => {
+                        self.mention(name, *span, OccurrenceKind::Write)
+                    }
+                    other => self.expr(other),
+                }
+                self.expr(value);
+            }
+            Stmt::If {
+                condition,
+                then_block,
+                elif_blocks,
+                else_block,
+                ..
+            } => {
+                self.expr(condition);
+                self.block(then_block);
+                for (condition, block) in elif_blocks {
+                    self.expr(condition);
+                    self.block(block);
+                }
+                if let Some(block) = else_block {
+                    self.block(block);
+                }
+            }
+            Stmt::While {
+                condition, body, ..
+            }
+            | Stmt::DoWhile {
+                body, condition, ..
+            } => {
+                self.expr(condition);
+                self.block(body);
+            }
+            Stmt::For {
+                pattern,
+                iterator,
+                body,
+                ..
+            } => {
+                self.expr(iterator);
+                self.scoped(|r| {
+                    r.pattern(pattern);
+                    r.stmts(body);
+                });
+            }
+            Stmt::Return { value, .. } | Stmt::ExprStmt { expr: value, .. } => self.expr(value),
+            Stmt::Break { value, .. } => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            Stmt::Continue { .. } => {}
+            Stmt::Match { expr, arms, .. } => self.match_arms(expr, arms),
+            Stmt::TypeDecl { name, span, .. } => {
+                let from = span.map_or(0, |s| s.start);
+                self.declare(name, self.find_name(*span, from, name));
+            }
+            Stmt::Export { names, span } => {
+                let mut from = span.map_or(0, |s| s.start);
+                for name in names {
+                    let found = self.find_name(*span, from, name);
+                    from = found.map_or(from, |s| s.end);
+                    self.mention(name, found, OccurrenceKind::Read);
+                }
+            }
+        }
+    }
+
+    fn match_arms(&mut self, expr: &Expr, arms: &[(Pattern, Vec<Stmt>)]) {
+        self.expr(expr);
+        for (pattern, body) in arms {
+            self.scoped(|r| {
+                r.pattern(pattern);
+                r.stmts(body);
+            });
+        }
+    }
+
+    /// Declare the variables a pattern binds
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Ident { name, span } => self.declare(name, *span),
+            Pattern::ListPattern {
+                elements,
+                rest,
+                span,
+            } => {
+                for element in elements {
+                    self.pattern(element);
+                }
+                if let Some(rest) = rest {
+                    let from = match elements.last().and_then(Pattern::span) {
+                        Some(last) => last.end,
+                        None => span.map_or(0, |s| s.start),
+                    };
+                    self.declare(rest, self.find_name(*span, from, rest));
+                }
+            }
+            Pattern::TablePattern { fields, span } | Pattern::Variant { fields, span, .. } => {
+                let mut from = span.map_or(0, |s| s.start);
+                if let Pattern::Variant { tag, .. } = pattern {
+                    from = self.find_name(*span, from, tag).map_or(from, |s| s.end);
+                }
+                for field in fields {
+                    match &field.pattern {
+                        Some(inner) => {
+                            self.pattern(inner);
+                            from = inner.span().map_or(from, |s| s.end);
+                        }
+                        None => {
+                            let found = self.find_name(*span, from, &field.key);
+                            from = found.map_or(from, |s| s.end);
+                            self.declare(&field.key, found);
+                        }
+                    }
+                }
+            }
+            Pattern::Wildcard { .. } | Pattern::Literal { .. } => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Identifier { name, span } => self.mention(name, *span, OccurrenceKind::Read),
+            Expr::Number { .. }
+            | Expr::String { .. }
+            | Expr::Boolean { .. }
+            | Expr::Null { .. } => {}
+            Expr::Function {
+                arguments, body, ..
+            } => {
+                for arg in arguments {
+                    if let Some(default) = &arg.default {
+                        self.expr(default);
+                    }
+                }
+                self.scoped(|r| {
+                    for arg in arguments {
+                        let span = arg
+                            .span
+                            .map(|s| Span::new(s.start, s.start + arg.name.len()));
+                        r.declare(&arg.name, span);
+                    }
+                    r.stmts(body);
+                });
+            }
+            Expr::List { elements, .. } => {
+                for element in elements {
+                    self.expr(element);
+                }
+            }
+            Expr::Table { fields, .. } => {
+                for (key, value) in fields {
+                    if let TableKey::Computed(key) = key {
+                        self.expr(key);
+                    }
+                    self.expr(value);
+                }
+            }
+            Expr::Binary { left, right, .. }
+            | Expr::Logical { left, right, .. }
+            | Expr::Range {
+                start: left,
+                end: right,
+                ..
+            } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Unary { operand, .. } | Expr::Spread { value: operand, .. } => self.expr(operand),
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                self.expr(callee);
+                self.arguments(arguments);
+            }
+            Expr::MethodCall {
+                object, arguments, ..
+            } => {
+                self.expr(object);
+                self.arguments(arguments);
+            }
+            Expr::MemberAccess { object, .. } => self.expr(object),
+            Expr::Index { object, index, .. } => {
+                self.expr(object);
+                self.expr(index);
+            }
+            Expr::If {
+                condition,
+                then_block,
+                else_block,
+                ..
+            } => {
+                self.expr(condition);
+                self.block(then_block);
+                if let Some(block) = else_block {
+                    self.block(block);
+                }
+            }
+            Expr::Block { statements, .. } => self.block(statements),
+            Expr::Import { path, .. } => self.expr(path),
+            Expr::Match { expr, arms, .. } => self.match_arms(expr, arms),
+            Expr::Loop { body, .. } => self.stmt(body),
+        }
+    }
+
+    fn arguments(&mut self, arguments: &[CallArgument]) {
+        for arg in arguments {
+            match arg {
+                CallArgument::Positional(value) | CallArgument::Named { value, .. } => {
+                    self.expr(value)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The text and kind of every mention of the variable at `offset`
+    fn mentions_at(source: &str, offset: usize) -> Vec<(&str, OccurrenceKind, usize)> {
+        let program = crate::parser::parse(source, "test.luma").unwrap();
+        let occurrences = resolve(&program, source);
+        let symbol = occurrences
+            .iter()
+            .find(|o| o.span.start <= offset && offset <= o.span.end)
+            .expect("offset is on a variable")
+            .symbol;
+        occurrences
+            .iter()
+            .filter(|o| o.symbol == symbol)
+            .map(|o| (&source[o.span.start..o.span.end], o.kind, o.span.start))
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_respects_shadowing() {
+        let source = "let x = 1\nif true do\n  let x = x + 1\n  print(x)\nend\nprint(x)";
+        let outer = mentions_at(source, 4);
+        assert_eq!(
+            outer,
+            vec![
+                ("x", OccurrenceKind::Declaration, 4),
+                ("x", OccurrenceKind::Read, 31),
+                ("x", OccurrenceKind::Read, 58),
+            ]
+        );
+        let inner = mentions_at(source, 45);
+        assert_eq!(
+            inner,
+            vec![
+                ("x", OccurrenceKind::Declaration, 27),
+                ("x", OccurrenceKind::Read, 45),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_functions_are_visible_before_their_definition() {
+        let source =
+            "let a = fn(): Number do\n  return b()\nend\nlet b = fn(): Number do\n  return 1\nend";
+        let mentions = mentions_at(source, 33);
+        assert_eq!(
+            mentions,
+            vec![
+                ("b", OccurrenceKind::Read, 33),
+                ("b", OccurrenceKind::Declaration, 45),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_binds_pattern_variables() {
+        let source = "let {name, age: years} = { name = \"a\", age = 1 }\nlet [first, ...others] = [1, 2]\nprint(name, years, others)";
+        let program = crate::parser::parse(source, "test.luma").unwrap();
+        let declared: Vec<&str> = resolve(&program, source)
+            .iter()
+            .filter(|o| o.kind == OccurrenceKind::Declaration)
+            .map(|o| &source[o.span.start..o.span.end])
+            .collect();
+        assert_eq!(declared, vec!["name", "years", "first", "others"]);
+        assert_eq!(mentions_at(source, 6).len(), 2);
+        assert_eq!(mentions_at(source, 66).len(), 2);
+    }
+}
@@ -0,0 +1,294 @@
+//! Circular initialization detection for top-level bindings.
+//!
+//! Top-level functions may reference each other freely because their bodies
+//! only run when called. Value initializers run eagerly in declaration order,
+//! so `let a = b` / `let b = a` can never both see an initialized value.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::*;
+
+use super::environment::TypeEnv;
+use super::errors::ErrorCode;
+
+/// DFS state of a binding while searching for cycles.
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+    Pending,
+    InProgress,
+    Done,
+}
+
+impl TypeEnv {
+    /// Report cycles among the initializers of top-level non-function bindings.
+    pub fn check_initialization_cycles(&mut self, statements: &[Stmt]) {
+        let mut order = Vec::new();
+        let mut spans = HashMap::new();
+        let mut initializers = HashMap::new();
+        for stmt in statements {
+            if let Stmt::VarDecl {
+                name, value, span, ..
+            } = stmt
+                && !matches!(value, Expr::Function { .. })
+                && !initializers.contains_key(name.as_str())
+            {
+                order.push(name.as_str());
+                spans.insert(name.as_str(), *span);
+                initializers.insert(name.as_str(), value);
+            }
+        }
+
+        // Edges only point at other value bindings; functions are excluded
+        let mut deps: HashMap<&str, Vec<String>> = HashMap::new();
+        for &name in &order {
+            let mut refs = Vec::new();
+            collect_expr_refs(initializers[name], &HashSet::new(), &mut refs);
+            let mut seen = HashSet::new();
+            refs.retain(|r| initializers.contains_key(r.as_str()) && seen.insert(r.clone()));
+            deps.insert(name, refs);
+        }
+
+        let mut state: HashMap<&str, Visit> = order.iter().map(|&n| (n, Visit::Pending)).collect();
+        let mut cycles = Vec::new();
+        for &name in &order {
+            if state[name] == Visit::Pending {
+                let mut stack = Vec::new();
+                find_cycles(name, &deps, &mut state, &mut stack, &mut cycles);
+            }
+        }
+
+        for cycle in cycles {
+            let span = spans[cycle[0].as_str()];
+            self.error(
+                ErrorCode::CircularDefinition,
+                format!("circular definition: {}", cycle.join(" -> ")),
+                span,
+            );
+        }
+    }
+}
+
+fn find_cycles<'a>(
+    name: &'a str,
+    deps: &'a HashMap<&'a str, Vec<String>>,
+    state: &mut HashMap<&'a str, Visit>,
+    stack: &mut Vec<&'a str>,
+    cycles: &mut Vec<Vec<String>>,
+) {
+    state.insert(name, Visit::InProgress);
+    stack.push(name);
+    for dep in &deps[name] {
+        match state[dep.as_str()] {
+            Visit::Pending => find_cycles(dep, deps, state, stack, cycles),
+            Visit::InProgress => {
+                let start = stack.iter().position(|n| n == dep).unwrap_or(0);
+                let mut cycle: Vec<String> = stack[start..].iter().map(|n| n.to_string()).collect();
+                cycle.push(dep.clone());
+                cycles.push(cycle);
+            }
+            Visit::Done => {}
+        }
+    }
+    stack.pop();
+    state.insert(name, Visit::Done);
+}
+
+/// Collect identifiers an expression reads while it is being evaluated.
+/// Function bodies are skipped since they do not run at definition time.
+fn collect_expr_refs(expr: &Expr, bound: &HashSet<String>, refs: &mut Vec<String>) {
+    match expr {
+        Expr::Identifier { name, .. } => {
+            if !bound.contains(name) {
+                refs.push(name.clone());
+            }
+        }
+        Expr::Number { .. }
+        | Expr::String { .. }
+        | Expr::Boolean { .. }
+        | Expr::Null { .. }
+        | Expr::Function { .. } => {}
+        Expr::List { elements, .. } => {
+            for e in elements {
+                collect_expr_refs(e, bound, refs);
+            }
+        }
+        Expr::Table { fields, .. } => {
+            for (key, value) in fields {
+                if let TableKey::Computed(k) = key {
+                    collect_expr_refs(k, bound, refs);
+                }
+                collect_expr_refs(value, bound, refs);
+            }
+        }
+        Expr::Binary { left, right, .. }
+        | Expr::Logical { left, right, .. }
+        | Expr::Range {
+            start: left,
+            end: right,
+            ..
+        } => {
+            collect_expr_refs(left, bound, refs);
+            collect_expr_refs(right, bound, refs);
+        }
+        Expr::Unary { operand, .. } | Expr::Spread { value: operand, .. } => {
+            collect_expr_refs(operand, bound, refs)
+        }
+        Expr::Call {
+            callee, arguments, ..
+        } => {
+            collect_expr_refs(callee, bound, refs);
+            collect_call_refs(arguments, bound, refs);
+        }
+        Expr::MethodCall {
+            object, arguments, ..
+        } => {
+            collect_expr_refs(object, bound, refs);
+            collect_call_refs(arguments, bound, refs);
+        }
+        Expr::MemberAccess { object, .. } => collect_expr_refs(object, bound, refs),
+        Expr::Index { object, index, .. } => {
+            collect_expr_refs(object, bound, refs);
+            collect_expr_refs(index, bound, refs);
+        }
+        Expr::If {
+            condition,
+            then_block,
+            else_block,
+            ..
+        } => {
+            collect_expr_refs(condition, bound, refs);
+            collect_block_refs(then_block, bound, refs);
+            if let Some(else_block) = else_block {
+                collect_block_refs(else_block, bound, refs);
+            }
+        }
+        Expr::Block { statements, .. } => collect_block_refs(statements, bound, refs),
+        Expr::Import { path, .. } => collect_expr_refs(path, bound, refs),
+        Expr::Match { expr, arms, .. } => {
+            collect_expr_refs(expr, bound, refs);
+            collect_arm_refs(arms, bound, refs);
+        }
+        Expr::Loop { body, .. } => {
+            collect_block_refs(std::slice::from_ref(body.as_ref()), bound, refs)
+        }
+    }
+}
+
+fn collect_call_refs(arguments: &[CallArgument], bound: &HashSet<String>, refs: &mut Vec<String>) {
+    for arg in arguments {
+        match arg {
+            CallArgument::Positional(e) | CallArgument::Named { value: e, .. } => {
+                collect_expr_refs(e, bound, refs)
+            }
+        }
+    }
+}
+
+fn collect_arm_refs(
+    arms: &[(Pattern, Vec<Stmt>)],
+    bound: &HashSet<String>,
+    refs: &mut Vec<String>,
+) {
+    for (pattern, body) in arms {
+        let mut arm_bound = bound.clone();
+        bind_pattern(pattern, &mut arm_bound);
+        collect_block_refs(body, &arm_bound, refs);
+    }
+}
+
+/// Walk a block, treating its own declarations as shadowing outer names.
+fn collect_block_refs(statements: &[Stmt], bound: &HashSet<String>, refs: &mut Vec<String>) {
+    let mut bound = bound.clone();
+    for stmt in statements {
+        match stmt {
+            Stmt::VarDecl { name, value, .. } => {
+                collect_expr_refs(value, &bound, refs);
+                bound.insert(name.clone());
+            }
+            Stmt::DestructuringVarDecl { pattern, value, .. } => {
+                collect_expr_refs(value, &bound, refs);
+                bind_pattern(pattern, &mut bound);
+            }
+            Stmt::Return { value, .. } => collect_expr_refs(value, &bound, refs),
+            Stmt::Assignment { target, value, .. } => {
+                collect_expr_refs(target, &bound, refs);
+                collect_expr_refs(value, &bound, refs);
+            }
+            Stmt::If {
+                condition,
+                then_block,
+                elif_blocks,
+                else_block,
+                ..
+            } => {
+                collect_expr_refs(condition, &bound, refs);
+                collect_block_refs(then_block, &bound, refs);
+                for (cond, block) in elif_blocks {
+                    collect_expr_refs(cond, &bound, refs);
+                    collect_block_refs(block, &bound, refs);
+                }
+                if let Some(else_block) = else_block {
+                    collect_block_refs(else_block, &bound, refs);
+                }
+            }
+            Stmt::While {
+                condition, body, ..
+            }
+            | Stmt::DoWhile {
+                condition, body, ..
+            } => {
+                collect_expr_refs(condition, &bound, refs);
+                collect_block_refs(body, &bound, refs);
+            }
+            Stmt::For {
+                pattern,
+                iterator,
+                body,
+                ..
+            } => {
+                collect_expr_refs(iterator, &bound, refs);
+                let mut body_bound = bound.clone();
+                bind_pattern(pattern, &mut body_bound);
+                collect_block_refs(body, &body_bound, refs);
+            }
+            Stmt::Break { value, .. } => {
+                if let Some(value) = value {
+                    collect_expr_refs(value, &bound, refs);
+                }
+            }
+            Stmt::ExprStmt { expr, .. } => collect_expr_refs(expr, &bound, refs),
+            Stmt::Match { expr, arms, .. } => {
+                collect_expr_refs(expr, &bound, refs);
+                collect_arm_refs(arms, &bound, refs);
+            }
+            Stmt::Continue { .. } | Stmt::TypeDecl { .. } | Stmt::Export { .. } => {}
+        }
+    }
+}
+
+fn bind_pattern(pattern: &Pattern, bound: &mut HashSet<String>) {
+    match pattern {
+        Pattern::Ident { name, .. } => {
+            bound.insert(name.clone());
+        }
+        Pattern::ListPattern { elements, rest, .. } => {
+            for element in elements {
+                bind_pattern(element, bound);
+            }
+            if let Some(rest) = rest {
+                bound.insert(rest.clone());
+            }
+        }
+        Pattern::TablePattern { fields, .. } | Pattern::Variant { fields, .. } => {
+            for field in fields {
+                match &field.pattern {
+                    Some(pattern) => bind_pattern(pattern, bound),
+                    None => {
+                        bound.insert(field.key.clone());
+                    }
+                }
+            }
+        }
+        Pattern::Wildcard { .. } | Pattern::Literal { .. } => {}
+    }
+}
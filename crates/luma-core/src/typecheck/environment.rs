@@ -1,12 +1,12 @@
 //! Type environment for scope and variable management.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-use crate::ast::{Expr, Span, Type};
+use crate::ast::{EnumVariant, Expr, Span, Type};
 
-use super::errors::TypeError;
+use super::errors::{ErrorCode, TypeError};
 use super::types::{TcType, VarInfo};
-use crate::diagnostics::FixIt;
+use crate::diagnostics::{Applicability, FixIt, Severity};
 
 /// Type environment that tracks variable scopes and accumulates errors.
 pub struct TypeEnv {
@@ -14,6 +14,30 @@ pub struct TypeEnv {
     pub errors: Vec<TypeError>,
     /// Track match arm context to relax certain checks inside arms.
     pub in_match_arm_depth: usize,
+    /// Number of function bodies enclosing the current statement (0 = module top level).
+    pub function_depth: usize,
+    /// Declared tagged-union types: type name -> variants. A variant name
+    /// belongs to at most one type, since values carry only the variant tag.
+    pub enums: BTreeMap<String, Vec<EnumVariant>>,
+    /// Types of `break` values seen in each enclosing loop (innermost last).
+    pub loop_breaks: Vec<Vec<(TcType, Option<Span>)>>,
+    /// Spans of `let`/`var` bindings per scope, parallel to `scopes`.
+    pub binding_spans: Vec<HashMap<String, Option<Span>>>,
+    /// Non-fatal findings such as same-scope shadowing.
+    pub warnings: Vec<TypeError>,
+    /// Inferred type of every checked expression, keyed by its span.
+    pub expr_types: HashMap<Span, TcType>,
+    /// Undefined names already reported in the statement being checked; later
+    /// uses in the same statement stay silent.
+    pub reported_undefined: HashSet<String>,
+    /// Bindings of empty lists whose element type was inferred from a later
+    /// store: binding span -> span of the store that fixed it.
+    pub inferred_elements: HashMap<Span, Span>,
+    /// Span of the value of the annotated binding being checked, whose
+    /// overloaded operator result needs no note asking for an annotation.
+    pub annotated_value: Option<Span>,
+    /// Report gradual `Any`/`Unknown` fallbacks as errors (`--strict-types`).
+    pub strict_types: bool,
 }
 
 impl TypeEnv {
@@ -23,6 +47,16 @@ impl TypeEnv {
             scopes: vec![HashMap::new()],
             errors: Vec::new(),
             in_match_arm_depth: 0,
+            function_depth: 0,
+            loop_breaks: Vec::new(),
+            binding_spans: vec![HashMap::new()],
+            warnings: Vec::new(),
+            expr_types: HashMap::new(),
+            enums: BTreeMap::new(),
+            reported_undefined: HashSet::new(),
+            inferred_elements: HashMap::new(),
+            annotated_value: None,
+            strict_types: false,
         };
 
         // Register built-in functions
@@ -62,6 +96,42 @@ impl TypeEnv {
             },
         );
 
+        // Debug builds of the stdlib also provide `inspect`
+        #[cfg(debug_assertions)]
+        env.declare(
+            "inspect".to_string(),
+            VarInfo {
+                ty: TcType::Function {
+                    params: vec![TcType::Any],
+                    ret: Box::new(TcType::String),
+                },
+                mutable: false,
+                annotated: true,
+            },
+        );
+
+        env.declare(
+            "len".to_string(),
+            VarInfo {
+                ty: TcType::Function {
+                    params: vec![TcType::Any],
+                    ret: Box::new(TcType::Number),
+                },
+                mutable: false,
+                annotated: true,
+            },
+        );
+
+        // Calls to the builtin are typed per argument by `check_iter_call`
+        env.declare(
+            "iter".to_string(),
+            VarInfo {
+                ty: Self::iter_builtin_type(),
+                mutable: false,
+                annotated: true,
+            },
+        );
+
         env.declare(
             "typeof".to_string(),
             VarInfo {
@@ -74,8 +144,70 @@ impl TypeEnv {
             },
         );
 
+        env.declare(
+            "is_callable".to_string(),
+            VarInfo {
+                ty: TcType::Function {
+                    params: vec![TcType::Any],
+                    ret: Box::new(TcType::Boolean),
+                },
+                mutable: false,
+                annotated: true,
+            },
+        );
+
+        env.declare(
+            "keys".to_string(),
+            VarInfo {
+                ty: TcType::Function {
+                    params: vec![TcType::Table],
+                    ret: Box::new(TcType::List(Box::new(TcType::String))),
+                },
+                mutable: false,
+                annotated: true,
+            },
+        );
+
+        env.declare(
+            "query".to_string(),
+            VarInfo {
+                ty: TcType::Function {
+                    params: vec![TcType::Any, TcType::String],
+                    ret: Box::new(TcType::Any),
+                },
+                mutable: false,
+                annotated: true,
+            },
+        );
+
+        for name in ["freeze", "deep_freeze"] {
+            env.declare(
+                name.to_string(),
+                VarInfo {
+                    ty: TcType::Function {
+                        params: vec![TcType::Table],
+                        ret: Box::new(TcType::Table),
+                    },
+                    mutable: false,
+                    annotated: true,
+                },
+            );
+        }
+
+        env.declare(
+            "values".to_string(),
+            VarInfo {
+                ty: TcType::Function {
+                    params: vec![TcType::Table],
+                    ret: Box::new(TcType::List(Box::new(TcType::Any))),
+                },
+                mutable: false,
+                annotated: true,
+            },
+        );
+
         // print is variadic - we use Any to accept any number of arguments
-        // The actual arity check is skipped for print in the VM
+        // The VM checks the count against the range print was registered with
         env.declare(
             "print".to_string(),
             VarInfo {
@@ -85,6 +217,28 @@ impl TypeEnv {
             },
         );
 
+        env.declare(
+            "pretty_print".to_string(),
+            VarInfo {
+                ty: TcType::Function {
+                    params: vec![TcType::Any, TcType::Number],
+                    ret: Box::new(TcType::Null),
+                },
+                mutable: false,
+                annotated: true,
+            },
+        );
+
+        // format is variadic like print: a template followed by its arguments
+        env.declare(
+            "format".to_string(),
+            VarInfo {
+                ty: TcType::Any,
+                mutable: false,
+                annotated: true,
+            },
+        );
+
         // Register I/O native functions
         env.declare(
             "write".to_string(),
@@ -212,15 +366,28 @@ impl TypeEnv {
             },
         );
 
-        // Prelude helpers registered as built-ins (MVP: treat as Any to allow flexible arity)
+        // Built-in kind markers (used as struct field specs and with isInstanceOf)
+        for marker in ["Number", "Boolean", "Table", "Function"] {
+            env.declare(
+                marker.to_string(),
+                VarInfo {
+                    ty: TcType::Table,
+                    mutable: false,
+                    annotated: true,
+                },
+            );
+        }
+
         env.declare(
             "range".to_string(),
             VarInfo {
-                ty: TcType::Any,
+                ty: Self::range_builtin_type(),
                 mutable: false,
                 annotated: true,
             },
         );
+
+        // Prelude helpers registered as built-ins (MVP: treat as Any to allow flexible arity)
         env.declare(
             "indexed".to_string(),
             VarInfo {
@@ -229,6 +396,32 @@ impl TypeEnv {
                 annotated: true,
             },
         );
+        env.declare(
+            "push".to_string(),
+            VarInfo {
+                ty: Self::push_builtin_type(),
+                mutable: false,
+                annotated: true,
+            },
+        );
+        // Lazy iterator adapters; what they yield depends on the source
+        for (name, params) in [
+            ("take", vec![TcType::Any, TcType::Number]),
+            ("skip", vec![TcType::Any, TcType::Number]),
+            ("enumerate", vec![TcType::Any]),
+        ] {
+            env.declare(
+                name.to_string(),
+                VarInfo {
+                    ty: TcType::Function {
+                        params,
+                        ret: Box::new(TcType::Any),
+                    },
+                    mutable: false,
+                    annotated: true,
+                },
+            );
+        }
 
         // Register FFI module
         env.declare(
@@ -240,6 +433,41 @@ impl TypeEnv {
             },
         );
 
+        env.declare(
+            "at_exit".to_string(),
+            VarInfo {
+                ty: TcType::Function {
+                    params: vec![TcType::Function {
+                        params: vec![],
+                        ret: Box::new(TcType::Any),
+                    }],
+                    ret: Box::new(TcType::Null),
+                },
+                mutable: false,
+                annotated: true,
+            },
+        );
+
+        // Path of the running file
+        env.declare(
+            "__file".to_string(),
+            VarInfo {
+                ty: TcType::String,
+                mutable: false,
+                annotated: true,
+            },
+        );
+
+        // Register path module
+        env.declare(
+            "path".to_string(),
+            VarInfo {
+                ty: TcType::Table,
+                mutable: false,
+                annotated: true,
+            },
+        );
+
         // Register process module
         env.declare(
             "process".to_string(),
@@ -250,6 +478,46 @@ impl TypeEnv {
             },
         );
 
+        // Register io module
+        env.declare(
+            "io".to_string(),
+            VarInfo {
+                ty: TcType::Table,
+                mutable: false,
+                annotated: true,
+            },
+        );
+
+        // Register math module
+        env.declare(
+            "math".to_string(),
+            VarInfo {
+                ty: TcType::Table,
+                mutable: false,
+                annotated: true,
+            },
+        );
+
+        // Register random module
+        env.declare(
+            "random".to_string(),
+            VarInfo {
+                ty: TcType::Table,
+                mutable: false,
+                annotated: true,
+            },
+        );
+
+        // Register stats module
+        env.declare(
+            "stats".to_string(),
+            VarInfo {
+                ty: TcType::Table,
+                mutable: false,
+                annotated: true,
+            },
+        );
+
         // Register External type marker
         env.declare(
             "External".to_string(),
@@ -266,11 +534,39 @@ impl TypeEnv {
     /// Push a new scope onto the scope stack.
     pub fn push_scope(&mut self) {
         self.scopes.push(HashMap::new());
+        self.binding_spans.push(HashMap::new());
     }
 
     /// Pop the current scope from the scope stack.
     pub fn pop_scope(&mut self) {
         self.scopes.pop();
+        self.binding_spans.pop();
+    }
+
+    /// Declared signature of the builtin `push`, which appends in place and
+    /// returns the same list
+    pub fn push_builtin_type() -> TcType {
+        TcType::Function {
+            params: vec![TcType::List(Box::new(TcType::Unknown)), TcType::Any],
+            ret: Box::new(TcType::List(Box::new(TcType::Unknown))),
+        }
+    }
+
+    /// Declared signature of the builtin `range`, whose step may be left out.
+    /// It returns an iterator, not a list
+    pub fn range_builtin_type() -> TcType {
+        TcType::Function {
+            params: vec![TcType::Number, TcType::Number, TcType::Number],
+            ret: Box::new(TcType::TableWithFields(vec!["__next".to_string()])),
+        }
+    }
+
+    /// Declared signature of the builtin `iter`, which returns a list to loop over
+    pub fn iter_builtin_type() -> TcType {
+        TcType::Function {
+            params: vec![TcType::Any],
+            ret: Box::new(TcType::List(Box::new(TcType::Unknown))),
+        }
     }
 
     /// Declare a variable in the current scope.
@@ -280,6 +576,39 @@ impl TypeEnv {
         }
     }
 
+    /// Declare a `let`/`var` binding in the current scope, warning when it
+    /// redeclares a binding from the same scope. Shadowing a binding from an
+    /// enclosing scope is intentional and stays silent.
+    pub fn declare_binding(&mut self, name: String, info: VarInfo, span: Option<Span>) {
+        self.note_binding(&name, span);
+        self.declare(name, info);
+    }
+
+    /// Record the span of a `let`/`var` binding for same-scope shadowing checks.
+    pub fn note_binding(&mut self, name: &str, span: Option<Span>) {
+        let Some(bindings) = self.binding_spans.last_mut() else {
+            return;
+        };
+        let Some(&first) = bindings.get(name) else {
+            bindings.insert(name.to_string(), span);
+            return;
+        };
+        self.warnings.push(TypeError {
+            message: format!("'{name}' is already declared in this scope"),
+            span,
+            severity: Severity::Warning,
+            suggestions: vec![format!(
+                "rename this binding or assign to the existing '{name}' instead"
+            )],
+            fixits: Vec::new(),
+            related: first
+                .map(|s| (format!("'{name}' first declared here"), s))
+                .into_iter()
+                .collect(),
+            code: Some(ErrorCode::DuplicateDeclaration),
+        });
+    }
+
     /// Look up a variable by name, searching from innermost to outermost scope.
     pub fn lookup(&self, name: &str) -> Option<&VarInfo> {
         for scope in self.scopes.iter().rev() {
@@ -290,18 +619,151 @@ impl TypeEnv {
         None
     }
 
+    /// Span of the `let`/`var` binding a name currently refers to, if it has one.
+    pub fn binding_span(&self, name: &str) -> Option<Span> {
+        self.scopes
+            .iter()
+            .zip(&self.binding_spans)
+            .rev()
+            .find(|(scope, _)| scope.contains_key(name))
+            .and_then(|(_, bindings)| bindings.get(name).copied().flatten())
+    }
+
+    /// Mutable access to the variable a name currently refers to.
+    pub fn lookup_mut(&mut self, name: &str) -> Option<&mut VarInfo> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    /// Infer a list's element type from an index store `xs[i] = value`; see
+    /// [`TypeEnv::infer_list_store`].
+    pub fn infer_list_element(
+        &mut self,
+        target: &Expr,
+        value_ty: &TcType,
+        span: Option<Span>,
+    ) -> bool {
+        let (Expr::Index { object, .. }, Some(span)) = (target, span) else {
+            return false;
+        };
+        let Expr::Identifier { name, .. } = object.as_ref() else {
+            return false;
+        };
+        self.infer_list_store(name, value_ty, span)
+    }
+
+    /// Infer the element type of a list bound empty (`var xs = []`) from the
+    /// first store into it, by index or by `push`, so later reads are checked.
+    /// A store that disagrees with the inferred type is a warning rather than
+    /// an error, and widens the element type so the list stays usable.
+    /// Returns whether the store was handled here.
+    pub fn infer_list_store(&mut self, name: &str, value_ty: &TcType, span: Span) -> bool {
+        if matches!(value_ty, TcType::Unknown | TcType::Any) {
+            return false;
+        }
+        let Some(binding) = self.binding_span(name) else {
+            return false;
+        };
+        let Some(VarInfo {
+            ty: TcType::List(elem),
+            annotated: false,
+            ..
+        }) = self.lookup(name)
+        else {
+            return false;
+        };
+        let elem = (**elem).clone();
+
+        match self.inferred_elements.get(&binding).copied() {
+            None if elem == TcType::Unknown => {
+                self.inferred_elements.insert(binding, span);
+                if let Some(info) = self.lookup_mut(name) {
+                    info.ty = TcType::List(Box::new(value_ty.clone()));
+                }
+                true
+            }
+            None => false,
+            Some(_) if value_ty.is_compatible(&elem) => true,
+            Some(first) => {
+                self.warnings.push(TypeError {
+                    message: format!(
+                        "Inconsistent list element types: '{name}' holds {elem}, but this stores {value_ty}"
+                    ),
+                    span: Some(span),
+                    severity: Severity::Warning,
+                    suggestions: vec![format!(
+                        "annotate '{name}' with a list type if it should hold mixed values"
+                    )],
+                    fixits: Vec::new(),
+                    related: vec![(format!("element type of '{name}' inferred from this store"), first)],
+                    code: Some(ErrorCode::InconsistentListElements),
+                });
+                if let Some(info) = self.lookup_mut(name) {
+                    info.ty = TcType::List(Box::new(TcType::Any));
+                }
+                true
+            }
+        }
+    }
+
+    /// Find the tagged-union type declaring a variant, returning the type name and variant.
+    pub fn find_enum_variant(&self, tag: &str) -> Option<(&str, &EnumVariant)> {
+        self.enums.iter().find_map(|(enum_name, variants)| {
+            variants
+                .iter()
+                .find(|v| v.name == tag)
+                .map(|v| (enum_name.as_str(), v))
+        })
+    }
+
     /// Record a type error.
-    pub fn error(&mut self, message: String, span: Option<Span>) {
+    pub fn error(&mut self, code: ErrorCode, message: String, span: Option<Span>) {
         self.errors.push(TypeError {
             message,
             span,
+            severity: Severity::Error,
+            suggestions: Vec::new(),
+            fixits: Vec::new(),
+            related: Vec::new(),
+            code: Some(code),
+        });
+    }
+
+    /// Record a warning; warnings never fail type checking.
+    pub fn warning(&mut self, code: ErrorCode, message: String, span: Option<Span>) {
+        self.warnings.push(TypeError {
+            message,
+            span,
+            severity: Severity::Warning,
+            suggestions: Vec::new(),
+            fixits: Vec::new(),
+            related: Vec::new(),
+            code: Some(code),
+        });
+    }
+
+    /// Record an informational note; like warnings, notes never fail type checking.
+    pub fn info(&mut self, message: String, span: Option<Span>) {
+        self.warnings.push(TypeError {
+            message,
+            span,
+            severity: Severity::Info,
             suggestions: Vec::new(),
             fixits: Vec::new(),
+            related: Vec::new(),
+            code: None,
         });
     }
 
-    /// Record an undefined variable error with did-you-mean suggestions and rename fix-its
+    /// Record an undefined variable error with did-you-mean suggestions and rename fix-its.
+    /// Only the first use of each undefined name in a statement is reported.
     pub fn error_undefined_variable(&mut self, name: &str, span: Option<Span>) {
+        if !self.reported_undefined.insert(name.to_string()) {
+            return;
+        }
+
         // Collect candidate names from all visible scopes
         let mut candidates: Vec<String> = Vec::new();
         for scope in self.scopes.iter() {
@@ -327,11 +789,10 @@ impl TypeEnv {
             if name.is_empty() || similar_enough(name, &cand) {
                 suggestions.push(format!("did you mean '{cand}'?"));
                 if let Some(s) = span {
-                    fixits.push(FixIt::replace(
-                        s,
-                        cand.clone(),
-                        format!("Change to '{cand}'"),
-                    ));
+                    fixits.push(
+                        FixIt::replace(s, cand.clone(), format!("Change to '{cand}'"))
+                            .with_applicability(Applicability::MaybeIncorrect),
+                    );
                 }
             }
         }
@@ -339,8 +800,11 @@ impl TypeEnv {
         self.errors.push(TypeError {
             message: format!("Undefined variable: {name}"),
             span,
+            severity: Severity::Error,
             suggestions,
             fixits,
+            related: Vec::new(),
+            code: Some(ErrorCode::UndefinedVariable),
         });
     }
 
@@ -349,13 +813,36 @@ impl TypeEnv {
         let ty = self.check_expr(expr);
         if !ty.is_compatible(expected) {
             self.error(
+                ErrorCode::TypeMismatch,
                 format!("{context}: expected {expected}, got {ty}"),
                 expr.span(),
             );
         }
+        self.check_strict_unknown(&ty, expected, context, expr.span());
         ty
     }
 
+    /// Under strict types, reject a value of unknown type flowing into a
+    /// position that declares a concrete type.
+    pub fn check_strict_unknown(
+        &mut self,
+        actual: &TcType,
+        expected: &TcType,
+        context: &str,
+        span: Option<Span>,
+    ) {
+        if self.strict_types
+            && *actual == TcType::Unknown
+            && !matches!(expected, TcType::Any | TcType::Unknown)
+        {
+            self.error(
+                ErrorCode::StrictTypes,
+                format!("{context}: expected {expected}, got a value of unknown type"),
+                span,
+            );
+        }
+    }
+
     /// Check if a type has an operator method (e.g., __neg, __mod, __lt).
     pub fn has_operator_method(ty: &TcType, method_name: &str) -> bool {
         match ty {
@@ -370,6 +857,18 @@ impl TypeEnv {
         }
     }
 
+    /// Check if a type is a list that orders element-wise by default: its
+    /// elements are numbers, or lists that are themselves ordered.
+    pub fn is_ordered_list(ty: &TcType) -> bool {
+        match ty {
+            TcType::List(elem) => match elem.as_ref() {
+                TcType::Number | TcType::Any | TcType::Unknown => true,
+                inner => Self::is_ordered_list(inner),
+            },
+            _ => false,
+        }
+    }
+
     /// Convert an AST type to a TcType.
     pub fn type_from_ast(ty: &Type) -> TcType {
         match ty {
@@ -1,17 +1,141 @@
 //! Type checking error types and result types.
 
 use crate::ast::Span;
-use crate::diagnostics::FixIt;
+use crate::diagnostics::{
+    Diagnostic, DiagnosticCode, DiagnosticKind, FixIt, RelatedInfo, Severity,
+};
 
-/// A type error with message and optional source location.
+/// A type error (or warning) with message and optional source location.
 #[derive(Debug, Clone)]
 pub struct TypeError {
     pub message: String,
     pub span: Option<Span>,
+    /// `Severity::Error` fails type checking; warnings are reported but never block execution
+    pub severity: Severity,
     /// Optional human-friendly suggestions
     pub suggestions: Vec<String>,
     /// Optional machine-applicable fixes
     pub fixits: Vec<FixIt>,
+    /// Other source locations relevant to this error, with a short message each
+    pub related: Vec<(String, Span)>,
+    /// What kind of problem this is; informational notes have no code
+    pub code: Option<ErrorCode>,
+}
+
+impl TypeError {
+    /// Convert into a core diagnostic for the given file, keeping suggestions,
+    /// fix-its and related locations.
+    pub fn to_diagnostic(&self, filename: &str) -> Diagnostic {
+        let span = self.span.unwrap_or_else(|| Span::new(0, 0));
+        let mut diag = Diagnostic::error(
+            DiagnosticKind::Type,
+            self.message.clone(),
+            span,
+            filename.to_string(),
+        );
+        diag.severity = self.severity;
+        if let Some(code) = self.code() {
+            diag = diag.with_code(code);
+        }
+        for s in &self.suggestions {
+            diag = diag.with_suggestion(s.clone());
+        }
+        for fix in &self.fixits {
+            diag = diag.with_fix(fix.clone());
+        }
+        for (message, span) in &self.related {
+            diag = diag.with_related(RelatedInfo::new(message.clone(), *span, filename));
+        }
+        diag
+    }
+
+    /// Stable code identifying the kind of problem, used in output and by
+    /// `-- luma:ignore CODE` comments.
+    pub fn code(&self) -> Option<DiagnosticCode> {
+        self.code
+            .map(|code| DiagnosticCode::new(code.as_str(), code.title()))
+    }
+}
+
+/// Kind of a type diagnostic, chosen where it is reported. Each maps to the
+/// stable code listed in the specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    TypeMismatch,
+    UndefinedVariable,
+    UnknownField,
+    ArgumentCount,
+    ImmutableAssignment,
+    NonExhaustiveMatch,
+    UnreachablePattern,
+    CircularDefinition,
+    LoopControl,
+    InvalidExport,
+    DynamicImport,
+    StrictTypes,
+    ReturnOutsideFunction,
+    DuplicateDeclaration,
+    FractionalIndex,
+    UnreachableCode,
+    IfWithoutElse,
+    InfiniteLoop,
+    InconsistentListElements,
+    SelfAssignment,
+    EmptyBlock,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::TypeMismatch => "E0001",
+            ErrorCode::UndefinedVariable => "E0002",
+            ErrorCode::UnknownField => "E0003",
+            ErrorCode::ArgumentCount => "E0004",
+            ErrorCode::ImmutableAssignment => "E0005",
+            ErrorCode::NonExhaustiveMatch => "E0006",
+            ErrorCode::UnreachablePattern => "E0007",
+            ErrorCode::CircularDefinition => "E0008",
+            ErrorCode::LoopControl => "E0009",
+            ErrorCode::InvalidExport => "E0010",
+            ErrorCode::DynamicImport => "E0011",
+            ErrorCode::StrictTypes => "E0012",
+            ErrorCode::ReturnOutsideFunction => "E0013",
+            ErrorCode::DuplicateDeclaration => "W0001",
+            ErrorCode::FractionalIndex => "W0002",
+            ErrorCode::UnreachableCode => "W0003",
+            ErrorCode::IfWithoutElse => "W0004",
+            ErrorCode::InfiniteLoop => "W0005",
+            ErrorCode::InconsistentListElements => "W0006",
+            ErrorCode::SelfAssignment => "W0007",
+            ErrorCode::EmptyBlock => "W0008",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            ErrorCode::TypeMismatch => "Type mismatch",
+            ErrorCode::UndefinedVariable => "Undefined variable",
+            ErrorCode::UnknownField => "Unknown field",
+            ErrorCode::ArgumentCount => "Wrong number of arguments",
+            ErrorCode::ImmutableAssignment => "Assignment to immutable variable",
+            ErrorCode::NonExhaustiveMatch => "Non-exhaustive match",
+            ErrorCode::UnreachablePattern => "Unreachable pattern",
+            ErrorCode::CircularDefinition => "Circular definition",
+            ErrorCode::LoopControl => "Loop control outside of loop",
+            ErrorCode::InvalidExport => "Invalid export",
+            ErrorCode::DynamicImport => "Dynamic import path",
+            ErrorCode::StrictTypes => "Imprecise type under strict types",
+            ErrorCode::ReturnOutsideFunction => "Return outside of function",
+            ErrorCode::DuplicateDeclaration => "Duplicate declaration",
+            ErrorCode::FractionalIndex => "Fractional list index",
+            ErrorCode::UnreachableCode => "Unreachable code",
+            ErrorCode::IfWithoutElse => "If without else used as a value",
+            ErrorCode::InfiniteLoop => "Infinite loop",
+            ErrorCode::InconsistentListElements => "Inconsistent list element types",
+            ErrorCode::SelfAssignment => "Self-assignment",
+            ErrorCode::EmptyBlock => "Empty block",
+        }
+    }
 }
 
 /// Result type for type checking operations.
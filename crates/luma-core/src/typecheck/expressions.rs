@@ -1,13 +1,23 @@
 //! Expression type checking.
 
 use crate::ast::*;
+use crate::diagnostics::Severity;
 
 use super::environment::TypeEnv;
+use super::errors::{ErrorCode, TypeError};
 use super::types::TcType;
 
 impl TypeEnv {
-    /// Type check an expression and return its type.
+    /// Type check an expression and return its type, recording it by span.
     pub fn check_expr(&mut self, expr: &Expr) -> TcType {
+        let ty = self.infer_expr(expr);
+        if let Some(span) = expr.span() {
+            self.expr_types.insert(span, ty.clone());
+        }
+        ty
+    }
+
+    fn infer_expr(&mut self, expr: &Expr) -> TcType {
         match expr {
             Expr::Number { value: _, .. } => TcType::Number,
             Expr::String { value: _, .. } => TcType::String,
@@ -32,6 +42,7 @@ impl TypeEnv {
                         let ty = self.check_expr(elem);
                         if !ty.is_compatible(&first_ty) {
                             self.error(
+                                ErrorCode::TypeMismatch,
                                 format!(
                                     "List elements have inconsistent types: {first_ty} vs {ty}"
                                 ),
@@ -46,19 +57,40 @@ impl TypeEnv {
             Expr::Table {
                 fields: entries, ..
             } => {
-                for (_, value) in entries {
-                    self.check_expr(value);
-                }
-                // Collect identifier and string literal keys for structural presence
+                let mut has_computed_key = false;
+                let mut dynamic_spread = false;
                 let mut fields = Vec::new();
-                for (k, _) in entries {
-                    match k {
+                for (key, value) in entries {
+                    if let TableKey::Computed(key_expr) = key {
+                        self.expect_type(key_expr, &TcType::String, "Computed table key");
+                        has_computed_key = true;
+                    }
+                    let value_ty = self.check_expr(value);
+                    // Collect identifier and string literal keys for structural presence,
+                    // along with the known fields of spread tables
+                    match key {
                         TableKey::Identifier(s) | TableKey::StringLiteral(s) => {
                             fields.push(s.clone())
                         }
                         TableKey::Computed(_) => {}
+                        TableKey::Spread => match value_ty {
+                            TcType::TableWithFields(spread) => fields.extend(spread),
+                            TcType::Table | TcType::Any | TcType::Unknown => dynamic_spread = true,
+                            other => {
+                                self.error(
+                                    ErrorCode::TypeMismatch,
+                                    format!("Cannot spread {other} into a table"),
+                                    value.span(),
+                                );
+                                dynamic_spread = true;
+                            }
+                        },
                     }
                 }
+                // Fields of a table with computed keys or dynamic spreads aren't statically known
+                if has_computed_key || dynamic_spread {
+                    return TcType::Table;
+                }
                 // Deduplicate while preserving order
                 let mut seen = std::collections::HashSet::new();
                 fields.retain(|f| seen.insert(f.clone()));
@@ -146,20 +178,67 @@ impl TypeEnv {
             } => self.check_if_expr(condition, then_block, else_block.as_deref(), *span),
 
             Expr::Import { path, span } => {
-                // Check that path is a string expression
+                // Check that path is a string expression known at compile time,
+                // so the module graph can be worked out without running anything
                 let path_ty = self.check_expr(path);
                 if !path_ty.is_compatible(&TcType::String) && path_ty != TcType::Unknown {
                     self.error(
+                        ErrorCode::TypeMismatch,
                         format!("Import path should be a String, got {path_ty}"),
                         *span,
                     );
+                } else if path.constant_string().is_none() {
+                    self.error(
+                        ErrorCode::DynamicImport,
+                        "import path must be a string literal".to_string(),
+                        path.span(),
+                    );
                 }
-                // Import returns the module's exported value
-                // For now, we type it as Unknown (proper typing would require module analysis)
+                // The checker sees one file at a time, so the module's exported
+                // value is Unknown here; its exports are checked in its own pass
                 TcType::Unknown
             }
 
             Expr::Match { expr, arms, span } => self.check_match_expr(expr, arms, *span),
+
+            Expr::Loop { body, .. } => self.check_loop(body),
+
+            // A spread stands for each of its list's elements
+            Expr::Spread { value, .. } => match self.check_expr(value) {
+                TcType::List(elem_ty) => *elem_ty,
+                TcType::Unknown | TcType::Any => TcType::Unknown,
+                other => {
+                    self.error(
+                        ErrorCode::TypeMismatch,
+                        format!("Cannot spread {other} into a list"),
+                        value.span(),
+                    );
+                    TcType::Unknown
+                }
+            },
+
+            Expr::Range { start, end, .. } => {
+                self.expect_type(start, &TcType::Number, "Range start");
+                self.expect_type(end, &TcType::Number, "Range end");
+                TcType::List(Box::new(TcType::Number))
+            }
+        }
+    }
+
+    /// Explain why an arithmetic result went dynamic when it comes from an
+    /// operator method on a table known to define it. Operands that are
+    /// already dynamic (`Any`, `Unknown`, `Table`) stay silent, as does the
+    /// value of an annotated binding.
+    fn note_overload_result(&mut self, left_ty: &TcType, method_name: &str, span: Option<Span>) {
+        if matches!(left_ty, TcType::TableWithFields(_))
+            && (span.is_none() || span != self.annotated_value)
+        {
+            self.info(
+                format!(
+                    "Result type unknown because the `{method_name}` overload is used; annotate the binding if needed"
+                ),
+                span,
+            );
         }
     }
 
@@ -184,9 +263,10 @@ impl TypeEnv {
                 {
                     TcType::Number
                 } else if Self::has_operator_method(&left_ty, "__add") {
+                    self.note_overload_result(&left_ty, "__add", span);
                     TcType::Unknown // Return type depends on implementation
                 } else {
-                    self.error(
+                    self.error(ErrorCode::TypeMismatch,
                         format!(
                             "ADD requires (Number, Number) or (String, String) or type with __add method, got ({left_ty}, {right_ty})"
                         ),
@@ -195,7 +275,7 @@ impl TypeEnv {
                     TcType::Unknown
                 }
             }
-            BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => {
+            BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod | BinaryOp::Pow => {
                 // Check if both operands are Numbers (default case)
                 if left_ty.is_compatible(&TcType::Number) && right_ty.is_compatible(&TcType::Number)
                 {
@@ -210,13 +290,15 @@ impl TypeEnv {
                         BinaryOp::Mul => "__mul",
                         BinaryOp::Div => "__div",
                         BinaryOp::Mod => "__mod",
+                        BinaryOp::Pow => "__pow",
                         _ => unreachable!(),
                     };
 
                     if Self::has_operator_method(&left_ty, method_name) {
+                        self.note_overload_result(&left_ty, method_name, span);
                         TcType::Unknown // Return type depends on implementation
                     } else {
-                        self.error(
+                        self.error(ErrorCode::TypeMismatch,
                             format!(
                                 "Arithmetic op {op:?} requires Number operands or type with {method_name} method, got ({left_ty}, {right_ty})"
                             ),
@@ -235,6 +317,9 @@ impl TypeEnv {
                 if left_ty.is_compatible(&TcType::Number) && right_ty.is_compatible(&TcType::Number)
                 {
                     TcType::Boolean
+                } else if Self::is_ordered_list(&left_ty) && Self::is_ordered_list(&right_ty) {
+                    // Lists of numbers (or of such lists) compare element by element
+                    TcType::Boolean
                 } else {
                     // Check for operator method fallback
                     // The method receives both operands; we can't validate right operand type
@@ -251,7 +336,7 @@ impl TypeEnv {
                     if Self::has_operator_method(&left_ty, method_name) {
                         TcType::Boolean // Comparison methods should return Boolean
                     } else {
-                        self.error(
+                        self.error(ErrorCode::TypeMismatch,
                             format!(
                                 "Comparison op {op:?} requires Number operands or type with {method_name} method, got ({left_ty}, {right_ty})"
                             ),
@@ -274,6 +359,7 @@ impl TypeEnv {
                     TcType::Unknown // Return type depends on implementation
                 } else {
                     self.error(
+                        ErrorCode::TypeMismatch,
                         format!(
                             "Unary negation requires Number or type with __neg method, got {ty}"
                         ),
@@ -295,11 +381,117 @@ impl TypeEnv {
         arguments: &[CallArgument],
         span: Option<Span>,
     ) -> TcType {
-        let callee_ty = self.check_expr(callee);
+        let mut callee_ty = self.check_expr(callee);
+        if let Expr::Identifier { name, .. } = callee
+            && name == "range"
+            && callee_ty == Self::range_builtin_type()
+            && !arguments
+                .iter()
+                .any(|a| matches!(a, CallArgument::Positional(Expr::Spread { .. })))
+            && let TcType::Function { params, ret } = &callee_ty
+        {
+            if !(2..=3).contains(&arguments.len()) {
+                self.error(
+                    ErrorCode::ArgumentCount,
+                    format!(
+                        "Function call: expected 2 or 3 arguments, got {}",
+                        arguments.len()
+                    ),
+                    span,
+                );
+                for arg in arguments {
+                    let (CallArgument::Positional(arg) | CallArgument::Named { value: arg, .. }) =
+                        arg;
+                    self.check_expr(arg);
+                }
+                return (**ret).clone();
+            }
+            // Without a step, check the call against start and stop alone
+            callee_ty = TcType::Function {
+                params: params[..arguments.len()].to_vec(),
+                ret: ret.clone(),
+            };
+        }
+        if let Expr::Identifier { name, .. } = callee
+            && name == "iter"
+            && callee_ty == Self::iter_builtin_type()
+            && let [CallArgument::Positional(arg)] = arguments
+        {
+            return self.check_iter_call(arg);
+        }
+        if let [
+            CallArgument::Positional(list),
+            CallArgument::Positional(item),
+        ] = arguments
+            && self.is_builtin_push(callee, &callee_ty)
+        {
+            return self.check_push_call(list, item, span);
+        }
+        let has_spread = arguments
+            .iter()
+            .any(|a| matches!(a, CallArgument::Positional(Expr::Spread { .. })));
+        if has_spread
+            && let Some(CallArgument::Named { value, .. }) = arguments
+                .iter()
+                .find(|a| matches!(a, CallArgument::Named { .. }))
+        {
+            // Named arguments are matched to parameters when compiling, which
+            // a spread's runtime length makes impossible
+            self.error(
+                ErrorCode::ArgumentCount,
+                "Named arguments cannot be combined with a spread".to_string(),
+                value.span().or(span),
+            );
+        }
         match callee_ty {
+            // How many arguments a spread supplies is only known at runtime,
+            // so its elements must suit every parameter from its position on
+            TcType::Function { params, ret } if has_spread => {
+                let mut spread_from = None;
+                for (i, arg) in arguments.iter().enumerate() {
+                    let arg_expr = match arg {
+                        CallArgument::Positional(expr) => expr,
+                        CallArgument::Named { value, .. } => value,
+                    };
+                    let arg_ty = self.check_expr(arg_expr);
+                    let is_spread = matches!(arg_expr, Expr::Spread { .. });
+                    if is_spread && spread_from.is_none() {
+                        spread_from = Some(i);
+                    }
+                    let expected = match spread_from {
+                        // Before the first spread, arguments line up with parameters
+                        None => params.get(i..=i).unwrap_or_default(),
+                        Some(from) if is_spread => params.get(from..).unwrap_or_default(),
+                        // A plain argument after a spread has no known position
+                        Some(_) => &[],
+                    };
+                    let first = if is_spread {
+                        spread_from.unwrap_or(i)
+                    } else {
+                        i
+                    };
+                    if let Some((j, param_ty)) = expected
+                        .iter()
+                        .enumerate()
+                        .find(|(_, param_ty)| !arg_ty.is_compatible(param_ty))
+                    {
+                        let what = if is_spread { "spread element for " } else { "" };
+                        self.error(
+                            ErrorCode::TypeMismatch,
+                            format!(
+                                "Function call: {what}argument {} expected {param_ty}, got {arg_ty}",
+                                first + j
+                            ),
+                            arg_expr.span(),
+                        );
+                    }
+                }
+                (*ret).clone()
+            }
             TcType::Function { params, ret } => {
                 if arguments.len() != params.len() {
                     self.error(
+                        ErrorCode::ArgumentCount,
                         format!(
                             "Function call: expected {} arguments, got {}",
                             params.len(),
@@ -316,13 +508,20 @@ impl TypeEnv {
                         };
                         let arg_ty = self.check_expr(arg_expr);
                         if !arg_ty.is_compatible(param_ty) {
-                            self.error(
-                                format!(
-                                    "Function call: argument {i} expected {param_ty}, got {arg_ty}"
-                                ),
-                                arg_expr.span(),
+                            let mut message = format!(
+                                "Function call: argument {i} expected {param_ty}, got {arg_ty}"
                             );
+                            if let Some(reason) = arg_ty.function_mismatch(param_ty) {
+                                message.push_str(&format!(" ({reason})"));
+                            }
+                            self.error(ErrorCode::TypeMismatch, message, arg_expr.span());
                         }
+                        self.check_strict_unknown(
+                            &arg_ty,
+                            param_ty,
+                            &format!("Function call: argument {i}"),
+                            arg_expr.span(),
+                        );
                     }
                 }
                 (*ret).clone()
@@ -340,6 +539,7 @@ impl TypeEnv {
             }
             _ => {
                 self.error(
+                    ErrorCode::TypeMismatch,
                     format!("Call expression requires a function, got {callee_ty}"),
                     span,
                 );
@@ -348,6 +548,74 @@ impl TypeEnv {
         }
     }
 
+    /// Whether a callee is the prelude's `push` or `List.push`, rather than a
+    /// user binding that shadows them
+    fn is_builtin_push(&self, callee: &Expr, callee_ty: &TcType) -> bool {
+        match callee {
+            Expr::Identifier { name, .. } => {
+                name == "push" && *callee_ty == Self::push_builtin_type()
+            }
+            Expr::MemberAccess { object, member, .. } => {
+                member == "push"
+                    && matches!(object.as_ref(), Expr::Identifier { name, .. }
+                        if name == "List" && self.binding_span(name).is_none())
+            }
+            _ => false,
+        }
+    }
+
+    /// Type of `push(list, item)`, inferring the element type of a list bound
+    /// empty from the first item pushed onto it
+    fn check_push_call(&mut self, list: &Expr, item: &Expr, span: Option<Span>) -> TcType {
+        let list_ty = self.check_expr(list);
+        let item_ty = self.check_expr(item);
+        let TcType::List(elem_ty) = list_ty else {
+            if !matches!(list_ty, TcType::Unknown | TcType::Any) {
+                self.error(
+                    ErrorCode::TypeMismatch,
+                    format!("push requires a List, got {list_ty}"),
+                    list.span(),
+                );
+            }
+            return TcType::Unknown;
+        };
+        if let (Expr::Identifier { name, .. }, Some(span)) = (list, span)
+            && self.infer_list_store(name, &item_ty, span)
+        {
+            return self
+                .lookup(name)
+                .map_or(TcType::Unknown, |info| info.ty.clone());
+        }
+        if !item_ty.is_compatible(&elem_ty) {
+            self.error(
+                ErrorCode::TypeMismatch,
+                format!("push: list holds {elem_ty}, got {item_ty}"),
+                item.span(),
+            );
+        }
+        TcType::List(elem_ty)
+    }
+
+    /// Type of `iter(arg)`: a list's own elements, or `[key, value]` pairs for a table
+    fn check_iter_call(&mut self, arg: &Expr) -> TcType {
+        let arg_ty = self.check_expr(arg);
+        match arg_ty {
+            TcType::List(elem_ty) => TcType::List(elem_ty),
+            TcType::Table | TcType::TableWithFields(_) => {
+                TcType::List(Box::new(TcType::List(Box::new(TcType::Unknown))))
+            }
+            TcType::Unknown | TcType::Any => TcType::List(Box::new(TcType::Unknown)),
+            _ => {
+                self.error(
+                    ErrorCode::TypeMismatch,
+                    format!("iter() requires a List or Table, got {arg_ty}"),
+                    arg.span(),
+                );
+                TcType::List(Box::new(TcType::Unknown))
+            }
+        }
+    }
+
     fn check_member_access(&mut self, object: &Expr, member: &str, span: Option<Span>) -> TcType {
         let obj_ty = self.check_expr(object);
 
@@ -364,14 +632,30 @@ impl TypeEnv {
         match obj_ty {
             TcType::Table => TcType::Unknown, // dynamic tables allowed
             TcType::TableWithFields(ref fields) => {
-                if !fields.contains(&member.to_string()) && self.in_match_arm_depth == 0 {
-                    self.error(format!("Unknown field '{member}' on table"), span);
+                if !fields.contains(&member.to_string())
+                    && (self.in_match_arm_depth == 0 || self.strict_types)
+                {
+                    self.error(
+                        ErrorCode::UnknownField,
+                        format!("Unknown field '{member}' on table"),
+                        span,
+                    );
+                }
+                TcType::Unknown
+            }
+            TcType::Unknown | TcType::Any => {
+                if self.strict_types {
+                    self.error(
+                        ErrorCode::StrictTypes,
+                        format!("Member access '{member}' requires a known type, got {obj_ty}"),
+                        span,
+                    );
                 }
                 TcType::Unknown
             }
-            TcType::Unknown | TcType::Any => TcType::Unknown,
             _ => {
                 self.error(
+                    ErrorCode::TypeMismatch,
                     format!("Member access requires a table, got {obj_ty}"),
                     span,
                 );
@@ -383,23 +667,33 @@ impl TypeEnv {
     fn check_index_expr(&mut self, object: &Expr, index: &Expr, span: Option<Span>) -> TcType {
         let obj_ty = self.check_expr(object);
         let idx_ty = self.check_expr(index);
+        self.check_fractional_index(index);
 
         match obj_ty {
             TcType::List(elem_ty) => {
                 if !idx_ty.is_compatible(&TcType::Number) {
-                    self.error(format!("List index requires Number, got {idx_ty}"), span);
+                    self.error(
+                        ErrorCode::TypeMismatch,
+                        format!("List index requires Number, got {idx_ty}"),
+                        span,
+                    );
                 }
                 (*elem_ty).clone()
             }
             TcType::Table | TcType::TableWithFields(_) => {
                 if !idx_ty.is_compatible(&TcType::String) {
-                    self.error(format!("Table index requires String, got {idx_ty}"), span);
+                    self.error(
+                        ErrorCode::TypeMismatch,
+                        format!("Table index requires String, got {idx_ty}"),
+                        span,
+                    );
                 }
                 TcType::Unknown
             }
             TcType::Unknown | TcType::Any => TcType::Unknown,
             _ => {
                 self.error(
+                    ErrorCode::TypeMismatch,
                     format!("Index operation requires List or Table, got {obj_ty}"),
                     span,
                 );
@@ -408,6 +702,20 @@ impl TypeEnv {
         }
     }
 
+    /// Warn when a number literal with a fractional part is used as an index;
+    /// list indexing rejects it at runtime.
+    pub(super) fn check_fractional_index(&mut self, index: &Expr) {
+        if let Expr::Number { value, span } = index
+            && value.fract() != 0.0
+        {
+            self.warning(
+                ErrorCode::FractionalIndex,
+                format!("list index must be an integer, got {value}"),
+                *span,
+            );
+        }
+    }
+
     fn check_function_expr(
         &mut self,
         arguments: &[Argument],
@@ -420,6 +728,16 @@ impl TypeEnv {
         let mut param_types = Vec::new();
         for arg in arguments {
             let param_ty = Self::type_from_ast(&arg.r#type);
+            if self.strict_types && param_ty == TcType::Any {
+                self.error(
+                    ErrorCode::StrictTypes,
+                    format!(
+                        "Parameter {}: strict types require a concrete type, got Any",
+                        arg.name
+                    ),
+                    arg.span,
+                );
+            }
             param_types.push(param_ty.clone());
             self.declare(
                 arg.name.clone(),
@@ -437,14 +755,21 @@ impl TypeEnv {
             TcType::Unknown
         };
 
+        // `break` never crosses a function boundary
+        let outer_loops = std::mem::take(&mut self.loop_breaks);
+        self.function_depth += 1;
         let actual_ret = self.check_block(body, &expected_ret);
+        self.function_depth -= 1;
+        self.loop_breaks = outer_loops;
 
         if !actual_ret.is_compatible(&expected_ret) && expected_ret != TcType::Unknown {
             self.error(
+                ErrorCode::TypeMismatch,
                 format!("Function return type mismatch: declared {expected_ret}, got {actual_ret}"),
                 span,
             );
         }
+        self.check_strict_unknown(&actual_ret, &expected_ret, "Function return", span);
 
         self.pop_scope();
 
@@ -472,6 +797,7 @@ impl TypeEnv {
         let cond_ty = self.check_expr(condition);
         if !cond_ty.is_compatible(&TcType::Boolean) && cond_ty != TcType::Unknown {
             self.error(
+                ErrorCode::TypeMismatch,
                 format!("If condition should be Boolean, got {cond_ty}"),
                 span,
             );
@@ -495,13 +821,28 @@ impl TypeEnv {
                 else_ty
             } else {
                 self.error(
+                    ErrorCode::TypeMismatch,
                     format!("If branches have incompatible types: {then_ty} vs {else_ty}"),
                     span,
                 );
                 TcType::Unknown
             }
         } else {
-            // No else branch: could be null
+            // An `if` expression is always used as a value; without `else` that
+            // value is null whenever the condition is false
+            if then_ty != TcType::Null {
+                self.warnings.push(TypeError {
+                    message: format!(
+                        "`if` without `else` used as a value is null when the condition is false, not {then_ty}"
+                    ),
+                    span,
+                    severity: Severity::Warning,
+                    suggestions: vec!["add an `else` branch".to_string()],
+                    fixits: Vec::new(),
+                    related: Vec::new(),
+                    code: Some(ErrorCode::IfWithoutElse),
+                });
+            }
             then_ty
         }
     }
@@ -538,6 +879,7 @@ impl TypeEnv {
                     unified_ret = Some(arm_ret);
                 } else {
                     self.error(
+                        ErrorCode::TypeMismatch,
                         format!("Match arms have incompatible types: {current} vs {arm_ret}"),
                         span,
                     );
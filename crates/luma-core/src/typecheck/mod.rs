@@ -20,7 +20,9 @@
 //!
 //! The type checker is designed to be permissive - it allows `Any` and `Unknown` types
 //! where exact types cannot be determined, falling back to runtime checking.
+//! [`TypecheckOptions::strict_types`] turns those fallbacks into errors.
 
+mod cycles;
 mod environment;
 mod errors;
 mod expressions;
@@ -28,9 +30,11 @@ mod patterns;
 mod statements;
 mod types;
 
-use crate::ast::{Expr, Program, Stmt};
+use std::collections::{HashMap, HashSet};
 
-pub use errors::{TypeError, TypecheckResult};
+use crate::ast::{Expr, Program, Span, Stmt};
+
+pub use errors::{ErrorCode, TypeError, TypecheckResult};
 pub use types::TcType;
 
 use environment::TypeEnv;
@@ -38,7 +42,71 @@ use types::VarInfo;
 
 /// Type check a program and return any errors found.
 pub fn typecheck_program(program: &Program) -> TypecheckResult<()> {
+    typecheck_program_with_warnings(program).0
+}
+
+/// Type check a program, also returning non-fatal warnings such as
+/// same-scope redeclarations. Warnings never turn an `Ok` result into an error.
+pub fn typecheck_program_with_warnings(program: &Program) -> (TypecheckResult<()>, Vec<TypeError>) {
+    let report = typecheck_report(program);
+    let result = if report.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(report.errors)
+    };
+    (result, report.warnings)
+}
+
+/// Settings that change how strictly a program is checked.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TypecheckOptions {
+    /// Reject `Any` parameters, unknown-typed values in typed positions and
+    /// member access on values of unknown type instead of deferring them to
+    /// runtime.
+    pub strict_types: bool,
+}
+
+/// Type check a program with the given options and return any errors found.
+pub fn typecheck_program_with_options(
+    program: &Program,
+    options: TypecheckOptions,
+) -> TypecheckResult<()> {
+    let errors = typecheck_report_with_options(program, options).errors;
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Drop diagnostics identical in message and span to an earlier one, which
+/// happens when the same expression is checked by more than one pass.
+fn dedup_diagnostics(diagnostics: &mut Vec<TypeError>) {
+    let mut seen = HashSet::new();
+    diagnostics.retain(|d| seen.insert((d.span, d.message.clone())));
+}
+
+/// Everything a type checking pass learns about a program.
+#[derive(Debug, Clone, Default)]
+pub struct TypecheckReport {
+    pub errors: Vec<TypeError>,
+    pub warnings: Vec<TypeError>,
+    /// Inferred type of every checked expression, keyed by its span
+    pub expr_types: HashMap<Span, TcType>,
+}
+
+/// Type check a program and collect errors, warnings and expression types.
+pub fn typecheck_report(program: &Program) -> TypecheckReport {
+    typecheck_report_with_options(program, TypecheckOptions::default())
+}
+
+/// Like [`typecheck_report`], checking under the given options.
+pub fn typecheck_report_with_options(
+    program: &Program,
+    options: TypecheckOptions,
+) -> TypecheckReport {
     let mut env = TypeEnv::new();
+    env.strict_types = options.strict_types;
 
     // First pass: Pre-declare all top-level let/var with function values
     // This enables mutual recursion between functions
@@ -84,15 +152,24 @@ pub fn typecheck_program(program: &Program) -> TypecheckResult<()> {
         }
     }
 
-    // Second pass: Check all statements (function bodies can now reference each other)
-    for stmt in &program.statements {
-        env.check_stmt(stmt);
-    }
+    // Value initializers run eagerly, so they must not depend on each other cyclically
+    env.check_initialization_cycles(&program.statements);
 
-    if env.errors.is_empty() {
-        Ok(())
-    } else {
-        Err(env.errors)
+    // Second pass: Check all statements (function bodies can now reference each other)
+    env.check_stmts(&program.statements);
+    env.check_exports(&program.statements);
+
+    // Passes run in several sweeps, so report findings top-to-bottom
+    let source_order = |e: &TypeError| e.span.map_or(usize::MAX, |s| s.start);
+    env.errors.sort_by_key(source_order);
+    env.warnings.sort_by_key(source_order);
+    dedup_diagnostics(&mut env.errors);
+    dedup_diagnostics(&mut env.warnings);
+
+    TypecheckReport {
+        errors: env.errors,
+        warnings: env.warnings,
+        expr_types: env.expr_types,
     }
 }
 
@@ -174,6 +251,171 @@ mod tests {
         let _ = &errors[0].suggestions;
     }
 
+    #[test]
+    fn test_break_in_function_nested_in_loop_is_error() {
+        let code = r#"
+            var i = 0
+            while i < 3 do
+                let f = fn() do
+                    break
+                end
+                i = i + 1
+            end
+        "#;
+        let errors = parse_and_typecheck(code).unwrap_err();
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].message, "break outside of loop");
+        assert_eq!(errors[0].code().unwrap().code, "E0009");
+
+        let code = "for x in [1] do\n  let g = fn() do continue end\nend";
+        let errors = parse_and_typecheck(code).unwrap_err();
+        assert_eq!(errors[0].message, "continue outside of loop");
+
+        let errors = parse_and_typecheck("while true do break level 2 end").unwrap_err();
+        assert_eq!(
+            errors[0].message,
+            "break level 2 exceeds loop nesting depth of 1"
+        );
+    }
+
+    #[test]
+    fn test_break_directly_in_loop_is_ok() {
+        let code = r#"
+            var i = 0
+            while true do
+                i = (fn(x: Number): Number do x + 1 end)(i)
+                if i > 2 do break end
+                continue
+            end
+        "#;
+        assert!(parse_and_typecheck(code).is_ok());
+        assert!(parse_and_typecheck("let v = while true do break (1) end").is_ok());
+    }
+
+    #[test]
+    fn test_for_destructuring_checks_element_type() {
+        let ok = "var t = 0\nfor [a, b] in [[1, 2]] do t = t + a + b end\nfor { x } in [{ x = 1 }] do t = t + x end";
+        assert!(parse_and_typecheck(ok).is_ok());
+
+        let errors = parse_and_typecheck("for [a, b] in [1, 2] do a end").unwrap_err();
+        assert!(
+            errors[0]
+                .message
+                .contains("List pattern requires List type, got Number"),
+            "{errors:?}"
+        );
+        let errors = parse_and_typecheck("for { x } in [\"s\"] do x end").unwrap_err();
+        assert!(
+            errors[0]
+                .message
+                .contains("Table pattern requires Table type, got String"),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_len_returns_number() {
+        assert!(parse_and_typecheck("let n: Number = len([1, 2]) + len(\"ab\")").is_ok());
+        let errors = parse_and_typecheck("let s: String = len({ a = 1 })").unwrap_err();
+        assert!(errors[0].message.contains("got Number"), "{errors:?}");
+    }
+
+    #[test]
+    fn test_for_over_iter_types_elements() {
+        let code = r#"
+            for e in iter([1, 2, 3]) do
+                let n: Number = e
+            end
+        "#;
+        assert!(parse_and_typecheck(code).is_ok());
+
+        let code = r#"
+            for e in iter([1, 2, 3]) do
+                let s: String = e
+            end
+        "#;
+        let errors = parse_and_typecheck(code).unwrap_err();
+        assert!(errors[0].message.contains("got Number"), "{errors:?}");
+
+        let code = r#"
+            for [k, v] in iter({ a = 1 }) do
+                let n = v
+            end
+        "#;
+        assert!(parse_and_typecheck(code).is_ok());
+    }
+
+    #[test]
+    fn test_iter_rejects_non_iterable() {
+        let errors = parse_and_typecheck("for e in iter(42) do end").unwrap_err();
+        assert_eq!(
+            errors[0].message,
+            "iter() requires a List or Table, got Number"
+        );
+        assert_eq!(errors[0].span, Some(Span::new(14, 16)));
+    }
+
+    #[test]
+    fn test_undefined_variable_reported_once_without_cascade() {
+        let code = r#"
+            let a = missing(missing.field, missing[0]) + 1
+            let c = missing(1, 2)
+            let e = a * 2 + c.x
+            let f: Number = c
+        "#;
+        let errors = parse_and_typecheck(code).unwrap_err();
+        let lines: Vec<usize> = errors
+            .iter()
+            .map(|e| code[..e.span.unwrap().start].lines().count())
+            .collect();
+        assert_eq!(lines, vec![2, 3], "{errors:?}");
+        assert!(
+            errors
+                .iter()
+                .all(|e| e.message == "Undefined variable: missing")
+        );
+    }
+
+    #[test]
+    fn test_undefined_variable_reported_again_in_later_statements() {
+        let code = r#"
+            let f = fn() do
+              print(missing)
+              missing + 1
+            end
+            if missing do missing end
+        "#;
+        let errors = parse_and_typecheck(code).unwrap_err();
+        assert_eq!(errors.len(), 4, "{errors:?}");
+        assert!(
+            errors
+                .iter()
+                .all(|e| e.message == "Undefined variable: missing")
+        );
+    }
+
+    #[test]
+    fn test_identical_diagnostics_are_deduplicated() {
+        let error = |message: &str, start: usize| TypeError {
+            message: message.to_string(),
+            span: Some(Span::new(start, start + 2)),
+            severity: crate::diagnostics::Severity::Error,
+            suggestions: Vec::new(),
+            fixits: Vec::new(),
+            related: Vec::new(),
+            code: Some(ErrorCode::TypeMismatch),
+        };
+        let mut errors = vec![
+            error("Type mismatch", 3),
+            error("Type mismatch", 3),
+            error("Other", 3),
+            error("Type mismatch", 6),
+        ];
+        dedup_diagnostics(&mut errors);
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["Type mismatch", "Other", "Type mismatch"]);
+    }
+
     #[test]
     fn test_undefined_variable_did_you_mean_and_fixit() {
         // Provide a close-in-scope name so suggestions kick in
@@ -193,8 +435,13 @@ mod tests {
         let has_suggestion = e.suggestions.iter().any(|s| s.contains("count"));
         assert!(has_suggestion, "expected a suggestion for 'count'");
         // Expect a fix-it whose replacement is 'count'
-        let has_fix = e.fixits.iter().any(|f| f.replacement() == "count");
-        assert!(has_fix, "expected a fix-it to change to 'count'");
+        let fix = e
+            .fixits
+            .iter()
+            .find(|f| f.replacement() == "count")
+            .expect("expected a fix-it to change to 'count'");
+        // A guessed rename is only offered, never applied unattended
+        assert!(!fix.is_machine_applicable());
     }
 
     #[test]
@@ -215,7 +462,9 @@ mod tests {
         let result = parse_and_typecheck("let f = fn(x: Number): String do return x + 1 end");
         assert!(result.is_err());
         let errors = result.unwrap_err();
-        assert!(errors[0].message.contains("Return type mismatch"));
+        // The signature-level mismatch is reported first, in source order
+        assert!(errors[0].message.contains("Function return type mismatch"));
+        assert!(errors[1].message.contains("Return type mismatch"));
     }
 
     #[test]
@@ -272,6 +521,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_compound_assignment_to_immutable_is_rejected() {
+        let errors = parse_and_typecheck("let x = 42\nx += 1").unwrap_err();
+        assert!(
+            errors[0]
+                .message
+                .contains("Cannot assign to immutable variable")
+        );
+        assert!(parse_and_typecheck("var x = 42\nx += 1").is_ok());
+    }
+
     #[test]
     fn test_mutable_assignment() {
         let result = parse_and_typecheck("var x = 42\nx = 43");
@@ -299,6 +559,17 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_string_interpolation_checks_embedded_expressions() {
+        // Interpolants need not be strings; they are converted at runtime
+        let result = parse_and_typecheck("let n = 1\nlet s: String = \"n = ${n + 1} ${[n]}\"");
+        assert!(result.is_ok(), "{result:?}");
+        let errors = parse_and_typecheck("let s = \"${missing}\"").unwrap_err();
+        assert!(errors[0].message.contains("Undefined variable: missing"));
+        let errors = parse_and_typecheck("let s = \"${1 + true}\"").unwrap_err();
+        assert!(!errors.is_empty());
+    }
+
     #[test]
     fn test_invalid_string_arithmetic() {
         let result = parse_and_typecheck("let x = \"hello\" * 2");
@@ -311,6 +582,18 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_power_requires_numbers() {
+        assert!(parse_and_typecheck("let x: Number = 2 ** 0.5").is_ok());
+        let errors = parse_and_typecheck("let x = \"a\" ** 2").unwrap_err();
+        assert!(
+            errors[0]
+                .message
+                .contains("requires Number operands or type with __pow method"),
+            "{errors:?}"
+        );
+    }
+
     // Comparison operator tests
     #[test]
     fn test_number_comparison() {
@@ -318,6 +601,13 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_list_comparison() {
+        assert!(parse_and_typecheck("let b = [1, 2] < [1, 3]").is_ok());
+        assert!(parse_and_typecheck("let b = [[1], [2]] >= [[1]]").is_ok());
+        assert!(parse_and_typecheck("let b = [\"a\"] < [\"b\"]").is_err());
+    }
+
     #[test]
     fn test_equality_comparison() {
         let result = parse_and_typecheck("let b = 42 == 42");
@@ -420,6 +710,19 @@ mod tests {
         assert!(errors[0].message.contains("expected Boolean"));
     }
 
+    #[test]
+    fn test_ternary_condition_must_be_boolean() {
+        let errors = parse_and_typecheck("let x = 1 ? 2 : 3").unwrap_err();
+        assert!(
+            errors[0].message.contains("If condition should be Boolean"),
+            "{}",
+            errors[0].message
+        );
+        let errors = parse_and_typecheck("let x = true ? 1 : \"a\"").unwrap_err();
+        assert!(errors[0].message.contains("incompatible types"));
+        assert!(parse_and_typecheck("let x: Number = true ? 1 : 2").is_ok());
+    }
+
     #[test]
     fn test_if_else_statement() {
         let result = parse_and_typecheck("if true do let x = 1 else do let y = 2 end");
@@ -446,6 +749,72 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_for_loop_table_single_variable_yields_string_keys() {
+        let code = r#"
+            let t = { a = 1 }
+            for k in t do
+                let key: String = k
+            end
+            for [k, v] in t do
+                let pair_key: String = k
+            end
+        "#;
+        let result = parse_and_typecheck(code);
+        assert!(result.is_ok(), "{result:?}");
+
+        let errors =
+            parse_and_typecheck("for k in { a = 1 } do let n: Number = k end").unwrap_err();
+        assert!(
+            errors[0]
+                .message
+                .contains("declared type Number, got String")
+        );
+    }
+
+    #[test]
+    fn test_for_loop_over_range() {
+        assert!(parse_and_typecheck("for i in 0..10 do let y: Number = i end").is_ok());
+        assert!(parse_and_typecheck("let n = 3\nfor i in 1..=n do let y = i * 2 end").is_ok());
+        let errors = parse_and_typecheck("for i in \"a\"..3 do let y = i end").unwrap_err();
+        assert!(
+            errors[0]
+                .message
+                .contains("Range start: expected Number, got String"),
+            "{}",
+            errors[0].message
+        );
+        let errors = parse_and_typecheck("let r = 0..true").unwrap_err();
+        assert!(errors[0].message.contains("Range end: expected Number"));
+    }
+
+    #[test]
+    fn test_range_call_returns_an_iterator() {
+        assert!(parse_and_typecheck("for i in range(0, 10, 2) do let y = i end").is_ok());
+        assert!(parse_and_typecheck("for i in range(0, 3) do let y = i end").is_ok());
+        let errors = parse_and_typecheck("let xs: List(Number) = range(0, 3)").unwrap_err();
+        assert_eq!(
+            errors[0].code,
+            Some(ErrorCode::TypeMismatch),
+            "{}",
+            errors[0].message
+        );
+        let errors = parse_and_typecheck("let r = range(0)").unwrap_err();
+        assert!(
+            errors[0]
+                .message
+                .contains("expected 2 or 3 arguments, got 1"),
+            "{}",
+            errors[0].message
+        );
+        let errors = parse_and_typecheck("let r = range(0, \"5\")").unwrap_err();
+        assert!(
+            errors[0]
+                .message
+                .contains("argument 1 expected Number, got String")
+        );
+    }
+
     #[test]
     fn test_for_loop_invalid_iterator() {
         let result = parse_and_typecheck("for x in 42 do let y = x end");
@@ -486,6 +855,59 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_literal_in_destructuring_is_rejected() {
+        for source in [
+            "let [1, x] = [2, 3]",
+            "let { pos: { x: 0 } } = { pos = { x = 1 } }",
+            "for [1, v] in [[2, 3]] do print(v) end",
+        ] {
+            let errors = parse_and_typecheck(source).unwrap_err();
+            assert_eq!(errors.len(), 1, "{source}: {errors:?}");
+            assert_eq!(
+                errors[0].message,
+                "Literal patterns can only be used in match"
+            );
+        }
+    }
+
+    #[test]
+    fn test_nested_table_destructuring() {
+        let code = r#"
+            let shape = { pos = { x = 1 }, size = 2 }
+            let { pos: { x, y }, size: s } = shape
+            let total: Number = s
+        "#;
+        // Nested fields are not tracked, so `y` is only checked at runtime
+        assert!(parse_and_typecheck(code).is_ok());
+
+        let code = r#"
+            let shape = { pos = { x = 1 } }
+            let { bounds: { w } } = shape
+        "#;
+        let errors = parse_and_typecheck(code).unwrap_err();
+        assert!(
+            errors[0]
+                .message
+                .contains("Table pattern requires field 'bounds' not present on value"),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_match_with_nested_patterns_binds_leaves() {
+        let code = r#"
+            let describe = fn(v: Any) do
+                return match v do
+                    { pos: { x, y } } do "at ${x},${y}" end
+                    [a, [b, ...rest]] do "${a} ${b} ${rest}" end
+                    _ do "other" end
+                end
+            end
+        "#;
+        assert!(parse_and_typecheck(code).is_ok());
+    }
+
     // Match statement tests
     #[test]
     fn test_match_with_literals() {
@@ -556,6 +978,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_match_default_arm_binds_scrutinee() {
+        // `_ as n` is a catch-all, so the match is exhaustive and a later
+        // arm is unreachable; `n` has the scrutinee's type
+        let code = r#"
+            let label = match 5 do
+                0 do "zero" end
+                _ as n do n.into(String) end
+            end
+        "#;
+        assert!(parse_and_typecheck(code).is_ok());
+
+        let code = r#"
+            match 5 do
+                _ as n do let s: String = n end
+            end
+        "#;
+        let errors = parse_and_typecheck(code).unwrap_err();
+        assert!(errors[0].message.contains("got Number"), "{errors:?}");
+
+        let code = r#"
+            match 5 do
+                _ as n do let x = n end
+                1 do let y = 1 end
+            end
+        "#;
+        let errors = parse_and_typecheck(code).unwrap_err();
+        assert!(errors[0].message.contains("Unreachable"), "{errors:?}");
+    }
+
     #[test]
     fn test_match_with_rest_pattern() {
         let code = r#"
@@ -605,4 +1057,893 @@ mod tests {
         let result = parse_and_typecheck(code);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_return_inside_function_ok() {
+        let code = r#"
+            let f = fn(x: Number): Number do
+                return x + 1
+            end
+        "#;
+        let result = parse_and_typecheck(code);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_return_at_top_level_flagged() {
+        let result = parse_and_typecheck("let x = 1\nreturn 5");
+        assert!(result.is_err());
+        let errors = result.unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.message.contains("`return` outside of function")
+                    && e.code == Some(ErrorCode::ReturnOutsideFunction))
+        );
+        assert_eq!(ErrorCode::ReturnOutsideFunction.as_str(), "E0013");
+    }
+
+    #[test]
+    fn test_trailing_expression_implicit_return_ok() {
+        let result = parse_and_typecheck("let x = 1\nx + 1");
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_variant_match_exhaustive_ok() {
+        let code = r#"
+            type Shape = Circle { r: Number } | Square { s: Number }
+            let area = fn(shape: Any): Number do
+                match shape do
+                    Circle { r } do return r * r end
+                    Square { s } do return s * s end
+                end
+            end
+        "#;
+        let result = parse_and_typecheck(code);
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[test]
+    fn test_variant_match_missing_variant_flagged() {
+        let code = r#"
+            type Shape = Circle { r: Number } | Square { s: Number }
+            let c = Shape.Circle(2)
+            match c do
+                Circle { r } do r end
+            end
+        "#;
+        let errors = parse_and_typecheck(code).unwrap_err();
+        let err = errors
+            .iter()
+            .find(|e| e.message.contains("not exhaustive"))
+            .expect("expected exhaustiveness error");
+        assert!(err.message.contains("'Square'"));
+        assert!(err.suggestions.iter().any(|s| s.contains("Square { s }")));
+    }
+
+    #[test]
+    fn test_variant_name_shared_between_types_rejected() {
+        let code = r#"
+            type Shape = Circle { r: Number } | Square { s: Number }
+            type Token = Circle { x: Number } | Dot {}
+        "#;
+        let errors = parse_and_typecheck(code).unwrap_err();
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(
+            errors[0].message,
+            "Variant 'Circle' of type 'Token' is already declared by type 'Shape'"
+        );
+    }
+
+    #[test]
+    fn test_variant_match_mixing_types_flagged() {
+        let code = r#"
+            type Shape = Circle { r: Number } | Square { s: Number }
+            type Token = Word { w: String } | Dot {}
+            match Shape.Circle(1) do
+                Circle { r } do r end
+                Square { s } do s end
+                Dot {} do 0 end
+            end
+        "#;
+        let errors = parse_and_typecheck(code).unwrap_err();
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(
+            errors[0].message,
+            "Match mixes variants of different types: 'Shape', 'Token'"
+        );
+    }
+
+    #[test]
+    fn test_variant_pattern_unknown_field_flagged() {
+        let code = r#"
+            type Shape = Circle { r: Number } | Square { s: Number }
+            match Shape.Circle(1) do
+                Circle { radius } do radius end
+                _ do 0 end
+            end
+        "#;
+        let errors = parse_and_typecheck(code).unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.message.contains("Variant 'Circle' has no field 'radius'"))
+        );
+    }
+
+    #[test]
+    fn test_match_arm_pattern_errors_reported_once() {
+        let code = r#"
+            type Shape = Circle { r: Number } | Square { s: Number }
+            let area = fn(shape: Any): Number do
+                match shape do
+                    Circle { r } do return r * r end
+                    Hexagon { side } do return side end
+                    _ do return 0 end
+                end
+            end
+        "#;
+        let errors = parse_and_typecheck(code).unwrap_err();
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].message, "Unknown variant 'Hexagon' in pattern");
+    }
+
+    #[test]
+    fn test_loop_expression_break_value_type() {
+        let code = r#"
+            let xs = [3, 8, 12]
+            let found: Number = for x in xs do
+                if x > 10 do break x end
+            end
+        "#;
+        let result = parse_and_typecheck(code);
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[test]
+    fn test_loop_expression_type_includes_normal_exit() {
+        let loop_type = |code: &str| {
+            let program = parse(code, "test.luma").unwrap();
+            let report = typecheck_report(&program);
+            assert!(report.errors.is_empty(), "{:?}", report.errors);
+            let Some(Stmt::VarDecl { value, .. }) = program.statements.last() else {
+                panic!("expected a declaration");
+            };
+            report.expr_types[&value.span().unwrap()].clone()
+        };
+        // Only breaks can leave a loop whose condition always holds
+        assert_eq!(
+            loop_type("let v = while true do break (5) end"),
+            TcType::Number
+        );
+        // Otherwise the loop may finish normally with null, which has no
+        // common type with a number
+        assert_eq!(
+            loop_type("let v = for x in [1, 2] do if x > 1 do break x end end"),
+            TcType::Unknown
+        );
+        assert_eq!(
+            loop_type("var i = 0\nlet v = while i < 3 do break (5) end"),
+            TcType::Unknown
+        );
+        assert_eq!(
+            loop_type("let v = for x in [1] do break null end"),
+            TcType::Null
+        );
+    }
+
+    #[test]
+    fn test_loop_expression_incompatible_break_values() {
+        let code = r#"
+            let x = while true do
+                if true do break (5) end
+                break "five"
+            end
+        "#;
+        let errors = parse_and_typecheck(code).unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.message.contains("Break values have incompatible types"))
+        );
+    }
+
+    #[test]
+    fn test_operator_overload_result_gets_a_note() {
+        let money =
+            "let m = { cents = 5, __sub = fn(a: Any, b: Any): Number do a.cents - b.cents end }\n";
+        let program = parse(&format!("{money}let d = m - m"), "test.luma").unwrap();
+        let (result, warnings) = typecheck_program_with_warnings(&program);
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert_eq!(warnings[0].severity, crate::diagnostics::Severity::Info);
+        assert!(
+            warnings[0].message.contains("`__sub` overload"),
+            "{warnings:?}"
+        );
+        assert!(warnings[0].code().is_none());
+
+        // Annotating the binding answers the note and types later uses
+        let annotated = format!("{money}let d: Number = m - m\nlet e = d + 1");
+        let program = parse(&annotated, "test.luma").unwrap();
+        let (result, warnings) = typecheck_program_with_warnings(&program);
+        assert!(result.is_ok(), "{result:?}");
+        assert!(warnings.is_empty(), "{warnings:?}");
+        let errors =
+            parse_and_typecheck(&format!("{money}let d: Number = m - m\nlet s: String = d"))
+                .unwrap_err();
+        assert!(errors[0].message.contains("got Number"), "{errors:?}");
+    }
+
+    #[test]
+    fn test_same_scope_redeclaration_warns_with_related_span() {
+        let program = parse("let x = 1\nlet x = 2", "test.luma").expect("Parse failed");
+        let (result, warnings) = typecheck_program_with_warnings(&program);
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        let warning = &warnings[0];
+        assert_eq!(warning.severity, crate::diagnostics::Severity::Warning);
+        assert!(warning.message.contains("'x' is already declared"));
+        assert_eq!(warning.related.len(), 1);
+        let (_, first) = warning.related[0];
+        assert_eq!(first.start, 0);
+    }
+
+    #[test]
+    fn test_nested_scope_shadowing_does_not_warn() {
+        let code = r#"
+            let x = 1
+            let f = fn() do
+                let x = "inner"
+                x
+            end
+            if true do
+                let x = 2
+            end
+        "#;
+        let program = parse(code, "test.luma").expect("Parse failed");
+        let (result, warnings) = typecheck_program_with_warnings(&program);
+        assert!(result.is_ok(), "{result:?}");
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn test_index_assignment_into_table_literal() {
+        assert!(parse_and_typecheck("var t = { a = 1 }\nt[\"b\"] = 2").is_ok());
+        let errors = parse_and_typecheck("var t = { a = 1 }\nt[0] = 2").unwrap_err();
+        assert!(errors[0].message.contains("Table index requires String"));
+    }
+
+    #[test]
+    fn test_string_index_assignment_is_rejected() {
+        let errors = parse_and_typecheck("var s = \"abc\"\ns[0] = \"x\"").unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.message == "strings are immutable; cannot assign to index"),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_circular_value_definition_flagged() {
+        let errors = parse_and_typecheck("let a = b + 1\nlet b = a").unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.message == "circular definition: a -> b -> a"),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_mutually_recursive_functions_not_circular() {
+        let code = r#"
+            let is_even = fn(n: Number): Boolean do
+                if n == 0 do return true end
+                return is_odd(n - 1)
+            end
+            let is_odd = fn(n: Number): Boolean do
+                if n == 0 do return false end
+                return is_even(n - 1)
+            end
+            let shadowed = do
+                let is_even = 1
+                is_even
+            end
+        "#;
+        let result = parse_and_typecheck(code);
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[test]
+    fn test_unless_condition_must_be_boolean() {
+        assert!(parse_and_typecheck("let x = true\nunless x do print(1) end").is_ok());
+
+        let errors = parse_and_typecheck("let x = 5\nunless x do print(1) end").unwrap_err();
+        assert!(
+            errors.iter().any(|e| e.message.contains("Boolean")),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_fractional_literal_index_warns() {
+        let program = parse("let arr = [1, 2, 3]\nlet x = arr[1.5]", "test.luma").unwrap();
+        let (result, warnings) = typecheck_program_with_warnings(&program);
+        assert!(result.is_ok(), "{result:?}");
+        assert!(
+            warnings
+                .iter()
+                .any(|w| w.message == "list index must be an integer, got 1.5"),
+            "{warnings:?}"
+        );
+
+        let program = parse("let arr = [1, 2, 3]\nlet x = arr[1]", "test.luma").unwrap();
+        let (_, warnings) = typecheck_program_with_warnings(&program);
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn test_if_without_else_as_value_warns() {
+        let check = |source: &str| {
+            let program = parse(source, "test.luma").unwrap();
+            let (result, warnings) = typecheck_program_with_warnings(&program);
+            assert!(result.is_ok(), "{result:?}");
+            warnings
+        };
+
+        let warnings = check("let c = true\nlet x = if c do 1 end");
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert_eq!(
+            warnings[0].message,
+            "`if` without `else` used as a value is null when the condition is false, not Number"
+        );
+        assert_eq!(warnings[0].code().unwrap().code, "W0004");
+
+        assert!(check("let c = true\nlet x: Number = if c do 1 else do 2 end").is_empty());
+        assert!(check("let c = true\nif c do print(1) end").is_empty());
+        assert!(check("let c = true\nlet x = if c do null end").is_empty());
+    }
+
+    #[test]
+    fn test_empty_list_element_type_inferred_from_stores() {
+        let check = |source: &str| {
+            let program = parse(source, "test.luma").unwrap();
+            typecheck_program_with_warnings(&program)
+        };
+
+        // Consistent stores fix the element type for later reads
+        let (result, warnings) =
+            check("var xs = []\nxs[0] = 1\nxs[1] = 2\nlet n: Number = xs[0] + 1");
+        assert!(result.is_ok(), "{result:?}");
+        assert!(warnings.is_empty(), "{warnings:?}");
+        let (result, _) = check("var xs = []\nxs[0] = 1\nlet s: String = xs[0]");
+        let errors = result.unwrap_err();
+        assert!(
+            errors[0]
+                .message
+                .contains("declared type String, got Number"),
+            "{errors:?}"
+        );
+
+        // A store that disagrees warns once, then the list holds anything
+        let (result, warnings) =
+            check("var xs = []\nxs[0] = 1\nxs[1] = \"s\"\nxs[2] = true\nlet s: String = xs[1]");
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert_eq!(
+            warnings[0].message,
+            "Inconsistent list element types: 'xs' holds Number, but this stores String"
+        );
+        assert_eq!(warnings[0].code().unwrap().code, "W0006");
+        assert_eq!(warnings[0].related.len(), 1);
+
+        // Annotated lists keep their declared element type
+        let (result, _) = check("let xs: List(Number) = []\nxs[0] = \"s\"");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_empty_list_element_type_inferred_from_push() {
+        let check = |source: &str| {
+            let program = parse(source, "test.luma").unwrap();
+            typecheck_program_with_warnings(&program)
+        };
+
+        let (result, warnings) =
+            check("var xs = []\npush(xs, 1)\nList.push(xs, 2)\nlet n: Number = xs[0] + 1");
+        assert!(result.is_ok(), "{result:?}");
+        assert!(warnings.is_empty(), "{warnings:?}");
+        let (result, _) = check("var xs = []\npush(xs, 1)\nlet s: String = xs[0]");
+        let errors = result.unwrap_err();
+        assert!(
+            errors[0]
+                .message
+                .contains("declared type String, got Number"),
+            "{errors:?}"
+        );
+
+        let (result, warnings) = check("var xs = []\nList.push(xs, 1)\npush(xs, \"s\")");
+        assert!(result.is_ok(), "{result:?}");
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert_eq!(
+            warnings[0].message,
+            "Inconsistent list element types: 'xs' holds Number, but this stores String"
+        );
+
+        let (result, _) = check("let xs: List(Number) = []\npush(xs, \"s\")");
+        assert!(result.is_err());
+
+        // A user function named `push` is not the builtin
+        let (result, warnings) = check(
+            "let push = fn(a: Any, b: Any): Number do 0 end\nvar xs = []\npush(xs, 1)\npush(xs, \"s\")",
+        );
+        assert!(result.is_ok(), "{result:?}");
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn test_constant_true_loop_without_break_warns() {
+        let check = |source: &str| {
+            let program = parse(source, "test.luma").unwrap();
+            let (result, warnings) = typecheck_program_with_warnings(&program);
+            assert!(result.is_ok(), "{result:?}");
+            warnings
+        };
+
+        let warnings = check("while true do let x = 1 end");
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert_eq!(
+            warnings[0].message,
+            "Loop condition is always true and the body never breaks out"
+        );
+        assert_eq!(warnings[0].code().unwrap().code, "W0005");
+        assert_eq!(check("while 1 < 2 do let x = 1 end").len(), 1);
+        assert_eq!(check("do let x = 1 while !false end").len(), 1);
+
+        // A break anywhere in the body, or from a nested loop, ends it
+        assert!(
+            check("var i = 0\nwhile true do\n  i = i + 1\n  if i > 3 do break end\nend").is_empty()
+        );
+        assert!(check("while true do\n  for x in [1] do break level 2 end\nend").is_empty());
+        // A break that only leaves an inner loop doesn't, and neither does a
+        // `return`, which doesn't exit early
+        assert_eq!(
+            check("while true do\n  while 1 == 1 do break end\nend").len(),
+            1
+        );
+        assert_eq!(
+            check("let f = fn() do\n  while true do\n    return 1\n  end\nend").len(),
+            1
+        );
+        // Conditions that aren't literal constants never warn
+        assert!(check("var go = true\nwhile go do let x = 1 end").is_empty());
+        assert!(check("while false do let x = 1 end").is_empty());
+    }
+
+    #[test]
+    fn test_no_op_statements_warn() {
+        let check = |source: &str| {
+            let program = parse(source, "test.luma").unwrap();
+            let (result, warnings) = typecheck_program_with_warnings(&program);
+            assert!(result.is_ok(), "{result:?}");
+            warnings
+        };
+
+        let warnings = check("var x = 1\nx = x");
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert_eq!(
+            warnings[0].message,
+            "Assignment of a value to itself has no effect"
+        );
+        assert_eq!(warnings[0].code().unwrap().code, "W0007");
+        assert_eq!(check("var a = [1, 2]\nlet i = 0\na[i] = a[i]").len(), 1);
+        assert_eq!(check("var t = { a = 1 }\nt.a = t.a").len(), 1);
+
+        // Different places, or indices that might not be the same, don't warn
+        assert!(check("var x = 1\nlet y = 2\nx = y").is_empty());
+        assert!(check("var a = [1, 2]\na[0] = a[1]").is_empty());
+        assert!(check("var a = [1, 2]\nlet f = fn(): Number do 0 end\na[f()] = a[f()]").is_empty());
+
+        let warnings = check("let c = true\nif c do end");
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert_eq!(warnings[0].message, "Empty `if` body");
+        assert_eq!(warnings[0].code().unwrap().code, "W0008");
+        assert_eq!(check("let c = true\nif c do print(1) else do end").len(), 1);
+        assert_eq!(check("for x in [1] do end").len(), 1);
+        assert!(check("let c = true\nif c do print(1) end").is_empty());
+    }
+
+    #[test]
+    fn test_import_path_must_be_constant() {
+        assert!(parse_and_typecheck("let m = import(\"./m.luma\")").is_ok());
+        // Literals joined with `+` are still known at compile time
+        assert!(parse_and_typecheck("let m = import(\"./lib/\" + \"m.luma\")").is_ok());
+
+        for source in [
+            "let name = \"m.luma\"\nlet m = import(name)",
+            "let dir = \"lib\"\nlet m = import(\"./${dir}/m.luma\")",
+        ] {
+            let errors = parse_and_typecheck(source).unwrap_err();
+            assert_eq!(errors.len(), 1, "{errors:?}");
+            assert_eq!(errors[0].message, "import path must be a string literal");
+            assert_eq!(errors[0].code().unwrap().code, "E0011");
+        }
+    }
+
+    #[test]
+    fn test_export_list_is_checked() {
+        let report = |source: &str| typecheck_report(&parse(source, "test.luma").unwrap());
+
+        let ok = report(
+            "let pi = 3\nlet add = fn(a: Number, b: Number) do a + b end\nexport { add, pi }",
+        );
+        assert!(ok.errors.is_empty(), "{:?}", ok.errors);
+
+        // Exports are checked after the whole module, so order doesn't matter
+        assert!(report("export later\nlet later = 1").errors.is_empty());
+
+        let errors = report("let a = 1\nexport { a, missing }").errors;
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].message, "Undefined variable: missing");
+
+        let errors = report("let a = 1\nexport a\nexport a").errors;
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].message, "'a' is exported more than once");
+        assert_eq!(errors[0].code().unwrap().code, "E0010");
+
+        let errors = report("let a = 1\nlet f = fn() do\n  export a\nend").errors;
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(
+            errors[0].message,
+            "`export` is only allowed at the top level of a module"
+        );
+    }
+
+    fn unreachable_warnings(source: &str) -> Vec<TypeError> {
+        let program = parse(source, "test.luma").unwrap();
+        let (result, warnings) = typecheck_program_with_warnings(&program);
+        assert!(result.is_ok(), "{result:?}");
+        warnings
+            .into_iter()
+            .filter(|w| w.message.starts_with("Unreachable code"))
+            .collect()
+    }
+
+    #[test]
+    fn test_statement_after_break_warns() {
+        let source = "while true do\n  break\n  print(\"never\")\nend";
+        let warnings = unreachable_warnings(source);
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert_eq!(warnings[0].message, "Unreachable code after `break`");
+        let span = warnings[0].span.unwrap();
+        assert_eq!(source[span.start..span.end].trim_end(), "print(\"never\")");
+        assert_eq!(warnings[0].code().unwrap().code, "W0003");
+    }
+
+    #[test]
+    fn test_statement_after_return_does_not_warn() {
+        // `return` sets the value but does not leave the function early
+        let source = "let f = fn(): Number do\n  return 1\n  print(\"reached\")\n  2\nend";
+        assert!(unreachable_warnings(source).is_empty());
+        let source = "let f = fn() do\n  var i = 0\n  while i < 3 do\n    i = i + 1\n    if i > 1 do\n      return 1\n      print(\"x\")\n    else do\n      break\n    end\n    print(\"reached\")\n  end\nend";
+        assert!(unreachable_warnings(source).is_empty());
+    }
+
+    #[test]
+    fn test_statement_after_exiting_if_warns() {
+        let source = "var i = 0\nwhile i < 3 do\n  i = i + 1\n  if i > 1 do break else do continue end\n  print(\"never\")\nend";
+        let warnings = unreachable_warnings(source);
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert_eq!(
+            warnings[0].message,
+            "Unreachable code after an `if` whose every branch exits"
+        );
+    }
+
+    #[test]
+    fn test_statement_after_non_exiting_if_does_not_warn() {
+        // One branch falls through
+        let source = "var i = 0\nwhile i < 3 do\n  i = i + 1\n  if i > 1 do break end\n  print(\"reached\")\nend";
+        assert!(unreachable_warnings(source).is_empty());
+        // Branches ending in a value don't leave the block
+        let source = "let x = 1\nif x > 0 do 1 else do 2 end\nprint(\"reached\")";
+        assert!(unreachable_warnings(source).is_empty());
+    }
+
+    #[test]
+    fn test_computed_table_key_must_be_string() {
+        let errors = parse_and_typecheck("let t = { [1 + 1] = true }").unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.message == "Computed table key: expected String, got Number"),
+            "{errors:?}"
+        );
+
+        assert!(parse_and_typecheck("let k = \"a\"\nlet t = { [k] = 1 }").is_ok());
+    }
+
+    #[test]
+    fn test_computed_table_key_makes_table_dynamic() {
+        let result = parse_and_typecheck(
+            "let k = \"color\"\nlet t = { name = \"x\", [k] = \"red\" }\nlet c = t.color\nlet s = t.size",
+        );
+        assert!(result.is_ok(), "{result:?}");
+
+        let errors = parse_and_typecheck("let t = { name = \"x\" }\nlet s = t.size").unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.message.contains("Unknown field 'size'"))
+        );
+    }
+
+    #[test]
+    fn test_table_spread_keeps_source_fields() {
+        let source = "let Point = { x = Number, y = Number }\n\
+                      let p: Point = { x = 1, y = 2 }\n\
+                      let p2 = { ...p, x = 10 }\n\
+                      let y = p2.y";
+        let program = parse(source, "test.luma").unwrap();
+        let (result, warnings) = typecheck_program_with_warnings(&program);
+        assert!(result.is_ok(), "{result:?}");
+        assert!(warnings.is_empty(), "{warnings:?}");
+
+        let errors =
+            parse_and_typecheck("let p = { x = 1, y = 2 }\nlet p2 = { ...p, z = 3 }\nlet w = p2.w")
+                .unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.message.contains("Unknown field 'w'")),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_table_spread_of_non_table_is_rejected() {
+        let errors = parse_and_typecheck("let t = { ...5 }").unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.message == "Cannot spread Number into a table"),
+            "{errors:?}"
+        );
+
+        let dynamic = "let f = fn(t: Table): Table do { ...t, x = 1 } end\nlet v = f({}).other";
+        assert!(parse_and_typecheck(dynamic).is_ok());
+    }
+
+    #[test]
+    fn test_list_spread_takes_the_element_type() {
+        assert!(parse_and_typecheck("let a = [1, 2]\nlet b: List(Number) = [...a, 3]").is_ok());
+
+        let errors = parse_and_typecheck("let a = [\"x\"]\nlet b = [1, ...a]").unwrap_err();
+        assert!(
+            errors.iter().any(|e| e
+                .message
+                .starts_with("List elements have inconsistent types")),
+            "{errors:?}"
+        );
+
+        let errors = parse_and_typecheck("let t = { a = 1 }\nlet b = [...t]").unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.message == "Cannot spread Table(a) into a list"),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_call_spread_skips_the_arity_check() {
+        let source = "let add = fn(a: Number, b: Number): Number do a + b end\n\
+                      let args = [1, 2]\n\
+                      let n: Number = add(...args)";
+        assert!(parse_and_typecheck(source).is_ok());
+    }
+
+    #[test]
+    fn test_call_spread_elements_are_checked_against_parameters() {
+        let f = "let f = fn(a: Number, s: String) do s end\n";
+        let errors = parse_and_typecheck(&format!("{f}let xs = [1, 2]\nf(...xs)")).unwrap_err();
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(
+            errors[0].message,
+            "Function call: spread element for argument 1 expected String, got Number"
+        );
+
+        let errors = parse_and_typecheck(&format!("{f}let xs = [1]\nf(\"a\", ...xs)")).unwrap_err();
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert_eq!(
+            errors[0].message,
+            "Function call: argument 0 expected Number, got String"
+        );
+
+        let ok = format!("{f}let xs = [\"b\"]\nf(1, ...xs)");
+        assert!(parse_and_typecheck(&ok).is_ok());
+    }
+
+    #[test]
+    fn test_call_spread_with_named_arguments_is_rejected() {
+        let source = "let f = fn(a: Number, b: Number) do a end\nf(...[1], b = 2)";
+        let errors = parse_and_typecheck(source).unwrap_err();
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(
+            errors[0].message,
+            "Named arguments cannot be combined with a spread"
+        );
+        assert_eq!(errors[0].code().unwrap().code, "E0004");
+    }
+
+    #[test]
+    fn test_pipe_is_checked_as_a_call() {
+        let double = "let double = fn(x: Number): Number do x * 2 end\n";
+        assert!(parse_and_typecheck(&format!("{double}let n: Number = 2 |> double")).is_ok());
+
+        let errors = parse_and_typecheck(&format!("{double}let n = \"a\" |> double")).unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.message == "Function call: argument 0 expected Number, got String"),
+            "{errors:?}"
+        );
+        let errors = parse_and_typecheck(&format!("{double}let n = 2 |> double(1)")).unwrap_err();
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.message == "Function call: expected 1 arguments, got 2"),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_arrow_function_type_annotation() {
+        let source = "let double: (Number) -> Number = fn(x: Number): Number do x * 2 end\nlet y: Number = double(4)";
+        assert!(parse_and_typecheck(source).is_ok());
+
+        let wrong_arity = "let f: (Number, Number) -> Number = fn(x: Number): Number do x end";
+        let errors = parse_and_typecheck(wrong_arity).unwrap_err();
+        assert!(
+            errors.iter().any(|e| e.message
+                == "Variable f: declared type Function(Number, Number) -> Number, got Function(Number) -> Number"),
+            "{errors:?}"
+        );
+
+        let wrong_return = "let f: (Number) -> String = fn(x: Number): Number do x end";
+        let errors = parse_and_typecheck(wrong_return).unwrap_err();
+        assert!(
+            errors.iter().any(|e| e
+                .message
+                .contains("declared type Function(Number) -> String")),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_arrow_function_type_in_parameter() {
+        let source = r#"
+            let apply = fn(f: (Number) -> Number, x: Number): Number do f(x) end
+            let y = apply(fn(n: Number): Number do n + 1 end, 2)
+        "#;
+        assert!(parse_and_typecheck(source).is_ok());
+
+        let source = r#"
+            let apply = fn(f: (Number) -> Number, x: Number): Number do f(x) end
+            let y = apply(fn(s: String): String do s end, 2)
+        "#;
+        assert!(parse_and_typecheck(source).is_err());
+    }
+
+    #[test]
+    fn test_higher_order_call_checks_callback_signature() {
+        let apply = "let apply = fn(f: (Number) -> Number, x: Number) do return f(x) end\n";
+
+        let ok = format!("{apply}let y = apply(fn(n: Number): Number do n * 2 end, 3)");
+        assert!(parse_and_typecheck(&ok).is_ok());
+
+        let cases = [
+            (
+                "fn(s: String): Number do 1 end",
+                "argument 0 expected Function(Number) -> Number, got Function(String) -> Number (parameter 0 is String, expected Number)",
+            ),
+            (
+                "fn(a: Number, b: Number): Number do a end",
+                "(takes 2 parameters, expected 1)",
+            ),
+            (
+                "fn(n: Number): String do \"x\" end",
+                "(returns String, expected Number)",
+            ),
+            (
+                "5",
+                "argument 0 expected Function(Number) -> Number, got Number",
+            ),
+        ];
+        for (callback, expected) in cases {
+            let source = format!("{apply}let y = apply({callback}, 3)");
+            let errors = parse_and_typecheck(&source).unwrap_err();
+            assert!(
+                errors.iter().any(|e| e.message.contains(expected)),
+                "{callback}: {errors:?}"
+            );
+        }
+    }
+
+    fn strict_errors(input: &str) -> Vec<String> {
+        let program = parse(input, "test.luma").expect("Parse failed");
+        let options = TypecheckOptions { strict_types: true };
+        match typecheck_program_with_options(&program, options) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|e| e.message).collect(),
+        }
+    }
+
+    #[test]
+    fn test_strict_types_rejects_gradual_fallbacks() {
+        let cases = [
+            (
+                "let f = fn(x: Any) do x end",
+                "Parameter x: strict types require a concrete type, got Any",
+            ),
+            (
+                "let p = { x = 1 }\nlet n: Number = p.x",
+                "Variable n: expected Number, got a value of unknown type",
+            ),
+            (
+                "let m = import(\"m.luma\")\nlet v = m.value",
+                "Member access 'value' requires a known type, got Unknown",
+            ),
+            (
+                "let p = { x = 1 }\nlet f = fn(n: Number): Number do n end\nlet y = f(p.x)",
+                "Function call: argument 0: expected Number, got a value of unknown type",
+            ),
+        ];
+        for (source, expected) in cases {
+            assert!(parse_and_typecheck(source).is_ok(), "{source}");
+            let errors = strict_errors(source);
+            assert!(errors.iter().any(|e| e == expected), "{source}: {errors:?}");
+        }
+    }
+
+    #[test]
+    fn test_codes_are_chosen_where_errors_are_reported() {
+        let program = parse("let p = { x = 1 }\nlet n: Number = p.x", "test.luma").unwrap();
+        let options = TypecheckOptions { strict_types: true };
+        let errors = typecheck_program_with_options(&program, options).unwrap_err();
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].code, Some(ErrorCode::StrictTypes));
+        assert_eq!(errors[0].code().unwrap().code, "E0012");
+
+        // The code does not depend on how the message is worded
+        let errors = parse_and_typecheck("let x: Number = \"a\"\nlet y = nope").unwrap_err();
+        let codes: Vec<_> = errors.iter().map(|e| e.code).collect();
+        assert_eq!(
+            codes,
+            [
+                Some(ErrorCode::TypeMismatch),
+                Some(ErrorCode::UndefinedVariable)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strict_types_accepts_annotated_program() {
+        let source = r#"
+            let greet = fn(name: String, times: Number): String do
+                var out = ""
+                for i in range(0, times) do
+                    out = out + name
+                end
+                return out
+            end
+            let s: String = greet("hi", 2)
+            let total: Number = len([1, 2, 3]) + 1
+        "#;
+        assert!(parse_and_typecheck(source).is_ok());
+        assert_eq!(strict_errors(source), Vec::<String>::new());
+    }
 }
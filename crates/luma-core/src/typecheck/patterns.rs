@@ -1,10 +1,12 @@
 //! Pattern type checking and match exhaustiveness.
 
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 
 use crate::ast::*;
+use crate::diagnostics::Severity;
 
 use super::environment::TypeEnv;
+use super::errors::{ErrorCode, TypeError};
 use super::types::{TcType, VarInfo};
 
 /// Known tag patterns for Result/Option types that should not be treated as catch-all bindings.
@@ -31,7 +33,7 @@ impl TypeEnv {
             Pattern::ListPattern { elements, rest, .. } => match ty {
                 TcType::List(elem_ty) => {
                     for elem in elements {
-                        self.check_pattern(elem, elem_ty, mutable, in_match);
+                        self.check_pattern(elem, elem_ty, mutable, false);
                     }
                     if let Some(rest_name) = rest {
                         self.declare(
@@ -46,7 +48,7 @@ impl TypeEnv {
                 }
                 TcType::Unknown | TcType::Any => {
                     for elem in elements {
-                        self.check_pattern(elem, &TcType::Unknown, mutable, in_match);
+                        self.check_pattern(elem, &TcType::Unknown, mutable, false);
                     }
                     if let Some(rest_name) = rest {
                         self.declare(
@@ -61,6 +63,7 @@ impl TypeEnv {
                 }
                 _ => {
                     self.error(
+                        ErrorCode::TypeMismatch,
                         format!("List pattern requires List type, got {ty}"),
                         pattern.span(),
                     );
@@ -73,6 +76,7 @@ impl TypeEnv {
                         for f in fields {
                             if !present.contains(&f.key) {
                                 self.error(
+                                    ErrorCode::TypeMismatch,
                                     format!(
                                         "Table pattern requires field '{}' not present on value",
                                         f.key
@@ -83,51 +87,68 @@ impl TypeEnv {
                         }
                         // Bind variables with Unknown type (no per-field typing yet)
                         for field in fields {
-                            let binding_name = field.binding.as_ref().unwrap_or(&field.key);
-                            self.declare(
-                                binding_name.clone(),
-                                VarInfo {
-                                    ty: TcType::Unknown,
-                                    mutable,
-                                    annotated: false,
-                                },
-                            );
+                            self.check_field_pattern(field, &TcType::Unknown, mutable);
                         }
                     }
                     TcType::Table => {
                         for field in fields {
-                            let binding_name = field.binding.as_ref().unwrap_or(&field.key);
-                            self.declare(
-                                binding_name.clone(),
-                                VarInfo {
-                                    ty: TcType::Unknown,
-                                    mutable,
-                                    annotated: false,
-                                },
-                            );
+                            self.check_field_pattern(field, &TcType::Unknown, mutable);
                         }
                     }
                     TcType::Unknown | TcType::Any => {
                         for field in fields {
-                            let binding_name = field.binding.as_ref().unwrap_or(&field.key);
-                            self.declare(
-                                binding_name.clone(),
-                                VarInfo {
-                                    ty: TcType::Unknown,
-                                    mutable,
-                                    annotated: false,
-                                },
-                            );
+                            self.check_field_pattern(field, &TcType::Unknown, mutable);
                         }
                     }
                     _ => {
                         self.error(
+                            ErrorCode::TypeMismatch,
                             format!("Table pattern requires Table type, got {ty}"),
                             pattern.span(),
                         );
                     }
                 }
             }
+            Pattern::Variant { tag, fields, .. } => {
+                if !matches!(
+                    ty,
+                    TcType::Table | TcType::TableWithFields(_) | TcType::Unknown | TcType::Any
+                ) {
+                    self.error(
+                        ErrorCode::TypeMismatch,
+                        format!("Variant pattern requires Table type, got {ty}"),
+                        pattern.span(),
+                    );
+                }
+                let variant_fields = match self.find_enum_variant(tag) {
+                    Some((_, variant)) => Some(variant.fields.clone()),
+                    None => {
+                        self.error(
+                            ErrorCode::UnknownField,
+                            format!("Unknown variant '{tag}' in pattern"),
+                            pattern.span(),
+                        );
+                        None
+                    }
+                };
+                for field in fields {
+                    let field_ty = match &variant_fields {
+                        Some(declared) => match declared.iter().find(|(n, _)| *n == field.key) {
+                            Some((_, t)) => Self::type_from_ast(t),
+                            None => {
+                                self.error(
+                                    ErrorCode::UnknownField,
+                                    format!("Variant '{tag}' has no field '{}'", field.key),
+                                    pattern.span(),
+                                );
+                                TcType::Unknown
+                            }
+                        },
+                        None => TcType::Unknown,
+                    };
+                    self.check_field_pattern(field, &field_ty, mutable);
+                }
+            }
             Pattern::Wildcard { .. } => {
                 // Wildcard pattern doesn't bind any variables, just accepts any type
             }
@@ -137,6 +158,102 @@ impl TypeEnv {
         }
     }
 
+    /// Declare the variables `pattern` binds without checking it against a
+    /// type, for passes over code whose patterns were already checked. Fields
+    /// of a declared variant get their declared types; everything else is
+    /// `Unknown`.
+    pub fn bind_pattern_names(&mut self, pattern: &Pattern, in_match: bool) {
+        let declare = |env: &mut Self, name: &str, ty: TcType| {
+            env.declare(
+                name.to_string(),
+                VarInfo {
+                    ty,
+                    mutable: false,
+                    annotated: false,
+                },
+            )
+        };
+        match pattern {
+            Pattern::Ident { name, .. } => {
+                if !(in_match && KNOWN_TAG_PATTERNS.contains(&name.as_str())) {
+                    declare(self, name, TcType::Unknown);
+                }
+            }
+            Pattern::ListPattern { elements, rest, .. } => {
+                for element in elements {
+                    self.bind_pattern_names(element, false);
+                }
+                if let Some(rest) = rest {
+                    declare(self, rest, TcType::List(Box::new(TcType::Unknown)));
+                }
+            }
+            Pattern::TablePattern { fields, .. } | Pattern::Variant { fields, .. } => {
+                let declared = match pattern {
+                    Pattern::Variant { tag, .. } => self
+                        .find_enum_variant(tag)
+                        .map(|(_, variant)| variant.fields.clone()),
+                    _ => None,
+                };
+                for field in fields {
+                    match &field.pattern {
+                        Some(nested) => self.bind_pattern_names(nested, false),
+                        None => {
+                            let ty = declared
+                                .iter()
+                                .flatten()
+                                .find(|(name, _)| *name == field.key)
+                                .map_or(TcType::Unknown, |(_, t)| Self::type_from_ast(t));
+                            declare(self, &field.key, ty);
+                        }
+                    }
+                }
+            }
+            Pattern::Wildcard { .. } | Pattern::Literal { .. } => {}
+        }
+    }
+
+    /// Reject literals inside a `let` or `for` pattern: only `match` tests
+    /// values, so there they would silently match anything.
+    pub fn check_irrefutable(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Literal { span, .. } => self.error(
+                ErrorCode::TypeMismatch,
+                "Literal patterns can only be used in match".to_string(),
+                *span,
+            ),
+            Pattern::ListPattern { elements, .. } => {
+                for element in elements {
+                    self.check_irrefutable(element);
+                }
+            }
+            Pattern::TablePattern { fields, .. } | Pattern::Variant { fields, .. } => {
+                for field in fields {
+                    if let Some(nested) = &field.pattern {
+                        self.check_irrefutable(nested);
+                    }
+                }
+            }
+            Pattern::Ident { .. } | Pattern::Wildcard { .. } => {}
+        }
+    }
+
+    /// Bind a table or variant field's value: to a variable named after the
+    /// key, or through its nested pattern. Nested names are always bindings,
+    /// never `ok`/`err`-style tags.
+    fn check_field_pattern(&mut self, field: &TablePatternField, ty: &TcType, mutable: bool) {
+        match &field.pattern {
+            Some(pattern) => self.check_pattern(pattern, ty, mutable, false),
+            None => self.declare(
+                field.key.clone(),
+                VarInfo {
+                    ty: ty.clone(),
+                    mutable,
+                    annotated: false,
+                },
+            ),
+        }
+    }
+
     /// Check for unreachable patterns in a match expression.
     /// A pattern is unreachable if a previous pattern already catches all cases.
     pub fn check_unreachable_patterns(&mut self, arms: &[(Pattern, Vec<Stmt>)]) {
@@ -144,7 +261,7 @@ impl TypeEnv {
 
         for (i, (pattern, _)) in arms.iter().enumerate() {
             if seen_catch_all {
-                self.error(format!(
+                self.error(ErrorCode::UnreachablePattern, format!(
                     "Unreachable pattern: pattern #{} is unreachable because a previous pattern already covers all cases",
                     i + 1
                 ), pattern.span());
@@ -155,11 +272,9 @@ impl TypeEnv {
                 Pattern::Wildcard { .. } => {
                     seen_catch_all = true;
                 }
-                Pattern::Ident { name, .. } => {
+                Pattern::Ident { name, .. } if !KNOWN_TAG_PATTERNS.contains(&name.as_str()) => {
                     // Identifier patterns that are not known tags are catch-all bindings
-                    if !KNOWN_TAG_PATTERNS.contains(&name.as_str()) {
-                        seen_catch_all = true;
-                    }
+                    seen_catch_all = true;
                 }
                 _ => {
                     // Other patterns are not catch-all
@@ -182,6 +297,7 @@ impl TypeEnv {
         let mut has_wildcard = false;
         let mut has_literal = false;
         let mut tags = HashSet::new();
+        let mut variant_tags = BTreeSet::new();
 
         for (pattern, _) in arms {
             match pattern {
@@ -203,6 +319,9 @@ impl TypeEnv {
                 Pattern::Literal { value: _, .. } => {
                     has_literal = true;
                 }
+                Pattern::Variant { tag, .. } => {
+                    variant_tags.insert(tag.as_str());
+                }
                 Pattern::ListPattern { .. } | Pattern::TablePattern { .. } => {
                     // Structural patterns are specific, not catch-all
                 }
@@ -218,6 +337,7 @@ impl TypeEnv {
             for &tag in &tags {
                 if !fields.contains(&tag.to_string()) {
                     self.error(
+                        ErrorCode::TypeMismatch,
                         format!("Match tag '{tag}' not present on matched table type"),
                         match_span,
                     );
@@ -230,6 +350,73 @@ impl TypeEnv {
             return;
         }
 
+        // Variant patterns must cover every variant of the tagged-union they belong to
+        let owners: BTreeSet<String> = variant_tags
+            .iter()
+            .filter_map(|tag| self.find_enum_variant(tag))
+            .map(|(enum_name, _)| enum_name.to_string())
+            .collect();
+        if owners.len() > 1 {
+            let names = owners
+                .iter()
+                .map(|n| format!("'{n}'"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.error(
+                ErrorCode::TypeMismatch,
+                format!("Match mixes variants of different types: {names}"),
+                match_span,
+            );
+            return;
+        }
+        if let Some(enum_name) = owners.into_iter().next() {
+            let missing: Vec<EnumVariant> = self.enums[&enum_name]
+                .iter()
+                .filter(|v| !variant_tags.contains(v.name.as_str()))
+                .cloned()
+                .collect();
+            if missing.is_empty() {
+                return;
+            }
+            let names = missing
+                .iter()
+                .map(|v| format!("'{}'", v.name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let suggestions = missing
+                .iter()
+                .map(|v| {
+                    let fields = v
+                        .fields
+                        .iter()
+                        .map(|(n, _)| n.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let arm = if fields.is_empty() {
+                        format!("{} {{}}", v.name)
+                    } else {
+                        format!("{} {{ {fields} }}", v.name)
+                    };
+                    format!(
+                        "missing variant '{}': add a `{arm}` arm or a wildcard (_)",
+                        v.name
+                    )
+                })
+                .collect();
+            self.errors.push(TypeError {
+                message: format!(
+                    "Match expression is not exhaustive: missing variant(s) {names} of type '{enum_name}'"
+                ),
+                span: match_span,
+                severity: Severity::Error,
+                suggestions,
+                fixits: Vec::new(),
+                related: Vec::new(),
+                code: Some(ErrorCode::NonExhaustiveMatch),
+            });
+            return;
+        }
+
         // Check if we have all known tag variants
         let has_result_tags = tags.contains("ok") && tags.contains("err");
         let has_option_tags = tags.contains("some") && tags.contains("none");
@@ -241,13 +428,13 @@ impl TypeEnv {
 
         // If we have literal patterns without wildcard, not exhaustive
         if has_literal {
-            self.error("Match expression is not exhaustive: literal patterns require a wildcard (_) or catch-all case".to_string(), match_span);
+            self.error(ErrorCode::NonExhaustiveMatch, "Match expression is not exhaustive: literal patterns require a wildcard (_) or catch-all case".to_string(), match_span);
             return;
         }
 
         // If we have tags but not all variants, not exhaustive
         if !tags.is_empty() {
-            self.error(format!(
+            self.error(ErrorCode::NonExhaustiveMatch, format!(
                 "Match expression is not exhaustive: found tags {tags:?} but missing wildcard or all variants (e.g., ok/err or some/none)"
             ), match_span);
             return;
@@ -255,6 +442,7 @@ impl TypeEnv {
 
         // Otherwise, we need a wildcard
         self.error(
+            ErrorCode::NonExhaustiveMatch,
             "Match expression is not exhaustive: add a wildcard (_) pattern or cover all cases"
                 .to_string(),
             match_span,
@@ -3,7 +3,9 @@
 use crate::ast::*;
 
 use super::environment::TypeEnv;
+use super::errors::{ErrorCode, TypeError};
 use super::types::{TcType, VarInfo};
+use crate::diagnostics::Severity;
 
 impl TypeEnv {
     /// Type check a block of statements and return the block's type.
@@ -51,14 +53,21 @@ impl TypeEnv {
             }
         }
 
+        self.check_unreachable(stmts);
         let len = stmts.len();
         for (i, stmt) in stmts.iter().enumerate() {
             let is_last = i == len - 1;
             match stmt {
-                Stmt::Return { value: expr, span } => {
-                    ret_ty = self.check_expr(expr);
+                Stmt::Return {
+                    value: expr,
+                    implicit,
+                    span,
+                } => {
+                    self.check_return_context(*implicit, *span);
+                    ret_ty = self.in_statement(|env| env.check_expr(expr));
                     if !ret_ty.is_compatible(expected_ret) && *expected_ret != TcType::Unknown {
                         self.error(
+                            ErrorCode::TypeMismatch,
                             format!("Return type mismatch: expected {expected_ret}, got {ret_ty}"),
                             *span,
                         );
@@ -77,6 +86,66 @@ impl TypeEnv {
         ret_ty
     }
 
+    /// Type check a block's statements in order.
+    pub fn check_stmts(&mut self, stmts: &[Stmt]) {
+        self.check_unreachable(stmts);
+        for stmt in stmts {
+            self.check_stmt(stmt);
+        }
+    }
+
+    /// Warn about the first statement of a block that follows a `break` or
+    /// `continue` (or an `if` whose every branch ends in one), since it can
+    /// never run. `return` only sets the block's value and execution carries
+    /// on, so it does not count.
+    pub fn check_unreachable(&mut self, stmts: &[Stmt]) {
+        let Some(pos) = stmts.iter().position(|stmt| match stmt {
+            Stmt::Break { .. } | Stmt::Continue { .. } => true,
+            Stmt::If { .. } => Self::if_always_exits(stmt),
+            _ => false,
+        }) else {
+            return;
+        };
+        let Some(next) = stmts.get(pos + 1) else {
+            return;
+        };
+        let reason = match &stmts[pos] {
+            Stmt::Break { .. } => "`break`",
+            Stmt::Continue { .. } => "`continue`",
+            _ => "an `if` whose every branch exits",
+        };
+        self.warning(
+            ErrorCode::UnreachableCode,
+            format!("Unreachable code after {reason}"),
+            next.span(),
+        );
+    }
+
+    /// Whether every branch of an `if` statement leaves the block early.
+    fn if_always_exits(stmt: &Stmt) -> bool {
+        let Stmt::If {
+            then_block,
+            elif_blocks,
+            else_block: Some(else_block),
+            ..
+        } = stmt
+        else {
+            return false;
+        };
+        Self::block_always_exits(then_block)
+            && elif_blocks.iter().all(|(_, b)| Self::block_always_exits(b))
+            && Self::block_always_exits(else_block)
+    }
+
+    /// Whether a branch always leaves through `break` or `continue`.
+    fn block_always_exits(block: &[Stmt]) -> bool {
+        block.iter().any(|stmt| match stmt {
+            Stmt::Break { .. } | Stmt::Continue { .. } => true,
+            Stmt::If { .. } => Self::if_always_exits(stmt),
+            _ => false,
+        })
+    }
+
     /// Compute the implicit return type of a statement when it's the last statement in a block.
     /// This handles control flow statements (if, match) that may implicitly return values.
     fn compute_implicit_return_type(&mut self, stmt: &Stmt, expected_ret: &TcType) -> TcType {
@@ -112,6 +181,7 @@ impl TypeEnv {
                 // Check if the unified type matches expected
                 if !unified.is_compatible(expected_ret) && *expected_ret != TcType::Unknown {
                     self.error(
+                        ErrorCode::TypeMismatch,
                         format!("Return type mismatch: expected {expected_ret}, got {unified}"),
                         *span,
                     );
@@ -126,8 +196,13 @@ impl TypeEnv {
 
                 let mut unified: Option<TcType> = None;
 
-                for (_, body) in arms {
+                for (pattern, body) in arms {
+                    // Re-bind the arm's pattern variables so the body can refer to them;
+                    // the pattern itself was checked along with the match.
+                    self.push_scope();
+                    self.bind_pattern_names(pattern, true);
                     let arm_ret = self.compute_block_return_type(body, expected_ret);
+                    self.pop_scope();
                     unified = Some(match unified {
                         None => arm_ret,
                         Some(current) => self.unify_return_types(current, arm_ret),
@@ -139,6 +214,7 @@ impl TypeEnv {
                 // Check if the unified type matches expected
                 if !result.is_compatible(expected_ret) && *expected_ret != TcType::Unknown {
                     self.error(
+                        ErrorCode::TypeMismatch,
                         format!("Return type mismatch: expected {expected_ret}, got {result}"),
                         *span,
                     );
@@ -173,9 +249,61 @@ impl TypeEnv {
         }
     }
 
-    /// Type check a single statement.
+    /// Type check a single statement. Each undefined name is reported once per
+    /// statement; statements nested inside it start afresh.
     pub fn check_stmt(&mut self, stmt: &Stmt) {
+        self.in_statement(|env| env.check_stmt_kind(stmt));
+    }
+
+    /// Run `check` with a fresh set of reported undefined names, then fold the
+    /// names it reported into the enclosing statement's set.
+    fn in_statement<R>(&mut self, check: impl FnOnce(&mut Self) -> R) -> R {
+        let outer = std::mem::take(&mut self.reported_undefined);
+        let result = check(self);
+        let inner = std::mem::replace(&mut self.reported_undefined, outer);
+        self.reported_undefined.extend(inner);
+        result
+    }
+
+    fn check_stmt_kind(&mut self, stmt: &Stmt) {
         match stmt {
+            Stmt::TypeDecl {
+                name,
+                variants,
+                span,
+            } => {
+                let mut seen = std::collections::HashSet::new();
+                for variant in variants {
+                    if !seen.insert(variant.name.as_str()) {
+                        self.error(
+                            ErrorCode::TypeMismatch,
+                            format!("Duplicate variant '{}' in type '{name}'", variant.name),
+                            variant.span.or(*span),
+                        );
+                    } else if let Some((other, _)) = self.find_enum_variant(&variant.name)
+                        && other != name
+                    {
+                        // Values only carry their tag, so a match could not
+                        // tell the two variants apart
+                        let message = format!(
+                            "Variant '{}' of type '{name}' is already declared by type '{other}'",
+                            variant.name
+                        );
+                        self.error(ErrorCode::TypeMismatch, message, variant.span.or(*span));
+                    }
+                }
+                self.enums.insert(name.clone(), variants.clone());
+                self.declare(
+                    name.clone(),
+                    VarInfo {
+                        ty: TcType::TableWithFields(
+                            variants.iter().map(|v| v.name.clone()).collect(),
+                        ),
+                        mutable: false,
+                        annotated: true,
+                    },
+                );
+            }
             Stmt::Match { expr, arms, .. } => {
                 // Check the match expression
                 let expr_ty = self.check_expr(expr);
@@ -194,9 +322,7 @@ impl TypeEnv {
 
                     // Check the body statements
                     self.in_match_arm_depth += 1;
-                    for stmt in body {
-                        self.check_stmt(stmt);
-                    }
+                    self.check_stmts(body);
                     self.in_match_arm_depth -= 1;
                     self.pop_scope();
                 }
@@ -213,32 +339,54 @@ impl TypeEnv {
                 let value_ty = match value {
                     Expr::Function { .. } => {
                         // Function was already declared, just check its body
+                        self.note_binding(name, *span);
                         self.check_expr(value)
                     }
                     _ => {
-                        // Non-function: check value and declare normally
+                        // Non-function: check value and declare normally. An
+                        // annotation states the type an overload note would ask for.
+                        let outer = std::mem::replace(
+                            &mut self.annotated_value,
+                            r#type.as_ref().and(value.span()),
+                        );
                         let val_ty = self.check_expr(value);
+                        self.annotated_value = outer;
 
                         let declared_ty = if let Some(ty) = r#type {
                             let t = Self::type_from_ast(ty);
                             if !val_ty.is_compatible(&t) {
                                 self.error(
+                                    ErrorCode::TypeMismatch,
                                     format!("Variable {name}: declared type {t}, got {val_ty}"),
                                     *span,
                                 );
                             }
-                            t
+                            self.check_strict_unknown(
+                                &val_ty,
+                                &t,
+                                &format!("Variable {name}"),
+                                *span,
+                            );
+                            // User-defined type names don't resolve yet, so keep the
+                            // fields the value is known to have
+                            if t == TcType::Unknown && matches!(val_ty, TcType::TableWithFields(_))
+                            {
+                                val_ty.clone()
+                            } else {
+                                t
+                            }
                         } else {
                             val_ty.clone()
                         };
 
-                        self.declare(
+                        self.declare_binding(
                             name.clone(),
                             VarInfo {
                                 ty: declared_ty,
                                 mutable: *mutable,
                                 annotated: r#type.is_some(),
                             },
+                            *span,
                         );
 
                         val_ty
@@ -252,6 +400,7 @@ impl TypeEnv {
                     let declared = Self::type_from_ast(ty);
                     if !value_ty.is_compatible(&declared) {
                         self.error(
+                            ErrorCode::TypeMismatch,
                             format!("Variable {name}: declared type {declared}, got {value_ty}"),
                             *span,
                         );
@@ -266,6 +415,7 @@ impl TypeEnv {
                 ..
             } => {
                 let value_ty = self.check_expr(value);
+                self.check_irrefutable(pattern);
                 self.check_pattern(pattern, &value_ty, *mutable, false);
             }
 
@@ -278,12 +428,23 @@ impl TypeEnv {
                 let target_ty = self.check_assignment_target(target);
                 let value_ty = self.check_expr(value);
 
-                if !value_ty.is_compatible(&target_ty) {
+                if !self.infer_list_element(target, &value_ty, *span)
+                    && !value_ty.is_compatible(&target_ty)
+                {
                     self.error(
+                        ErrorCode::TypeMismatch,
                         format!("Assignment type mismatch: target {target_ty}, value {value_ty}"),
                         *span,
                     );
                 }
+                self.check_strict_unknown(&value_ty, &target_ty, "Assignment", *span);
+                if same_place(target, value) {
+                    self.warning(
+                        ErrorCode::SelfAssignment,
+                        "Assignment of a value to itself has no effect".to_string(),
+                        *span,
+                    );
+                }
             }
 
             Stmt::If {
@@ -291,54 +452,147 @@ impl TypeEnv {
                 then_block,
                 elif_blocks,
                 else_block,
-                ..
+                span,
             } => {
                 self.expect_type(condition, &TcType::Boolean, "If condition");
+                let branches = std::iter::once(then_block)
+                    .chain(elif_blocks.iter().map(|(_, block)| block))
+                    .chain(else_block);
+                if branches.into_iter().any(|block| block.is_empty()) {
+                    self.warning(ErrorCode::EmptyBlock, "Empty `if` body".to_string(), *span);
+                }
 
                 self.push_scope();
-                for stmt in then_block {
-                    self.check_stmt(stmt);
-                }
+                self.check_stmts(then_block);
                 self.pop_scope();
 
                 for (cond, block) in elif_blocks {
                     self.expect_type(cond, &TcType::Boolean, "Elif condition");
                     self.push_scope();
-                    for stmt in block {
-                        self.check_stmt(stmt);
-                    }
+                    self.check_stmts(block);
                     self.pop_scope();
                 }
 
                 if let Some(block) = else_block {
                     self.push_scope();
-                    for stmt in block {
-                        self.check_stmt(stmt);
-                    }
+                    self.check_stmts(block);
                     self.pop_scope();
                 }
             }
 
+            Stmt::While { .. } | Stmt::DoWhile { .. } | Stmt::For { .. } => {
+                self.check_loop(stmt);
+            }
+
+            Stmt::Break { level, value, span } => {
+                let ty = value.as_ref().map(|value| self.check_expr(value));
+                let level = level.unwrap_or(1) as usize;
+                if self.check_loop_level("break", level, *span)
+                    && let (Some(ty), Some(value)) = (ty, value)
+                {
+                    let depth = self.loop_breaks.len();
+                    self.loop_breaks[depth - level].push((ty, value.span()));
+                }
+            }
+
+            Stmt::Continue { level, span } => {
+                self.check_loop_level("continue", level.unwrap_or(1) as usize, *span);
+            }
+
+            Stmt::Return {
+                value: expr,
+                implicit,
+                span,
+            } => {
+                self.check_return_context(*implicit, *span);
+                self.check_expr(expr);
+            }
+
+            Stmt::ExprStmt { expr, .. } => {
+                self.check_expr(expr);
+            }
+
+            Stmt::Export { span, .. } => {
+                if self.function_depth > 0 || self.scopes.len() > 1 {
+                    self.error(
+                        ErrorCode::InvalidExport,
+                        "`export` is only allowed at the top level of a module".to_string(),
+                        *span,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Check a module's export list once every top-level binding is known:
+    /// each exported name must be bound, and listed only once.
+    pub fn check_exports(&mut self, stmts: &[Stmt]) {
+        let mut seen = std::collections::HashSet::new();
+        for stmt in stmts {
+            let Stmt::Export { names, span } = stmt else {
+                continue;
+            };
+            for name in names {
+                if self.lookup(name).is_none() {
+                    self.error_undefined_variable(name, *span);
+                } else if !seen.insert(name.as_str()) {
+                    self.error(
+                        ErrorCode::InvalidExport,
+                        format!("'{name}' is exported more than once"),
+                        *span,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Check that `break`/`continue` at `level` targets a loop in the current
+    /// function; function bodies start with no enclosing loops.
+    fn check_loop_level(&mut self, keyword: &str, level: usize, span: Option<Span>) -> bool {
+        let depth = self.loop_breaks.len();
+        if depth == 0 {
+            self.error(
+                ErrorCode::LoopControl,
+                format!("{keyword} outside of loop"),
+                span,
+            );
+            false
+        } else if level == 0 || level > depth {
+            self.error(
+                ErrorCode::LoopControl,
+                format!("{keyword} level {level} exceeds loop nesting depth of {depth}"),
+                span,
+            );
+            false
+        } else {
+            true
+        }
+    }
+
+    /// Type check a loop statement and return its type when used as an
+    /// expression: the values it yields through `break`, together with the
+    /// `null` it produces when it finishes normally.
+    pub fn check_loop(&mut self, stmt: &Stmt) -> TcType {
+        self.loop_breaks.push(Vec::new());
+        match stmt {
             Stmt::While {
                 condition, body, ..
             } => {
                 self.expect_type(condition, &TcType::Boolean, "While condition");
                 self.push_scope();
-                for stmt in body {
-                    self.check_stmt(stmt);
-                }
+                self.check_stmts(body);
                 self.pop_scope();
+                self.check_infinite_loop(condition, body);
             }
 
             Stmt::DoWhile {
                 body, condition, ..
             } => {
                 self.push_scope();
-                for stmt in body {
-                    self.check_stmt(stmt);
-                }
+                self.check_stmts(body);
                 self.pop_scope();
                 self.expect_type(condition, &TcType::Boolean, "Do-while condition");
+                self.check_infinite_loop(condition, body);
             }
 
             Stmt::For {
@@ -348,22 +602,34 @@ impl TypeEnv {
                 span,
             } => {
                 let iter_ty = self.check_expr(iterator);
+                if body.is_empty() {
+                    self.warning(ErrorCode::EmptyBlock, "Empty `for` body".to_string(), *span);
+                }
 
+                self.check_irrefutable(pattern);
                 self.push_scope();
                 match &iter_ty {
                     TcType::List(elem_ty) => {
                         self.check_pattern(pattern, elem_ty, true, false);
                     }
+                    // An iterator yields whatever its `__next` produces
+                    TcType::TableWithFields(fields) if fields.iter().any(|f| f == "__next") => {
+                        self.check_pattern(pattern, &TcType::Unknown, true, false);
+                    }
                     TcType::Table | TcType::TableWithFields(_) => {
-                        // Iteration over tables yields [key, value] pairs
-                        let pair_elem = TcType::List(Box::new(TcType::Unknown));
-                        self.check_pattern(pattern, &pair_elem, true, false);
+                        // A single name iterates keys; destructuring iterates [key, value] pairs
+                        let item_ty = match pattern {
+                            Pattern::Ident { .. } | Pattern::Wildcard { .. } => TcType::String,
+                            _ => TcType::List(Box::new(TcType::Unknown)),
+                        };
+                        self.check_pattern(pattern, &item_ty, true, false);
                     }
                     TcType::Unknown | TcType::Any => {
                         self.check_pattern(pattern, &TcType::Unknown, true, false);
                     }
                     _ => {
                         self.error(
+                            ErrorCode::TypeMismatch,
                             format!("For loop requires List or Table iterator, got {iter_ty}"),
                             *span,
                         );
@@ -371,23 +637,73 @@ impl TypeEnv {
                     }
                 }
 
-                for stmt in body {
-                    self.check_stmt(stmt);
-                }
+                self.check_stmts(body);
                 self.pop_scope();
             }
+            _ => {}
+        }
+        let breaks = self.loop_breaks.pop().unwrap_or_default();
+
+        let mut unified: Option<TcType> = None;
+        for (ty, span) in breaks {
+            unified = Some(match unified {
+                None => ty,
+                Some(current) if current.is_compatible(&ty) => current,
+                Some(current) if ty.is_compatible(&current) => ty,
+                Some(current) => {
+                    self.error(
+                        ErrorCode::TypeMismatch,
+                        format!("Break values have incompatible types: {current} vs {ty}"),
+                        span,
+                    );
+                    TcType::Unknown
+                }
+            });
+        }
+        match unified {
+            None => TcType::Null,
+            Some(ty) if Self::loop_never_finishes(stmt) => ty,
+            // There is no nullable type, so a value that may be null is Unknown
+            Some(ty) => self.unify_return_types(ty, TcType::Null),
+        }
+    }
 
-            Stmt::Break { level: _, .. } | Stmt::Continue { level: _, .. } => {
-                // TODO: Could check if we're inside a loop
+    /// Whether a loop can only be left through `break`: its condition is a
+    /// literal that always holds.
+    fn loop_never_finishes(stmt: &Stmt) -> bool {
+        match stmt {
+            Stmt::While { condition, .. } | Stmt::DoWhile { condition, .. } => {
+                constant_condition(condition) == Some(true)
             }
+            _ => false,
+        }
+    }
 
-            Stmt::Return { value: expr, .. } => {
-                self.check_expr(expr);
-            }
+    /// Warn about a loop whose condition is a constant that always holds and
+    /// whose body never leaves it. Only literal conditions such as `true` or
+    /// `1 < 2` count, so a condition that merely looks constant never warns.
+    fn check_infinite_loop(&mut self, condition: &Expr, body: &[Stmt]) {
+        if constant_condition(condition) == Some(true) && !block_leaves_loop(body, 1) {
+            self.warnings.push(TypeError {
+                message: "Loop condition is always true and the body never breaks out".to_string(),
+                span: condition.span(),
+                severity: Severity::Warning,
+                suggestions: vec!["add a `break`, or a condition that can become false".into()],
+                fixits: Vec::new(),
+                related: Vec::new(),
+                code: Some(ErrorCode::InfiniteLoop),
+            });
+        }
+    }
 
-            Stmt::ExprStmt { expr, .. } => {
-                self.check_expr(expr);
-            }
+    /// Reject an explicit `return` statement outside of any function body.
+    fn check_return_context(&mut self, implicit: bool, span: Option<Span>) {
+        if !implicit && self.function_depth == 0 {
+            self.error(
+                ErrorCode::ReturnOutsideFunction,
+                "`return` outside of function".to_string(),
+                span,
+            );
         }
     }
 
@@ -400,13 +716,18 @@ impl TypeEnv {
                     let mutable = info.mutable;
                     if !mutable {
                         self.error(
+                            ErrorCode::ImmutableAssignment,
                             format!("Cannot assign to immutable variable: {name}"),
                             *span,
                         );
                     }
                     ty
                 } else {
-                    self.error(format!("Undefined variable: {name}"), *span);
+                    self.error(
+                        ErrorCode::UndefinedVariable,
+                        format!("Undefined variable: {name}"),
+                        *span,
+                    );
                     TcType::Unknown
                 }
             }
@@ -421,6 +742,7 @@ impl TypeEnv {
                     TcType::Unknown | TcType::Any => TcType::Unknown,
                     _ => {
                         self.error(
+                            ErrorCode::TypeMismatch,
                             format!("Member assignment requires a table, got {obj_ty}"),
                             *span,
                         );
@@ -435,23 +757,41 @@ impl TypeEnv {
             } => {
                 let obj_ty = self.check_expr(object);
                 let idx_ty = self.check_expr(index);
+                self.check_fractional_index(index);
 
                 match obj_ty {
                     TcType::List(elem_ty) => {
                         if !idx_ty.is_compatible(&TcType::Number) {
-                            self.error(format!("List index requires Number, got {idx_ty}"), *span);
+                            self.error(
+                                ErrorCode::TypeMismatch,
+                                format!("List index requires Number, got {idx_ty}"),
+                                *span,
+                            );
                         }
                         (*elem_ty).clone()
                     }
-                    TcType::Table => {
+                    TcType::Table | TcType::TableWithFields(_) => {
                         if !idx_ty.is_compatible(&TcType::String) {
-                            self.error(format!("Table index requires String, got {idx_ty}"), *span);
+                            self.error(
+                                ErrorCode::TypeMismatch,
+                                format!("Table index requires String, got {idx_ty}"),
+                                *span,
+                            );
                         }
                         TcType::Unknown
                     }
+                    TcType::String => {
+                        self.error(
+                            ErrorCode::TypeMismatch,
+                            "strings are immutable; cannot assign to index".to_string(),
+                            *span,
+                        );
+                        TcType::Unknown
+                    }
                     TcType::Unknown | TcType::Any => TcType::Unknown,
                     _ => {
                         self.error(
+                            ErrorCode::TypeMismatch,
                             format!("Index assignment requires List or Table, got {obj_ty}"),
                             *span,
                         );
@@ -460,9 +800,123 @@ impl TypeEnv {
                 }
             }
             _ => {
-                self.error("Invalid assignment target".to_string(), target.span());
+                self.error(
+                    ErrorCode::TypeMismatch,
+                    "Invalid assignment target".to_string(),
+                    target.span(),
+                );
                 TcType::Unknown
             }
         }
     }
 }
+
+/// Evaluate a condition built only from boolean and number literals.
+fn constant_condition(expr: &Expr) -> Option<bool> {
+    match expr {
+        Expr::Boolean { value, .. } => Some(*value),
+        Expr::Unary {
+            op: UnaryOp::Not,
+            operand,
+            ..
+        } => constant_condition(operand).map(|b| !b),
+        Expr::Logical {
+            left, op, right, ..
+        } => {
+            let (left, right) = (constant_condition(left)?, constant_condition(right)?);
+            Some(match op {
+                LogicalOp::And => left && right,
+                LogicalOp::Or => left || right,
+            })
+        }
+        Expr::Binary {
+            left, op, right, ..
+        } => {
+            let (Expr::Number { value: a, .. }, Expr::Number { value: b, .. }) =
+                (left.as_ref(), right.as_ref())
+            else {
+                return None;
+            };
+            match op {
+                BinaryOp::Eq => Some(a == b),
+                BinaryOp::Ne => Some(a != b),
+                BinaryOp::Lt => Some(a < b),
+                BinaryOp::Le => Some(a <= b),
+                BinaryOp::Gt => Some(a > b),
+                BinaryOp::Ge => Some(a >= b),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Whether an assignment target and value name the same place, such as
+/// `x = x` or `t.a[0] = t.a[0]`. Only names, fields and literal or variable
+/// indices count, so anything that might have a side effect never matches.
+fn same_place(target: &Expr, value: &Expr) -> bool {
+    match (target, value) {
+        (Expr::Identifier { name: a, .. }, Expr::Identifier { name: b, .. }) => a == b,
+        (
+            Expr::MemberAccess {
+                object: a,
+                member: ma,
+                ..
+            },
+            Expr::MemberAccess {
+                object: b,
+                member: mb,
+                ..
+            },
+        ) => ma == mb && same_place(a, b),
+        (
+            Expr::Index {
+                object: a,
+                index: ia,
+                ..
+            },
+            Expr::Index {
+                object: b,
+                index: ib,
+                ..
+            },
+        ) => {
+            let same_index = match (ia.as_ref(), ib.as_ref()) {
+                (Expr::Number { value: x, .. }, Expr::Number { value: y, .. }) => x == y,
+                (Expr::String { value: x, .. }, Expr::String { value: y, .. }) => x == y,
+                (x, y) => same_place(x, y),
+            };
+            same_index && same_place(a, b)
+        }
+        _ => false,
+    }
+}
+
+/// Whether a loop body, `depth` loops inside the loop in question, contains a
+/// `break` that reaches it. `return` doesn't exit early, so it never does.
+fn block_leaves_loop(body: &[Stmt], depth: usize) -> bool {
+    body.iter().any(|stmt| match stmt {
+        Stmt::Break { level, .. } => level.unwrap_or(1) as usize >= depth,
+        Stmt::If {
+            then_block,
+            elif_blocks,
+            else_block,
+            ..
+        } => {
+            block_leaves_loop(then_block, depth)
+                || elif_blocks
+                    .iter()
+                    .any(|(_, block)| block_leaves_loop(block, depth))
+                || else_block
+                    .as_ref()
+                    .is_some_and(|block| block_leaves_loop(block, depth))
+        }
+        Stmt::Match { arms, .. } => arms
+            .iter()
+            .any(|(_, block)| block_leaves_loop(block, depth)),
+        Stmt::While { body, .. } | Stmt::DoWhile { body, .. } | Stmt::For { body, .. } => {
+            block_leaves_loop(body, depth + 1)
+        }
+        _ => false,
+    })
+}
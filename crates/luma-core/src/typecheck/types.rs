@@ -51,6 +51,37 @@ impl TcType {
             _ => false,
         }
     }
+
+    /// Explain why a function type does not fit an expected function type,
+    /// or `None` if either isn't a function or they are compatible.
+    pub fn function_mismatch(&self, expected: &TcType) -> Option<String> {
+        let (
+            TcType::Function { params, ret },
+            TcType::Function {
+                params: expected_params,
+                ret: expected_ret,
+            },
+        ) = (self, expected)
+        else {
+            return None;
+        };
+        if params.len() != expected_params.len() {
+            return Some(format!(
+                "takes {} parameters, expected {}",
+                params.len(),
+                expected_params.len()
+            ));
+        }
+        for (i, (param, expected)) in params.iter().zip(expected_params).enumerate() {
+            if !param.is_compatible(expected) {
+                return Some(format!("parameter {i} is {param}, expected {expected}"));
+            }
+        }
+        if !ret.is_compatible(expected_ret) {
+            return Some(format!("returns {ret}, expected {expected_ret}"));
+        }
+        None
+    }
 }
 
 impl std::fmt::Display for TcType {
@@ -1,7 +1,9 @@
 //! Error types for VM runtime errors
 
+use std::rc::Rc;
+
 use crate::ast::Span;
-use crate::diagnostics::LineIndex;
+use crate::diagnostics::{Diagnostic, DiagnosticKind};
 
 /// Represents a runtime error with optional source location information
 #[derive(Debug)]
@@ -9,6 +11,36 @@ pub struct VmError {
     pub message: String,
     pub span: Option<Span>,
     pub file: Option<String>,
+    /// Calls active when the error was raised, innermost first. Empty for
+    /// errors raised at the top level of a program.
+    pub trace: Vec<TraceFrame>,
+    /// Paths and text of the imported modules that `file` or a trace frame
+    /// points into. Spans in any other file are resolved against the source
+    /// passed in when formatting.
+    pub sources: Vec<(String, Rc<str>)>,
+}
+
+/// One active call in a runtime error's stack trace
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+    /// Name of the function's chunk: its binding name, `<anonymous>` for an
+    /// anonymous function, or `<program>` for the top level
+    pub function: String,
+    /// The statement that was executing in this frame
+    pub span: Option<Span>,
+    /// The file `span` points into
+    pub file: Option<String>,
+}
+
+impl TraceFrame {
+    /// How the frame reads in a rendered trace, e.g. "function `foo`"
+    fn describe(&self) -> String {
+        match self.function.as_str() {
+            "<program>" => "top level".to_string(),
+            "<anonymous>" => "anonymous function".to_string(),
+            name => format!("function `{name}`"),
+        }
+    }
 }
 
 impl std::fmt::Display for VmError {
@@ -24,6 +56,8 @@ impl VmError {
             message,
             span: None,
             file: None,
+            trace: Vec::new(),
+            sources: Vec::new(),
         }
     }
 
@@ -33,9 +67,24 @@ impl VmError {
             message,
             span,
             file,
+            trace: Vec::new(),
+            sources: Vec::new(),
         }
     }
 
+    /// Attach the call stack that was active when the error was raised
+    pub fn with_trace(mut self, trace: Vec<TraceFrame>) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// The text to resolve spans in `file` against: a recorded module
+    /// source, or `main` (the source of the program being run)
+    pub fn source_for<'a>(&'a self, file: Option<&str>, main: &'a str) -> &'a str {
+        file.and_then(|f| self.sources.iter().find(|(path, _)| path == f))
+            .map_or(main, |(_, source)| source)
+    }
+
     /// Format error without source code (brief format)
     pub fn format_display(&self) -> String {
         if let (Some(file), Some(_span)) = (&self.file, &self.span) {
@@ -53,108 +102,47 @@ impl VmError {
         }
     }
 
-    /// Format error with source code context (like parser diagnostics)
+    /// Format error with source code context, using the same snippet
+    /// renderer as parse and type diagnostics
     pub fn format_with_source(&self, source: &str) -> String {
-        if let (Some(span), Some(file)) = (self.span, self.file.as_ref()) {
-            let line_index = LineIndex::new(source);
-            let (start_line, start_col) = line_index.line_col(span.start);
-            let (end_line, end_col) = line_index.line_col(span.end);
-
-            let mut output = String::new();
-
-            // Header
-            output.push_str(&format!("error: {}\n", self.message));
-
-            // Location
-            output.push_str(&format!("  --> {}:{}:{}\n", file, start_line, start_col));
-
-            // Source snippet
-            output.push_str(&self.format_snippet(
-                source,
-                &line_index,
-                start_line,
-                start_col,
-                end_line,
-                end_col,
-            ));
-
-            output
-        } else {
-            self.format_display()
-        }
+        let formatted = match self.to_diagnostic() {
+            Some(diagnostic) => diagnostic.format(self.source_for(self.file.as_deref(), source)),
+            None => self.format_display(),
+        };
+        self.append_trace(formatted, source)
     }
 
-    fn format_snippet(
-        &self,
-        source: &str,
-        line_index: &LineIndex,
-        start_line: usize,
-        start_col: usize,
-        end_line: usize,
-        end_col: usize,
-    ) -> String {
-        let mut output = String::new();
-
-        // Determine line number width for padding
-        let max_line = end_line.max(start_line);
-        let line_num_width = max_line.to_string().len();
-
-        // Show lines with context (1 line before/after)
-        let context_start = start_line.saturating_sub(1).max(1);
-        let context_end = (end_line + 1).min(line_index.line_count());
-
-        output.push_str(&format!("{:width$} |\n", "", width = line_num_width));
-
-        for line_num in context_start..=context_end {
-            if let Some((line_start, line_end)) = line_index.line_range(line_num) {
-                let line_end = line_end.min(source.len());
-                let line_text = &source[line_start..line_end];
-
-                // Print line number and source
-                output.push_str(&format!("{line_num:line_num_width$} | {line_text}\n"));
-
-                // Print underline/caret for error span
-                if line_num >= start_line && line_num <= end_line {
-                    output.push_str(&format!("{:width$} | ", "", width = line_num_width));
-
-                    let line_span_start = if line_num == start_line {
-                        start_col - 1
-                    } else {
-                        0
-                    };
-                    let line_span_end = if line_num == end_line {
-                        end_col - 1
-                    } else {
-                        line_text.chars().count()
-                    };
-
-                    // Add spaces up to start of error
-                    for _ in 0..line_span_start {
-                        output.push(' ');
-                    }
-
-                    // Add carets/tildes for error span
-                    let span_width = (line_span_end.saturating_sub(line_span_start)).max(1);
-                    if span_width == 1 {
-                        output.push('^');
-                    } else {
-                        for i in 0..span_width {
-                            if i == 0 {
-                                output.push('^');
-                            } else {
-                                output.push('~');
-                            }
-                        }
-                    }
-
-                    output.push('\n');
-                }
+    /// Follow `formatted` (the rendered error) with the stack trace, one
+    /// "at ..." line per frame, innermost first. Each frame is located in
+    /// its own file. Errors raised at the top level have no trace and are
+    /// returned unchanged.
+    pub fn append_trace(&self, formatted: String, source: &str) -> String {
+        if self.trace.is_empty() {
+            return formatted;
+        }
+        let mut out = formatted.trim_end_matches('\n').to_string();
+        for frame in &self.trace {
+            out.push_str("\n  at ");
+            out.push_str(&frame.describe());
+            if let (Some(file), Some(span)) = (&frame.file, frame.span) {
+                let loc = span.location(self.source_for(Some(file), source));
+                out.push_str(&format!(" ({file}:{}:{})", loc.line, loc.col));
             }
         }
+        out.push('\n');
+        out
+    }
 
-        output.push_str(&format!("{:width$} |\n", "", width = line_num_width));
-
-        output
+    /// Convert into a `Runtime` diagnostic. Errors without a span and file
+    /// have nothing to point at and return `None`.
+    pub fn to_diagnostic(&self) -> Option<Diagnostic> {
+        let (span, file) = (self.span?, self.file.as_ref()?);
+        Some(Diagnostic::error(
+            DiagnosticKind::Runtime,
+            self.message.clone(),
+            span,
+            file.clone(),
+        ))
     }
 }
 
@@ -245,4 +233,31 @@ mod tests {
         // Should have file location
         assert!(formatted.contains("math.luma"));
     }
+
+    #[test]
+    fn test_vm_error_to_diagnostic() {
+        let source = "let xs = [1]\nlet y = xs[5]\nlet z = 2\n";
+        let err = VmError::with_location(
+            "List index out of bounds".to_string(),
+            Some(Span::new(13, 27)), // The whole second statement, newline included
+            Some("script.luma".to_string()),
+        );
+
+        let diagnostic = err.to_diagnostic().unwrap();
+        assert_eq!(diagnostic.kind, DiagnosticKind::Runtime);
+        assert_eq!(diagnostic.filename, "script.luma");
+
+        let formatted = err.format_with_source(source);
+        assert!(formatted.contains("  --> script.luma:2:1"), "{formatted}");
+        assert!(
+            formatted.contains("2 | let y = xs[5]\n  | ^~~~~~~~~~~~~\n3 | let z = 2\n  |\n"),
+            "{formatted}"
+        );
+
+        assert!(
+            VmError::runtime("no location".to_string())
+                .to_diagnostic()
+                .is_none()
+        );
+    }
 }
@@ -29,18 +29,26 @@ impl VM {
                 Instruction::Pop => self.exec_pop()?,
                 Instruction::PopNPreserve(n) => self.exec_pop_n_preserve(n)?,
                 Instruction::Dup => self.exec_dup()?,
+                Instruction::Dup2 => self.exec_dup2()?,
                 Instruction::Add => self.exec_add()?,
                 Instruction::Sub => self.exec_sub()?,
                 Instruction::Mul => self.exec_mul()?,
                 Instruction::Div => self.exec_div()?,
                 Instruction::Mod => self.exec_mod()?,
+                Instruction::Pow => self.exec_pow()?,
                 Instruction::Neg => self.exec_neg()?,
                 Instruction::GetGlobal(idx) => self.exec_get_global(idx)?,
                 Instruction::SetGlobal(idx) => self.exec_set_global(idx)?,
                 Instruction::BuildList(n) => self.exec_build_list(n)?,
+                Instruction::BuildRange(inclusive) => self.exec_build_range(inclusive)?,
+                Instruction::RangeBounds(inclusive) => self.exec_range_bounds(inclusive)?,
                 Instruction::BuildTable(n) => self.exec_build_table(n)?,
+                Instruction::MergeTable => self.exec_merge_table()?,
+                Instruction::SpreadList => self.exec_spread_list()?,
                 Instruction::GetIndex => self.exec_get_index()?,
                 Instruction::GetProp(idx) => self.exec_get_prop(idx)?,
+                Instruction::MatchField(idx) => self.exec_match_field(idx)?,
+                Instruction::MatchList(len, at_least) => self.exec_match_list(len, at_least)?,
                 Instruction::GetLen => self.exec_get_len()?,
                 Instruction::SetIndex => self.exec_set_index()?,
                 Instruction::SetProp(idx) => self.exec_set_prop(idx)?,
@@ -56,11 +64,14 @@ impl VM {
                 Instruction::Not => self.exec_not()?,
                 Instruction::Jump(target) => self.exec_jump(target)?,
                 Instruction::JumpIfFalse(target) => self.exec_jump_if_false(target)?,
+                Instruction::MatchJump(idx) => self.exec_match_jump(idx)?,
+                Instruction::IterNext(target) => self.exec_iter_next(target)?,
                 Instruction::MakeFunction(idx) => self.exec_make_function(idx)?,
                 Instruction::Closure(idx) => self.exec_closure(idx)?,
                 Instruction::GetUpvalue(idx) => self.exec_get_upvalue(idx)?,
                 Instruction::SetUpvalue(idx) => self.exec_set_upvalue(idx)?,
                 Instruction::Call(arity) => self.exec_call(arity)?,
+                Instruction::CallList => self.exec_call_list()?,
                 Instruction::Return => {
                     if let Some(ret_val) = self.exec_return()? {
                         return Ok(ret_val);
@@ -80,7 +91,7 @@ impl VM {
             Some(Constant::Boolean(b)) => Value::Boolean(*b),
             Some(Constant::Null) => Value::Null,
             Some(Constant::Function(chunk)) => Value::Function {
-                chunk: chunk.clone(),
+                chunk: (**chunk).clone(),
                 arity: chunk.local_count as usize,
             },
             None => return Err(self._error("Bad const index".into())),
@@ -117,6 +128,15 @@ impl VM {
         }
     }
 
+    fn exec_dup2(&mut self) -> Result<(), VmError> {
+        let len = self.stack.len();
+        if len < 2 {
+            return Err(self._error("DUP2 stack underflow".into()));
+        }
+        self.stack.extend_from_within(len - 2..);
+        Ok(())
+    }
+
     // Arithmetic operations
     fn exec_add(&mut self) -> Result<(), VmError> {
         let b = self
@@ -199,6 +219,22 @@ impl VM {
         })
     }
 
+    fn exec_pow(&mut self) -> Result<(), VmError> {
+        let b = self
+            .stack
+            .pop()
+            .ok_or_else(|| self._error("POW right underflow".into()))?;
+        let a = self
+            .stack
+            .pop()
+            .ok_or_else(|| self._error("POW left underflow".into()))?;
+
+        operators::execute_binary_op(self, a, b, "__pow", |a, b| match (a, b) {
+            (Value::Number(x), Value::Number(y)) => Ok(Value::Number(x.powf(*y))),
+            _ => Err("Type mismatch".to_string()),
+        })
+    }
+
     fn exec_neg(&mut self) -> Result<(), VmError> {
         let a = self
             .stack
@@ -280,10 +316,71 @@ impl VM {
             };
             map.insert(key, val);
         }
-        self.stack.push(Value::Table(Rc::new(RefCell::new(map))));
+        self.stack
+            .push(Value::Table(Rc::new(RefCell::new(map.into()))));
         Ok(())
     }
 
+    /// Copy the fields of the spread table on top of the stack into the
+    /// table being built below it.
+    fn exec_merge_table(&mut self) -> Result<(), VmError> {
+        let source = self
+            .stack
+            .pop()
+            .ok_or_else(|| self._error("MERGE_TABLE underflow".into()))?;
+        let Some(Value::Table(target)) = self.stack.last() else {
+            return Err(self._error("MERGE_TABLE target must be a table".into()));
+        };
+        match source {
+            Value::Table(source) => {
+                let entries: Vec<(String, Value)> = source
+                    .borrow()
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                target.borrow_mut().extend(entries);
+                Ok(())
+            }
+            other => Err(self._error(format!("Cannot spread {other} into a table"))),
+        }
+    }
+
+    /// Append the elements of the spread list on top of the stack to the list
+    /// being built below it.
+    fn exec_spread_list(&mut self) -> Result<(), VmError> {
+        let source = self
+            .stack
+            .pop()
+            .ok_or_else(|| self._error("SPREAD_LIST underflow".into()))?;
+        let Some(Value::List(target)) = self.stack.last() else {
+            return Err(self._error("SPREAD_LIST target must be a list".into()));
+        };
+        match source {
+            Value::List(source) => {
+                let items = source.borrow().clone();
+                target.borrow_mut().extend(items);
+                Ok(())
+            }
+            other => Err(self._error(format!("Cannot spread {other} into a list"))),
+        }
+    }
+
+    /// Convert a numeric list index, rejecting fractional and negative values
+    /// instead of letting the cast truncate them.
+    fn list_index(&self, n: f64) -> Result<usize, VmError> {
+        if n.fract() != 0.0 || !n.is_finite() {
+            return Err(self._error(format!(
+                "list index must be an integer, got {}",
+                Value::Number(n)
+            )));
+        }
+        if n < 0.0 {
+            return Err(self._error("List index negative".into()));
+        }
+        // Indices beyond usize saturate and then fail the bounds check
+        Ok(n as usize)
+    }
+
     fn exec_get_index(&mut self) -> Result<(), VmError> {
         let index = self
             .stack
@@ -295,11 +392,7 @@ impl VM {
             .ok_or_else(|| self._error("GET_INDEX obj underflow".into()))?;
         match (obj, index) {
             (Value::List(arr), Value::Number(n)) => {
-                let i = n as i64;
-                if i < 0 {
-                    return Err(self._error("List index negative".into()));
-                }
-                let i = i as usize;
+                let i = self.list_index(n)?;
                 let borrowed = arr.borrow();
                 match borrowed.get(i) {
                     Some(v) => {
@@ -360,27 +453,48 @@ impl VM {
         }
     }
 
+    fn exec_match_field(&mut self, idx: usize) -> Result<(), VmError> {
+        let name = match self.chunk.constants.get(idx) {
+            Some(Constant::String(s)) => s.clone(),
+            _ => return Err(self._error("MATCH_FIELD expects string const".into())),
+        };
+        let value = self
+            .stack
+            .pop()
+            .ok_or_else(|| self._error("MATCH_FIELD underflow".into()))?;
+        let matched = matches!(&value, Value::Table(map) if map.borrow().contains_key(&name));
+        self.stack.push(Value::Boolean(matched));
+        Ok(())
+    }
+
+    fn exec_match_list(&mut self, len: usize, at_least: bool) -> Result<(), VmError> {
+        let value = self
+            .stack
+            .pop()
+            .ok_or_else(|| self._error("MATCH_LIST underflow".into()))?;
+        let matched = match &value {
+            Value::List(items) if at_least => items.borrow().len() >= len,
+            Value::List(items) => items.borrow().len() == len,
+            _ => false,
+        };
+        self.stack.push(Value::Boolean(matched));
+        Ok(())
+    }
+
     fn exec_get_len(&mut self) -> Result<(), VmError> {
         let obj = self
             .stack
             .pop()
             .ok_or_else(|| self._error("GET_LEN obj underflow".into()))?;
-        match obj {
-            Value::List(arr) => {
-                let borrowed = arr.borrow();
-                self.stack.push(Value::Number(borrowed.len() as f64));
-                Ok(())
-            }
-            Value::Table(map) => {
-                let borrowed = map.borrow();
-                self.stack.push(Value::Number(borrowed.len() as f64));
+        match obj.length() {
+            Some(len) => {
+                self.stack.push(Value::Number(len as f64));
                 Ok(())
             }
-            Value::String(s) => {
-                self.stack.push(Value::Number(s.len() as f64));
-                Ok(())
-            }
-            _ => Err(self._error("GET_LEN requires list, table, or string".into())),
+            None => Err(self._error(format!(
+                "Cannot take the length of {}; expected a List, Table or String",
+                operators::value_type_name(&obj)
+            ))),
         }
     }
 
@@ -400,11 +514,7 @@ impl VM {
 
         match (obj, index) {
             (Value::List(arr), Value::Number(n)) => {
-                let i = n as i64;
-                if i < 0 {
-                    return Err(self._error("List index negative".into()));
-                }
-                let i = i as usize;
+                let i = self.list_index(n)?;
                 let mut borrowed = arr.borrow_mut();
                 if i == borrowed.len() {
                     borrowed.push(value);
@@ -417,9 +527,15 @@ impl VM {
             }
             (Value::Table(map), Value::String(k)) => {
                 let mut borrowed = map.borrow_mut();
+                if borrowed.frozen {
+                    return Err(self._error("cannot mutate frozen table".into()));
+                }
                 borrowed.insert(k, value);
                 Ok(())
             }
+            (Value::String(_), _) => {
+                Err(self._error("strings are immutable; cannot assign to index".into()))
+            }
             _ => Err(self._error("SET_INDEX type error".into())),
         }
     }
@@ -441,6 +557,9 @@ impl VM {
         match obj {
             Value::Table(map) => {
                 let mut borrowed = map.borrow_mut();
+                if borrowed.frozen {
+                    return Err(self._error("cannot mutate frozen table".into()));
+                }
                 borrowed.insert(name, value);
                 Ok(())
             }
@@ -482,6 +601,69 @@ impl VM {
         Ok(())
     }
 
+    fn exec_build_range(&mut self, inclusive: bool) -> Result<(), VmError> {
+        let (start, end, count) = self.pop_range_bounds("BUILD_RANGE", inclusive)?;
+        // Bounds far apart could ask for more memory than exists, which
+        // would abort the process instead of raising an error
+        let count = count as usize;
+        let mut items = Vec::new();
+        if items.try_reserve_exact(count).is_err() {
+            return Err(self._error(format!(
+                "Range {start}..{end} is too large to build as a list ({count} elements); \
+                 iterate over it with `for` or use range() instead"
+            )));
+        }
+        items.extend((0..count).map(|i| Value::Number(start + i as f64)));
+        self.stack.push(Value::List(Rc::new(RefCell::new(items))));
+        Ok(())
+    }
+
+    /// Check a range a `for` loop counts through, leaving its start and how
+    /// many numbers it holds; the loop adds an index to the start
+    fn exec_range_bounds(&mut self, inclusive: bool) -> Result<(), VmError> {
+        let (start, _, count) = self.pop_range_bounds("RANGE_BOUNDS", inclusive)?;
+        self.stack.push(Value::Number(start));
+        self.stack.push(Value::Number(count));
+        Ok(())
+    }
+
+    /// Pop a range's end and start, which must be finite numbers, and count
+    /// the numbers from start up to end
+    fn pop_range_bounds(
+        &mut self,
+        name: &str,
+        inclusive: bool,
+    ) -> Result<(f64, f64, f64), VmError> {
+        let end = self
+            .stack
+            .pop()
+            .ok_or_else(|| self._error(format!("{name} end underflow")))?;
+        let start = self
+            .stack
+            .pop()
+            .ok_or_else(|| self._error(format!("{name} start underflow")))?;
+        let (Value::Number(start), Value::Number(end)) = (&start, &end) else {
+            return Err(self._error(format!(
+                "Range bounds must be numbers, got {} and {}",
+                operators::value_type_name(&start),
+                operators::value_type_name(&end)
+            )));
+        };
+        if !start.is_finite() || !end.is_finite() {
+            return Err(self._error(format!(
+                "Range bounds must be finite, got {start} and {end}"
+            )));
+        }
+        // Counting by index keeps huge bounds from stalling on `n + 1 == n`
+        let span = end - start;
+        let count = if inclusive {
+            span.floor() + 1.0
+        } else {
+            span.ceil()
+        };
+        Ok((*start, *end, count.max(0.0)))
+    }
+
     fn exec_slice_list(&mut self, start_index: usize) -> Result<(), VmError> {
         let arr = self
             .stack
@@ -600,11 +782,95 @@ impl VM {
         Ok(())
     }
 
+    fn exec_match_jump(&mut self, idx: usize) -> Result<(), VmError> {
+        let v = self
+            .stack
+            .pop()
+            .ok_or_else(|| self._error("MATCH_JUMP pop underflow".into()))?;
+        let Some(table) = self.chunk.jump_tables.get(idx) else {
+            return Err(self._error("MATCH_JUMP expects a jump table".into()));
+        };
+        self.ip = match v {
+            Value::Number(n) => table.target(n),
+            _ => table.default,
+        };
+        Ok(())
+    }
+
+    /// Advance a `for` loop: lists yield `list[i]`, and iterator tables have
+    /// `__next()` called for an Option. A `__next` that takes a parameter is
+    /// handed the iterator, so natives without closures can keep their state
+    /// in it. Any table with a `__next` key counts as an iterator; there is
+    /// no other marker. Jumps to `target` once exhausted.
+    fn exec_iter_next(&mut self, target: usize) -> Result<(), VmError> {
+        let len = self
+            .stack
+            .pop()
+            .ok_or_else(|| self._error("ITER_NEXT length underflow".into()))?;
+        let index = self
+            .stack
+            .pop()
+            .ok_or_else(|| self._error("ITER_NEXT index underflow".into()))?;
+        let iterable = self
+            .stack
+            .pop()
+            .ok_or_else(|| self._error("ITER_NEXT iterable underflow".into()))?;
+        let next = match (&iterable, index) {
+            // A list that grew during the loop stops at its starting length;
+            // one that shrank ends early
+            (Value::List(list), Value::Number(i)) => match len {
+                Value::Number(len) if i >= len => None,
+                _ => list.borrow().get(i as usize).cloned(),
+            },
+            (Value::Table(table), _) => {
+                let next_fn = table.borrow().get("__next").cloned();
+                let Some(next_fn) = next_fn else {
+                    return Err(
+                        self._error("Cannot iterate over a Table without a __next method".into())
+                    );
+                };
+                let args = match next_fn {
+                    Value::Function { arity: 1, .. }
+                    | Value::Closure { arity: 1, .. }
+                    | Value::NativeFunction { arity: 1, .. } => vec![iterable.clone()],
+                    _ => Vec::new(),
+                };
+                match self.call_value(next_fn, args)? {
+                    Value::Table(option) => {
+                        let option = option.borrow();
+                        if option.get("none").is_some_and(truthy) {
+                            None
+                        } else {
+                            Some(option.get("some").cloned().unwrap_or(Value::Null))
+                        }
+                    }
+                    other => {
+                        return Err(self._error(format!(
+                            "__next() must return an Option, got {}",
+                            operators::value_type_name(&other)
+                        )));
+                    }
+                }
+            }
+            _ => {
+                return Err(self._error(format!(
+                    "Cannot iterate over {}",
+                    operators::value_type_name(&iterable)
+                )));
+            }
+        };
+        match next {
+            Some(value) => self.stack.push(value),
+            None => self.ip = target,
+        }
+        Ok(())
+    }
+
     // Function operations
     fn exec_make_function(&mut self, idx: usize) -> Result<(), VmError> {
         let v = match self.chunk.constants.get(idx) {
             Some(Constant::Function(chunk)) => Value::Function {
-                chunk: chunk.clone(),
+                chunk: (**chunk).clone(),
                 arity: chunk.local_count as usize,
             },
             _ => {
@@ -617,7 +883,7 @@ impl VM {
 
     fn exec_closure(&mut self, idx: usize) -> Result<(), VmError> {
         let chunk = match self.chunk.constants.get(idx) {
-            Some(Constant::Function(chunk)) => chunk.clone(),
+            Some(Constant::Function(chunk)) => (**chunk).clone(),
             _ => {
                 return Err(self._error("CLOSURE expects function constant".into()));
             }
@@ -690,6 +956,30 @@ impl VM {
         Ok(())
     }
 
+    /// Build an arity mismatch error, naming the callee when the compiler
+    /// recorded it for this call site (falling back to the native name).
+    fn arity_error(&self, native_name: Option<&str>, expected: usize, got: usize) -> VmError {
+        let callee = match self.chunk.get_call_name(self.ip - 1).or(native_name) {
+            Some(name) => format!("function `{name}`"),
+            None => "anonymous function".to_string(),
+        };
+        let plural = if expected == 1 { "" } else { "s" };
+        self._error(format!(
+            "{callee} expected {expected} argument{plural}, got {got}"
+        ))
+    }
+
+    /// Call with the arguments collected in a list, for calls with spreads
+    fn exec_call_list(&mut self) -> Result<(), VmError> {
+        let Some(Value::List(args)) = self.stack.pop() else {
+            return Err(self._error("CALL_LIST arguments must be a list".into()));
+        };
+        let args = args.borrow().clone();
+        let arity = args.len();
+        self.stack.extend(args);
+        self.exec_call(arity)
+    }
+
     fn exec_call(&mut self, arity: usize) -> Result<(), VmError> {
         let callee_idx = self.stack.len() - arity - 1;
         let callee = self
@@ -704,10 +994,7 @@ impl VM {
                 arity: fn_arity,
             } => {
                 if arity != fn_arity {
-                    let fn_name = &fn_chunk.name;
-                    return Err(
-                        self._error(format!("Arity mismatch in function '{fn_name}': expected {fn_arity} argument(s), got {arity}"))
-                    );
+                    return Err(self.arity_error(None, fn_arity, arity));
                 }
                 let frame = CallFrame {
                     chunk: self.chunk.clone(),
@@ -731,10 +1018,7 @@ impl VM {
                 upvalues: fn_upvalues,
             } => {
                 if arity != fn_arity {
-                    let fn_name = &fn_chunk.name;
-                    return Err(
-                        self._error(format!("Arity mismatch in function '{fn_name}': expected {fn_arity} argument(s), got {arity}"))
-                    );
+                    return Err(self.arity_error(None, fn_arity, arity));
                 }
                 let frame = CallFrame {
                     chunk: self.chunk.clone(),
@@ -769,16 +1053,37 @@ impl VM {
                         "ffi.free_cstr",
                     ]
                     .contains(&name.as_str());
-                if name != "print" && !is_ffi_dispatch && arity != fn_arity {
-                    return Err(
-                        self._error(format!("Arity mismatch in function '{name}': expected {fn_arity} argument(s), got {arity}"))
-                    );
+                if let Some(accepted) = self.variadic_natives.get(&name) {
+                    if !accepted.contains(&arity) {
+                        let (min, max) = (*accepted.start(), *accepted.end());
+                        let expected = match (min, max) {
+                            (1, usize::MAX) => "at least 1 argument".to_string(),
+                            (_, usize::MAX) => format!("at least {min} arguments"),
+                            _ => format!("{min} to {max} arguments"),
+                        };
+                        return Err(self._error(format!(
+                            "function `{name}` expected {expected}, got {arity}"
+                        )));
+                    }
+                } else if !is_ffi_dispatch && arity != fn_arity {
+                    return Err(self.arity_error(Some(&name), fn_arity, arity));
                 }
                 let args: Vec<Value> = self.stack.drain(callee_idx + 1..).collect();
                 self.stack.pop();
 
                 if name == "into" {
                     self.exec_native_into(args)
+                } else if name == "at_exit" {
+                    match &args[0] {
+                        Value::Function { arity: 0, .. } | Value::Closure { arity: 0, .. } => {
+                            self.exit_hooks.push(args[0].clone());
+                            self.stack.push(Value::Null);
+                            Ok(())
+                        }
+                        other => Err(self._error(format!(
+                            "at_exit() expects a function without parameters, got {other}"
+                        ))),
+                    }
                 } else if name == "__method_into" {
                     // Method call: object.into(Type)
                     // Stack has: [object, target_type]
@@ -807,7 +1112,24 @@ impl VM {
                             "FFI dispatch not available. FFI function '{name}' cannot be called without stdlib."
                         )))
                     }
+                } else if let Some(&read) = self.input_natives.get(&name) {
+                    let result = super::input::call_input_native(
+                        read,
+                        self.stdin
+                            .as_mut()
+                            .map(|input| input.as_mut() as &mut dyn std::io::BufRead),
+                    )
+                    .map_err(|e| self._error(e))?;
+                    self.stack.push(result);
+                    Ok(())
+                } else if let Some(&func) = self.stateful_natives.get(&name) {
+                    let result = func(&mut self.native_state, &args).map_err(|e| self._error(e))?;
+                    self.stack.push(result);
+                    Ok(())
                 } else {
+                    if name == "process.exit" {
+                        self.run_exit_hooks()?;
+                    }
                     let func = self.native_functions.get(&name).ok_or_else(|| {
                         self._error(format!("Native function '{name}' not found"))
                     })?;
@@ -0,0 +1,25 @@
+//! Program input: which source the input natives registered with
+//! [`VM::register_input_native_function`](super::VM::register_input_native_function) read from.
+//!
+//! That is the input given with [`VM::set_stdin`](super::VM::set_stdin), or
+//! the process's stdin when none was given.
+
+use super::interpreter::InputNativeFunction;
+use super::value::Value;
+use std::io::{self, BufRead, Write};
+
+/// Run the input native `read` on `input`, or on the process's stdin when
+/// there is none
+pub(super) fn call_input_native(
+    read: InputNativeFunction,
+    input: Option<&mut dyn BufRead>,
+) -> Result<Value, String> {
+    match input {
+        Some(input) => read(input),
+        None => {
+            // Show any prompt written without a newline before blocking
+            let _ = io::stdout().flush();
+            read(&mut io::stdin().lock())
+        }
+    }
+}
@@ -22,18 +22,28 @@
 //! Runtime errors are returned as `VmError` with optional source location information
 //! for better error messages.
 
-use super::errors::VmError;
+use super::errors::{TraceFrame, VmError};
 use super::frames::CallFrame;
+use super::native_state::NativeState;
 use super::value::{Upvalue, Value};
 use crate::ast::Span;
-use crate::bytecode::ir::Chunk;
+use crate::bytecode::ir::{Chunk, Instruction};
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::rc::Rc;
 
 /// Type alias for native function signatures
 pub type NativeFunction = fn(&[Value]) -> Result<Value, String>;
 
+/// Type alias for natives that read the program's input, such as
+/// `io.read_line`; the VM hands them the input source to read from
+pub type InputNativeFunction = fn(&mut dyn std::io::BufRead) -> Result<Value, String>;
+
+/// Type alias for natives that keep state between calls, such as the
+/// `random` generator; the VM hands them its [`NativeState`]
+pub type StatefulNativeFunction = fn(&mut NativeState, &[Value]) -> Result<Value, String>;
+
 /// Type alias for FFI dispatch function (special handling for ffi.* functions)
 pub type FfiDispatchFunction = fn(&str, &[Value]) -> Result<Value, String>;
 
@@ -48,11 +58,28 @@ pub struct VM {
     pub upvalues: Vec<Upvalue>,
     pub captured_locals: HashMap<usize, Upvalue>,
     pub native_functions: HashMap<String, NativeFunction>,
+    /// Argument counts accepted by natives registered as variadic; every
+    /// other native takes exactly its declared arity
+    pub variadic_natives: HashMap<String, RangeInclusive<usize>>,
+    /// Natives that read the program's input, called with `stdin`
+    pub input_natives: HashMap<String, InputNativeFunction>,
+    /// Natives called with `native_state`
+    pub stateful_natives: HashMap<String, StatefulNativeFunction>,
+    /// State kept by stateful natives, private to this VM
+    pub native_state: NativeState,
     pub ffi_dispatch: Option<FfiDispatchFunction>,
     pub module_cache: Rc<RefCell<HashMap<String, Value>>>,
     pub loading_modules: Rc<RefCell<Vec<String>>>,
+    /// Source text of every imported `.luma` module, keyed by path, so
+    /// errors in their functions can be located
+    pub module_sources: Rc<RefCell<HashMap<String, Rc<str>>>>,
     pub current_file: Option<String>,
     pub source: Option<String>,
+    /// Functions registered with `at_exit`, run last-in first-out on exit
+    pub exit_hooks: Vec<Value>,
+    /// Where input natives such as `io.read_line` read from; the process's
+    /// stdin when unset
+    pub stdin: Option<Box<dyn std::io::BufRead>>,
 }
 
 impl VM {
@@ -65,37 +92,104 @@ impl VM {
     /// Create a new VM with the given chunk and file path (without stdlib initialization)
     /// For a fully initialized VM with stdlib, use luma_stdlib::init_vm()
     pub fn new_with_file(chunk: Chunk, current_file: Option<String>) -> Self {
+        // Scripts see their own path as `__file`, resolved when the file exists
+        let mut globals = HashMap::new();
+        if let Some(file) = &current_file {
+            let resolved = std::path::Path::new(file)
+                .canonicalize()
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_else(|_| file.clone());
+            globals.insert("__file".to_string(), Value::String(resolved));
+        }
         VM {
             stack: Vec::new(),
             ip: 0,
             chunk,
-            globals: HashMap::new(),
+            globals,
             base: 0,
             frames: Vec::new(),
             upvalues: Vec::new(),
             captured_locals: HashMap::new(),
             native_functions: HashMap::new(),
+            variadic_natives: HashMap::new(),
+            input_natives: HashMap::new(),
+            stateful_natives: HashMap::new(),
+            native_state: NativeState::default(),
             ffi_dispatch: None,
             module_cache: Rc::new(RefCell::new(HashMap::new())),
             loading_modules: Rc::new(RefCell::new(Vec::new())),
+            module_sources: Rc::new(RefCell::new(HashMap::new())),
             current_file,
             source: None,
+            exit_hooks: Vec::new(),
+            stdin: None,
         }
     }
 
+    /// Serve the program's stdin reads from `input`, e.g. a buffer in tests
+    /// or a pipe when embedding
+    pub fn set_stdin(&mut self, input: impl std::io::BufRead + 'static) {
+        self.stdin = Some(Box::new(input));
+    }
+
     /// Set the source code for error reporting
     pub fn set_source(&mut self, source: String) {
         self.source = Some(source);
     }
 
-    /// Get the current span based on IP
+    /// Get the span of the executing instruction; `ip` has already moved
+    /// past it by the time it runs
     pub(crate) fn _current_span(&self) -> Option<Span> {
-        self.chunk.get_span(self.ip)
+        self.chunk.get_span(self.ip.saturating_sub(1))
     }
 
-    /// Create a runtime error with current location
+    /// The file a chunk's spans point into: its own for functions of an
+    /// imported module, otherwise the file being run
+    fn chunk_file(&self, chunk: &Chunk) -> Option<String> {
+        chunk.file.clone().or_else(|| self.current_file.clone())
+    }
+
+    /// Create a runtime error with current location, and the call stack if
+    /// the error happened inside a function
     pub(crate) fn _error(&self, message: String) -> VmError {
-        VmError::with_location(message, self._current_span(), self.current_file.clone())
+        let mut error =
+            VmError::with_location(message, self._current_span(), self.chunk_file(&self.chunk))
+                .with_trace(self.stack_trace());
+        let module_sources = self.module_sources.borrow();
+        let files = error
+            .trace
+            .iter()
+            .filter_map(|frame| frame.file.as_ref())
+            .chain(error.file.as_ref());
+        let mut sources: Vec<(String, Rc<str>)> = files
+            .filter_map(|file| Some((file.clone(), module_sources.get(file)?.clone())))
+            .collect();
+        sources.sort();
+        sources.dedup_by(|a, b| a.0 == b.0);
+        error.sources = sources;
+        error
+    }
+
+    /// Describe the active calls, innermost first. Each caller is reported
+    /// at the statement making the call; `call_value` trampolines are skipped.
+    fn stack_trace(&self) -> Vec<TraceFrame> {
+        if self.frames.is_empty() {
+            return Vec::new();
+        }
+        let current = TraceFrame {
+            function: self.chunk.name.clone(),
+            span: self._current_span(),
+            file: self.chunk_file(&self.chunk),
+        };
+        let callers = self.frames.iter().rev().map(|frame| TraceFrame {
+            function: frame.chunk.name.clone(),
+            span: frame.chunk.get_span(frame.ip.saturating_sub(1)),
+            file: self.chunk_file(&frame.chunk),
+        });
+        std::iter::once(current)
+            .chain(callers)
+            .filter(|frame| frame.function != "<call>")
+            .collect()
     }
 
     /// Register a native function
@@ -108,6 +202,56 @@ impl VM {
         self.native_functions.insert(name.to_string(), func);
     }
 
+    /// Register a native function that accepts any argument count in `arity`,
+    /// like `range(start, stop, step?)`; an end of `usize::MAX` leaves the
+    /// count unbounded, as for `print`
+    pub fn register_variadic_native_function(
+        &mut self,
+        name: &str,
+        arity: RangeInclusive<usize>,
+        func: NativeFunction,
+    ) {
+        self.register_native_function(name, *arity.start(), func);
+        self.variadic_natives.insert(name.to_string(), arity);
+    }
+
+    /// Register a native without parameters that reads the program's input,
+    /// from the source given with `set_stdin` or else the process's stdin
+    pub fn register_input_native_function(&mut self, name: &str, func: InputNativeFunction) {
+        let native_val = Value::NativeFunction {
+            name: name.to_string(),
+            arity: 0,
+        };
+        self.globals.insert(name.to_string(), native_val);
+        self.input_natives.insert(name.to_string(), func);
+    }
+
+    /// Register a native that keeps state in this VM's `native_state`. It
+    /// accepts any argument count in `arity`, as for
+    /// [`register_variadic_native_function`](Self::register_variadic_native_function).
+    pub fn register_stateful_native_function(
+        &mut self,
+        name: &str,
+        arity: RangeInclusive<usize>,
+        func: StatefulNativeFunction,
+    ) {
+        let native_val = Value::NativeFunction {
+            name: name.to_string(),
+            arity: *arity.start(),
+        };
+        self.globals.insert(name.to_string(), native_val);
+        if arity.start() != arity.end() {
+            self.variadic_natives.insert(name.to_string(), arity);
+        }
+        self.stateful_natives.insert(name.to_string(), func);
+    }
+
+    /// Register a native that is only reached through values naming it, such
+    /// as an iterator's `__next`, without declaring it as a global
+    pub fn register_internal_native_function(&mut self, name: &str, func: NativeFunction) {
+        self.native_functions.insert(name.to_string(), func);
+    }
+
     /// Load and execute the prelude (standard library) from provided source
     pub fn load_prelude(&mut self, prelude_source: &str) -> Result<(), VmError> {
         let ast = match crate::parser::parse(prelude_source, "<prelude>") {
@@ -145,17 +289,30 @@ impl VM {
 
         match result {
             Ok(prelude_exports) => {
-                // Store the prelude exports table itself as 'prelude'
-                self.globals
-                    .insert("prelude".to_string(), prelude_exports.clone());
-
                 // Load all exported values into global scope for direct access
-                if let Value::Table(table_ref) = prelude_exports {
-                    let table = table_ref.borrow();
-                    for (name, value) in table.iter() {
+                if let Value::Table(table_ref) = &prelude_exports {
+                    for (name, value) in table_ref.borrow().iter() {
                         self.globals.insert(name.clone(), value.clone());
                     }
                 }
+
+                // Store the exports as 'prelude', merging with any earlier prelude's
+                // exports so several preludes can be layered
+                match (self.globals.get("prelude"), &prelude_exports) {
+                    (Some(Value::Table(existing)), Value::Table(new)) => {
+                        let merged: Vec<_> = new
+                            .borrow()
+                            .iter()
+                            .map(|(k, v)| (k.clone(), v.clone()))
+                            .collect();
+                        existing.borrow_mut().extend(merged);
+                    }
+                    (Some(Value::Table(_)), _) => {}
+                    _ => {
+                        self.globals
+                            .insert("prelude".to_string(), prelude_exports.clone());
+                    }
+                }
                 Ok(())
             }
             Err(e) => Err(VmError::runtime(format!(
@@ -169,6 +326,25 @@ impl VM {
         self.execute()
     }
 
+    /// Call a function value with the given arguments and return its result
+    pub fn call_value(&mut self, callee: Value, args: Vec<Value>) -> Result<Value, VmError> {
+        let argc = args.len();
+        self.stack.push(callee);
+        self.stack.extend(args);
+        let mut trampoline = Chunk::new_empty("<call>".to_string());
+        trampoline.instructions = vec![Instruction::Call(argc), Instruction::Halt];
+        self.eval(trampoline)
+    }
+
+    /// Run the `at_exit` hooks, most recently registered first. Each hook runs
+    /// at most once, even if a hook itself exits.
+    pub fn run_exit_hooks(&mut self) -> Result<(), VmError> {
+        while let Some(hook) = self.exit_hooks.pop() {
+            self.call_value(hook, Vec::new())?;
+        }
+        Ok(())
+    }
+
     /// Evaluate a chunk in the context of this VM's existing state
     pub fn eval(&mut self, chunk: Chunk) -> Result<Value, VmError> {
         let saved_chunk = std::mem::replace(&mut self.chunk, chunk);
@@ -192,6 +368,7 @@ impl VM {
 mod tests {
     use super::*;
     use crate::bytecode::compile::compile_program;
+    use crate::bytecode::ir::Constant;
     use crate::parser::parse;
 
     fn run_source(source: &str) -> Result<Value, VmError> {
@@ -208,6 +385,98 @@ mod tests {
         assert!(matches!(result, Value::Number(n) if (n - 42.0).abs() < f64::EPSILON));
     }
 
+    #[test]
+    fn test_vm_radix_literals() {
+        let result = run_source("0xff + 1").unwrap();
+        assert!(matches!(result, Value::Number(n) if n == 256.0));
+        let result = run_source("0o17 + 0b11").unwrap();
+        assert!(matches!(result, Value::Number(n) if n == 18.0));
+    }
+
+    #[test]
+    fn test_vm_power() {
+        let result = run_source("2 ** 10").unwrap();
+        assert!(matches!(result, Value::Number(n) if n == 1024.0));
+        let result = run_source("2 ** 3 ** 2").unwrap();
+        assert!(matches!(result, Value::Number(n) if n == 512.0));
+        let result = run_source("2 ** 0.5").unwrap();
+        assert!(matches!(result, Value::Number(n) if n == std::f64::consts::SQRT_2));
+    }
+
+    #[test]
+    fn test_vm_compound_assignment() {
+        let source = r#"
+            var sum = 0
+            var i = 1
+            while i <= 3 do
+                sum += i
+                i += 1
+            end
+            sum
+        "#;
+        let result = run_source(source).unwrap();
+        assert!(matches!(result, Value::Number(n) if n == 6.0));
+
+        let source = r#"
+            var t = { n = 2 }
+            var xs = [10, 20]
+            t.n *= 5
+            xs[1] -= 5
+            xs[0] /= 4
+            t.n %= 3
+            t.n + xs[0] + xs[1]
+        "#;
+        let result = run_source(source).unwrap();
+        assert!(matches!(result, Value::Number(n) if n == 18.5));
+    }
+
+    #[test]
+    fn test_vm_compound_assignment_evaluates_target_once() {
+        let source = r#"
+            var calls = 0
+            let xs = [1, 2]
+            let t = { n = 5 }
+            let idx = fn() do
+                calls = calls + 1
+                1
+            end
+            let table = fn() do
+                calls = calls + 10
+                t
+            end
+            xs[idx()] += 10
+            table().n *= 2
+            [calls, xs[1], t.n]
+        "#;
+        let result = run_source(source).unwrap();
+        assert_eq!(
+            result.to_string(),
+            "[11, 12, 10]",
+            "each part of the target should run once"
+        );
+    }
+
+    #[test]
+    fn test_vm_power_overload() {
+        let source = r#"
+            let V = { n = 3, __pow = fn(a: Any, b: Any) do a.n * 10 + b end }
+            V ** 2
+        "#;
+        let result = run_source(source).unwrap();
+        assert!(matches!(result, Value::Number(n) if n == 32.0));
+
+        let err = run_source("\"a\" ** 2").unwrap_err();
+        assert!(err.message.contains("no __pow method"), "{}", err.message);
+    }
+
+    #[test]
+    fn test_vm_digit_separators() {
+        let result = run_source("1_000 == 1000").unwrap();
+        assert!(matches!(result, Value::Boolean(true)));
+        let result = run_source("0xFF_FF - 2.718_5").unwrap();
+        assert!(matches!(result, Value::Number(n) if n == 65535.0 - 2.7185));
+    }
+
     #[test]
     fn test_vm_string() {
         let result = run_source("\"hello\"").unwrap();
@@ -319,6 +588,42 @@ mod tests {
         assert!(matches!(result, Value::Boolean(true)));
     }
 
+    #[test]
+    fn test_vm_collection_equality_and_ordering_agree() {
+        let check = |source: &str, expected: bool| {
+            assert_eq!(
+                run_source(source).unwrap(),
+                Value::Boolean(expected),
+                "{source}"
+            );
+        };
+        check("[1, 2] == [1, 2]", true);
+        check("{ a = 1 } == { a = 2 }", false);
+        check("{ a = 1, b = [2] } == { b = [2], a = 1 }", true);
+
+        // Lists order element-wise; a prefix comes first
+        check("[1, 2] < [1, 3]", true);
+        check("[2] > [1, 9]", true);
+        check("[1] < [1, 0]", true);
+        check("[[1, 2], [0]] < [[1, 3]]", true);
+        // <= and >= hold exactly when the lists are equal or ordered that way
+        check("[1, 2] <= [1, 2]", true);
+        check("[1, 2] >= [1, 2]", true);
+        check("[1, 2] < [1, 2]", false);
+
+        // NaN leaves lists unordered, like the numbers themselves, but a
+        // list is still equal to (and so <=) itself
+        check(
+            "let nan = 0 / 0\nlet a = [nan]\nlet b = [nan]\na <= b || a >= b",
+            false,
+        );
+        check("let nan = 0 / 0\nlet l = [nan]\nl == l && l <= l", true);
+
+        let err = run_source("let a = [\"a\"]\na < [\"b\"]").unwrap_err();
+        assert!(err.message.contains("no ordering"), "{err}");
+        assert_eq!(err.span.map(|s| s.start), Some(14), "{err:?}");
+    }
+
     // Logical operations
     #[test]
     fn test_vm_logical_not() {
@@ -379,6 +684,93 @@ mod tests {
         assert!(matches!(result, Value::Number(n) if (n - 42.0).abs() < f64::EPSILON));
     }
 
+    #[test]
+    fn test_vm_table_spread_later_entries_win() {
+        let result = run_source(
+            "let p = { x = 1, y = 2 }\nlet q = { x = 0, z = 0, ...p, z = 3 }\n[p.x, q.x, q.y, q.z]",
+        )
+        .unwrap();
+        let Value::List(items) = result else {
+            panic!("Expected list, got {result:?}");
+        };
+        let items: Vec<Value> = items.borrow().clone();
+        assert_eq!(
+            items,
+            vec![
+                Value::Number(1.0),
+                Value::Number(1.0),
+                Value::Number(2.0),
+                Value::Number(3.0)
+            ]
+        );
+
+        let err = run_source("let n = 5\n{ ...n }").unwrap_err();
+        assert!(err.message.contains("Cannot spread 5"), "{err:?}");
+    }
+
+    #[test]
+    fn test_vm_list_spread() {
+        let result = run_source("[...[1, 2], 3] == [1, 2, 3]").unwrap();
+        assert_eq!(result, Value::Boolean(true));
+
+        let result = run_source("let a = [2, 3]\n[...[], 1, ...a, 4, ...a]").unwrap();
+        let Value::List(items) = result else {
+            panic!("Expected list, got {result:?}");
+        };
+        let items: Vec<Value> = items.borrow().clone();
+        assert_eq!(
+            items,
+            [1.0, 2.0, 3.0, 4.0, 2.0, 3.0].map(Value::Number).to_vec()
+        );
+
+        let err = run_source("let n = 5\n[...n]").unwrap_err();
+        assert!(err.message.contains("Cannot spread 5"), "{err:?}");
+    }
+
+    #[test]
+    fn test_vm_table_spread_has_both_keys() {
+        let result = run_source("let t = {...{a = 1}, b = 2}\n[t.a, t.b]").unwrap();
+        let Value::List(items) = result else {
+            panic!("Expected list, got {result:?}");
+        };
+        let items: Vec<Value> = items.borrow().clone();
+        assert_eq!(items, vec![Value::Number(1.0), Value::Number(2.0)]);
+    }
+
+    #[test]
+    fn test_vm_call_spread() {
+        let result = run_source(
+            "let f = fn(a: Number, b: Number, c: Number): Number do a * 100 + b * 10 + c end\nlet args = [2, 3]\nf(1, ...args)",
+        )
+        .unwrap();
+        assert_eq!(result, Value::Number(123.0));
+
+        let result = run_source(
+            "let t = { n = 1, add = fn(self: Table, a: Number, b: Number): Number do self.n + a + b end }\nt:add(...[2, 3])",
+        )
+        .unwrap();
+        assert_eq!(result, Value::Number(6.0));
+
+        let err =
+            run_source("let f = fn(a: Number, b: Number): Number do a end\nf(...[1])").unwrap_err();
+        assert!(
+            err.message.contains("expected 2 arguments, got 1"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn test_vm_pipeline() {
+        let result = run_source(
+            "let double = fn(x: Number): Number do x * 2 end\n\
+             let add = fn(x: Number, y: Number): Number do x + y end\n\
+             let square = fn(x: Number): Number do x * x end\n\
+             3 |> double |> add(1) |> square",
+        )
+        .unwrap();
+        assert_eq!(result, Value::Number(49.0));
+    }
+
     #[test]
     fn test_vm_table_index_access() {
         let result = run_source("let t = { x = 42 }\nt[\"x\"]").unwrap();
@@ -398,6 +790,90 @@ mod tests {
         assert!(matches!(result, Value::Number(n) if (n - 10.0).abs() < f64::EPSILON));
     }
 
+    #[test]
+    fn test_vm_ternary() {
+        let result = run_source("true ? 1 : 2").unwrap();
+        assert!(matches!(result, Value::Number(n) if n == 1.0));
+        let source = r#"
+            let pick = fn(n: Number) do n < 0 ? "neg" : n == 0 ? "zero" : "pos" end
+            pick(-1) + pick(0) + pick(5)
+        "#;
+        let result = run_source(source).unwrap();
+        assert!(matches!(result, Value::String(ref s) if s == "negzeropos"));
+    }
+
+    #[test]
+    fn test_vm_for_over_range() {
+        let count = |range: &str| {
+            let source = format!("var n = 0\nfor i in {range} do n += 1 end\nn");
+            match run_source(&source).unwrap() {
+                Value::Number(n) => n,
+                other => panic!("Expected number, got {other:?}"),
+            }
+        };
+        assert_eq!(count("0..10"), 10.0);
+        assert_eq!(count("0..0"), 0.0);
+        assert_eq!(count("5..0"), 0.0);
+        assert_eq!(count("1..=3"), 3.0);
+        assert_eq!(count("3..=3"), 1.0);
+
+        let result = run_source("var sum = 0\nfor i in 1..=4 do sum += i end\nsum").unwrap();
+        assert!(matches!(result, Value::Number(n) if n == 10.0));
+    }
+
+    #[test]
+    fn test_vm_range_value() {
+        let result = run_source("let n = 2\n0.5..n + 1").unwrap();
+        assert_eq!(result.to_string(), "[0.5, 1.5, 2.5]");
+        let result = run_source("[5..0, 1..=1]").unwrap();
+        assert_eq!(result.to_string(), "[[], [1]]");
+        let err = run_source("let f = fn(x: Any) do x..2 end\nf(\"a\")").unwrap_err();
+        assert!(
+            err.message.contains("Range bounds must be numbers"),
+            "{}",
+            err.message
+        );
+    }
+
+    #[test]
+    fn test_vm_huge_range_value_is_an_error() {
+        let err = run_source("let r = 0..1000000000000000").unwrap_err();
+        assert!(
+            err.message.contains("too large to build as a list"),
+            "{}",
+            err.message
+        );
+        // A `for` loop counts through the bounds without building a list
+        let result = run_source(
+            "var n = 0\nfor i in 0..1000000000000000 do\n  n = i\n  if i == 3 do break end\nend\nn",
+        )
+        .unwrap();
+        assert_eq!(result, Value::Number(3.0));
+    }
+
+    #[test]
+    fn test_vm_for_over_range_checks_bounds_like_a_range_value() {
+        for (bounds, message) in [
+            ("0..(1 / 0)", "Range bounds must be finite, got 0 and inf"),
+            (
+                "\"a\"..3",
+                "Range bounds must be numbers, got String and Number",
+            ),
+        ] {
+            let value = run_source(&format!("let r = {bounds}")).unwrap_err();
+            let looped = run_source(&format!("for i in {bounds} do end")).unwrap_err();
+            assert_eq!(value.message, message);
+            assert_eq!(looped.message, message);
+        }
+
+        // Past 2^53, `i + 1 == i`; counting by index still ends the loop
+        let result = run_source(
+            "var n = 0\nfor i in 9007199254740992..9007199254740994 do\n  n = n + 1\nend\nn",
+        )
+        .unwrap();
+        assert_eq!(result, Value::Number(2.0));
+    }
+
     #[test]
     fn test_vm_while_loop() {
         let code = r#"
@@ -478,6 +954,80 @@ mod tests {
         assert!(matches!(result, Value::Number(n) if (n - 3.0).abs() < f64::EPSILON));
     }
 
+    #[test]
+    fn test_vm_closures_share_one_cell_across_nesting_levels() {
+        // `innermost` captures `count` through two intermediate closures; every
+        // closure must read and write the same cell
+        let code = r#"
+            let make = fn(): Any do
+                var count = 0
+                let bump = fn(): Any do
+                    fn(): Any do
+                        fn(): Number do
+                            count = count + 1
+                            count
+                        end
+                    end
+                end
+                let read = fn(): Number do count end
+                let add_ten = fn(): Number do
+                    count = count + 10
+                    count
+                end
+                { bump = bump, read = read, add_ten = add_ten }
+            end
+            let c = make()
+            let step = c.bump()()
+            step()
+            step()
+            c.add_ten()
+            let other = c.bump()()
+            other()
+            [c.read(), step(), c.read()]
+        "#;
+        let result = run_source(code).unwrap();
+        assert_eq!(
+            result,
+            Value::List(Rc::new(RefCell::new(vec![
+                Value::Number(13.0),
+                Value::Number(14.0),
+                Value::Number(14.0),
+            ])))
+        );
+    }
+
+    #[test]
+    fn test_vm_closure_sees_reassignment_after_capture() {
+        let code = r#"
+            let outer = fn(): Any do
+                var n = 0
+                let mid = fn(): Any do
+                    fn(): Number do
+                        n = n + 1
+                        n
+                    end
+                end
+                let a = mid()
+                let b = mid()
+                a()
+                b()
+                n = n * 10
+                let seen = a()
+                [seen, b(), n]
+            end
+            outer()
+        "#;
+        let result = run_source(code).unwrap();
+        assert_eq!(
+            result,
+            Value::List(Rc::new(RefCell::new(vec![
+                Value::Number(21.0),
+                Value::Number(22.0),
+                Value::Number(22.0),
+            ])))
+        );
+    }
+
     // Complex expression tests
     #[test]
     fn test_vm_operator_precedence() {
@@ -498,6 +1048,158 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_vm_string_index_assignment_rejected() {
+        let err = run_source("var s = \"abc\"\ns[0] = \"x\"").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("strings are immutable; cannot assign to index"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_vm_assignment_evaluates_target_then_value_once() {
+        let source = r#"
+var log = ""
+let t = { a = { b = 0 } }
+let xs = [0, 0]
+let base = fn() do
+  log = log + "t"
+  return t
+end
+let f = fn() do
+  log = log + "f"
+  return 7
+end
+let list = fn() do
+  log = log + "x"
+  return xs
+end
+let i = fn() do
+  log = log + "i"
+  return 1
+end
+let g = fn() do
+  log = log + "g"
+  return 9
+end
+base().a.b = f()
+list()[i()] = g()
+[log, t.a.b, xs[0], xs[1]]
+"#;
+        match run_source(source).unwrap() {
+            Value::List(items) => {
+                let items = items.borrow();
+                assert!(
+                    matches!(&items[0], Value::String(s) if s == "tfxig"),
+                    "{}",
+                    items[0]
+                );
+                assert!(matches!(items[1], Value::Number(n) if n == 7.0));
+                assert!(matches!(items[2], Value::Number(n) if n == 0.0));
+                assert!(matches!(items[3], Value::Number(n) if n == 9.0));
+            }
+            other => panic!("Expected list, got {other}"),
+        }
+    }
+
+    #[test]
+    fn test_vm_nested_index_assignment_order() {
+        let source = r#"
+var log = ""
+let grid = [[0, 0], [0, 0]]
+let at = fn(label: String, n: Number) do
+  log = log + label
+  return n
+end
+grid[at("r", 1)][at("c", 0)] = at("v", 5)
+[log, grid[1][0]]
+"#;
+        match run_source(source).unwrap() {
+            Value::List(items) => {
+                let items = items.borrow();
+                assert!(
+                    matches!(&items[0], Value::String(s) if s == "rcv"),
+                    "{}",
+                    items[0]
+                );
+                assert!(matches!(items[1], Value::Number(n) if n == 5.0));
+            }
+            other => panic!("Expected list, got {other}"),
+        }
+    }
+
+    #[test]
+    fn test_vm_error_trace_names_nested_calls() {
+        let source = "let inner = fn(xs: List(Number)) do\n  return xs[5]\nend\nlet outer = fn() do\n  return inner([1])\nend\nouter()";
+        let program = parse(source, "test.luma").unwrap();
+        let mut vm = VM::new_with_file(compile_program(&program), Some("test.luma".into()));
+        let err = vm.run().unwrap_err();
+
+        let names: Vec<&str> = err.trace.iter().map(|f| f.function.as_str()).collect();
+        assert_eq!(names, ["inner", "outer", "<program>"]);
+
+        let formatted = err.format_with_source(source);
+        assert!(formatted.contains("  --> test.luma:2:3"), "{formatted}");
+        assert!(
+            formatted.ends_with(
+                "  at function `inner` (test.luma:2:3)\n  at function `outer` (test.luma:5:3)\n  at top level (test.luma:7:1)\n"
+            ),
+            "{formatted}"
+        );
+    }
+
+    #[test]
+    fn test_vm_top_level_error_has_no_trace() {
+        let err = run_source("let xs = [1]\nxs[5]").unwrap_err();
+        assert!(err.trace.is_empty());
+    }
+
+    #[test]
+    fn test_vm_arity_error_names_function() {
+        let err =
+            run_source("let add = fn(a: Number, b: Number) do return a + b end\nadd(1, 2, 3)")
+                .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("function `add` expected 2 arguments, got 3"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_vm_arity_error_anonymous_callee() {
+        let err = run_source("let fs = [fn(a: Number) do return a end]\nfs[0](1, 2)").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("anonymous function expected 1 argument, got 2"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_vm_fractional_list_index_rejected() {
+        let err = run_source("let arr = [1, 2, 3]\narr[1.9]").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("list index must be an integer, got 1.9"),
+            "{err}"
+        );
+
+        let err = run_source("var arr = [1, 2, 3]\narr[0.5] = 9").unwrap_err();
+        assert!(
+            err.to_string().contains("list index must be an integer"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_vm_integral_float_list_index_allowed() {
+        let result = run_source("let arr = [1, 2, 3]\narr[1.0]").unwrap();
+        assert_eq!(result, Value::Number(2.0));
+    }
+
     #[test]
     fn test_vm_division_by_zero() {
         let result = run_source("10 / 0").unwrap();
@@ -510,4 +1212,377 @@ mod tests {
         let result = run_source("[1, 2, 3][10]");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_vm_enum_constructs_tagged_tables() {
+        let source = r#"
+            type Shape = Circle { r: Number } | Square { s: Number }
+            let c = Shape.Circle(2)
+            let s = Shape.Square(3)
+            if c.__tag == "Circle" && s.__tag == "Square" do c.r + s.s else do 0 end
+        "#;
+        let result = run_source(source).unwrap();
+        assert!(matches!(result, Value::Number(n) if (n - 5.0).abs() < f64::EPSILON));
+    }
+
+    #[test]
+    fn test_vm_enum_match_selects_variant_arm() {
+        let source = r#"
+            type Shape = Circle { r: Number } | Square { s: Number }
+            let area = fn(shape: Any): Number do
+                match shape do
+                    Circle { r } do return 3 * r * r end
+                    Square { s } do return s * s end
+                end
+            end
+            area(Shape.Circle(2)) + area(Shape.Square(3))
+        "#;
+        let result = run_source(source).unwrap();
+        assert!(matches!(result, Value::Number(n) if (n - 21.0).abs() < f64::EPSILON));
+    }
+
+    #[test]
+    fn test_vm_nested_destructuring() {
+        let source = r#"
+            let shape = { pos = { x = 3, y = 4 }, tags = [[1, 2], 5] }
+            let { pos: { x, y: py }, tags: [[a, b], c] } = shape
+            let inner = fn(): Number do
+                let { pos: { x: lx }, tags: [_, ...rest] } = shape
+                return lx * rest[0]
+            end
+            [x, py, a + b + c, inner()]
+        "#;
+        let Value::List(items) = run_source(source).unwrap() else {
+            panic!("Expected list");
+        };
+        let values: Vec<String> = items.borrow().iter().map(|v| v.to_string()).collect();
+        assert_eq!(values, ["3", "4", "8", "15"]);
+    }
+
+    #[test]
+    fn test_vm_match_nested_structural_patterns() {
+        let source = r#"
+            let describe = fn(v: Any): Number do
+                return match v do
+                    { kind: "circle", r } do r end
+                    { pos: { x, y } } do x + y end
+                    [a, [b, c]] do a + b + c end
+                    [a, ...rest] do 100 + a end
+                    _ do 0 end
+                end
+            end
+            [
+                describe({ kind = "circle", r = 2 }),
+                describe({ kind = "square", pos = { x = 1, y = 2 } }),
+                describe({ pos = 5 }),
+                describe([1, [2, 3]]),
+                describe([1, [2]]),
+                describe(7)
+            ]
+        "#;
+        let Value::List(items) = run_source(source).unwrap() else {
+            panic!("Expected list");
+        };
+        let values: Vec<String> = items.borrow().iter().map(|v| v.to_string()).collect();
+        assert_eq!(values, ["2", "3", "0", "6", "101", "0"]);
+    }
+
+    #[test]
+    fn test_vm_break_in_loop_with_nested_function() {
+        let source = r#"
+            var i = 0
+            while true do
+                let step = fn(x: Number): Number do
+                    var j = 0
+                    while true do
+                        j = j + 1
+                        if j == 2 do break end
+                    end
+                    x + j
+                end
+                i = step(i)
+                if i >= 6 do break end
+            end
+            i
+        "#;
+        assert_eq!(run_source(source).unwrap(), Value::Number(6.0));
+    }
+
+    #[test]
+    fn test_vm_match_default_arm_binds_scrutinee() {
+        let source = r#"
+            let describe = fn(v: Number): Number do
+                return match v do
+                    0 do -1 end
+                    _ as n do n * 10 end
+                end
+            end
+            var other = 0
+            match 4 do
+                1 do other = -1 end
+                rest do other = rest end
+            end
+            describe(0) + describe(3) + other
+        "#;
+        assert_eq!(run_source(source).unwrap(), Value::Number(33.0));
+    }
+
+    #[test]
+    fn test_vm_dense_literal_match_uses_jump_table() {
+        let digits = "let name = fn(d: Number): String do\n  return match d do\n    0 do \"zero\" end\n    1 do \"one\" end\n    2 do \"two\" end\n    3 do \"three\" end\n    4 do \"four\" end\n    5 do \"five\" end\n    6 do \"six\" end\n    7 do \"seven\" end\n    8 do \"eight\" end\n    9 do \"nine\" end\n    _ do \"other\" end\n  end\nend\n";
+        let uses_jump_table = |source: &str| {
+            let chunk = compile_program(&parse(source, "test.luma").unwrap());
+            chunk.constants.iter().any(|c| {
+                matches!(c, Constant::Function(f)
+                    if f.instructions.iter().any(|i| matches!(i, Instruction::MatchJump(_))))
+            })
+        };
+        assert!(uses_jump_table(digits));
+
+        for (arg, expected) in [
+            ("0", "zero"),
+            ("4", "four"),
+            ("9", "nine"),
+            ("4.0", "four"),
+            ("4.5", "other"),
+            ("10", "other"),
+            ("-1", "other"),
+            ("\"4\"", "other"),
+        ] {
+            let source = format!("{digits}name({arg})");
+            assert_eq!(
+                run_source(&source).unwrap(),
+                Value::String(expected.to_string()),
+                "name({arg})"
+            );
+        }
+
+        // Sparse literals keep the sequential equality tests
+        let sparse = "let f = fn(d: Number): Number do\n  return match d do\n    1 do 1 end\n    100 do 2 end\n    5000 do 3 end\n    90000 do 4 end\n    _ do 0 end\n  end\nend\nf(5000)";
+        assert!(!uses_jump_table(sparse));
+        assert_eq!(run_source(sparse).unwrap(), Value::Number(3.0));
+    }
+
+    #[test]
+    fn test_vm_match_scrutinee_evaluated_once() {
+        // Every arm tests the cached scrutinee, so a side-effecting
+        // expression runs once no matter which arm (if any) matches
+        let source = r#"
+            var calls = 0
+            let compute = fn(): Number do
+                calls = calls + 1
+                return 3
+            end
+            match compute() do
+                1 do calls = calls + 100 end
+                2 do calls = calls + 100 end
+                3 do calls = calls + 10 end
+                _ do calls = calls + 100 end
+            end
+            let label = match compute() do
+                0 do "zero" end
+                1 do "one" end
+                _ do "many" end
+            end
+            match compute() do
+                7 do calls = calls + 100 end
+                8 do calls = calls + 100 end
+            end
+            if label == "many" do calls else do -1 end
+        "#;
+        let result = run_source(source).unwrap();
+        assert_eq!(result, Value::Number(13.0));
+    }
+
+    #[test]
+    fn test_vm_locals_after_statement_values_keep_their_slots() {
+        // Earlier if/match statements must not leave values that shift the
+        // slots of locals declared after them
+        let source = r#"
+            let f = fn(x: Number): Number do
+                if x > 0 do
+                    let unused = 1
+                end
+                match x do
+                    1 do let other = 2 end
+                end
+                let y = x * 10
+                return y
+            end
+            var total = 0
+            var n = 0
+            while n < 3 do
+                n = n + 1
+                if n == 2 do
+                    continue
+                end
+                let twice = n * 2
+                total = total + twice
+            end
+            f(1) + f(-1) + total
+        "#;
+        // 10 - 10 + (2 + 6)
+        let result = run_source(source).unwrap();
+        assert_eq!(result, Value::Number(8.0));
+    }
+
+    #[test]
+    fn test_vm_hoisted_loop_invariants_keep_semantics() {
+        let code = r#"
+            let a = 2
+            let b = 3
+            var k = 1
+            var i = 0
+            var t = 0
+            while i < 3 do
+                t = t + a * b + k * 10
+                k = k + 1
+                i = i + 1
+            end
+            var j = 0
+            do
+                t = t - (a + b)
+                j = j + 1
+            while j < 2 end
+            t
+        "#;
+        // 3 * 6 + (10 + 20 + 30) - 2 * 5
+        let result = run_source(code).unwrap();
+        assert!(matches!(result, Value::Number(n) if (n - 68.0).abs() < f64::EPSILON));
+    }
+
+    #[test]
+    fn test_vm_overloaded_operator_not_hoisted() {
+        // `v + 1` runs `__add` once per iteration, and never for a loop that does not run
+        let code = r#"
+            let v = {n = 0, __add = fn(self: Table, o: Number): Table do
+                self.n = self.n + o
+                self
+            end}
+            var i = 0
+            while i < 3 do
+                let w = v + 1
+                i = i + 1
+            end
+            while i < 0 do
+                let w = v + 100
+            end
+            for x in 0..0 do
+                let w = v + 100
+            end
+            v.n
+        "#;
+        let result = run_source(code).unwrap();
+        assert!(matches!(result, Value::Number(n) if (n - 3.0).abs() < f64::EPSILON));
+    }
+
+    #[test]
+    fn test_vm_hoisted_invariant_in_untaken_branch() {
+        let code = r#"
+            let v = {n = 0, __add = fn(self: Table, o: Number): Table do
+                self.n = self.n + o
+                self
+            end}
+            let a = 2
+            var i = 0
+            var t = 0
+            while i < 4 do
+                if i > 10 do
+                    t = v + a * 3
+                end
+                t = a * 3
+                i = i + 1
+            end
+            [v.n, t]
+        "#;
+        let result = run_source(code).unwrap();
+        let Value::List(items) = result else {
+            panic!("expected a list");
+        };
+        assert_eq!(
+            items.borrow().clone(),
+            vec![Value::Number(0.0), Value::Number(6.0)]
+        );
+    }
+
+    #[test]
+    fn test_vm_hoisted_loop_invariant_with_break() {
+        let code = r#"
+            let limit = 4
+            var i = 0
+            let found = while true do
+                var j = 0
+                while j < limit - 1 do
+                    if i * j > limit + 2 do
+                        break level 2 i + j
+                    end
+                    j = j + 1
+                end
+                i = i + 1
+            end
+            found
+        "#;
+        let result = run_source(code).unwrap();
+        // First product above 6 with j < 3 is i = 4, j = 2
+        assert!(matches!(result, Value::Number(n) if (n - 6.0).abs() < f64::EPSILON));
+    }
+
+    #[test]
+    fn test_vm_break_number_leaves_that_many_loops() {
+        let code = r#"
+            var outer = 0
+            while outer < 3 do
+                outer = outer + 1
+                while true do
+                    break 2
+                end
+            end
+            outer
+        "#;
+        let result = run_source(code).unwrap();
+        assert!(matches!(result, Value::Number(n) if (n - 1.0).abs() < f64::EPSILON));
+    }
+
+    #[test]
+    fn test_vm_while_expression_breaks_with_value() {
+        let source = r#"
+            let xs = [3, 8, 12, 5]
+            var i = 0
+            let found = while i < 4 do
+                if xs[i] > 10 do
+                    break xs[i]
+                end
+                i = i + 1
+            end
+            found
+        "#;
+        let result = run_source(source).unwrap();
+        assert!(matches!(result, Value::Number(n) if (n - 12.0).abs() < f64::EPSILON));
+    }
+
+    #[test]
+    fn test_vm_loop_expression_without_break_value_is_null() {
+        let result = run_source("let x = while false do end\nx").unwrap();
+        assert!(matches!(result, Value::Null));
+    }
+
+    #[test]
+    fn test_vm_labeled_break_value_exits_outer_loop() {
+        let source = r#"
+            var a = 0
+            let pair = while a < 3 do
+                a = a + 1
+                var b = 0
+                while b < 3 do
+                    b = b + 1
+                    if a * b == 6 do
+                        break level 2 a * 10 + b
+                    end
+                end
+            end
+            pair
+        "#;
+        let result = run_source(source).unwrap();
+        assert!(matches!(result, Value::Number(n) if (n - 23.0).abs() < f64::EPSILON));
+    }
 }
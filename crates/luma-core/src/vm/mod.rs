@@ -2,8 +2,10 @@ mod errors;
 mod execute;
 mod frames;
 mod interpreter;
+mod native_state;
 mod stack;
 
+pub mod input;
 pub mod modules;
 pub mod operators;
 pub mod value;
@@ -11,4 +13,5 @@ pub mod value;
 pub use errors::*;
 pub use frames::*;
 pub use interpreter::*;
+pub use native_state::NativeState;
 pub use stack::*;
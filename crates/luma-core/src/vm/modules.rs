@@ -5,6 +5,7 @@
 //! - Loading and caching modules
 //! - Detecting circular dependencies
 //! - Module-level execution in isolated VM context
+//! - Importing `.json` and `.txt` files as data
 //!
 //! # Module Resolution
 //!
@@ -13,6 +14,12 @@
 //! 2. Relative paths are resolved relative to the current file's directory
 //! 3. If no current file exists, relative to the current working directory
 //!
+//! # Data Imports
+//!
+//! Files are dispatched by extension: `.luma` (or no extension) runs as a
+//! module, `.json` is decoded into lists/tables/primitives, and `.txt`
+//! yields its contents as a `String`. Other extensions are rejected.
+//!
 //! # Caching
 //!
 //! Modules are cached after first load using their canonical path as the key.
@@ -25,8 +32,10 @@
 
 use super::value::Value;
 use super::{VM, VmError};
+use std::cell::RefCell;
 use std::fs;
 use std::path::Path;
+use std::rc::Rc;
 
 /// Resolve an import path to an absolute canonical path
 ///
@@ -78,7 +87,65 @@ pub fn resolve_import_path(path: &str, current_file: Option<&String>) -> Result<
     Ok(canonical.to_string_lossy().to_string())
 }
 
-/// Load and execute a module, returning its result value
+/// Load an imported file, dispatching on its extension
+///
+/// # Arguments
+/// * `vm` - The VM instance making the import (for shared state)
+/// * `path` - Canonical path to the imported file
+///
+/// # Returns
+/// The module's result value, or the file's data for `.json`/`.txt` imports
+pub fn load_module(vm: &mut VM, path: &str) -> Result<Value, VmError> {
+    match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        None | Some("luma") => load_luma_module(vm, path),
+        Some("json") => load_data_file(vm, path, |source| {
+            let json = serde_json::from_str(source)
+                .map_err(|e| VmError::runtime(format!("Invalid JSON in '{path}': {e}")))?;
+            Ok(json_to_value(json))
+        }),
+        Some("txt") => load_data_file(vm, path, |source| Ok(Value::String(source.to_string()))),
+        Some(ext) => Err(VmError::runtime(format!(
+            "Cannot import '{path}': unsupported file type '.{ext}' (expected .luma, .json or .txt)"
+        ))),
+    }
+}
+
+/// Read a data file, convert its contents and cache the result like a module.
+fn load_data_file(
+    vm: &mut VM,
+    path: &str,
+    convert: impl FnOnce(&str) -> Result<Value, VmError>,
+) -> Result<Value, VmError> {
+    let source = fs::read_to_string(path)
+        .map_err(|e| VmError::runtime(format!("Failed to read '{path}': {e}")))?;
+    let value = convert(&source)?;
+    vm.module_cache
+        .borrow_mut()
+        .insert(path.to_string(), value.clone());
+    Ok(value)
+}
+
+/// Convert decoded JSON into Luma values: objects become tables, arrays lists.
+fn json_to_value(json: serde_json::Value) -> Value {
+    match json {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Boolean(b),
+        serde_json::Value::Number(n) => Value::Number(n.as_f64().unwrap_or(f64::NAN)),
+        serde_json::Value::String(s) => Value::String(s),
+        serde_json::Value::Array(items) => Value::List(Rc::new(RefCell::new(
+            items.into_iter().map(json_to_value).collect(),
+        ))),
+        serde_json::Value::Object(fields) => Value::Table(Rc::new(RefCell::new(
+            fields
+                .into_iter()
+                .map(|(k, v)| (k, json_to_value(v)))
+                .collect::<std::collections::HashMap<_, _>>()
+                .into(),
+        ))),
+    }
+}
+
+/// Load and execute a `.luma` module, returning its result value
 ///
 /// This function:
 /// 1. Marks the module as "loading" for circular dependency detection
@@ -89,13 +156,9 @@ pub fn resolve_import_path(path: &str, current_file: Option<&String>) -> Result<
 /// 6. Caches the result
 /// 7. Unmarks the module as loading
 ///
-/// # Arguments
-/// * `vm` - The VM instance making the import (for shared state)
-/// * `path` - Canonical path to the module file
-///
 /// # Returns
 /// The value returned by the module's execution
-pub fn load_module(vm: &mut VM, path: &str) -> Result<Value, VmError> {
+fn load_luma_module(vm: &mut VM, path: &str) -> Result<Value, VmError> {
     // Mark module as loading (for circular dependency detection)
     vm.loading_modules.borrow_mut().push(path.to_string());
 
@@ -130,8 +193,13 @@ pub fn load_module(vm: &mut VM, path: &str) -> Result<Value, VmError> {
             ))
         })?;
 
-        // Compile the module
-        let chunk = crate::bytecode::compile::compile_program(&ast);
+        // Compile the module; its functions may run in the importer's VM,
+        // so they carry their own file for error locations
+        let mut chunk = crate::bytecode::compile::compile_program(&ast);
+        chunk.set_file(path);
+        vm.module_sources
+            .borrow_mut()
+            .insert(path.to_string(), Rc::from(source.as_str()));
 
         // Create a new VM for the module with the module's path as current file
         let mut module_vm = VM::new_with_file(chunk, Some(path.to_string()));
@@ -139,6 +207,7 @@ pub fn load_module(vm: &mut VM, path: &str) -> Result<Value, VmError> {
         // Share the module cache and loading stack
         module_vm.module_cache = std::rc::Rc::clone(&vm.module_cache);
         module_vm.loading_modules = std::rc::Rc::clone(&vm.loading_modules);
+        module_vm.module_sources = std::rc::Rc::clone(&vm.module_sources);
 
         // Execute the module
         let module_value = module_vm
@@ -158,3 +227,118 @@ pub fn load_module(vm: &mut VM, path: &str) -> Result<Value, VmError> {
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::parse;
+    use crate::vm::VM;
+    use crate::vm::value::Value;
+
+    /// Write `contents` to a temp file and evaluate `import("<path>")`
+    fn import_file(name: &str, contents: &str) -> Result<Value, crate::vm::VmError> {
+        let path = std::env::temp_dir().join(format!("luma_import_{}_{name}", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        let source = format!("import(\"{}\")", path.display());
+        let program = parse(&source, "test.luma").expect("Parse failed");
+        let chunk = crate::bytecode::compile::compile_program(&program);
+        let result = VM::new(chunk).run();
+        let _ = std::fs::remove_file(path);
+        result
+    }
+
+    #[test]
+    fn test_import_json_yields_values() {
+        let value = import_file(
+            "config.json",
+            r#"{"name": "luma", "ports": [80, 443], "debug": false}"#,
+        )
+        .unwrap();
+        let Value::Table(fields) = value else {
+            panic!("Expected table, got {value:?}");
+        };
+        let fields = fields.borrow();
+        assert_eq!(fields["name"], Value::String("luma".into()));
+        assert_eq!(fields["debug"], Value::Boolean(false));
+        let Value::List(ports) = &fields["ports"] else {
+            panic!("Expected list");
+        };
+        assert_eq!(
+            *ports.borrow(),
+            vec![Value::Number(80.0), Value::Number(443.0)]
+        );
+    }
+
+    #[test]
+    fn test_import_text_yields_contents() {
+        let value = import_file("notes.txt", "line one\nline two\n").unwrap();
+        assert_eq!(value, Value::String("line one\nline two\n".into()));
+    }
+
+    #[test]
+    fn test_import_exposes_only_exported_names() {
+        let module = "let pi = 3\nlet add = fn(a: Number, b: Number) do a + b end\nlet secret = 42\nexport { add, pi }\n";
+        let value = import_file("exports.luma", module).unwrap();
+        let Value::Table(fields) = value else {
+            panic!("Expected table, got {value:?}");
+        };
+        let fields = fields.borrow();
+        let mut names: Vec<&str> = fields.keys().map(String::as_str).collect();
+        names.sort();
+        assert_eq!(names, ["add", "pi"]);
+        assert_eq!(fields["pi"], Value::Number(3.0));
+    }
+
+    #[test]
+    fn test_import_non_exported_name_errors() {
+        let path =
+            std::env::temp_dir().join(format!("luma_import_{}_hidden.luma", std::process::id()));
+        std::fs::write(&path, "let shown = 1\nlet hidden = 2\nexport shown\n").unwrap();
+        let run = |member: &str| {
+            let source = format!("import(\"{}\").{member}", path.display());
+            let program = parse(&source, "test.luma").expect("Parse failed");
+            VM::new(crate::bytecode::compile::compile_program(&program)).run()
+        };
+        assert_eq!(run("shown").unwrap(), Value::Number(1.0));
+        assert!(run("hidden").is_err());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_import_unsupported_extension_errors() {
+        let err = import_file("data.csv", "a,b\n").unwrap_err();
+        assert!(
+            err.to_string().contains("unsupported file type '.csv'"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_error_in_imported_function_points_into_module() {
+        let path =
+            std::env::temp_dir().join(format!("luma_import_{}_boom.luma", std::process::id()));
+        let module = "let pad = 0\nlet boom = fn(xs: List(Number)) do\n  return xs[5]\nend\nboom";
+        std::fs::write(&path, module).unwrap();
+        let path = path.canonicalize().unwrap().display().to_string();
+        let source = format!("let boom = import(\"{path}\")\n\nboom([1])");
+        let program = parse(&source, "main.luma").expect("Parse failed");
+        let chunk = crate::bytecode::compile::compile_program(&program);
+        let err = VM::new_with_file(chunk, Some("main.luma".into()))
+            .run()
+            .unwrap_err();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(err.file.as_deref(), Some(path.as_str()));
+        let formatted = err.format_with_source(&source);
+        assert!(
+            formatted.contains(&format!("--> {path}:3:3")),
+            "{formatted}"
+        );
+        assert!(formatted.contains("return xs[5]"), "{formatted}");
+        assert!(
+            formatted.ends_with(&format!(
+                "  at function `boom` ({path}:3:3)\n  at top level (main.luma:3:1)\n"
+            )),
+            "{formatted}"
+        );
+    }
+}
@@ -0,0 +1,38 @@
+//! State that natives keep per VM, such as the seed of a random number
+//! generator or output settings.
+//!
+//! Natives registered with
+//! [`VM::register_stateful_native_function`](super::VM::register_stateful_native_function)
+//! receive the VM's [`NativeState`], which holds at most one value of each type.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// Values natives keep between calls, one per type
+#[derive(Default)]
+pub struct NativeState {
+    values: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl NativeState {
+    /// Store `value`, replacing any earlier value of the same type
+    pub fn insert<T: Any>(&mut self, value: T) {
+        self.values.insert(TypeId::of::<T>(), Box::new(value));
+    }
+
+    /// The stored value of type `T`, if any
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.values
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// The stored value of type `T`, storing `T::default()` first if there is none
+    pub fn get_or_default<T: Any + Default>(&mut self) -> &mut T {
+        self.values
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(T::default()))
+            .downcast_mut()
+            .expect("native state is keyed by its type")
+    }
+}
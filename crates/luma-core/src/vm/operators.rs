@@ -1,12 +1,12 @@
 //! Operator overloading support for the Luma VM
 //!
 //! This module provides operator overloading functionality, allowing tables to
-//! implement custom behavior for operators like `+`, `-`, `*`, `/`, `==`, `<`, etc.
+//! implement custom behavior for operators like `+`, `-`, `*`, `/`, `**`, `==`, `<`, etc.
 //!
 //! # Operator Methods
 //!
 //! Tables can define the following operator methods:
-//! - `__add`, `__sub`, `__mul`, `__div`, `__mod` - Arithmetic operators
+//! - `__add`, `__sub`, `__mul`, `__div`, `__mod`, `__pow` - Arithmetic operators
 //! - `__neg` - Unary negation
 //! - `__eq` - Equality comparison
 //! - `__lt`, `__le`, `__gt`, `__ge` - Comparison operators
@@ -19,7 +19,9 @@
 
 use super::value::Value;
 use super::{CallFrame, VM, VmError};
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::rc::Rc;
 
 /// Check if a value is a table with a specific method
 ///
@@ -191,8 +193,8 @@ pub fn execute_eq_op(vm: &mut VM, a: Value, b: Value) -> Result<(), VmError> {
 
 /// Execute comparison operation with operator overloading
 ///
-/// First tries numeric comparison. If operands are not both numbers,
-/// looks for operator overload method on the left operand.
+/// First tries numeric comparison, then element-wise comparison of lists.
+/// Otherwise looks for operator overload method on the left operand.
 pub fn execute_cmp_op(
     vm: &mut VM,
     a: Value,
@@ -206,12 +208,21 @@ pub fn execute_cmp_op(
             vm.stack.push(Value::Boolean(default_cmp(*x, *y)));
             Ok(())
         }
+        (Value::List(x), Value::List(y)) => {
+            let result = match compare_lists(x, y).map_err(|msg| vm._error(msg))? {
+                // Map the ordering onto a number so each operator keeps its meaning
+                Some(ordering) => default_cmp(ordering as i8 as f64, 0.0),
+                None => false,
+            };
+            vm.stack.push(Value::Boolean(result));
+            Ok(())
+        }
         _ => {
             // Try operator overloading
             if let Some(method) = has_method(&a, method_name) {
                 call_overload_method(vm, method, vec![a, b], 2, method_name)
             } else {
-                Err(VmError::runtime(format!(
+                Err(vm._error(format!(
                     "Comparison {} requires numbers or {} method; got {} and {}",
                     method_name,
                     method_name,
@@ -223,8 +234,41 @@ pub fn execute_cmp_op(
     }
 }
 
+/// Lexicographic order of two lists: the first differing element decides,
+/// and a list that is a prefix of the other comes first. Elements must be
+/// numbers or lists themselves. `None` when a NaN leaves the lists unordered,
+/// which makes every comparison false, as it does for numbers. Errors are
+/// plain messages for the caller to locate at the comparing instruction.
+fn compare_lists(
+    a: &Rc<std::cell::RefCell<Vec<Value>>>,
+    b: &Rc<std::cell::RefCell<Vec<Value>>>,
+) -> Result<Option<Ordering>, String> {
+    // A list equals itself (see `Value::eq`), so it must not be less than itself
+    if Rc::ptr_eq(a, b) {
+        return Ok(Some(Ordering::Equal));
+    }
+    for (x, y) in a.borrow().iter().zip(b.borrow().iter()) {
+        let ordering = match (x, y) {
+            (Value::Number(x), Value::Number(y)) => x.partial_cmp(y),
+            (Value::List(x), Value::List(y)) => compare_lists(x, y)?,
+            _ => {
+                return Err(format!(
+                    "Cannot order lists: elements {} and {} have no ordering",
+                    value_type_name(x),
+                    value_type_name(y)
+                ));
+            }
+        };
+        match ordering {
+            Some(Ordering::Equal) => continue,
+            other => return Ok(other),
+        }
+    }
+    Ok(Some(a.borrow().len().cmp(&b.borrow().len())))
+}
+
 /// Get a human-readable type name for error messages
-fn value_type_name(value: &Value) -> &'static str {
+pub(crate) fn value_type_name(value: &Value) -> &'static str {
     match value {
         Value::Number(_) => "Number",
         Value::String(_) => "String",
@@ -2,9 +2,14 @@ use crate::bytecode::ir::Chunk;
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::fmt;
+use std::ops::{Deref, DerefMut};
 use std::rc::Rc;
 
+mod codec;
+mod format;
+
+pub use codec::{DecodeError, EncodeError, decode, encode};
+
 // Type for native function pointers
 pub type NativeFn = fn(&[Value]) -> Result<Value, String>;
 
@@ -23,6 +28,54 @@ impl Upvalue {
     }
 }
 
+/// Backing storage for `Value::Table`.
+/// Dereferences to its entries; `frozen` tables reject mutation from scripts.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    entries: HashMap<String, Value>,
+    pub frozen: bool,
+}
+
+impl Table {
+    pub fn new(entries: HashMap<String, Value>) -> Self {
+        Self {
+            entries,
+            frozen: false,
+        }
+    }
+
+    /// Consume the table, returning its entries
+    pub fn into_entries(self) -> HashMap<String, Value> {
+        self.entries
+    }
+}
+
+impl From<HashMap<String, Value>> for Table {
+    fn from(entries: HashMap<String, Value>) -> Self {
+        Self::new(entries)
+    }
+}
+
+impl Deref for Table {
+    type Target = HashMap<String, Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl DerefMut for Table {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.entries
+    }
+}
+
+impl PartialEq for Table {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Value {
     Number(f64),
@@ -32,7 +85,7 @@ pub enum Value {
     #[serde(skip)]
     List(Rc<RefCell<Vec<Value>>>),
     #[serde(skip)]
-    Table(Rc<RefCell<HashMap<String, Value>>>),
+    Table(Rc<RefCell<Table>>),
     Function {
         chunk: Chunk,
         arity: usize,
@@ -57,7 +110,7 @@ pub enum Value {
     /// It's essentially a table that describes the structure of a type,
     /// including field definitions and optional __parent for inheritance.
     #[serde(skip)]
-    Type(Rc<RefCell<HashMap<String, Value>>>),
+    Type(Rc<RefCell<Table>>),
     /// External represents an opaque pointer to external/native data.
     /// Used for FFI to hold references to C data structures.
     /// The usize is an opaque handle that the FFI system uses to track the resource.
@@ -70,6 +123,20 @@ pub enum Value {
     },
 }
 
+impl Value {
+    /// Element count of a list, entry count of a table, or character count
+    /// of a string; `None` for values without a length. Shared by `GetLen`
+    /// and the `len` native.
+    pub fn length(&self) -> Option<usize> {
+        match self {
+            Value::List(list) => Some(list.borrow().len()),
+            Value::Table(table) => Some(table.borrow().len()),
+            Value::String(s) => Some(s.chars().count()),
+            _ => None,
+        }
+    }
+}
+
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
@@ -103,54 +170,3 @@ impl PartialEq for Value {
         }
     }
 }
-
-impl fmt::Display for Value {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Value::Number(n) => {
-                // Format numbers nicely - remove .0 for whole numbers
-                if n.fract() == 0.0 && n.is_finite() {
-                    write!(f, "{}", *n as i64)
-                } else {
-                    write!(f, "{n}")
-                }
-            }
-            Value::String(s) => write!(f, "{s}"),
-            Value::Boolean(b) => write!(f, "{b}"),
-            Value::Null => write!(f, "null"),
-            Value::List(arr) => {
-                let borrowed = arr.borrow();
-                write!(f, "[")?;
-                for (i, val) in borrowed.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "{val}")?;
-                }
-                write!(f, "]")
-            }
-            Value::Table(table) => {
-                let borrowed = table.borrow();
-                write!(f, "{{")?;
-                let mut first = true;
-                for (key, val) in borrowed.iter() {
-                    if !first {
-                        write!(f, ", ")?;
-                    }
-                    first = false;
-                    write!(f, "{key}: {val}")?;
-                }
-                write!(f, "}}")
-            }
-            Value::Function { arity, .. } => write!(f, "<function/{arity}>"),
-            Value::Closure { arity, .. } => write!(f, "<closure/{arity}>"),
-            Value::NativeFunction { name, arity } => {
-                write!(f, "<native function {name}/{arity}>",)
-            }
-            Value::Type(_) => write!(f, "<type>"),
-            Value::External { handle, type_name } => {
-                write!(f, "<external {type_name} id:{handle}>")
-            }
-        }
-    }
-}
@@ -0,0 +1,381 @@
+//! Compact binary encoding of plain values, for passing data between
+//! processes. Only data survives the trip: scalars, lists and tables.
+//! Functions, types and externals refer to VM state and are rejected.
+//!
+//! Every value starts with a one-byte tag. Numbers are 8 bytes of
+//! little-endian `f64`; lengths and counts are LEB128 varints; strings are
+//! a length followed by UTF-8 bytes. Table entries are written in key
+//! order, so equal tables always encode to the same bytes.
+
+use super::{Table, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+const TAG_NULL: u8 = 0;
+const TAG_FALSE: u8 = 1;
+const TAG_TRUE: u8 = 2;
+const TAG_NUMBER: u8 = 3;
+const TAG_STRING: u8 = 4;
+const TAG_LIST: u8 = 5;
+const TAG_TABLE: u8 = 6;
+
+/// How deeply lists and tables may nest in decoded input. Decoding recurses
+/// once per level, so untrusted bytes could otherwise overflow the stack.
+pub const MAX_DECODE_DEPTH: usize = 128;
+
+/// Why a value could not be encoded
+#[derive(Debug, Clone, PartialEq)]
+pub enum EncodeError {
+    /// The value (or something nested in it) has no data representation
+    NotSerializable(&'static str),
+    /// A list or table contains itself
+    Cycle,
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::NotSerializable(kind) => write!(f, "cannot serialize a {kind}"),
+            EncodeError::Cycle => write!(f, "cannot serialize a value that contains itself"),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+/// Why a byte buffer could not be decoded
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    /// The input ended in the middle of a value
+    UnexpectedEnd,
+    /// A tag byte that does not start any value
+    UnknownTag(u8),
+    /// A string or table key was not valid UTF-8
+    InvalidUtf8,
+    /// A length does not fit in memory on this platform
+    LengthOverflow,
+    /// Bytes were left over after the value
+    TrailingBytes(usize),
+    /// Lists and tables nest deeper than [`MAX_DECODE_DEPTH`]
+    TooDeep,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            DecodeError::UnknownTag(tag) => write!(f, "unknown value tag 0x{tag:02x}"),
+            DecodeError::InvalidUtf8 => write!(f, "string is not valid UTF-8"),
+            DecodeError::LengthOverflow => write!(f, "length is too large"),
+            DecodeError::TrailingBytes(n) => write!(f, "{n} trailing byte(s) after value"),
+            DecodeError::TooDeep => write!(
+                f,
+                "lists and tables nest more than {MAX_DECODE_DEPTH} levels deep"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Encode a value into the compact binary format
+pub fn encode(value: &Value) -> Result<Vec<u8>, EncodeError> {
+    let mut out = Vec::new();
+    let mut path = Vec::new();
+    encode_into(value, &mut out, &mut path)?;
+    Ok(out)
+}
+
+/// Decode a value produced by [`encode`]; the whole buffer must be consumed
+pub fn decode(bytes: &[u8]) -> Result<Value, DecodeError> {
+    let mut reader = Reader {
+        bytes,
+        pos: 0,
+        depth: 0,
+    };
+    let value = reader.value()?;
+    match bytes.len() - reader.pos {
+        0 => Ok(value),
+        rest => Err(DecodeError::TrailingBytes(rest)),
+    }
+}
+
+/// `path` holds the containers currently being written, to catch cycles
+fn encode_into(
+    value: &Value,
+    out: &mut Vec<u8>,
+    path: &mut Vec<*const ()>,
+) -> Result<(), EncodeError> {
+    match value {
+        Value::Null => out.push(TAG_NULL),
+        Value::Boolean(false) => out.push(TAG_FALSE),
+        Value::Boolean(true) => out.push(TAG_TRUE),
+        Value::Number(n) => {
+            out.push(TAG_NUMBER);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        Value::String(s) => {
+            out.push(TAG_STRING);
+            write_str(out, s);
+        }
+        Value::List(list) => {
+            enter(path, Rc::as_ptr(list).cast())?;
+            let items = list.borrow();
+            out.push(TAG_LIST);
+            write_len(out, items.len());
+            for item in items.iter() {
+                encode_into(item, out, path)?;
+            }
+            path.pop();
+        }
+        Value::Table(table) => {
+            enter(path, Rc::as_ptr(table).cast())?;
+            let table = table.borrow();
+            let mut entries: Vec<_> = table.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            out.push(TAG_TABLE);
+            write_len(out, entries.len());
+            for (key, item) in entries {
+                write_str(out, key);
+                encode_into(item, out, path)?;
+            }
+            path.pop();
+        }
+        Value::Function { .. } => return Err(EncodeError::NotSerializable("function")),
+        Value::Closure { .. } => return Err(EncodeError::NotSerializable("closure")),
+        Value::NativeFunction { .. } => {
+            return Err(EncodeError::NotSerializable("native function"));
+        }
+        Value::Type(_) => return Err(EncodeError::NotSerializable("type")),
+        Value::External { .. } => return Err(EncodeError::NotSerializable("external value")),
+    }
+    Ok(())
+}
+
+fn enter(path: &mut Vec<*const ()>, ptr: *const ()) -> Result<(), EncodeError> {
+    if path.contains(&ptr) {
+        return Err(EncodeError::Cycle);
+    }
+    path.push(ptr);
+    Ok(())
+}
+
+fn write_len(out: &mut Vec<u8>, len: usize) {
+    let mut n = len as u64;
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    write_len(out, s.len());
+    out.extend_from_slice(s.as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// Lists and tables currently open around the value being read
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or(DecodeError::UnexpectedEnd)?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn len(&mut self) -> Result<usize, DecodeError> {
+        let mut n: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            if shift >= 64 {
+                return Err(DecodeError::LengthOverflow);
+            }
+            n |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return usize::try_from(n).map_err(|_| DecodeError::LengthOverflow);
+            }
+            shift += 7;
+        }
+    }
+
+    fn string(&mut self) -> Result<String, DecodeError> {
+        let len = self.len()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8)
+    }
+
+    fn value(&mut self) -> Result<Value, DecodeError> {
+        match self.byte()? {
+            TAG_LIST | TAG_TABLE if self.depth == MAX_DECODE_DEPTH => Err(DecodeError::TooDeep),
+            TAG_NULL => Ok(Value::Null),
+            TAG_FALSE => Ok(Value::Boolean(false)),
+            TAG_TRUE => Ok(Value::Boolean(true)),
+            TAG_NUMBER => {
+                let bytes: [u8; 8] = self.take(8)?.try_into().expect("took 8 bytes");
+                Ok(Value::Number(f64::from_le_bytes(bytes)))
+            }
+            TAG_STRING => Ok(Value::String(self.string()?)),
+            TAG_LIST => {
+                let count = self.len()?;
+                // Every item takes at least one byte; don't trust `count` further
+                let mut items = Vec::with_capacity(count.min(self.bytes.len() - self.pos));
+                self.depth += 1;
+                for _ in 0..count {
+                    items.push(self.value()?);
+                }
+                self.depth -= 1;
+                Ok(Value::List(Rc::new(RefCell::new(items))))
+            }
+            TAG_TABLE => {
+                let count = self.len()?;
+                let mut entries = HashMap::new();
+                self.depth += 1;
+                for _ in 0..count {
+                    let key = self.string()?;
+                    let item = self.value()?;
+                    entries.insert(key, item);
+                }
+                self.depth -= 1;
+                Ok(Value::Table(Rc::new(RefCell::new(Table::new(entries)))))
+            }
+            tag => Err(DecodeError::UnknownTag(tag)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::ir::Chunk;
+    use crate::vm::value::Upvalue;
+
+    fn list(items: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(items)))
+    }
+
+    fn table(entries: Vec<(&str, Value)>) -> Value {
+        let entries = entries
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect::<HashMap<_, _>>();
+        Value::Table(Rc::new(RefCell::new(Table::new(entries))))
+    }
+
+    #[test]
+    fn test_round_trip_scalars() {
+        for value in [
+            Value::Null,
+            Value::Boolean(true),
+            Value::Boolean(false),
+            Value::Number(-1.5),
+            Value::Number(f64::INFINITY),
+            Value::String(String::new()),
+            Value::String("héllo".to_string()),
+        ] {
+            assert_eq!(decode(&encode(&value).unwrap()).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_nested_structures() {
+        let value = table(vec![
+            ("name", Value::String("luma".to_string())),
+            (
+                "items",
+                list(vec![
+                    Value::Number(1.0),
+                    list(vec![Value::Null, Value::Boolean(true)]),
+                    table(vec![("deep", list(vec![Value::String("x".repeat(200))]))]),
+                ]),
+            ),
+            ("empty", table(vec![])),
+        ]);
+        let bytes = encode(&value).unwrap();
+        assert_eq!(decode(&bytes).unwrap(), value);
+        // Key order is fixed, so encoding is deterministic
+        assert_eq!(encode(&decode(&bytes).unwrap()).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_encode_rejects_closures_and_cycles() {
+        let closure = Value::Closure {
+            chunk: Chunk::new_empty("f".to_string()),
+            arity: 0,
+            upvalues: vec![Upvalue::new(Value::Null)],
+        };
+        assert_eq!(
+            encode(&list(vec![Value::Number(1.0), closure])),
+            Err(EncodeError::NotSerializable("closure"))
+        );
+
+        let cyclic = Rc::new(RefCell::new(Vec::new()));
+        cyclic.borrow_mut().push(Value::List(cyclic.clone()));
+        assert_eq!(
+            encode(&Value::List(cyclic.clone())),
+            Err(EncodeError::Cycle)
+        );
+        cyclic.borrow_mut().clear();
+
+        // Sharing without a cycle is fine
+        let shared = list(vec![Value::Number(2.0)]);
+        assert!(encode(&list(vec![shared.clone(), shared])).is_ok());
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_input() {
+        assert_eq!(decode(&[]), Err(DecodeError::UnexpectedEnd));
+        assert_eq!(decode(&[0xff]), Err(DecodeError::UnknownTag(0xff)));
+        assert_eq!(decode(&[TAG_NUMBER, 0, 0]), Err(DecodeError::UnexpectedEnd));
+        assert_eq!(
+            decode(&[TAG_STRING, 1, 0xc3]),
+            Err(DecodeError::InvalidUtf8)
+        );
+        assert_eq!(
+            decode(&[TAG_NULL, TAG_NULL]),
+            Err(DecodeError::TrailingBytes(1))
+        );
+        assert_eq!(
+            decode(&[TAG_LIST, 0xff, 0xff, 0xff, 0xff, 0x0f]),
+            Err(DecodeError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn test_decode_rejects_excessive_nesting() {
+        // A list holding a list holding ... an empty list
+        let nested = |depth: usize| {
+            let mut bytes = [TAG_LIST, 1].repeat(depth);
+            bytes.extend([TAG_LIST, 0]);
+            bytes
+        };
+        assert!(decode(&nested(MAX_DECODE_DEPTH - 1)).is_ok());
+        assert_eq!(decode(&nested(MAX_DECODE_DEPTH)), Err(DecodeError::TooDeep));
+        // Deep enough to overflow the stack without the limit
+        assert_eq!(decode(&nested(1_000_000)), Err(DecodeError::TooDeep));
+
+        let mut tables = [TAG_TABLE, 1, 1, b'k'].repeat(MAX_DECODE_DEPTH + 1);
+        tables.push(TAG_NULL);
+        assert_eq!(decode(&tables), Err(DecodeError::TooDeep));
+    }
+}
@@ -0,0 +1,241 @@
+//! Text form of values, as shown by `print`.
+//!
+//! The inline form puts a whole list or table on one line. The pretty form
+//! breaks a list or table over several indented lines when its inline form
+//! would not fit in [`PRETTY_WIDTH`] columns, like pretty-printed JSON.
+//! Table entries are shown in key order, and a list or table nested inside
+//! itself is shown as `<cycle>`.
+
+use super::Value;
+use std::fmt::{self, Write};
+
+/// Column limit the pretty form tries to keep each line within
+pub const PRETTY_WIDTH: usize = 80;
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_inline(f, self, &mut Vec::new())
+    }
+}
+
+impl Value {
+    /// Render the value, breaking lists and tables that don't fit in
+    /// [`PRETTY_WIDTH`] columns into one element per line, each nesting
+    /// level indented by `indent` more spaces.
+    pub fn pretty(&self, indent: usize) -> String {
+        let mut out = String::new();
+        let _ = write_pretty(&mut out, self, indent, 0, 0, &mut Vec::new());
+        out
+    }
+}
+
+/// Identity of the list or table behind a value, for cycle detection
+fn container_ptr(value: &Value) -> Option<*const ()> {
+    match value {
+        Value::List(list) => Some(std::rc::Rc::as_ptr(list).cast()),
+        Value::Table(table) => Some(std::rc::Rc::as_ptr(table).cast()),
+        _ => None,
+    }
+}
+
+/// Items of a list or table as (key, value) pairs, with table keys sorted
+fn entries(value: &Value) -> Vec<(Option<String>, Value)> {
+    match value {
+        Value::List(list) => list.borrow().iter().map(|v| (None, v.clone())).collect(),
+        Value::Table(table) => {
+            let mut entries: Vec<_> = table
+                .borrow()
+                .iter()
+                .map(|(k, v)| (Some(k.clone()), v.clone()))
+                .collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            entries
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Brackets that open and close a list or table
+fn delimiters(value: &Value) -> (char, char) {
+    if matches!(value, Value::Table(_)) {
+        ('{', '}')
+    } else {
+        ('[', ']')
+    }
+}
+
+/// Write `value` on one line. `path` holds the containers being written,
+/// outermost first.
+fn write_inline(out: &mut impl Write, value: &Value, path: &mut Vec<*const ()>) -> fmt::Result {
+    let Some(ptr) = container_ptr(value) else {
+        return write_scalar(out, value);
+    };
+    if path.contains(&ptr) {
+        return out.write_str("<cycle>");
+    }
+    path.push(ptr);
+    let (open, close) = delimiters(value);
+    out.write_char(open)?;
+    for (i, (key, item)) in entries(value).iter().enumerate() {
+        if i > 0 {
+            out.write_str(", ")?;
+        }
+        if let Some(key) = key {
+            write!(out, "{key}: ")?;
+        }
+        write_inline(out, item, path)?;
+    }
+    path.pop();
+    out.write_char(close)
+}
+
+/// Write `value` nested `depth` levels deep, starting at column `column`,
+/// breaking it over several lines if its inline form would run past
+/// [`PRETTY_WIDTH`].
+fn write_pretty(
+    out: &mut String,
+    value: &Value,
+    indent: usize,
+    depth: usize,
+    column: usize,
+    path: &mut Vec<*const ()>,
+) -> fmt::Result {
+    let Some(ptr) = container_ptr(value) else {
+        return write_scalar(out, value);
+    };
+    let mut inline = String::new();
+    write_inline(&mut inline, value, path)?;
+    let entries = entries(value);
+    if column + inline.chars().count() <= PRETTY_WIDTH || entries.is_empty() || path.contains(&ptr)
+    {
+        out.push_str(&inline);
+        return Ok(());
+    }
+
+    path.push(ptr);
+    let (open, close) = delimiters(value);
+    let margin = (depth + 1) * indent;
+    out.push(open);
+    for (i, (key, item)) in entries.iter().enumerate() {
+        out.push('\n');
+        out.push_str(&" ".repeat(margin));
+        let mut column = margin;
+        if let Some(key) = key {
+            write!(out, "{key}: ")?;
+            column += key.chars().count() + 2;
+        }
+        write_pretty(out, item, indent, depth + 1, column, path)?;
+        if i + 1 < entries.len() {
+            out.push(',');
+        }
+    }
+    out.push('\n');
+    out.push_str(&" ".repeat(depth * indent));
+    out.push(close);
+    path.pop();
+    Ok(())
+}
+
+/// Write a value that has no elements to nest
+fn write_scalar(out: &mut impl Write, value: &Value) -> fmt::Result {
+    match value {
+        Value::Number(n) => {
+            // Format numbers nicely - remove .0 for whole numbers
+            if n.fract() == 0.0 && n.is_finite() {
+                write!(out, "{}", *n as i64)
+            } else {
+                write!(out, "{n}")
+            }
+        }
+        Value::String(s) => write!(out, "{s}"),
+        Value::Boolean(b) => write!(out, "{b}"),
+        Value::Null => write!(out, "null"),
+        Value::List(_) | Value::Table(_) => unreachable!("containers are written by the caller"),
+        Value::Function { arity, .. } => write!(out, "<function/{arity}>"),
+        Value::Closure { arity, .. } => write!(out, "<closure/{arity}>"),
+        Value::NativeFunction { name, arity } => {
+            write!(out, "<native function {name}/{arity}>",)
+        }
+        Value::Type(_) => write!(out, "<type>"),
+        Value::External { handle, type_name } => {
+            write!(out, "<external {type_name} id:{handle}>")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::value::Table;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+
+    fn list(items: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(items)))
+    }
+
+    fn table(entries: Vec<(&str, Value)>) -> Value {
+        let entries = entries
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect::<HashMap<_, _>>();
+        Value::Table(Rc::new(RefCell::new(Table::new(entries))))
+    }
+
+    fn numbers(range: std::ops::Range<u32>) -> Value {
+        list(range.map(|n| Value::Number(n.into())).collect())
+    }
+
+    #[test]
+    fn test_small_structures_stay_inline() {
+        let value = table(vec![("b", numbers(1..4)), ("a", Value::Null)]);
+        assert_eq!(value.to_string(), "{a: null, b: [1, 2, 3]}");
+        assert_eq!(value.pretty(2), value.to_string());
+    }
+
+    #[test]
+    fn test_large_structures_break_over_indented_lines() {
+        let value = table(vec![
+            ("name", Value::String("luma".to_string())),
+            ("rows", list(vec![numbers(0..30), numbers(0..3)])),
+        ]);
+        let expected = "\
+{
+  name: luma,
+  rows: [
+    [
+      0,
+      1,
+";
+        let pretty = value.pretty(2);
+        assert!(pretty.starts_with(expected), "{pretty}");
+        assert!(
+            pretty.ends_with("      29\n    ],\n    [0, 1, 2]\n  ]\n}"),
+            "{pretty}"
+        );
+        assert!(pretty.lines().all(|line| line.len() <= PRETTY_WIDTH));
+
+        let four = value.pretty(4);
+        assert!(
+            four.contains("\n    rows: [\n        [\n            0,"),
+            "{four}"
+        );
+    }
+
+    #[test]
+    fn test_cycles_print_as_marker() {
+        let inner = Rc::new(RefCell::new(vec![Value::Number(1.0)]));
+        inner.borrow_mut().push(Value::List(inner.clone()));
+        let value = table(vec![("self", Value::List(inner.clone()))]);
+        assert_eq!(value.to_string(), "{self: [1, <cycle>]}");
+        assert_eq!(value.pretty(2), "{self: [1, <cycle>]}");
+
+        // Shared but acyclic values are printed in full each time
+        let shared = numbers(0..2);
+        let value = list(vec![shared.clone(), shared]);
+        assert_eq!(value.to_string(), "[[0, 1], [0, 1]]");
+
+        inner.borrow_mut().clear();
+    }
+}
@@ -4,37 +4,95 @@
 //! that support the Language Server Protocol.
 
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 use tokio::sync::RwLock;
-use tower_lsp::jsonrpc::Result;
+use tower_lsp::jsonrpc::{Error, Result};
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer, LspService, Server};
 
-use luma_core::diagnostics::{Diagnostic as LumaDiagnostic, LineIndex};
+use luma_core::ast::Span;
+use luma_core::diagnostics::{Diagnostic as LumaDiagnostic, FixIt, LineIndex};
+use luma_core::pipeline::{Analysis, Pipeline};
+use luma_core::resolve::OccurrenceKind;
 
-type DocDiagnostics = (String, Vec<LumaDiagnostic>);
-type DiagMap = HashMap<Url, DocDiagnostics>;
+/// Command that runs a document and returns its output
+const RUN_FILE_COMMAND: &str = "luma.runFile";
+
+/// Source action applying every machine-applicable fix-it in a document
+const FIX_ALL_KIND: &str = "source.fixAll.luma";
 
 /// Document state tracked by the language server
-///
-/// Fields are stored for future features like:
-/// - content: For incremental updates, goto definition, completion
-/// - version: For document versioning in incremental sync mode
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 struct Document {
     content: String,
     version: i32,
 }
 
+/// Per-document analysis results keyed by document version
+///
+/// Every feature request against the same version of a document shares one
+/// parse and typecheck; entries are dropped when the document changes or closes.
+#[derive(Debug, Default)]
+struct AnalysisCache {
+    entries: Mutex<HashMap<Url, (i32, Arc<Analysis>)>>,
+    /// Number of analyses actually run, so tests can observe cache hits
+    runs: AtomicUsize,
+}
+
+impl AnalysisCache {
+    /// Return the cached analysis for this document version, running it if needed
+    ///
+    /// The analysis runs without holding the lock, so requests for other
+    /// documents aren't blocked behind a slow parse or typecheck.
+    fn get_or_analyze(&self, uri: &Url, doc: &Document) -> Arc<Analysis> {
+        if let Some(analysis) = self.cached(uri, doc.version) {
+            return analysis;
+        }
+
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        let pipeline = Pipeline::new(doc.content.clone(), uri.path().to_string());
+        let analysis = Arc::new(pipeline.analyze());
+
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(uri) {
+            // Another request analyzed this version in the meantime
+            Some((version, cached)) if *version == doc.version => cached.clone(),
+            // Don't replace the analysis of a newer version with this one
+            Some((version, _)) if *version > doc.version => analysis,
+            _ => {
+                entries.insert(uri.clone(), (doc.version, analysis.clone()));
+                analysis
+            }
+        }
+    }
+
+    fn cached(&self, uri: &Url, version: i32) -> Option<Arc<Analysis>> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(uri)
+            .filter(|(cached_version, _)| *cached_version == version)
+            .map(|(_, analysis)| analysis.clone())
+    }
+
+    fn invalidate(&self, uri: &Url) {
+        self.entries.lock().unwrap().remove(uri);
+    }
+
+    #[cfg(test)]
+    fn runs(&self) -> usize {
+        self.runs.load(Ordering::Relaxed)
+    }
+}
+
 /// The Luma Language Server backend
 #[derive(Debug)]
 pub struct LumaLanguageServer {
     client: Client,
     documents: Arc<RwLock<HashMap<Url, Document>>>,
-    /// Last computed diagnostics per document (content + core diagnostics)
-    last_diags: Arc<RwLock<DiagMap>>,
+    /// Cached parse/typecheck results per document
+    analyses: Arc<AnalysisCache>,
 }
 
 impl LumaLanguageServer {
@@ -42,34 +100,36 @@ impl LumaLanguageServer {
         Self {
             client,
             documents: Arc::new(RwLock::new(HashMap::new())),
-            last_diags: Arc::new(RwLock::new(HashMap::new())),
+            analyses: Arc::new(AnalysisCache::default()),
         }
     }
 
+    /// Convert a byte span to an LSP range. Columns are UTF-16 code units,
+    /// the default position encoding, so multibyte characters count correctly.
+    fn to_lsp_range(line_index: &LineIndex, source: &str, span: Span) -> Range {
+        let position = |offset| {
+            let (line, col) = line_index.line_col_utf16(source, offset);
+            Position::new((line - 1) as u32, (col - 1) as u32)
+        };
+        Range::new(position(span.start), position(span.end))
+    }
+
     /// Convert Luma diagnostic to LSP diagnostic
     fn to_lsp_diagnostic(diag: &LumaDiagnostic, source: &str) -> Diagnostic {
         let line_index = LineIndex::new(source);
-        let (start_line, start_col) = line_index.line_col(diag.span.start);
-        let (end_line, end_col) = line_index.line_col(diag.span.end);
 
         Diagnostic {
-            range: Range {
-                start: Position {
-                    line: (start_line - 1) as u32,
-                    character: (start_col - 1) as u32,
-                },
-                end: Position {
-                    line: (end_line - 1) as u32,
-                    character: (end_col - 1) as u32,
-                },
-            },
+            range: Self::to_lsp_range(&line_index, source, diag.span),
             severity: Some(match diag.severity {
                 luma_core::diagnostics::Severity::Error => DiagnosticSeverity::ERROR,
                 luma_core::diagnostics::Severity::Warning => DiagnosticSeverity::WARNING,
                 luma_core::diagnostics::Severity::Info => DiagnosticSeverity::INFORMATION,
                 luma_core::diagnostics::Severity::Hint => DiagnosticSeverity::HINT,
             }),
-            code: None,
+            code: diag
+                .code
+                .as_ref()
+                .map(|c| NumberOrString::String(c.code.clone())),
             code_description: None,
             source: Some("luma".to_string()),
             message: diag.message.clone(),
@@ -79,51 +139,132 @@ impl LumaLanguageServer {
         }
     }
 
-    /// Validate a document and publish diagnostics
-    async fn validate_document(&self, uri: &Url, content: &str) {
-        let filename = uri.path().to_string();
+    /// Turn one fix-it into a quick fix. Only machine-applicable fixes are
+    /// preferred, so editors never auto-apply a speculative edit; the others
+    /// get a `quickfix.<applicability>` sub-kind.
+    fn fix_code_action(
+        uri: &Url,
+        diag: &LumaDiagnostic,
+        fix: &FixIt,
+        source: &str,
+        line_index: &LineIndex,
+    ) -> CodeAction {
+        let edit = TextEdit {
+            range: Self::to_lsp_range(line_index, source, fix.span()),
+            new_text: fix.replacement().to_string(),
+        };
+        let kind = if fix.is_machine_applicable() {
+            CodeActionKind::QUICKFIX
+        } else {
+            CodeActionKind::from(format!(
+                "{}.{}",
+                CodeActionKind::QUICKFIX.as_str(),
+                fix.applicability().as_str()
+            ))
+        };
 
-        // Parse the document
-        let mut diagnostics = Vec::new();
-        let mut core_diags: Vec<LumaDiagnostic> = Vec::new();
-
-        match luma_core::parser::parse(content, &filename) {
-            Ok(ast) => {
-                // Try to typecheck the AST
-                if let Err(type_errors) = luma_core::typecheck::typecheck_program(&ast) {
-                    for err in type_errors {
-                        // Convert to core diagnostic to preserve suggestions/fix-its
-                        let span = err.span.unwrap_or_else(|| luma_core::ast::Span::new(0, 0));
-                        let mut core = luma_core::diagnostics::Diagnostic::error(
-                            luma_core::diagnostics::DiagnosticKind::Type,
-                            err.message.clone(),
-                            span,
-                            filename.clone(),
-                        );
-                        for s in err.suggestions {
-                            core = core.with_suggestion(s);
-                        }
-                        for fix in err.fixits {
-                            core = core.with_fix(fix);
-                        }
-                        diagnostics.push(Self::to_lsp_diagnostic(&core, content));
-                        core_diags.push(core);
-                    }
-                }
-            }
-            Err(parse_errors) => {
-                for err in &parse_errors {
-                    diagnostics.push(Self::to_lsp_diagnostic(err, content));
-                }
-                core_diags.extend(parse_errors.into_iter());
-            }
+        CodeAction {
+            title: fix.label().to_string(),
+            kind: Some(kind),
+            diagnostics: Some(vec![Self::to_lsp_diagnostic(diag, source)]),
+            edit: Some(WorkspaceEdit {
+                changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
+                document_changes: None,
+                change_annotations: None,
+            }),
+            command: None,
+            is_preferred: Some(fix.is_machine_applicable()),
+            disabled: None,
+            data: None,
         }
+    }
 
-        // Store the last diagnostics along with content for CodeActions
-        {
-            let mut m = self.last_diags.write().await;
-            m.insert(uri.clone(), (content.to_string(), core_diags));
+    /// Combine the machine-applicable fix-its of `diagnostics` into one
+    /// source action. As with `luma check --fix`, only each diagnostic's first
+    /// fix is considered; a fix overlapping one already taken is dropped.
+    /// Returns `None` when there is nothing to apply.
+    fn fix_all_action(
+        uri: &Url,
+        diagnostics: &[LumaDiagnostic],
+        source: &str,
+        line_index: &LineIndex,
+    ) -> Option<CodeAction> {
+        let mut fixes: Vec<&FixIt> = Vec::new();
+        for fix in diagnostics.iter().filter_map(|d| d.fixits.first()) {
+            if fix.is_machine_applicable() && !fixes.iter().any(|f| f.overlaps(fix)) {
+                fixes.push(fix);
+            }
         }
+        if fixes.is_empty() {
+            return None;
+        }
+        fixes.sort_by_key(|f| f.span().start);
+
+        let edits = fixes
+            .iter()
+            .map(|fix| TextEdit {
+                range: Self::to_lsp_range(line_index, source, fix.span()),
+                new_text: fix.replacement().to_string(),
+            })
+            .collect();
+        Some(CodeAction {
+            title: "Apply all fixes".to_string(),
+            kind: Some(CodeActionKind::from(FIX_ALL_KIND)),
+            diagnostics: None,
+            edit: Some(WorkspaceEdit {
+                changes: Some(HashMap::from([(uri.clone(), edits)])),
+                document_changes: None,
+                change_annotations: None,
+            }),
+            command: None,
+            is_preferred: None,
+            disabled: None,
+            data: None,
+        })
+    }
+
+    /// Whether a code action request restricted to `only` accepts `kind`.
+    /// Kinds are hierarchical, so asking for `source` also admits `source.fixAll`.
+    fn wants_kind(only: Option<&[CodeActionKind]>, kind: &str) -> bool {
+        only.is_none_or(|only| {
+            only.iter().any(|k| {
+                kind == k.as_str()
+                    || kind
+                        .strip_prefix(k.as_str())
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
+        })
+    }
+
+    /// Fetch a tracked document together with its (possibly cached) analysis
+    async fn analysis(&self, uri: &Url) -> Option<(Document, Arc<Analysis>)> {
+        let doc = self.documents.read().await.get(uri)?.clone();
+        let analysis = self.analyses.get_or_analyze(uri, &doc);
+        Some((doc, analysis))
+    }
+
+    /// Run a program with the standard library, returning what it wrote to
+    /// stdout and, if it failed, the rendered error. Stdin carries the
+    /// protocol, so the program sees it as empty.
+    fn run_captured(source: String, filename: String) -> (String, Option<String>) {
+        let (result, output) = luma_stdlib::capture_stdout(|| {
+            luma_stdlib::run_program_with_stdin(source.clone(), filename, std::io::empty())
+        });
+        (output, result.err().map(|e| e.format_with_source(&source)))
+    }
+
+    /// Validate a document and publish diagnostics
+    async fn validate_document(&self, uri: &Url) {
+        let Some((doc, analysis)) = self.analysis(uri).await else {
+            return;
+        };
+
+        // Analysis diagnostics are already sorted into source order
+        let diagnostics = analysis
+            .diagnostics
+            .iter()
+            .map(|d| Self::to_lsp_diagnostic(d, &doc.content))
+            .collect();
 
         self.client
             .publish_diagnostics(uri.clone(), diagnostics, None)
@@ -152,7 +293,21 @@ impl LanguageServer for LumaLanguageServer {
                     },
                 )),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
-                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                document_highlight_provider: Some(OneOf::Left(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Options(
+                    CodeActionOptions {
+                        code_action_kinds: Some(vec![
+                            CodeActionKind::QUICKFIX,
+                            CodeActionKind::from(FIX_ALL_KIND),
+                        ]),
+                        work_done_progress_options: Default::default(),
+                        resolve_provider: None,
+                    },
+                )),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![RUN_FILE_COMMAND.to_string()],
+                    work_done_progress_options: Default::default(),
+                }),
                 ..Default::default()
             },
         })
@@ -176,17 +331,12 @@ impl LanguageServer for LumaLanguageServer {
         // Store the document
         {
             let mut docs = self.documents.write().await;
-            docs.insert(
-                uri.clone(),
-                Document {
-                    content: content.clone(),
-                    version,
-                },
-            );
+            docs.insert(uri.clone(), Document { content, version });
         }
+        self.analyses.invalidate(&uri);
 
         // Validate and publish diagnostics
-        self.validate_document(&uri, &content).await;
+        self.validate_document(&uri).await;
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
@@ -200,43 +350,41 @@ impl LanguageServer for LumaLanguageServer {
             // Update stored document
             {
                 let mut docs = self.documents.write().await;
-                docs.insert(
-                    uri.clone(),
-                    Document {
-                        content: content.clone(),
-                        version,
-                    },
-                );
+                docs.insert(uri.clone(), Document { content, version });
             }
+            self.analyses.invalidate(&uri);
 
             // Validate and publish diagnostics
-            self.validate_document(&uri, &content).await;
+            self.validate_document(&uri).await;
         }
     }
 
     async fn did_save(&self, params: DidSaveTextDocumentParams) {
         let uri = params.text_document.uri;
 
-        // If we have the content, validate it
+        // Saved text only differs from ours if a change notification was missed
         if let Some(content) = params.text {
-            self.validate_document(&uri, &content).await;
+            let mut docs = self.documents.write().await;
+            if let Some(doc) = docs.get_mut(&uri)
+                && doc.content != content
+            {
+                doc.content = content;
+                self.analyses.invalidate(&uri);
+            }
         }
+
+        self.validate_document(&uri).await;
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
         let uri = params.text_document.uri;
 
-        // Remove document from tracking
+        // Remove document and its cached analysis from tracking
         {
             let mut docs = self.documents.write().await;
             docs.remove(&uri);
         }
-
-        // Clear stored diagnostics
-        {
-            let mut m = self.last_diags.write().await;
-            m.remove(&uri);
-        }
+        self.analyses.invalidate(&uri);
 
         // Clear diagnostics for the closed document
         self.client.publish_diagnostics(uri, Vec::new(), None).await;
@@ -244,51 +392,90 @@ impl LanguageServer for LumaLanguageServer {
 
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
         let uri = params.text_document_position_params.text_document.uri;
-        let _position = params.text_document_position_params.position;
-
-        // Get the document content
-        let docs = self.documents.read().await;
-        if docs.get(&uri).is_some() {
-            // For now, just return a simple hover message
-            // In the future, we can provide type information, documentation, etc.
-            Ok(Some(Hover {
-                contents: HoverContents::Markup(MarkupContent {
-                    kind: MarkupKind::Markdown,
-                    value: "Luma code".to_string(),
+        let position = params.text_document_position_params.position;
+
+        let Some((doc, analysis)) = self.analysis(&uri).await else {
+            return Ok(None);
+        };
+
+        // Show the inferred type of the innermost expression under the cursor
+        let line_index = LineIndex::new(&doc.content);
+        let Some(offset) = line_index.offset_utf16(
+            &doc.content,
+            position.line as usize + 1,
+            position.character as usize,
+        ) else {
+            return Ok(None);
+        };
+
+        Ok(analysis.type_at(offset).map(|ty| Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```luma\n{ty}\n```"),
+            }),
+            range: None,
+        }))
+    }
+
+    async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        let Some((doc, analysis)) = self.analysis(&uri).await else {
+            return Ok(None);
+        };
+
+        // Highlight every mention of the variable under the cursor
+        let line_index = LineIndex::new(&doc.content);
+        let Some(offset) = line_index.offset_utf16(
+            &doc.content,
+            position.line as usize + 1,
+            position.character as usize,
+        ) else {
+            return Ok(None);
+        };
+
+        let highlights: Vec<DocumentHighlight> = analysis
+            .occurrences_at(offset)
+            .into_iter()
+            .map(|o| DocumentHighlight {
+                range: Self::to_lsp_range(&line_index, &doc.content, o.span),
+                kind: Some(match o.kind {
+                    // A declaration binds a value, so it highlights as a write
+                    OccurrenceKind::Declaration | OccurrenceKind::Write => {
+                        DocumentHighlightKind::WRITE
+                    }
+                    OccurrenceKind::Read => DocumentHighlightKind::READ,
                 }),
-                range: None,
-            }))
-        } else {
-            Ok(None)
-        }
+            })
+            .collect();
+        Ok((!highlights.is_empty()).then_some(highlights))
     }
 
     async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
         let uri = params.text_document.uri;
         let range = params.range;
+        let only = params.context.only.as_deref();
 
-        let guard = self.last_diags.read().await;
-        let Some((content, core_diags)) = guard.get(&uri) else {
+        let Some((doc, analysis)) = self.analysis(&uri).await else {
             return Ok(None);
         };
+        let content = &doc.content;
 
         let line_index = LineIndex::new(content);
 
         let mut actions: Vec<CodeActionOrCommand> = Vec::new();
-        for d in core_diags {
+        let quick_fixes = if Self::wants_kind(only, CodeActionKind::QUICKFIX.as_str()) {
+            &analysis.diagnostics[..]
+        } else {
+            &[]
+        };
+        for d in quick_fixes {
             // Intersect diagnostic range with requested range
-            let (ds_line, ds_col) = line_index.line_col(d.span.start);
-            let (de_line, de_col) = line_index.line_col(d.span.end);
-            let d_range = Range {
-                start: Position {
-                    line: (ds_line - 1) as u32,
-                    character: (ds_col - 1) as u32,
-                },
-                end: Position {
-                    line: (de_line - 1) as u32,
-                    character: (de_col - 1) as u32,
-                },
-            };
+            let d_range = Self::to_lsp_range(&line_index, content, d.span);
 
             if d_range.start.line > range.end.line || d_range.end.line < range.start.line {
                 continue;
@@ -296,47 +483,57 @@ impl LanguageServer for LumaLanguageServer {
 
             // Create an action per fix-it
             for fix in &d.fixits {
-                let span = fix.span();
-                let (fs_line, fs_col) = line_index.line_col(span.start);
-                let (fe_line, fe_col) = line_index.line_col(span.end);
-                let edit = TextEdit {
-                    range: Range {
-                        start: Position {
-                            line: (fs_line - 1) as u32,
-                            character: (fs_col - 1) as u32,
-                        },
-                        end: Position {
-                            line: (fe_line - 1) as u32,
-                            character: (fe_col - 1) as u32,
-                        },
-                    },
-                    new_text: fix.replacement().to_string(),
-                };
-
-                let action = CodeAction {
-                    title: fix.label().to_string(),
-                    kind: Some(CodeActionKind::QUICKFIX),
-                    diagnostics: Some(vec![Self::to_lsp_diagnostic(d, content)]),
-                    edit: Some(WorkspaceEdit {
-                        changes: Some(HashMap::from([(uri.clone(), vec![edit])])),
-                        document_changes: None,
-                        change_annotations: None,
-                    }),
-                    command: None,
-                    is_preferred: Some(true),
-                    disabled: None,
-                    data: None,
-                };
+                let action = Self::fix_code_action(&uri, d, fix, content, &line_index);
                 actions.push(CodeActionOrCommand::CodeAction(action));
             }
         }
 
+        // The fix-all action covers the whole document, whatever the range
+        if Self::wants_kind(only, FIX_ALL_KIND)
+            && let Some(action) =
+                Self::fix_all_action(&uri, &analysis.diagnostics, content, &line_index)
+        {
+            actions.push(CodeActionOrCommand::CodeAction(action));
+        }
+
         if actions.is_empty() {
             Ok(None)
         } else {
             Ok(Some(actions))
         }
     }
+
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<serde_json::Value>> {
+        if params.command != RUN_FILE_COMMAND {
+            return Err(Error::invalid_params(format!(
+                "Unknown command: {}",
+                params.command
+            )));
+        }
+        let uri = params
+            .arguments
+            .first()
+            .and_then(|arg| serde_json::from_value::<Url>(arg.clone()).ok())
+            .ok_or_else(|| Error::invalid_params("luma.runFile expects a document URI"))?;
+        let Some(doc) = self.documents.read().await.get(&uri).cloned() else {
+            return Err(Error::invalid_params(format!("Document not open: {uri}")));
+        };
+
+        // The VM is single-threaded and may run for a while, so keep it off the
+        // async workers; its stdout is captured so it can't corrupt the LSP stream
+        let filename = uri.path().to_string();
+        let (output, error) =
+            tokio::task::spawn_blocking(move || Self::run_captured(doc.content, filename))
+                .await
+                .map_err(|_| Error::internal_error())?;
+
+        Ok(Some(
+            serde_json::json!({ "output": output, "error": error }),
+        ))
+    }
 }
 
 /// Run the LSP server
@@ -351,8 +548,7 @@ pub async fn run_server() {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use luma_core::ast::Span;
-    use luma_core::diagnostics::{Diagnostic as LumaDiagnostic, DiagnosticKind};
+    use luma_core::diagnostics::{Applicability, Diagnostic as LumaDiagnostic, DiagnosticKind};
 
     #[test]
     fn test_to_lsp_diagnostic_single_line() {
@@ -421,4 +617,304 @@ mod tests {
         // The range should span across lines
         assert!(lsp_diag.range.start.line <= lsp_diag.range.end.line);
     }
+
+    #[test]
+    fn test_to_lsp_diagnostic_counts_utf16_columns() {
+        let source = "let s = \"café\" + 1";
+        // `+` is at byte 16 but UTF-16 column 15: `é` is two bytes, one unit
+        let plus = source.find('+').unwrap();
+        assert_eq!(plus, 16);
+        let diag = LumaDiagnostic::error(
+            DiagnosticKind::Type,
+            "Test".to_string(),
+            Span::new(plus, plus + 1),
+            "test.luma".to_string(),
+        );
+
+        let lsp_diag = LumaLanguageServer::to_lsp_diagnostic(&diag, source);
+
+        assert_eq!(
+            lsp_diag.range,
+            Range::new(Position::new(0, 15), Position::new(0, 16))
+        );
+    }
+
+    #[test]
+    fn test_only_machine_applicable_fixes_are_preferred() {
+        let source = "print(1";
+        let uri = Url::parse("file:///test.luma").unwrap();
+        let line_index = LineIndex::new(source);
+        let diag = LumaDiagnostic::error(
+            DiagnosticKind::Parse,
+            "unexpected end of input".to_string(),
+            Span::new(7, 7),
+            "test.luma".to_string(),
+        );
+
+        let safe = FixIt::replace(Span::new(7, 7), ")", "Insert ')'");
+        let action = LumaLanguageServer::fix_code_action(&uri, &diag, &safe, source, &line_index);
+        assert_eq!(action.is_preferred, Some(true));
+        assert_eq!(action.kind, Some(CodeActionKind::QUICKFIX));
+
+        let guess = FixIt::replace(Span::new(0, 5), "printf", "Change to 'printf'")
+            .with_applicability(Applicability::MaybeIncorrect);
+        let action = LumaLanguageServer::fix_code_action(&uri, &diag, &guess, source, &line_index);
+        assert_eq!(action.is_preferred, Some(false));
+        assert_eq!(
+            action.kind,
+            Some(CodeActionKind::from("quickfix.maybe-incorrect"))
+        );
+    }
+
+    #[test]
+    fn test_fix_all_combines_independent_fixes() {
+        let source = "print(1\nprint(2";
+        let uri = Url::parse("file:///test.luma").unwrap();
+        let line_index = LineIndex::new(source);
+        let diag = |span: Span, fixes: Vec<FixIt>| {
+            let mut d = LumaDiagnostic::error(
+                DiagnosticKind::Parse,
+                "unexpected end of input".to_string(),
+                span,
+                "test.luma".to_string(),
+            );
+            d.fixits = fixes;
+            d
+        };
+        let diagnostics = [
+            diag(
+                Span::new(15, 15),
+                vec![FixIt::replace(Span::new(15, 15), ")", "Insert ')'")],
+            ),
+            diag(
+                Span::new(7, 7),
+                vec![FixIt::replace(Span::new(7, 7), ")", "Insert ')'")],
+            ),
+            // Overlaps the first fix, so it is dropped
+            diag(
+                Span::new(15, 15),
+                vec![FixIt::replace(Span::new(15, 15), "]", "Insert ']'")],
+            ),
+            // Speculative fixes are never applied in bulk
+            diag(
+                Span::new(0, 5),
+                vec![
+                    FixIt::replace(Span::new(0, 5), "printf", "Change to 'printf'")
+                        .with_applicability(Applicability::MaybeIncorrect),
+                ],
+            ),
+        ];
+
+        let action =
+            LumaLanguageServer::fix_all_action(&uri, &diagnostics, source, &line_index).unwrap();
+        assert_eq!(action.kind, Some(CodeActionKind::from(FIX_ALL_KIND)));
+        let changes = action.edit.unwrap().changes.unwrap();
+        assert_eq!(changes.len(), 1);
+        let edits = &changes[&uri];
+        assert_eq!(
+            edits,
+            &[
+                TextEdit {
+                    range: Range::new(Position::new(0, 7), Position::new(0, 7)),
+                    new_text: ")".to_string(),
+                },
+                TextEdit {
+                    range: Range::new(Position::new(1, 7), Position::new(1, 7)),
+                    new_text: ")".to_string(),
+                },
+            ]
+        );
+
+        assert!(LumaLanguageServer::fix_all_action(&uri, &[], source, &line_index).is_none());
+    }
+
+    #[test]
+    fn test_code_action_kind_filter() {
+        let source = [CodeActionKind::SOURCE];
+        let fix_all = [CodeActionKind::SOURCE_FIX_ALL];
+        let quick = [CodeActionKind::QUICKFIX];
+        assert!(LumaLanguageServer::wants_kind(None, FIX_ALL_KIND));
+        assert!(LumaLanguageServer::wants_kind(Some(&source), FIX_ALL_KIND));
+        assert!(LumaLanguageServer::wants_kind(Some(&fix_all), FIX_ALL_KIND));
+        assert!(!LumaLanguageServer::wants_kind(Some(&quick), FIX_ALL_KIND));
+        assert!(!LumaLanguageServer::wants_kind(
+            Some(&fix_all),
+            CodeActionKind::QUICKFIX.as_str()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_feature_requests_share_one_analysis() {
+        let (service, _socket) = LspService::new(LumaLanguageServer::new);
+        let server = service.inner();
+        let uri = Url::parse("file:///test.luma").unwrap();
+        let position = TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            position: Position::new(0, 8),
+        };
+
+        server
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    uri.clone(),
+                    "luma".to_string(),
+                    1,
+                    "let x = 42".to_string(),
+                ),
+            })
+            .await;
+        let hover = server
+            .hover(HoverParams {
+                text_document_position_params: position.clone(),
+                work_done_progress_params: Default::default(),
+            })
+            .await
+            .unwrap();
+        server
+            .code_action(CodeActionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                range: Range::new(Position::new(0, 0), Position::new(0, 10)),
+                context: Default::default(),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(server.analyses.runs(), 1);
+        let Some(Hover {
+            contents: HoverContents::Markup(markup),
+            ..
+        }) = hover
+        else {
+            panic!("expected a type hover");
+        };
+        assert!(markup.value.contains("Number"));
+
+        server
+            .did_change(DidChangeTextDocumentParams {
+                text_document: VersionedTextDocumentIdentifier::new(uri.clone(), 2),
+                content_changes: vec![TextDocumentContentChangeEvent {
+                    range: None,
+                    range_length: None,
+                    text: "let x = \"hi\"".to_string(),
+                }],
+            })
+            .await;
+        server
+            .hover(HoverParams {
+                text_document_position_params: position,
+                work_done_progress_params: Default::default(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(server.analyses.runs(), 2);
+    }
+
+    #[test]
+    fn test_stale_analysis_does_not_replace_newer_version() {
+        let cache = AnalysisCache::default();
+        let uri = Url::parse("file:///test.luma").unwrap();
+        let doc = |content: &str, version| Document {
+            content: content.to_string(),
+            version,
+        };
+        cache.get_or_analyze(&uri, &doc("let x = 2", 2));
+        let stale = cache.get_or_analyze(&uri, &doc("let x = ", 1));
+
+        assert!(stale.program.is_none());
+        assert!(cache.cached(&uri, 1).is_none());
+        assert!(cache.cached(&uri, 2).is_some_and(|a| a.program.is_some()));
+        assert_eq!(cache.runs(), 2);
+    }
+
+    async fn run_file(server: &LumaLanguageServer, source: &str) -> serde_json::Value {
+        let uri = Url::parse("file:///run.luma").unwrap();
+        server
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    uri.clone(),
+                    "luma".to_string(),
+                    1,
+                    source.to_string(),
+                ),
+            })
+            .await;
+        server
+            .execute_command(ExecuteCommandParams {
+                command: RUN_FILE_COMMAND.to_string(),
+                arguments: vec![serde_json::json!(uri)],
+                work_done_progress_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_run_file_command_returns_output() {
+        let (service, _socket) = LspService::new(LumaLanguageServer::new);
+        let result = run_file(service.inner(), "print(\"hello \")\nprint(1 + 2)").await;
+        assert_eq!(result["output"], "hello 3");
+        assert!(result["error"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_run_file_command_reports_runtime_error() {
+        let (service, _socket) = LspService::new(LumaLanguageServer::new);
+        let result = run_file(
+            service.inner(),
+            "print(\"partial\")\nlet xs = [1]\nlet y = xs[5]",
+        )
+        .await;
+        assert_eq!(result["output"], "partial");
+        let error = result["error"].as_str().unwrap();
+        assert!(error.contains("run.luma:3"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn test_document_highlight_marks_reads_and_writes() {
+        let (service, _socket) = LspService::new(LumaLanguageServer::new);
+        let server = service.inner();
+        let uri = Url::parse("file:///test.luma").unwrap();
+        let source = "var count = 0\ncount = count + 1\nlet f = fn(count: Number): Number do\n  return count\nend\nprint(count)";
+        server
+            .did_open(DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(
+                    uri.clone(),
+                    "luma".to_string(),
+                    1,
+                    source.to_string(),
+                ),
+            })
+            .await;
+
+        let highlights = server
+            .document_highlight(DocumentHighlightParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri },
+                    position: Position::new(5, 7),
+                },
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .await
+            .unwrap()
+            .expect("cursor is on a variable");
+
+        // The parameter `count` shadows the outer variable and is not highlighted
+        let found: Vec<_> = highlights
+            .iter()
+            .map(|h| (h.range.start.line, h.range.start.character, h.kind))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (0, 4, Some(DocumentHighlightKind::WRITE)),
+                (1, 0, Some(DocumentHighlightKind::WRITE)),
+                (1, 8, Some(DocumentHighlightKind::READ)),
+                (5, 6, Some(DocumentHighlightKind::READ)),
+            ]
+        );
+    }
 }
@@ -12,9 +12,119 @@ pub const PRELUDE: &str = include_str!("prelude.luma");
 // Re-export native functions for convenience
 pub use native::*;
 
+#[cfg(test)]
+mod lib_tests;
+
 /// Initialize a VM with the standard library (native functions + prelude).
 /// This registers all native functions, globals, and loads the prelude.
-pub fn init_vm(mut vm: VM) -> Result<VM, luma_core::vm::VmError> {
+pub fn init_vm(vm: VM) -> Result<VM, luma_core::vm::VmError> {
+    StdlibBuilder::new().init(vm)
+}
+
+/// Configures how the standard library is installed into a VM.
+///
+/// Embedders can append their own Luma preludes, which run after the standard
+/// one (so they may use everything it defines), or drop the standard prelude
+/// entirely for a minimal sandbox that only has the native functions.
+///
+/// ```no_run
+/// # use luma_core::vm::VM;
+/// # use luma_core::bytecode::ir::Chunk;
+/// let vm = VM::new(Chunk::new_empty("<embed>".to_string()));
+/// let vm = luma_stdlib::StdlibBuilder::new()
+///     .with_prelude("let greet = fn(name: String) do return \"hi \" + name end")
+///     .init(vm)?;
+/// # Ok::<(), luma_core::vm::VmError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct StdlibBuilder {
+    standard_prelude: bool,
+    extra_preludes: Vec<String>,
+    seed: Option<u64>,
+    pretty_print: bool,
+}
+
+impl Default for StdlibBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StdlibBuilder {
+    /// Start from the default configuration: natives plus the standard prelude
+    pub fn new() -> Self {
+        StdlibBuilder {
+            standard_prelude: true,
+            extra_preludes: Vec::new(),
+            seed: None,
+            pretty_print: false,
+        }
+    }
+
+    /// Skip loading [`PRELUDE`]; native functions and globals are still registered
+    pub fn without_standard_prelude(mut self) -> Self {
+        self.standard_prelude = false;
+        self
+    }
+
+    /// Load an additional prelude after the standard one (and any added before it)
+    pub fn with_prelude(mut self, source: impl Into<String>) -> Self {
+        self.extra_preludes.push(source.into());
+        self
+    }
+
+    /// Seed the `random` module, as `random.seed(seed)` would
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Make `print` and `write` lay out large lists and tables over several lines
+    pub fn with_pretty_print(mut self, enabled: bool) -> Self {
+        self.pretty_print = enabled;
+        self
+    }
+
+    /// Install the configured standard library into `vm`
+    pub fn init(self, mut vm: VM) -> Result<VM, luma_core::vm::VmError> {
+        register_natives(&mut vm);
+        if let Some(seed) = self.seed {
+            seed_random(&mut vm.native_state, seed);
+        }
+        set_pretty_print(&mut vm.native_state, self.pretty_print);
+        if self.standard_prelude {
+            vm.load_prelude(PRELUDE)?;
+        }
+        for source in &self.extra_preludes {
+            vm.load_prelude(source)?;
+        }
+        Ok(vm)
+    }
+
+    /// Like [`run_program`], but with this configuration of the standard library
+    pub fn run_program(
+        self,
+        source: String,
+        filename: String,
+    ) -> Result<Value, luma_core::pipeline::PipelineError> {
+        run_program_in(self, source, filename, |_| {})
+    }
+
+    /// Like [`run_program_timed`], but with this configuration of the standard library
+    pub fn run_program_timed(
+        self,
+        source: String,
+        filename: String,
+    ) -> (
+        Result<Value, luma_core::pipeline::PipelineError>,
+        luma_core::pipeline::PhaseTimings,
+    ) {
+        run_program_timed_in(self, source, filename)
+    }
+}
+
+/// Register native functions and built-in globals shared by every configuration.
+fn register_natives(vm: &mut VM) {
     // Set FFI dispatch function
     vm.ffi_dispatch = Some(native_ffi_dispatch);
 
@@ -23,14 +133,38 @@ pub fn init_vm(mut vm: VM) -> Result<VM, luma_core::vm::VmError> {
     vm.register_native_function("isInstanceOf", 2, native_is_instance_of);
     vm.register_native_function("into", 2, native_into);
     vm.register_native_function("typeof", 1, native_typeof);
+    vm.register_native_function("len", 1, native_len);
+    // Exposes reference counts, so it stays out of release builds
+    #[cfg(debug_assertions)]
+    vm.register_native_function("inspect", 1, native_inspect);
+    vm.register_native_function("is_callable", 1, native_is_callable);
     vm.register_native_function("iter", 1, native_iter);
-    vm.register_native_function("print", 0, native_print);
+    vm.register_native_function("iter_keys", 1, native_iter_keys);
+    vm.register_variadic_native_function("range", 2..=3, native_range);
+    vm.register_internal_native_function("__range_next", native_range_next);
+    vm.register_native_function("keys", 1, native_keys);
+    vm.register_native_function("values", 1, native_values);
+    vm.register_native_function("query", 2, native_query);
+    vm.register_native_function("freeze", 1, native_freeze);
+    vm.register_native_function("deep_freeze", 1, native_deep_freeze);
+    vm.register_stateful_native_function("print", 0..=usize::MAX, native_print);
+    vm.register_native_function("pretty_print", 2, native_pretty_print);
+    vm.register_variadic_native_function("format", 1..=usize::MAX, native_format);
+
+    // Register list natives (wrapped by the List table in the prelude)
+    vm.register_native_function("__list_sort", 1, native_list_sort);
+    vm.register_native_function("__list_sort_by_keys", 2, native_list_sort_by_keys);
+    vm.register_native_function("__list_with_capacity", 1, native_list_with_capacity);
+    vm.register_native_function("__list_reserve", 2, native_list_reserve);
+    vm.register_native_function("__list_push", 2, native_list_push);
 
     // Register I/O functions
-    vm.register_native_function("write", 2, native_write);
+    vm.register_stateful_native_function("write", 2..=2, native_write);
     vm.register_native_function("read_file", 1, native_read_file);
     vm.register_native_function("write_file", 2, native_write_file);
     vm.register_native_function("file_exists", 1, native_file_exists);
+    vm.register_input_native_function("io.read_line", native_io_read_line);
+    vm.register_input_native_function("io.read_all", native_io_read_all);
 
     // Register panic function
     vm.register_native_function("panic", 1, native_panic);
@@ -45,30 +179,87 @@ pub fn init_vm(mut vm: VM) -> Result<VM, luma_core::vm::VmError> {
     vm.register_native_function("ffi.free_cstr", 1, native_ffi_free_cstr);
     vm.register_native_function("ffi.call", 0, native_ffi_call);
 
+    // Register path functions
+    vm.register_variadic_native_function("path.join", 1..=usize::MAX, native_path_join);
+    vm.register_native_function("path.dirname", 1, native_path_dirname);
+    vm.register_variadic_native_function("path.resolve", 0..=usize::MAX, native_path_resolve);
+
     // Register process functions
     vm.register_native_function("process.exit", 1, native_process_exit);
+    vm.register_native_function("at_exit", 1, native_at_exit);
+
+    // Register math functions
+    vm.register_native_function("math.approx_eq", 3, native_math_approx_eq);
+    vm.register_native_function("math.is_nan", 1, native_math_is_nan);
+    vm.register_native_function("math.to_radix", 2, native_math_to_radix);
+    vm.register_native_function("math.clamp", 3, native_math_clamp);
+    vm.register_native_function("math.lerp", 3, native_math_lerp);
+    vm.register_native_function("math.sign", 1, native_math_sign);
+    vm.register_native_function("math.sin", 1, native_math_sin);
+    vm.register_native_function("math.cos", 1, native_math_cos);
+    vm.register_native_function("math.tan", 1, native_math_tan);
+    vm.register_native_function("math.atan2", 2, native_math_atan2);
+
+    // Register random functions
+    vm.register_stateful_native_function("random.seed", 1..=1, native_random_seed);
+    vm.register_stateful_native_function("random.int", 2..=2, native_random_int);
+    vm.register_stateful_native_function("random.float", 0..=0, native_random_float);
+    vm.register_stateful_native_function("random.choice", 1..=1, native_random_choice);
+    vm.register_stateful_native_function("random.shuffle", 1..=1, native_random_shuffle);
+
+    // Register stats functions
+    vm.register_native_function("stats.sum", 1, native_stats_sum);
+    vm.register_native_function("stats.mean", 1, native_stats_mean);
+    vm.register_native_function("stats.median", 1, native_stats_median);
+    vm.register_native_function("stats.min", 1, native_stats_min);
+    vm.register_native_function("stats.max", 1, native_stats_max);
+    vm.register_native_function("stats.stddev", 1, native_stats_stddev);
 
     // Expose file descriptor constants
     vm.globals.insert("STDOUT".to_string(), Value::Number(1.0));
     vm.globals.insert("STDERR".to_string(), Value::Number(2.0));
 
+    // Expose io module
+    vm.globals.insert("io".to_string(), create_io_module());
+
     // Expose ffi module
     vm.globals.insert("ffi".to_string(), create_ffi_module());
 
+    // Expose path module
+    vm.globals.insert("path".to_string(), create_path_module());
+
     // Expose process module
     vm.globals
         .insert("process".to_string(), create_process_module());
 
+    // Expose math module
+    vm.globals.insert("math".to_string(), create_math_module());
+
+    // Expose random module
+    vm.globals
+        .insert("random".to_string(), create_random_module());
+
+    // Expose stats module
+    vm.globals
+        .insert("stats".to_string(), create_stats_module());
+
     // Expose type markers for into() conversions
     vm.globals.insert(
         "String".to_string(),
         Value::Type(Rc::new(RefCell::new({
             let mut t = HashMap::new();
             t.insert("String".to_string(), Value::Boolean(true));
-            t
+            t.into()
         }))),
     );
 
+    // Expose built-in kind markers for isInstanceOf() and struct field specs.
+    // List and String are prelude tables tagged with the same __builtin field.
+    for name in ["Number", "Boolean", "Table", "Function"] {
+        vm.globals
+            .insert(name.to_string(), native::helpers::make_builtin_type(name));
+    }
+
     // Expose External type marker
     vm.globals.insert(
         "External".to_string(),
@@ -77,11 +268,6 @@ pub fn init_vm(mut vm: VM) -> Result<VM, luma_core::vm::VmError> {
             type_name: "External".to_string(),
         },
     );
-
-    // Load prelude
-    vm.load_prelude(PRELUDE)?;
-
-    Ok(vm)
 }
 
 /// Execute a Luma program with the standard library loaded.
@@ -89,6 +275,29 @@ pub fn init_vm(mut vm: VM) -> Result<VM, luma_core::vm::VmError> {
 pub fn run_program(
     source: String,
     filename: String,
+) -> Result<Value, luma_core::pipeline::PipelineError> {
+    StdlibBuilder::new().run_program(source, filename)
+}
+
+/// Like [`run_program`], but `io.read_line` and `io.read_all` read from
+/// `input` instead of the process's stdin.
+pub fn run_program_with_stdin(
+    source: String,
+    filename: String,
+    input: impl std::io::BufRead + 'static,
+) -> Result<Value, luma_core::pipeline::PipelineError> {
+    run_program_in(StdlibBuilder::new(), source, filename, |vm| {
+        vm.set_stdin(input)
+    })
+}
+
+/// Compile and run a program in a fresh VM with `stdlib` installed, letting
+/// `configure` adjust the VM before execution starts
+fn run_program_in(
+    stdlib: StdlibBuilder,
+    source: String,
+    filename: String,
+    configure: impl FnOnce(&mut VM),
 ) -> Result<Value, luma_core::pipeline::PipelineError> {
     use luma_core::bytecode::ir::Chunk;
     use luma_core::pipeline::Pipeline;
@@ -101,8 +310,84 @@ pub fn run_program(
     // Create and initialize VM with stdlib
     let vm_chunk = Chunk::new_empty(filename.clone());
     let vm = VM::new_with_file(vm_chunk, Some(filename));
-    let mut vm = init_vm(vm).map_err(luma_core::pipeline::PipelineError::Runtime)?;
+    let mut vm = stdlib
+        .init(vm)
+        .map_err(luma_core::pipeline::PipelineError::Runtime)?;
+    configure(&mut vm);
 
     // Execute with the initialized VM
-    pipeline.execute_with_vm(chunk, &mut vm)
+    execute_with_exit_hooks(&pipeline, chunk, &mut vm)
+}
+
+/// Run a compiled program, then its `at_exit` hooks once it completed normally
+fn execute_with_exit_hooks(
+    pipeline: &luma_core::pipeline::Pipeline,
+    chunk: luma_core::bytecode::ir::Chunk,
+    vm: &mut VM,
+) -> Result<Value, luma_core::pipeline::PipelineError> {
+    let value = pipeline.execute_with_vm(chunk, vm)?;
+    vm.run_exit_hooks()
+        .map_err(luma_core::pipeline::PipelineError::Runtime)?;
+    Ok(value)
+}
+
+/// Like [`run_program`], but also measures how long each pipeline phase took.
+///
+/// Phases that never ran (because an earlier one failed) report zero. Standard
+/// library initialization is counted as part of execution.
+pub fn run_program_timed(
+    source: String,
+    filename: String,
+) -> (
+    Result<Value, luma_core::pipeline::PipelineError>,
+    luma_core::pipeline::PhaseTimings,
+) {
+    StdlibBuilder::new().run_program_timed(source, filename)
+}
+
+/// Time each phase of running a program in a fresh VM with `stdlib` installed
+fn run_program_timed_in(
+    stdlib: StdlibBuilder,
+    source: String,
+    filename: String,
+) -> (
+    Result<Value, luma_core::pipeline::PipelineError>,
+    luma_core::pipeline::PhaseTimings,
+) {
+    use luma_core::bytecode::ir::Chunk;
+    use luma_core::pipeline::{PhaseTimings, Pipeline, PipelineError};
+    use std::time::Instant;
+
+    let mut timings = PhaseTimings::default();
+    let pipeline = Pipeline::new(source, filename.clone());
+
+    let start = Instant::now();
+    let ast = pipeline.parse();
+    timings.parse = start.elapsed();
+    let ast = match ast {
+        Ok(ast) => ast,
+        Err(e) => return (Err(e), timings),
+    };
+
+    let start = Instant::now();
+    let checked = pipeline.typecheck(&ast);
+    timings.typecheck = start.elapsed();
+    if let Err(e) = checked {
+        return (Err(e), timings);
+    }
+
+    let start = Instant::now();
+    let chunk = pipeline.compile(&ast);
+    timings.compile = start.elapsed();
+
+    let start = Instant::now();
+    let vm_chunk = Chunk::new_empty(filename.clone());
+    let vm = VM::new_with_file(vm_chunk, Some(filename));
+    let result = stdlib
+        .init(vm)
+        .map_err(PipelineError::Runtime)
+        .and_then(|mut vm| execute_with_exit_hooks(&pipeline, chunk, &mut vm));
+    timings.execute = start.elapsed();
+
+    (result, timings)
 }
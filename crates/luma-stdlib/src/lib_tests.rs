@@ -0,0 +1,179 @@
+//! Tests for standard library initialization
+
+use super::*;
+use luma_core::bytecode::ir::Chunk;
+use luma_core::pipeline::Pipeline;
+
+fn empty_vm() -> VM {
+    VM::new(Chunk::new_empty("test.luma".to_string()))
+}
+
+/// Run user code against a prepared VM. Typechecking is skipped because
+/// embedder-defined globals are unknown to the static checker.
+fn run_in(vm: &mut VM, source: &str) -> Value {
+    let pipeline = Pipeline::new(source.to_string(), "test.luma".to_string());
+    let ast = pipeline.parse().unwrap();
+    let chunk = pipeline.compile(&ast);
+    pipeline.execute_with_vm(chunk, vm).unwrap()
+}
+
+#[test]
+fn test_extra_prelude_functions_callable_from_user_code() {
+    let mut vm = StdlibBuilder::new()
+        .with_prelude("let times_two = fn(x: Number): Number do return x * 2 end")
+        .with_prelude(
+            "let quadruple = fn(x: Number): Number do return times_two(times_two(x)) end\n\
+             let exports = { quadruple = quadruple }\n\
+             exports",
+        )
+        .init(empty_vm())
+        .unwrap();
+
+    assert_eq!(run_in(&mut vm, "quadruple(3)"), Value::Number(12.0));
+    // Extra preludes can build on the standard one
+    assert_eq!(
+        run_in(&mut vm, "List.length(indexed([4, 5, 6]))"),
+        Value::Number(3.0)
+    );
+    match vm.globals.get("prelude") {
+        Some(Value::Table(exports)) => {
+            let exports = exports.borrow();
+            assert!(exports.contains_key("range"));
+            assert!(exports.contains_key("quadruple"));
+        }
+        other => panic!("Expected prelude exports table, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_standard_prelude_can_be_disabled() {
+    let vm = StdlibBuilder::new()
+        .without_standard_prelude()
+        .init(empty_vm())
+        .unwrap();
+
+    for name in ["indexed", "Option", "Result", "prelude"] {
+        assert!(!vm.globals.contains_key(name), "{name} should be absent");
+    }
+    // Natives are still available
+    assert!(vm.globals.contains_key("typeof"));
+    assert!(vm.globals.contains_key("range"));
+    // Natives reached only through values stay out of the globals
+    assert!(!vm.globals.contains_key("__range_next"));
+}
+
+#[test]
+fn test_exit_hooks_run_in_reverse_registration_order() {
+    let mut vm = init_vm(empty_vm()).unwrap();
+    let log = run_in(
+        &mut vm,
+        "let log = { calls = \"\" }\n\
+         at_exit(fn() do log.calls = log.calls + \"a\" end)\n\
+         at_exit(fn() do log.calls = log.calls + \"b\" end)\n\
+         log",
+    );
+    assert_eq!(vm.exit_hooks.len(), 2);
+
+    vm.run_exit_hooks().unwrap();
+    assert!(vm.exit_hooks.is_empty());
+    let Value::Table(log) = log else {
+        panic!("Expected table, got {log:?}");
+    };
+    assert_eq!(
+        log.borrow().get("calls"),
+        Some(&Value::String("ba".to_string()))
+    );
+}
+
+#[test]
+fn test_run_program_runs_exit_hooks_after_completion() {
+    let result = run_program(
+        "var log = { calls = 0 }\n\
+         at_exit(fn() do log.calls = log.calls + 1 end)\n\
+         log"
+        .to_string(),
+        "test.luma".to_string(),
+    )
+    .unwrap();
+    let Value::Table(log) = result else {
+        panic!("Expected table, got {result:?}");
+    };
+    assert_eq!(log.borrow().get("calls"), Some(&Value::Number(1.0)));
+}
+
+#[test]
+fn test_run_program_compound_assignment_in_loop() {
+    let result = run_program(
+        "var sum = 0\n\
+         for i in [1, 2, 3] do sum += i end\n\
+         sum"
+        .to_string(),
+        "test.luma".to_string(),
+    )
+    .unwrap();
+    assert_eq!(result, Value::Number(6.0));
+}
+
+#[test]
+fn test_run_program_string_interpolation_converts_values() {
+    let run = |source: &str| run_program(source.to_string(), "test.luma".to_string()).unwrap();
+    assert_eq!(
+        run("let name = \"Ann\"\nlet count = 2\n\"hi ${name}, ${count + 1} new\""),
+        Value::String("hi Ann, 3 new".to_string())
+    );
+    assert_eq!(
+        run("\"${[1, 2]} ${true} ${0.5} \\${x}\""),
+        Value::String("[1, 2] true 0.5 ${x}".to_string())
+    );
+}
+
+#[test]
+fn test_for_over_list_visits_only_its_starting_length() {
+    let result = run_program(
+        "var xs = [1, 2, 3]\n\
+         var seen = []\n\
+         for x in xs do\n\
+           xs[len(xs)] = x * 10\n\
+           seen[len(seen)] = x\n\
+         end\n\
+         [seen, xs]"
+            .to_string(),
+        "test.luma".to_string(),
+    )
+    .unwrap();
+    assert_eq!(result.to_string(), "[[1, 2, 3], [1, 2, 3, 10, 20, 30]]");
+}
+
+#[test]
+fn test_for_over_table_walks_a_snapshot_of_its_keys() {
+    let result = run_program(
+        "var t = { a = 1, b = 2 }\n\
+         var count = 0\n\
+         for k in t do\n\
+           t[k + \"2\"] = 0\n\
+           count += 1\n\
+         end\n\
+         for [k, v] in t do t[k] = v + 1 end\n\
+         [count, t.a, t.b, t[\"a2\"], len(t)]"
+            .to_string(),
+        "test.luma".to_string(),
+    )
+    .unwrap();
+    assert_eq!(result.to_string(), "[2, 2, 3, 1, 4]");
+}
+
+#[test]
+fn test_vm_stdin_serves_program_reads() {
+    let mut vm = init_vm(empty_vm()).unwrap();
+    vm.set_stdin("hello\nworld\n".as_bytes());
+
+    let (result, output) = capture_stdout(|| {
+        run_in(
+            &mut vm,
+            "let line = io.read_line()\nprint(\"echo: \" + line)\nio.read_all()",
+        )
+    });
+    assert_eq!(output, "echo: hello");
+    assert_eq!(result, Value::String("world\n".to_string()));
+    assert_eq!(run_in(&mut vm, "io.read_line()"), Value::Null);
+}
@@ -1,7 +1,8 @@
-//! Core native functions: cast, isInstanceOf, into, typeof, iter
+//! Core native functions: cast, isInstanceOf, into, typeof, iter, range, query
 
 use super::helpers::*;
 use luma_core::vm::value::Value;
+use std::collections::HashSet;
 use std::rc::Rc;
 
 /// Native function: cast(type, value) -> typed_value
@@ -31,7 +32,7 @@ pub fn native_cast(args: &[Value]) -> Result<Value, String> {
     match value {
         Value::Table(table) => {
             let value_borrowed = table.borrow();
-            let mut new_table = value_borrowed.clone();
+            let mut new_table = value_borrowed.clone().into_entries();
 
             // Merge inherited fields from parent if any
             for (key, val) in merged_type.iter() {
@@ -46,7 +47,9 @@ pub fn native_cast(args: &[Value]) -> Result<Value, String> {
             // Attach the type definition as metadata
             new_table.insert("__type".to_string(), Value::Type(type_def.clone()));
 
-            Ok(Value::Table(Rc::new(std::cell::RefCell::new(new_table))))
+            Ok(Value::Table(Rc::new(std::cell::RefCell::new(
+                new_table.into(),
+            ))))
         }
         _ => unreachable!("is_castable ensures value is a Table"),
     }
@@ -68,6 +71,11 @@ pub fn native_is_instance_of(args: &[Value]) -> Result<Value, String> {
         _ => return Err("isInstanceOf() second argument must be a type (table)".to_string()),
     };
 
+    // Built-in markers (Number, List, Table, Function, ...) match on the runtime kind
+    if let Some(kind) = builtin_type_name(&type_def.borrow()) {
+        return Ok(Value::Boolean(value_type_name(value) == kind));
+    }
+
     // Check if the value is a table with __type metadata
     if let Value::Table(table) = value {
         let borrowed = table.borrow();
@@ -174,36 +182,99 @@ pub fn native_typeof(args: &[Value]) -> Result<Value, String> {
         return Err(format!("typeof() expects 1 argument, got {}", args.len()));
     }
 
-    let type_name = match &args[0] {
-        Value::Number(_) => "Number",
-        Value::String(_) => "String",
-        Value::Boolean(_) => "Boolean",
-        Value::Null => "Null",
-        Value::List(_) => "List",
-        Value::Table(table) => {
-            // Check if this table has a __type field (from cast())
-            let borrowed = table.borrow();
-            if let Some(Value::Type(_)) = borrowed.get("__type") {
-                // This is a typed instance, return "Table" as the base type
-                // The actual type information is in the __type field
-                "Table"
-            } else {
-                "Table"
-            }
+    let type_name = value_type_name(&args[0]);
+
+    Ok(Value::String(type_name.to_string()))
+}
+
+/// Native function: len(value: List|Table|String) -> Number
+/// Counts list elements, table entries or string characters, exactly as the
+/// `GetLen` instruction behind `for` loops does.
+pub fn native_len(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!("len() expects 1 argument, got {}", args.len()));
+    }
+
+    match args[0].length() {
+        Some(len) => Ok(Value::Number(len as f64)),
+        None => Err(format!(
+            "len() requires a List, Table or String, got {}",
+            value_type_name(&args[0])
+        )),
+    }
+}
+
+/// Native function: inspect(value: Any) -> String (debug builds only)
+/// Describes a value's variant and, for shared values, how many other
+/// references point at the same allocation, to help diagnose aliasing.
+/// The reference held by the call's own argument is not counted.
+#[cfg(debug_assertions)]
+pub fn native_inspect(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!("inspect() expects 1 argument, got {}", args.len()));
+    }
+
+    let refs = |count: usize| count.saturating_sub(1);
+    let description = match &args[0] {
+        Value::List(list) => format!(
+            "List(len={}, refs={})",
+            list.borrow().len(),
+            refs(Rc::strong_count(list))
+        ),
+        Value::Table(table) => format!(
+            "Table(entries={}, refs={})",
+            table.borrow().len(),
+            refs(Rc::strong_count(table))
+        ),
+        Value::Type(table) => format!("Type(refs={})", refs(Rc::strong_count(table))),
+        Value::Closure {
+            arity, upvalues, ..
+        } => {
+            let counts: Vec<String> = upvalues
+                .iter()
+                .map(|u| refs(Rc::strong_count(&u.value)).to_string())
+                .collect();
+            format!(
+                "Closure(arity={arity}, upvalue_refs=[{}])",
+                counts.join(", ")
+            )
         }
-        Value::Function { .. } => "Function",
-        Value::Closure { .. } => "Function",
-        Value::NativeFunction { .. } => "Function",
-        Value::Type(_) => "Type",
-        Value::External { .. } => "External",
+        Value::Function { arity, .. } => format!("Function(arity={arity})"),
+        Value::NativeFunction { name, .. } => format!("NativeFunction({name})"),
+        Value::String(s) => format!("String({s:?})"),
+        Value::External { handle, type_name } => format!("External({type_name}, {handle})"),
+        Value::Null => "Null".to_string(),
+        other => format!("{}({other})", value_type_name(other)),
     };
+    Ok(Value::String(description))
+}
 
-    Ok(Value::String(type_name.to_string()))
+/// Native function: is_callable(value: Any) -> Boolean
+/// True for every value `typeof` reports as "Function": user functions,
+/// closures and natives alike.
+pub fn native_is_callable(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "is_callable() expects 1 argument, got {}",
+            args.len()
+        ));
+    }
+
+    Ok(Value::Boolean(matches!(
+        args[0],
+        Value::Function { .. } | Value::Closure { .. } | Value::NativeFunction { .. }
+    )))
 }
 
 /// Native function: iter(value: List|Table) -> List
 /// - List: returns the same list (no copy)
+/// - Iterator (a table with `__next`): returned as is, so it is walked lazily
 /// - Table: returns list of [key, value] pairs
+///
+/// There is no separate iterator marker: any table holding a `__next` key is
+/// treated as an iterator, whatever the value under it.
+///
+/// Used by `for` loops that destructure each item, e.g. `for [k, v] in table`.
 pub fn native_iter(args: &[Value]) -> Result<Value, String> {
     if args.len() != 1 {
         return Err(format!("iter() expects 1 argument, got {}", args.len()));
@@ -211,6 +282,7 @@ pub fn native_iter(args: &[Value]) -> Result<Value, String> {
 
     match &args[0] {
         Value::List(list_rc) => Ok(Value::List(list_rc.clone())),
+        Value::Table(map_rc) if map_rc.borrow().contains_key("__next") => Ok(args[0].clone()),
         Value::Table(map_rc) => {
             let map = map_rc.borrow();
             let mut out: Vec<Value> = Vec::with_capacity(map.len());
@@ -223,3 +295,256 @@ pub fn native_iter(args: &[Value]) -> Result<Value, String> {
         _ => Err("iter() requires a List or Table".to_string()),
     }
 }
+
+/// Native function: iter_keys(value: List|Table) -> List
+/// - List: returns the same list (no copy)
+/// - Iterator (a table with `__next`): returned as is
+/// - Table: returns its keys as strings
+///
+/// Used by single-variable `for` loops, so `for k in table` iterates keys.
+pub fn native_iter_keys(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "iter_keys() expects 1 argument, got {}",
+            args.len()
+        ));
+    }
+
+    match &args[0] {
+        Value::List(list_rc) => Ok(Value::List(list_rc.clone())),
+        Value::Table(map_rc) if map_rc.borrow().contains_key("__next") => Ok(args[0].clone()),
+        Value::Table(_) => native_keys(args),
+        _ => Err("iter_keys() requires a List or Table".to_string()),
+    }
+}
+
+/// Native function: range(start: Number, stop: Number, step?: Number) -> iterator
+/// Counts lazily from `start` by `step` (1 by default) while below `stop`, or
+/// while above it for a negative step, so `range(5, 0)` is empty and
+/// `range(5, 0, -1)` counts down. The iterator keeps its position in its
+/// `index` field, and each number is computed from `start` rather than
+/// summed, so fractional steps don't drift.
+pub fn native_range(args: &[Value]) -> Result<Value, String> {
+    if !(2..=3).contains(&args.len()) {
+        return Err(format!(
+            "range() expects 2 or 3 arguments, got {}",
+            args.len()
+        ));
+    }
+    let mut bounds = [0.0, 0.0, 1.0];
+    for (slot, arg) in bounds.iter_mut().zip(args) {
+        match arg {
+            Value::Number(n) => *slot = *n,
+            _ => return Err("range() arguments must be numbers".to_string()),
+        }
+    }
+    let [start, stop, step] = bounds;
+    if step == 0.0 || step.is_nan() {
+        return Err("range() step must not be 0".to_string());
+    }
+
+    let mut it = std::collections::HashMap::new();
+    it.insert("start".to_string(), Value::Number(start));
+    it.insert("stop".to_string(), Value::Number(stop));
+    it.insert("step".to_string(), Value::Number(step));
+    it.insert("index".to_string(), Value::Number(0.0));
+    it.insert(
+        "__next".to_string(),
+        Value::NativeFunction {
+            name: "__range_next".to_string(),
+            arity: 1,
+        },
+    );
+    Ok(Value::Table(Rc::new(std::cell::RefCell::new(it.into()))))
+}
+
+/// Native function: __range_next(it) -> Option
+/// The `__next` of a `range()` iterator, which the `for` loop hands the
+/// iterator itself.
+pub fn native_range_next(args: &[Value]) -> Result<Value, String> {
+    let [Value::Table(it)] = args else {
+        return Err("__range_next() expects a range iterator".to_string());
+    };
+    let mut it = it.borrow_mut();
+    let field = |name: &str| match it.get(name) {
+        Some(Value::Number(n)) => Ok(*n),
+        _ => Err(format!("range iterator field '{name}' must be a number")),
+    };
+    let (start, stop, step, index) = (
+        field("start")?,
+        field("stop")?,
+        field("step")?,
+        field("index")?,
+    );
+
+    let next = start + index * step;
+    let more = if step > 0.0 { next < stop } else { next > stop };
+    if !more {
+        return Ok(make_option(None));
+    }
+    it.insert("index".to_string(), Value::Number(index + 1.0));
+    Ok(make_option(Some(Value::Number(next))))
+}
+
+/// One step of a `query()` path
+#[derive(Debug, PartialEq)]
+enum PathStep {
+    Key(String),
+    Index(usize),
+}
+
+/// Split a path like `users[0].name` into its steps. Keys are the text between
+/// dots, and `[n]` takes a non-negative integer index.
+fn parse_query_path(path: &str) -> Result<Vec<PathStep>, String> {
+    let invalid = |reason: &str| format!("query() invalid path '{path}': {reason}");
+    let mut steps = Vec::new();
+    let mut rest = path;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let close = after.find(']').ok_or_else(|| invalid("missing ']'"))?;
+            let index = after[..close]
+                .parse::<usize>()
+                .map_err(|_| invalid("index must be a non-negative integer"))?;
+            steps.push(PathStep::Index(index));
+            rest = &after[close + 1..];
+        } else {
+            if !steps.is_empty() {
+                rest = rest
+                    .strip_prefix('.')
+                    .ok_or_else(|| invalid("expected '.' or '[' after ']'"))?;
+            }
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(invalid("empty key"));
+            }
+            steps.push(PathStep::Key(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+    }
+    Ok(steps)
+}
+
+/// Native function: query(value: Any, path: String) -> Any
+/// Follows a path like `"users[0].name"` through nested tables and lists.
+/// A missing key, an out-of-range index, or a step that doesn't fit the value
+/// (a key on a list, an index on a table, anything on a number) yields null.
+/// An empty path returns `value` itself.
+pub fn native_query(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err(format!("query() expects 2 arguments, got {}", args.len()));
+    }
+    let Value::String(path) = &args[1] else {
+        return Err("query() path must be a string".to_string());
+    };
+
+    let mut current = args[0].clone();
+    for step in parse_query_path(path)? {
+        let next = match (&current, &step) {
+            (Value::Table(map), PathStep::Key(key)) => map.borrow().get(key).cloned(),
+            (Value::List(list), PathStep::Index(i)) => list.borrow().get(*i).cloned(),
+            _ => None,
+        };
+        match next {
+            Some(value) => current = value,
+            None => return Ok(Value::Null),
+        }
+    }
+    Ok(current)
+}
+
+/// Native function: keys(table: Table) -> List
+/// Returns the table's keys as strings, in the same order as `values(table)`.
+pub fn native_keys(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!("keys() expects 1 argument, got {}", args.len()));
+    }
+
+    match &args[0] {
+        Value::Table(map_rc) => {
+            let keys = map_rc
+                .borrow()
+                .keys()
+                .map(|k| Value::String(k.clone()))
+                .collect();
+            Ok(Value::List(Rc::new(std::cell::RefCell::new(keys))))
+        }
+        _ => Err("keys() requires a Table".to_string()),
+    }
+}
+
+/// Native function: values(table: Table) -> List
+/// Returns the table's values, in the same order as `keys(table)`.
+pub fn native_values(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!("values() expects 1 argument, got {}", args.len()));
+    }
+
+    match &args[0] {
+        Value::Table(map_rc) => {
+            let values = map_rc.borrow().values().cloned().collect();
+            Ok(Value::List(Rc::new(std::cell::RefCell::new(values))))
+        }
+        _ => Err("values() requires a Table".to_string()),
+    }
+}
+
+/// Native function: freeze(table: Table) -> Table
+/// Marks the table immutable and returns it. Nested tables are left mutable.
+pub fn native_freeze(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!("freeze() expects 1 argument, got {}", args.len()));
+    }
+
+    match &args[0] {
+        Value::Table(map_rc) => {
+            map_rc.borrow_mut().frozen = true;
+            Ok(args[0].clone())
+        }
+        _ => Err("freeze() requires a Table".to_string()),
+    }
+}
+
+/// Native function: deep_freeze(table: Table) -> Table
+/// Freezes the table and every table reachable through its values or through
+/// list elements. Lists themselves stay mutable.
+pub fn native_deep_freeze(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "deep_freeze() expects 1 argument, got {}",
+            args.len()
+        ));
+    }
+
+    match &args[0] {
+        Value::Table(_) => {
+            deep_freeze_value(&args[0], &mut HashSet::new());
+            Ok(args[0].clone())
+        }
+        _ => Err("deep_freeze() requires a Table".to_string()),
+    }
+}
+
+/// Freeze every table reachable from `value`. `visited` holds the lists and
+/// tables already walked, so structures that contain themselves terminate.
+fn deep_freeze_value(value: &Value, visited: &mut HashSet<*const ()>) {
+    match value {
+        Value::Table(map_rc) => {
+            if !visited.insert(Rc::as_ptr(map_rc).cast()) {
+                return;
+            }
+            map_rc.borrow_mut().frozen = true;
+            for nested in map_rc.borrow().values() {
+                deep_freeze_value(nested, visited);
+            }
+        }
+        Value::List(list_rc) => {
+            if !visited.insert(Rc::as_ptr(list_rc).cast()) {
+                return;
+            }
+            for nested in list_rc.borrow().iter() {
+                deep_freeze_value(nested, visited);
+            }
+        }
+        _ => {}
+    }
+}
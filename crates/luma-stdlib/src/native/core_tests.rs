@@ -1,17 +1,18 @@
 //! Tests for core native functions
 
 use super::core::*;
+use super::helpers::make_builtin_type;
 use luma_core::vm::value::Value;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 
 fn make_table() -> Value {
-    Value::Table(Rc::new(RefCell::new(HashMap::new())))
+    Value::Table(Rc::new(RefCell::new(HashMap::new().into())))
 }
 
 fn make_type(fields: HashMap<String, Value>) -> Value {
-    Value::Type(Rc::new(RefCell::new(fields)))
+    Value::Type(Rc::new(RefCell::new(fields.into())))
 }
 
 #[test]
@@ -43,7 +44,7 @@ fn test_native_cast_basic() {
 
     let mut table_fields = HashMap::new();
     table_fields.insert("x".to_string(), Value::Number(10.0));
-    let table = Value::Table(Rc::new(RefCell::new(table_fields)));
+    let table = Value::Table(Rc::new(RefCell::new(table_fields.into())));
 
     let result = native_cast(&[type_def, table]);
     assert!(result.is_ok());
@@ -90,18 +91,95 @@ fn test_native_is_instance_of_no_type() {
 
 #[test]
 fn test_native_is_instance_of_matches() {
-    let type_def = Rc::new(RefCell::new(HashMap::new()));
+    let type_def = Rc::new(RefCell::new(HashMap::new().into()));
     let type_val = Value::Type(type_def.clone());
 
     let mut table_fields = HashMap::new();
     table_fields.insert("__type".to_string(), Value::Type(type_def.clone()));
-    let table = Value::Table(Rc::new(RefCell::new(table_fields)));
+    let table = Value::Table(Rc::new(RefCell::new(table_fields.into())));
 
     let result = native_is_instance_of(&[table, type_val]);
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), Value::Boolean(true));
 }
 
+fn make_point_type() -> Value {
+    let mut fields = HashMap::new();
+    fields.insert("x".to_string(), make_builtin_type("Number"));
+    fields.insert("y".to_string(), make_builtin_type("Number"));
+    make_type(fields)
+}
+
+fn make_table_with(fields: &[(&str, Value)]) -> Value {
+    let map = fields
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.clone()))
+        .collect::<HashMap<_, _>>();
+    Value::Table(Rc::new(RefCell::new(map.into())))
+}
+
+#[test]
+fn test_native_is_instance_of_struct_type_fields_present() {
+    let point = make_table_with(&[("x", Value::Number(1.0)), ("y", Value::Number(2.0))]);
+    let result = native_is_instance_of(&[point, make_point_type()]).unwrap();
+    assert_eq!(result, Value::Boolean(true));
+}
+
+#[test]
+fn test_native_is_instance_of_struct_type_missing_field() {
+    let point = make_table_with(&[("x", Value::Number(1.0))]);
+    let result = native_is_instance_of(&[point, make_point_type()]).unwrap();
+    assert_eq!(result, Value::Boolean(false));
+}
+
+#[test]
+fn test_native_is_instance_of_struct_type_incompatible_field() {
+    let point = make_table_with(&[
+        ("x", Value::Number(1.0)),
+        ("y", Value::String("two".to_string())),
+    ]);
+    let result = native_is_instance_of(&[point, make_point_type()]).unwrap();
+    assert_eq!(result, Value::Boolean(false));
+}
+
+#[test]
+fn test_native_is_instance_of_nested_struct_type() {
+    let mut line_fields = HashMap::new();
+    line_fields.insert("start".to_string(), make_point_type());
+    let line_type = make_type(line_fields);
+
+    let origin = make_table_with(&[("x", Value::Number(0.0)), ("y", Value::Number(0.0))]);
+    let line = make_table_with(&[("start", origin)]);
+    let result = native_is_instance_of(&[line, line_type.clone()]).unwrap();
+    assert_eq!(result, Value::Boolean(true));
+
+    let bad_line = make_table_with(&[("start", make_table_with(&[("x", Value::Number(0.0))]))]);
+    let result = native_is_instance_of(&[bad_line, line_type]).unwrap();
+    assert_eq!(result, Value::Boolean(false));
+}
+
+#[test]
+fn test_native_is_instance_of_builtin_kinds() {
+    let list = Value::List(Rc::new(RefCell::new(vec![Value::Number(1.0)])));
+    let function = Value::NativeFunction {
+        name: "print".to_string(),
+        arity: 0,
+    };
+
+    let is = |value: &Value, kind: &str| {
+        native_is_instance_of(&[value.clone(), make_builtin_type(kind)]).unwrap()
+            == Value::Boolean(true)
+    };
+
+    assert!(is(&list, "List"));
+    assert!(!is(&make_table(), "List"));
+    assert!(is(&function, "Function"));
+    assert!(!is(&list, "Function"));
+    assert!(is(&make_table(), "Table"));
+    assert!(!is(&list, "Table"));
+    assert!(is(&Value::Number(3.0), "Number"));
+}
+
 #[test]
 fn test_native_into_invalid_arg_count() {
     let result = native_into(&[Value::Number(42.0)]);
@@ -229,6 +307,67 @@ fn test_native_typeof_function() {
     assert_eq!(result.unwrap(), Value::String("Function".to_string()));
 }
 
+fn callables() -> Vec<Value> {
+    use luma_core::bytecode::ir::Chunk;
+    vec![
+        Value::Function {
+            arity: 0,
+            chunk: Chunk::new_empty("f".to_string()),
+        },
+        Value::Closure {
+            arity: 0,
+            chunk: Chunk::new_empty("c".to_string()),
+            upvalues: vec![],
+        },
+        Value::NativeFunction {
+            name: "print".to_string(),
+            arity: 0,
+        },
+    ]
+}
+
+#[test]
+fn test_native_typeof_reports_function_for_every_callable() {
+    for callable in callables() {
+        let result = native_typeof(&[callable]).unwrap();
+        assert_eq!(result, Value::String("Function".to_string()));
+    }
+}
+
+#[test]
+fn test_native_is_callable() {
+    for callable in callables() {
+        assert_eq!(native_is_callable(&[callable]), Ok(Value::Boolean(true)));
+    }
+    for value in [Value::Number(1.0), make_table(), Value::Null] {
+        assert_eq!(native_is_callable(&[value]), Ok(Value::Boolean(false)));
+    }
+    assert!(native_is_callable(&[]).is_err());
+}
+
+#[test]
+fn test_is_callable_in_program() {
+    let source = r#"
+        let add = fn(a: Number, b: Number): Number do a + b end
+        let n = 2
+        let add_n = fn(x: Number): Number do x + n end
+        [is_callable(add), is_callable(add_n), is_callable(print), is_callable(1), is_callable({}), typeof(add_n) == "Function"]
+    "#;
+    let result = crate::run_program(source.to_string(), "test.luma".to_string()).unwrap();
+    let Value::List(items) = result else {
+        panic!("expected list, got {result:?}");
+    };
+    let expected = [
+        Value::Boolean(true),
+        Value::Boolean(true),
+        Value::Boolean(true),
+        Value::Boolean(false),
+        Value::Boolean(false),
+        Value::Boolean(true),
+    ];
+    assert_eq!(*items.borrow(), expected);
+}
+
 #[test]
 fn test_native_iter_invalid_arg_count() {
     let result = native_iter(&[]);
@@ -244,7 +383,7 @@ fn test_native_iter_list() {
         Value::Number(3.0),
     ])));
 
-    let result = native_iter(&[list.clone()]);
+    let result = native_iter(std::slice::from_ref(&list));
     assert!(result.is_ok());
 
     // Should return the same list (no copy)
@@ -263,3 +402,511 @@ fn test_native_iter_invalid_type() {
     assert!(result.is_err());
     assert!(result.unwrap_err().contains("requires a List or Table"));
 }
+
+#[test]
+fn test_native_keys_and_values_align() {
+    let table = make_table();
+    if let Value::Table(map) = &table {
+        map.borrow_mut().insert("a".into(), Value::Number(1.0));
+        map.borrow_mut().insert("b".into(), Value::Number(2.0));
+    }
+
+    let (Value::List(keys), Value::List(values)) = (
+        native_keys(std::slice::from_ref(&table)).unwrap(),
+        native_values(std::slice::from_ref(&table)).unwrap(),
+    ) else {
+        panic!("Expected lists");
+    };
+    let Value::Table(map) = &table else {
+        unreachable!()
+    };
+    for (k, v) in keys.borrow().iter().zip(values.borrow().iter()) {
+        let Value::String(k) = k else {
+            panic!("Expected string key, got {k:?}");
+        };
+        assert_eq!(map.borrow().get(k), Some(v));
+    }
+    assert_eq!(keys.borrow().len(), 2);
+}
+
+#[test]
+fn test_native_iter_keys_table_yields_keys() {
+    let table = make_table();
+    if let Value::Table(map) = &table {
+        map.borrow_mut().insert("only".into(), Value::Number(1.0));
+    }
+    let result = native_iter_keys(&[table]).unwrap();
+    let Value::List(items) = result else {
+        panic!("Expected list");
+    };
+    assert_eq!(*items.borrow(), vec![Value::String("only".into())]);
+}
+
+#[test]
+fn test_for_single_variable_over_table_yields_keys() {
+    let source = r#"
+        let t = { a = 1, b = 2 }
+        var total = 0
+        for k in t do
+            total = total + t[k]
+        end
+        total
+    "#;
+    let result = crate::run_program(source.to_string(), "test.luma".to_string()).unwrap();
+    assert_eq!(result, Value::Number(3.0));
+}
+
+#[test]
+fn test_for_pair_pattern_over_table_yields_key_value_pairs() {
+    let source = r#"
+        let t = { a = 1, b = 2 }
+        var total = 0
+        var seen = ""
+        for [k, v] in t do
+            total = total + v
+            seen = seen + k
+        end
+        { total = total, seen = seen }
+    "#;
+    let result = crate::run_program(source.to_string(), "test.luma".to_string()).unwrap();
+    let Value::Table(fields) = result else {
+        panic!("Expected table");
+    };
+    let fields = fields.borrow();
+    assert_eq!(fields["total"], Value::Number(3.0));
+    assert!(matches!(&fields["seen"], Value::String(s) if s == "ab" || s == "ba"));
+}
+
+#[test]
+fn test_for_destructures_list_elements() {
+    let source = r#"
+        var total = 0
+        for [a, b] in [[1, 2], [3, 4]] do
+            total = total + a + b
+        end
+        total
+    "#;
+    let result = crate::run_program(source.to_string(), "test.luma".to_string()).unwrap();
+    assert_eq!(result, Value::Number(10.0));
+}
+
+#[test]
+fn test_for_destructures_table_elements() {
+    let source = r#"
+        let points = [{ x = 1, y = 2 }, { x = 5, y = 6 }]
+        var total = 0
+        for { x, y: height } in points do
+            total = total + x * height
+        end
+        total
+    "#;
+    let result = crate::run_program(source.to_string(), "test.luma".to_string()).unwrap();
+    assert_eq!(result, Value::Number(32.0));
+}
+
+#[test]
+fn test_freeze_rejects_mutation() {
+    let source = r#"
+        var t = freeze({ a = 1 })
+        t.a = 2
+    "#;
+    let err = crate::run_program(source.to_string(), "test.luma".to_string()).unwrap_err();
+    assert!(
+        err.to_string().contains("cannot mutate frozen table"),
+        "{err}"
+    );
+
+    let source = r#"
+        var t = freeze({ a = 1 })
+        t["b"] = 2
+    "#;
+    let err = crate::run_program(source.to_string(), "test.luma".to_string()).unwrap_err();
+    assert!(
+        err.to_string().contains("cannot mutate frozen table"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_frozen_table_is_readable() {
+    let source = r#"
+        let t = freeze({ a = 1, b = 2 })
+        t.a + t["b"]
+    "#;
+    let result = crate::run_program(source.to_string(), "test.luma".to_string()).unwrap();
+    assert_eq!(result, Value::Number(3.0));
+}
+
+#[test]
+fn test_freeze_is_shallow() {
+    let source = r#"
+        let t = freeze({ inner = { a = 1 } })
+        t.inner.a = 2
+        t.inner.a
+    "#;
+    let result = crate::run_program(source.to_string(), "test.luma".to_string()).unwrap();
+    assert_eq!(result, Value::Number(2.0));
+}
+
+#[test]
+fn test_deep_freeze_rejects_nested_mutation() {
+    let source = r#"
+        let t = deep_freeze({ inner = { a = 1 }, items = [{ b = 2 }] })
+        t.inner.a = 2
+    "#;
+    let err = crate::run_program(source.to_string(), "test.luma".to_string()).unwrap_err();
+    assert!(
+        err.to_string().contains("cannot mutate frozen table"),
+        "{err}"
+    );
+
+    let source = r#"
+        let t = deep_freeze({ items = [{ b = 2 }] })
+        t.items[0].b = 3
+    "#;
+    let err = crate::run_program(source.to_string(), "test.luma".to_string()).unwrap_err();
+    assert!(
+        err.to_string().contains("cannot mutate frozen table"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_deep_freeze_handles_self_containing_values() {
+    let source = r#"
+        let items: List(Any) = [{ b = 2 }]
+        push(items, items)
+        let t = { items = items }
+        t.self = t
+        deep_freeze(t)
+        items[0].b = 3
+    "#;
+    let err = crate::run_program(source.to_string(), "test.luma".to_string()).unwrap_err();
+    assert!(
+        err.to_string().contains("cannot mutate frozen table"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_len_in_program() {
+    let run = |source: &str| crate::run_program(source.to_string(), "test.luma".to_string());
+    assert_eq!(run("len([1, 2, 3])").unwrap(), Value::Number(3.0));
+    assert_eq!(run("len({ a = 1 })").unwrap(), Value::Number(1.0));
+    assert_eq!(run(r#"len("abc")"#).unwrap(), Value::Number(3.0));
+    assert_eq!(run(r#"len("héllo")"#).unwrap(), Value::Number(5.0));
+    assert_eq!(
+        run(r#"List.length([1, 2]) + String.length("xyz")"#).unwrap(),
+        Value::Number(5.0)
+    );
+
+    let err = run("len(42)").unwrap_err().to_string();
+    assert!(
+        err.contains("len() requires a List, Table or String, got Number"),
+        "{err}"
+    );
+}
+
+#[test]
+fn test_native_len_arity() {
+    assert!(native_len(&[]).is_err());
+    assert_eq!(native_len(&[make_table()]), Ok(Value::Number(0.0)));
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn test_inspect_reports_shared_references() {
+    let source = r#"
+        let xs = [1, 2, 3]
+        var alias = xs
+        let shared = inspect(xs)
+        alias = []
+        [shared, inspect(xs), inspect([]), inspect(42), inspect({ a = 1 })]
+    "#;
+    let result = crate::run_program(source.to_string(), "test.luma".to_string()).unwrap();
+    let Value::List(items) = result else {
+        panic!("expected list, got {result:?}");
+    };
+    let expected = [
+        "List(len=3, refs=2)",
+        "List(len=3, refs=1)",
+        "List(len=0, refs=0)",
+        "Number(42)",
+        "Table(entries=1, refs=0)",
+    ];
+    let items: Vec<Value> = items.borrow().clone();
+    assert_eq!(
+        items,
+        expected.map(|s| Value::String(s.to_string())).to_vec()
+    );
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn test_inspect_closure_upvalues() {
+    let source = r#"
+        let make = fn(): Any do
+            var n = 0
+            fn(): Number do
+                n = n + 1
+                n
+            end
+        end
+        inspect(make())
+    "#;
+    let result = crate::run_program(source.to_string(), "test.luma".to_string()).unwrap();
+    let Value::String(description) = result else {
+        panic!("expected string, got {result:?}");
+    };
+    assert!(
+        description.starts_with("Closure(arity=0, upvalue_refs=["),
+        "{description}"
+    );
+}
+
+#[test]
+fn test_query_walks_nested_values() {
+    let run = |source: &str| crate::run_program(source.to_string(), "test.luma".to_string());
+    let config = r#"let config = { users = [{ name = "ada" }, { name = "bob", tags = ["x", "y"] }] }
+"#;
+    assert_eq!(
+        run(&format!("{config}query(config, \"users[1].name\")")).unwrap(),
+        Value::String("bob".to_string())
+    );
+    assert_eq!(
+        run(&format!("{config}query(config, \"users[1].tags[0]\")")).unwrap(),
+        Value::String("x".to_string())
+    );
+    assert_eq!(
+        run(&format!("{config}len(query(config, \"\"))")).unwrap(),
+        Value::Number(1.0)
+    );
+}
+
+#[test]
+fn test_query_missing_steps_give_null() {
+    let run = |source: &str| crate::run_program(source.to_string(), "test.luma".to_string());
+    let config = r#"let config = { users = [{ name = "ada", age = 36 }] }
+"#;
+    for path in [
+        "groups[0].name",
+        "users[5].name",
+        "users[0].missing.deeper",
+        "users.name",
+        "users[0].age.value",
+        "users[0][0]",
+    ] {
+        assert_eq!(
+            run(&format!("{config}query(config, \"{path}\")")).unwrap(),
+            Value::Null,
+            "{path}"
+        );
+    }
+}
+
+#[test]
+fn test_query_rejects_malformed_paths() {
+    let table = make_table();
+    for path in ["users[", "users[-1]", "users[x]", "a..b", "a[0]b", ".a"] {
+        let err = native_query(&[table.clone(), Value::String(path.to_string())]).unwrap_err();
+        assert!(err.starts_with("query() invalid path"), "{path}: {err}");
+    }
+    assert!(native_query(&[table, Value::Number(1.0)]).is_err());
+}
+
+fn collect(source: &str) -> String {
+    let source = format!("let out = []\nvar i = 0\n{source}\nout");
+    crate::run_program(source, "test.luma".to_string())
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn test_for_destructures_nested_patterns() {
+    let source = r#"
+        let items = [{ pos = { x = 1 }, tags = [2, 3, 4] }, { pos = { x = 10 }, tags = [20] }]
+        for { pos: { x }, tags: [first, ...rest] } in items do
+            out[i] = [x, first, rest]
+            i = i + 1
+        end
+        for [[a, _], ...b] in [[[1, 9], [2]], [[3, 9]]] do
+            out[i] = [a, len(b)]
+            i = i + 1
+        end
+    "#;
+    assert_eq!(
+        collect(source),
+        "[[1, 2, [3, 4]], [10, 20, []], [1, 1], [3, 0]]"
+    );
+}
+
+#[test]
+fn test_take_stops_an_infinite_iterator() {
+    let source = r#"
+        var n = 0
+        var calls = 0
+        let counter = {
+            __next = fn() do
+                calls = calls + 1
+                n = n + 1
+                Option.new_some(n)
+            end
+        }
+        for x in take(counter, 5) do
+            out[i] = x
+            i = i + 1
+        end
+        out[i] = calls
+    "#;
+    assert_eq!(collect(source), "[1, 2, 3, 4, 5, 5]");
+}
+
+#[test]
+fn test_skip_drops_leading_elements() {
+    let source = r#"
+        for x in skip([1, 2, 3, 4, 5], 2) do
+            out[i] = x
+            i = i + 1
+        end
+        for x in skip([1], 3) do
+            out[i] = x
+        end
+    "#;
+    assert_eq!(collect(source), "[3, 4, 5]");
+}
+
+#[test]
+fn test_skip_stops_pulling_once_source_is_exhausted() {
+    let source = r#"
+        var calls = 0
+        var left = 2
+        let source = {
+            __next = fn() do
+                calls = calls + 1
+                if left > 0 do
+                    left = left - 1
+                    Option.new_some(left)
+                else do
+                    Option.new_none()
+                end
+            end
+        }
+        let rest = skip(source, 5)
+        for x in rest do
+            out[i] = x
+        end
+        rest.__next()
+        out[0] = calls
+    "#;
+    assert_eq!(collect(source), "[3]");
+}
+
+#[test]
+fn test_enumerate_pairs_indices_with_values() {
+    let source = r#"
+        for [index, value] in enumerate(take(skip(["a", "b", "c", "d"], 1), 2)) do
+            out[i] = [index, value]
+            i = i + 1
+        end
+    "#;
+    assert_eq!(collect(source), "[[0, b], [1, c]]");
+}
+
+#[test]
+fn test_range_sums_lazily() {
+    let source = r#"
+        var sum = 0
+        for n in range(0, 5) do
+            sum = sum + n
+        end
+        out[0] = sum
+    "#;
+    assert_eq!(collect(source), "[10]");
+}
+
+#[test]
+fn test_range_with_step_and_descending() {
+    let source = r#"
+        for n in range(0, 10, 2) do
+            out[i] = n
+            i = i + 1
+        end
+        for n in range(5, 0, -2) do
+            out[i] = n
+            i = i + 1
+        end
+        for n in range(5, 0) do
+            out[i] = n
+        end
+    "#;
+    assert_eq!(collect(source), "[0, 2, 4, 6, 8, 5, 3, 1]");
+}
+
+#[test]
+fn test_range_works_with_iterator_adapters() {
+    let source = r#"
+        for [index, n] in enumerate(take(range(10, 1000000), 2)) do
+            out[i] = [index, n]
+            i = i + 1
+        end
+    "#;
+    assert_eq!(collect(source), "[[0, 10], [1, 11]]");
+}
+
+#[test]
+fn test_range_is_used_up_by_iterating() {
+    let source = r#"
+        let r = range(0, 3)
+        for n in r do
+            out[i] = n
+            i = i + 1
+        end
+        for n in r do
+            out[i] = n
+            i = i + 1
+        end
+        out[i] = typeof(r)
+    "#;
+    assert_eq!(collect(source), "[0, 1, 2, Table]");
+}
+
+#[test]
+fn test_range_rejects_zero_step() {
+    let err =
+        native_range(&[Value::Number(0.0), Value::Number(5.0), Value::Number(0.0)]).unwrap_err();
+    assert_eq!(err, "range() step must not be 0");
+    assert!(native_range(&[Value::Number(0.0)]).is_err());
+    assert!(native_range(&[Value::Number(0.0), Value::String("5".into())]).is_err());
+
+    let result = crate::run_program(
+        "for n in range(0, 5, 0) do end".to_string(),
+        "test.luma".to_string(),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_next_taking_a_parameter_receives_its_iterator() {
+    let source = r#"
+        let counter = {
+            n = 0,
+            __next = fn(self: Any) do
+                if self.n < 3 do
+                    self.n = self.n + 1
+                    Option.new_some(self.n)
+                else do
+                    Option.new_none()
+                end
+            end
+        }
+        for x in take(counter, 2) do
+            out[i] = x
+            i = i + 1
+        end
+        for x in counter do
+            out[i] = x
+            i = i + 1
+        end
+    "#;
+    assert_eq!(collect(source), "[1, 2, 3]");
+}
@@ -266,7 +266,7 @@ pub fn native_ffi_def(args: &[Value]) -> Result<Value, String> {
         }
     }
 
-    Ok(Value::Table(Rc::new(RefCell::new(ffi_table))))
+    Ok(Value::Table(Rc::new(RefCell::new(ffi_table.into()))))
 }
 
 /// Dispatch function for FFI calls
@@ -708,5 +708,5 @@ pub fn create_ffi_module() -> Value {
         },
     );
 
-    Value::Table(Rc::new(RefCell::new(ffi_table)))
+    Value::Table(Rc::new(RefCell::new(ffi_table.into())))
 }
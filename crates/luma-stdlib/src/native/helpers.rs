@@ -1,6 +1,6 @@
 //! Helper functions for native function implementations.
 
-use luma_core::vm::value::Value;
+use luma_core::vm::value::{Table, Value};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -10,7 +10,7 @@ pub fn make_result_ok(value: Value) -> Value {
     let mut map = HashMap::new();
     map.insert("ok".to_string(), value);
     map.insert("err".to_string(), Value::Null);
-    Value::Table(Rc::new(RefCell::new(map)))
+    Value::Table(Rc::new(RefCell::new(map.into())))
 }
 
 /// Create a Result value with err field set
@@ -18,11 +18,56 @@ pub fn make_result_err(error: String) -> Value {
     let mut map = HashMap::new();
     map.insert("ok".to_string(), Value::Null);
     map.insert("err".to_string(), Value::String(error));
-    Value::Table(Rc::new(RefCell::new(map)))
+    Value::Table(Rc::new(RefCell::new(map.into())))
+}
+
+/// Create an Option value: `some` set for a value, `none` set for `None`
+pub fn make_option(value: Option<Value>) -> Value {
+    let mut map = HashMap::new();
+    map.insert("none".to_string(), Value::Boolean(value.is_none()));
+    map.insert("some".to_string(), value.unwrap_or(Value::Null));
+    Value::Table(Rc::new(RefCell::new(map.into())))
+}
+
+/// Field marking a type table as one of the built-in kinds (`Number`, `List`, ...).
+pub const BUILTIN_TYPE_FIELD: &str = "__builtin";
+
+/// Create a type marker for a built-in kind, matched against `typeof` names.
+pub fn make_builtin_type(name: &str) -> Value {
+    let mut map = HashMap::new();
+    map.insert(
+        BUILTIN_TYPE_FIELD.to_string(),
+        Value::String(name.to_string()),
+    );
+    Value::Type(Rc::new(RefCell::new(map.into())))
+}
+
+/// Name of the built-in kind a type definition stands for, if it is a marker.
+pub fn builtin_type_name(type_def: &HashMap<String, Value>) -> Option<String> {
+    match type_def.get(BUILTIN_TYPE_FIELD) {
+        Some(Value::String(name)) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// Runtime type name of a value, as reported by `typeof`.
+pub fn value_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Number(_) => "Number",
+        Value::String(_) => "String",
+        Value::Boolean(_) => "Boolean",
+        Value::Null => "Null",
+        Value::List(_) => "List",
+        // Typed instances (from cast()) are still tables; the type lives in __type
+        Value::Table(_) => "Table",
+        Value::Function { .. } | Value::Closure { .. } | Value::NativeFunction { .. } => "Function",
+        Value::Type(_) => "Type",
+        Value::External { .. } => "External",
+    }
 }
 
 /// Helper to extract type definition from a Value (either Table or Type)
-pub fn get_type_map(value: &Value) -> Option<Rc<RefCell<HashMap<String, Value>>>> {
+pub fn get_type_map(value: &Value) -> Option<Rc<RefCell<Table>>> {
     match value {
         Value::Type(t) => Some(t.clone()),
         Value::Table(t) => Some(t.clone()),
@@ -30,42 +75,59 @@ pub fn get_type_map(value: &Value) -> Option<Rc<RefCell<HashMap<String, Value>>>
     }
 }
 
-/// Helper function to check if a value has all required fields for a type (for trait matching)
+/// Maximum nesting of struct-typed fields followed by structural matching.
+const MAX_STRUCTURAL_DEPTH: usize = 32;
+
+/// Helper function to check if a value has all required fields for a type (for trait matching).
+/// Every declared data field must be present; fields declared with a built-in marker
+/// (`x = Number`) or another type table (`origin = Point`) must also hold a matching value.
 pub fn has_required_fields(
     value: &Value,
     type_def: &HashMap<String, Value>,
 ) -> Result<bool, String> {
-    match value {
-        Value::Table(table) => {
-            let borrowed = table.borrow();
-
-            // Check all required fields in the type definition
-            for (field_name, field_type) in type_def.iter() {
-                // Skip special fields like __parent and methods (functions)
-                if field_name.starts_with("__") {
-                    continue;
-                }
-
-                // If the field type is a function, it's a method - skip validation for methods
-                if matches!(
-                    field_type,
-                    Value::Function { .. } | Value::NativeFunction { .. }
-                ) {
-                    continue;
-                }
-
-                // Check if the field exists in the value
-                if !borrowed.contains_key(field_name) {
-                    return Ok(false);
-                }
-
-                // For now, we do structural matching - just check if field exists
-                // Full type checking would require recursively validating field types
+    Ok(matches_structure(value, type_def, 0))
+}
+
+fn matches_structure(value: &Value, type_def: &HashMap<String, Value>, depth: usize) -> bool {
+    if let Some(kind) = builtin_type_name(type_def) {
+        return value_type_name(value) == kind;
+    }
+    let Value::Table(table) = value else {
+        return false;
+    };
+    if depth > MAX_STRUCTURAL_DEPTH {
+        // Self-referential data; assume the deeper levels match
+        return true;
+    }
+    let borrowed = table.borrow();
+
+    for (field_name, field_type) in type_def.iter() {
+        // Skip special fields like __parent
+        if field_name.starts_with("__") {
+            continue;
+        }
+
+        // If the field type is a function, it's a method - skip validation for methods
+        if matches!(
+            field_type,
+            Value::Function { .. } | Value::Closure { .. } | Value::NativeFunction { .. }
+        ) {
+            continue;
+        }
+
+        let Some(field_value) = borrowed.get(field_name) else {
+            return false;
+        };
+
+        // Type tables constrain the field's value; other defaults only require presence
+        if let Some(field_def) = get_type_map(field_type) {
+            let field_def = field_def.borrow();
+            if !matches_structure(field_value, &field_def, depth + 1) {
+                return false;
             }
-            Ok(true)
         }
-        _ => Ok(false),
     }
+    true
 }
 
 /// Helper function to check if a value is compatible for casting
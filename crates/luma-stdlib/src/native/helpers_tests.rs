@@ -37,7 +37,7 @@ fn test_make_result_err() {
 
 #[test]
 fn test_get_type_map_from_type() {
-    let map = Rc::new(RefCell::new(HashMap::new()));
+    let map = Rc::new(RefCell::new(HashMap::new().into()));
     let type_val = Value::Type(map.clone());
 
     let result = get_type_map(&type_val);
@@ -47,7 +47,7 @@ fn test_get_type_map_from_type() {
 
 #[test]
 fn test_get_type_map_from_table() {
-    let map = Rc::new(RefCell::new(HashMap::new()));
+    let map = Rc::new(RefCell::new(HashMap::new().into()));
     let table_val = Value::Table(map.clone());
 
     let result = get_type_map(&table_val);
@@ -63,7 +63,7 @@ fn test_get_type_map_from_non_type() {
 
 #[test]
 fn test_is_castable_table() {
-    let table = Value::Table(Rc::new(RefCell::new(HashMap::new())));
+    let table = Value::Table(Rc::new(RefCell::new(HashMap::new().into())));
     assert!(is_castable(&table));
 }
 
@@ -79,12 +79,12 @@ fn test_is_castable_non_table() {
 fn test_has_required_fields_empty_type() {
     let mut table_fields = HashMap::new();
     table_fields.insert("x".to_string(), Value::Number(10.0));
-    let table = Value::Table(Rc::new(RefCell::new(table_fields)));
+    let table = Value::Table(Rc::new(RefCell::new(table_fields.into())));
 
     let type_fields = HashMap::new();
     let result = has_required_fields(&table, &type_fields);
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), true);
+    assert!(result.unwrap());
 }
 
 #[test]
@@ -92,7 +92,7 @@ fn test_has_required_fields_matching() {
     let mut table_fields = HashMap::new();
     table_fields.insert("x".to_string(), Value::Number(10.0));
     table_fields.insert("y".to_string(), Value::String("hello".to_string()));
-    let table = Value::Table(Rc::new(RefCell::new(table_fields)));
+    let table = Value::Table(Rc::new(RefCell::new(table_fields.into())));
 
     let mut type_fields = HashMap::new();
     type_fields.insert("x".to_string(), Value::Number(0.0));
@@ -100,14 +100,14 @@ fn test_has_required_fields_matching() {
 
     let result = has_required_fields(&table, &type_fields);
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), true);
+    assert!(result.unwrap());
 }
 
 #[test]
 fn test_has_required_fields_missing() {
     let mut table_fields = HashMap::new();
     table_fields.insert("x".to_string(), Value::Number(10.0));
-    let table = Value::Table(Rc::new(RefCell::new(table_fields)));
+    let table = Value::Table(Rc::new(RefCell::new(table_fields.into())));
 
     let mut type_fields = HashMap::new();
     type_fields.insert("x".to_string(), Value::Number(0.0));
@@ -115,14 +115,14 @@ fn test_has_required_fields_missing() {
 
     let result = has_required_fields(&table, &type_fields);
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), false);
+    assert!(!result.unwrap());
 }
 
 #[test]
 fn test_has_required_fields_ignores_special_fields() {
     let mut table_fields = HashMap::new();
     table_fields.insert("x".to_string(), Value::Number(10.0));
-    let table = Value::Table(Rc::new(RefCell::new(table_fields)));
+    let table = Value::Table(Rc::new(RefCell::new(table_fields.into())));
 
     let mut type_fields = HashMap::new();
     type_fields.insert("x".to_string(), Value::Number(0.0));
@@ -130,7 +130,7 @@ fn test_has_required_fields_ignores_special_fields() {
 
     let result = has_required_fields(&table, &type_fields);
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), true);
+    assert!(result.unwrap());
 }
 
 #[test]
@@ -138,7 +138,7 @@ fn test_has_required_fields_non_table() {
     let type_fields = HashMap::new();
     let result = has_required_fields(&Value::Number(42.0), &type_fields);
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), false);
+    assert!(!result.unwrap());
 }
 
 #[test]
@@ -156,7 +156,7 @@ fn test_merge_parent_fields_with_parent() {
     let mut parent_fields = HashMap::new();
     parent_fields.insert("a".to_string(), Value::Number(1.0));
     parent_fields.insert("b".to_string(), Value::Number(2.0));
-    let parent_type = Value::Type(Rc::new(RefCell::new(parent_fields)));
+    let parent_type = Value::Type(Rc::new(RefCell::new(parent_fields.into())));
 
     let mut child_fields = HashMap::new();
     child_fields.insert("b".to_string(), Value::Number(20.0)); // Override
@@ -1,7 +1,13 @@
-//! I/O native functions: print, write, read_file, write_file, file_exists, panic
+//! I/O native functions: print, pretty_print, write, read_file, write_file,
+//! file_exists, panic, and the `io` module for reading standard input
 
 use super::helpers::{make_result_err, make_result_ok};
+use luma_core::vm::NativeState;
 use luma_core::vm::value::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
 
 /// Standard output file descriptor
 const FD_STDOUT: i32 = 1;
@@ -9,23 +15,105 @@ const FD_STDOUT: i32 = 1;
 /// Standard error file descriptor
 const FD_STDERR: i32 = 2;
 
+thread_local! {
+    /// Buffer that replaces stdout while [`capture_stdout`] runs
+    static CAPTURED_STDOUT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with everything the program writes to stdout collected into a
+/// string instead, for hosts such as the language server whose own stdout
+/// carries a protocol. Applies to the current thread only.
+pub fn capture_stdout<R>(f: impl FnOnce() -> R) -> (R, String) {
+    let previous = CAPTURED_STDOUT.with(|c| c.replace(Some(String::new())));
+    let result = f();
+    let captured = CAPTURED_STDOUT.with(|c| c.replace(previous));
+    (result, captured.unwrap_or_default())
+}
+
+/// Whether stdout is currently being captured on this thread
+pub(crate) fn is_capturing_stdout() -> bool {
+    CAPTURED_STDOUT.with(|c| c.borrow().is_some())
+}
+
+/// Write to stdout, or to the capture buffer when one is installed
+fn write_stdout(content: &str) -> std::io::Result<()> {
+    let captured = CAPTURED_STDOUT.with(|c| match c.borrow_mut().as_mut() {
+        Some(buf) => {
+            buf.push_str(content);
+            true
+        }
+        None => false,
+    });
+    if captured {
+        Ok(())
+    } else {
+        std::io::stdout().write_all(content.as_bytes())
+    }
+}
+
+/// Whether `print` lays out large lists and tables over several lines, kept
+/// in each VM's [`NativeState`]
+struct PrettyPrint(bool);
+
+fn is_pretty_print(state: &NativeState) -> bool {
+    state.get::<PrettyPrint>().is_some_and(|p| p.0)
+}
+
+/// Indentation `print` uses per nesting level when pretty printing
+const PRINT_INDENT: usize = 2;
+
+/// Make `print` and `write` in the VM owning `state` render large lists and
+/// tables the way `pretty_print` does, as `luma run --pretty` requests
+pub fn set_pretty_print(state: &mut NativeState, enabled: bool) {
+    state.insert(PrettyPrint(enabled));
+}
+
 /// Native function: print(...values) -> null
 /// Prints all arguments to stdout, separated by tabs
-pub fn native_print(args: &[Value]) -> Result<Value, String> {
+pub fn native_print(state: &mut NativeState, args: &[Value]) -> Result<Value, String> {
+    let pretty = is_pretty_print(state);
     let mut output = String::new();
     for (i, arg) in args.iter().enumerate() {
         if i > 0 {
             output.push('\t');
         }
-        output.push_str(&format!("{arg}"));
+        if pretty {
+            output.push_str(&arg.pretty(PRINT_INDENT));
+        } else {
+            output.push_str(&format!("{arg}"));
+        }
+    }
+    output.push('\n');
+    let _ = write_stdout(&output);
+    Ok(Value::Null)
+}
+
+/// Native function: pretty_print(value: Any, indent: Number) -> null
+/// Writes a value to stdout like `print`, putting each element of a list or
+/// table that is too wide for one line on its own line, indented `indent`
+/// spaces per level
+pub fn native_pretty_print(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err(format!(
+            "pretty_print() expects 2 arguments, got {}",
+            args.len()
+        ));
     }
-    println!("{output}");
+    let indent = match &args[1] {
+        Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => *n as usize,
+        other => {
+            return Err(format!(
+                "pretty_print() indent must be a non-negative integer, got {other}"
+            ));
+        }
+    };
+    let _ = write_stdout(&args[0].pretty(indent));
     Ok(Value::Null)
 }
 
 /// Native function: write(fd: Number, content: String) -> Result(Null, String)
 /// Writes content to a file descriptor (1=stdout, 2=stderr)
-pub fn native_write(args: &[Value]) -> Result<Value, String> {
+pub fn native_write(state: &mut NativeState, args: &[Value]) -> Result<Value, String> {
     if args.len() != 2 {
         return Err(format!("write() expects 2 arguments, got {}", args.len()));
     }
@@ -44,12 +132,12 @@ pub fn native_write(args: &[Value]) -> Result<Value, String> {
         Value::Number(n) => n.to_string(),
         Value::Boolean(b) => b.to_string(),
         Value::Null => "null".to_string(),
+        other if is_pretty_print(state) => other.pretty(PRINT_INDENT),
         other => format!("{other}"),
     };
 
-    use std::io::Write;
     let result = match fd {
-        FD_STDOUT => std::io::stdout().write_all(content.as_bytes()),
+        FD_STDOUT => write_stdout(&content),
         FD_STDERR => std::io::stderr().write_all(content.as_bytes()),
         _ => {
             return Ok(make_result_err(format!(
@@ -85,9 +173,7 @@ pub fn native_read_file(args: &[Value]) -> Result<Value, String> {
 
     match std::fs::read_to_string(path) {
         Ok(content) => Ok(make_result_ok(Value::String(content))),
-        Err(e) => Ok(make_result_err(format!(
-            "Failed to read file '{path}': {e}"
-        ))),
+        Err(e) => Ok(make_result_err(file_error("read_file", path, &e))),
     }
 }
 
@@ -120,12 +206,28 @@ pub fn native_write_file(args: &[Value]) -> Result<Value, String> {
 
     match std::fs::write(path, content) {
         Ok(_) => Ok(make_result_ok(Value::Null)),
-        Err(e) => Ok(make_result_err(format!(
-            "Failed to write file '{path}': {e}"
-        ))),
+        Err(e) => Ok(make_result_err(file_error("write_file", path, &e))),
     }
 }
 
+/// Error message for a failed file operation, naming the path and the
+/// `io::ErrorKind`, e.g. `read_file: no such file './missing.luma' (NotFound)`.
+fn file_error(op: &str, path: &str, e: &std::io::Error) -> String {
+    use std::io::ErrorKind;
+    let kind = e.kind();
+    let reason = match kind {
+        // Writing creates the file, so only a missing directory can be at fault
+        ErrorKind::NotFound if op == "write_file" => "no such directory for",
+        ErrorKind::NotFound => "no such file",
+        ErrorKind::PermissionDenied => "permission denied for",
+        ErrorKind::IsADirectory => "is a directory:",
+        ErrorKind::NotADirectory => "a path component is not a directory in",
+        ErrorKind::InvalidData => "not valid UTF-8 text in",
+        _ => return format!("{op}: cannot access '{path}' ({kind:?}): {e}"),
+    };
+    format!("{op}: {reason} '{path}' ({kind:?})")
+}
+
 /// Native function: file_exists(path: String) -> Boolean
 /// Checks if a file or directory exists at the given path
 pub fn native_file_exists(args: &[Value]) -> Result<Value, String> {
@@ -159,3 +261,57 @@ pub fn native_panic(args: &[Value]) -> Result<Value, String> {
     eprintln!("PANIC: {message}");
     std::process::exit(1);
 }
+
+/// Runtime error message for a failed read in the native `op`
+fn read_error(op: &str, e: &std::io::Error) -> String {
+    match e.kind() {
+        std::io::ErrorKind::InvalidData => format!("{op}: stdin is not valid UTF-8 text"),
+        kind => format!("{op}: cannot read stdin ({kind:?}): {e}"),
+    }
+}
+
+/// Native function: io.read_line() -> String | Null
+/// Reads one line of the program's input without its trailing `\n` or `\r\n`;
+/// null at end of input.
+pub fn native_io_read_line(input: &mut dyn BufRead) -> Result<Value, String> {
+    let mut line = String::new();
+    match input.read_line(&mut line) {
+        Ok(0) => return Ok(Value::Null),
+        Ok(_) => {}
+        Err(e) => return Err(read_error("io.read_line", &e)),
+    }
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(Value::String(line))
+}
+
+/// Native function: io.read_all() -> String
+/// Reads everything left of the program's input.
+pub fn native_io_read_all(input: &mut dyn BufRead) -> Result<Value, String> {
+    let mut content = String::new();
+    input
+        .read_to_string(&mut content)
+        .map_err(|e| read_error("io.read_all", &e))?;
+    Ok(Value::String(content))
+}
+
+/// Create the io module table
+pub fn create_io_module() -> Value {
+    let mut io_table: HashMap<String, Value> = HashMap::new();
+
+    for name in ["read_line", "read_all"] {
+        io_table.insert(
+            name.to_string(),
+            Value::NativeFunction {
+                name: format!("io.{name}"),
+                arity: 0,
+            },
+        );
+    }
+
+    Value::Table(Rc::new(RefCell::new(io_table.into())))
+}
@@ -1,46 +1,56 @@
 //! Tests for I/O native functions
 
 use super::io::*;
+use luma_core::vm::NativeState;
 use luma_core::vm::value::Value;
 
 #[test]
 fn test_native_print_no_args() {
-    let result = native_print(&[]);
+    let result = native_print(&mut NativeState::default(), &[]);
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), Value::Null);
 }
 
 #[test]
 fn test_native_print_single_arg() {
-    let result = native_print(&[Value::String("Hello".to_string())]);
+    let result = native_print(
+        &mut NativeState::default(),
+        &[Value::String("Hello".to_string())],
+    );
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), Value::Null);
 }
 
 #[test]
 fn test_native_print_multiple_args() {
-    let result = native_print(&[
-        Value::String("Hello".to_string()),
-        Value::Number(42.0),
-        Value::Boolean(true),
-    ]);
+    let result = native_print(
+        &mut NativeState::default(),
+        &[
+            Value::String("Hello".to_string()),
+            Value::Number(42.0),
+            Value::Boolean(true),
+        ],
+    );
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), Value::Null);
 }
 
 #[test]
 fn test_native_write_invalid_arg_count() {
-    let result = native_write(&[Value::Number(1.0)]);
+    let result = native_write(&mut NativeState::default(), &[Value::Number(1.0)]);
     assert!(result.is_err());
     assert!(result.unwrap_err().contains("expects 2 arguments"));
 }
 
 #[test]
 fn test_native_write_non_number_fd() {
-    let result = native_write(&[
-        Value::String("not a number".to_string()),
-        Value::String("content".to_string()),
-    ]);
+    let result = native_write(
+        &mut NativeState::default(),
+        &[
+            Value::String("not a number".to_string()),
+            Value::String("content".to_string()),
+        ],
+    );
     assert!(result.is_ok());
     // Should return error result table
     if let Value::Table(map) = result.unwrap() {
@@ -56,7 +66,10 @@ fn test_native_write_non_number_fd() {
 
 #[test]
 fn test_native_write_valid_stdout() {
-    let result = native_write(&[Value::Number(1.0), Value::String("test".to_string())]);
+    let result = native_write(
+        &mut NativeState::default(),
+        &[Value::Number(1.0), Value::String("test".to_string())],
+    );
     assert!(result.is_ok());
     // Should return ok result
     if let Value::Table(map) = result.unwrap() {
@@ -69,7 +82,10 @@ fn test_native_write_valid_stdout() {
 
 #[test]
 fn test_native_write_valid_stderr() {
-    let result = native_write(&[Value::Number(2.0), Value::String("error".to_string())]);
+    let result = native_write(
+        &mut NativeState::default(),
+        &[Value::Number(2.0), Value::String("error".to_string())],
+    );
     assert!(result.is_ok());
     // Should return ok result
     if let Value::Table(map) = result.unwrap() {
@@ -82,7 +98,10 @@ fn test_native_write_valid_stderr() {
 
 #[test]
 fn test_native_write_invalid_fd() {
-    let result = native_write(&[Value::Number(99.0), Value::String("test".to_string())]);
+    let result = native_write(
+        &mut NativeState::default(),
+        &[Value::Number(99.0), Value::String("test".to_string())],
+    );
     assert!(result.is_ok());
     // Should return error result
     if let Value::Table(map) = result.unwrap() {
@@ -99,15 +118,24 @@ fn test_native_write_invalid_fd() {
 #[test]
 fn test_native_write_converts_types() {
     // Test number conversion
-    let result = native_write(&[Value::Number(1.0), Value::Number(42.0)]);
+    let result = native_write(
+        &mut NativeState::default(),
+        &[Value::Number(1.0), Value::Number(42.0)],
+    );
     assert!(result.is_ok());
 
     // Test boolean conversion
-    let result = native_write(&[Value::Number(1.0), Value::Boolean(true)]);
+    let result = native_write(
+        &mut NativeState::default(),
+        &[Value::Number(1.0), Value::Boolean(true)],
+    );
     assert!(result.is_ok());
 
     // Test null conversion
-    let result = native_write(&[Value::Number(1.0), Value::Null]);
+    let result = native_write(
+        &mut NativeState::default(),
+        &[Value::Number(1.0), Value::Null],
+    );
     assert!(result.is_ok());
 }
 
@@ -143,13 +171,63 @@ fn test_native_read_file_nonexistent() {
         let map = map.borrow();
         assert!(map.contains_key("err"));
         if let Some(Value::String(err_msg)) = map.get("err") {
-            assert!(err_msg.contains("Failed to read file"));
+            assert_eq!(
+                err_msg,
+                "read_file: no such file '/nonexistent/file.txt' (NotFound)"
+            );
         }
     } else {
         panic!("Expected table result");
     }
 }
 
+fn err_message(result: Value) -> String {
+    let Value::Table(map) = result else {
+        panic!("Expected table result, got {result:?}");
+    };
+    match map.borrow().get("err") {
+        Some(Value::String(err_msg)) => err_msg.clone(),
+        other => panic!("Expected err message, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_native_read_file_directory_and_binary_errors() {
+    let dir = std::env::temp_dir();
+    let dir_str = dir.to_str().unwrap().to_string();
+    let err = err_message(native_read_file(&[Value::String(dir_str.clone())]).unwrap());
+    assert_eq!(
+        err,
+        format!("read_file: is a directory: '{dir_str}' (IsADirectory)")
+    );
+
+    let binary = dir.join(format!("luma_test_binary_{}.bin", std::process::id()));
+    std::fs::write(&binary, [0xff, 0xfe, 0x00]).unwrap();
+    let binary_str = binary.to_str().unwrap().to_string();
+    let err = err_message(native_read_file(&[Value::String(binary_str.clone())]).unwrap());
+    let _ = std::fs::remove_file(&binary);
+    assert_eq!(
+        err,
+        format!("read_file: not valid UTF-8 text in '{binary_str}' (InvalidData)")
+    );
+}
+
+#[test]
+fn test_native_write_file_invalid_path_errors() {
+    let path = "/nonexistent/dir/out.txt";
+    let err = err_message(
+        native_write_file(&[
+            Value::String(path.to_string()),
+            Value::String("x".to_string()),
+        ])
+        .unwrap(),
+    );
+    assert_eq!(
+        err,
+        format!("write_file: no such directory for '{path}' (NotFound)")
+    );
+}
+
 #[test]
 fn test_native_write_file_invalid_arg_count() {
     let result = native_write_file(&[Value::String("test.txt".to_string())]);
@@ -273,3 +351,120 @@ fn test_read_write_file_roundtrip() {
     // Cleanup
     let _ = std::fs::remove_file(temp_path);
 }
+
+#[test]
+fn test_capture_stdout_collects_print_and_write() {
+    let (result, output) = capture_stdout(|| {
+        crate::run_program(
+            "print(\"hello\")\nwrite(STDOUT, \"raw\")\n42".to_string(),
+            "test.luma".to_string(),
+        )
+    });
+    assert_eq!(result.unwrap(), Value::Number(42.0));
+    assert_eq!(output, "helloraw");
+}
+
+#[test]
+fn test_pretty_print_breaks_only_wide_values() {
+    let source = "pretty_print({ a = [1, 2] }, 2)\n\
+                  write(STDOUT, \"|\")\n\
+                  pretty_print({ rows = [0..40, [1]] }, 4)";
+    let (result, output) =
+        capture_stdout(|| crate::run_program(source.to_string(), "test.luma".to_string()));
+    assert!(result.is_ok(), "{result:?}");
+    let (small, large) = output.split_once('|').unwrap();
+    assert_eq!(small, "{a: [1, 2]}");
+    assert!(
+        large.starts_with("{\n    rows: [\n        [\n            0,\n            1,\n"),
+        "{large}"
+    );
+    assert!(large.ends_with("            39\n        ],\n        [1]\n    ]\n}"));
+}
+
+#[test]
+fn test_pretty_print_marks_cycles() {
+    let source = "let t = { name = \"loop\" }\nt.me = t\npretty_print(t, 2)\nprint(t)";
+    let (result, output) =
+        capture_stdout(|| crate::run_program(source.to_string(), "test.luma".to_string()));
+    assert!(result.is_ok(), "{result:?}");
+    assert_eq!(output, "{me: <cycle>, name: loop}{me: <cycle>, name: loop}");
+}
+
+#[test]
+fn test_pretty_print_rejects_bad_indent() {
+    let result = native_pretty_print(&[Value::Null, Value::Number(-1.0)]);
+    assert!(result.unwrap_err().contains("non-negative integer"));
+    let result = native_pretty_print(&[Value::Null]);
+    assert!(result.unwrap_err().contains("expects 2 arguments"));
+}
+
+#[test]
+fn test_pretty_mode_applies_to_print() {
+    let source = "print(0..40)";
+    let (result, output) = capture_stdout(|| {
+        crate::StdlibBuilder::new()
+            .with_pretty_print(true)
+            .run_program(source.to_string(), "test.luma".to_string())
+    });
+    assert!(result.is_ok(), "{result:?}");
+    assert!(output.starts_with("[\n  0,\n  1,\n"), "{output}");
+
+    let (_, output) =
+        capture_stdout(|| crate::run_program(source.to_string(), "test.luma".to_string()));
+    assert!(!output.contains('\n'), "{output}");
+}
+
+#[test]
+fn test_process_exit_errors_while_capturing() {
+    let (result, output) = capture_stdout(|| {
+        crate::run_program(
+            "print(\"before\")\nprocess.exit(3)".to_string(),
+            "test.luma".to_string(),
+        )
+    });
+    let err = result.unwrap_err();
+    assert!(
+        format!("{err:?}").contains("process.exit(3) called"),
+        "{err:?}"
+    );
+    assert_eq!(output, "before");
+}
+
+#[test]
+fn test_io_read_line_strips_newline_and_returns_null_at_eof() {
+    let source = "[io.read_line(), io.read_line(), io.read_line(), io.read_line()]";
+    let result = crate::run_program_with_stdin(
+        source.to_string(),
+        "test.luma".to_string(),
+        "first\nsecond\r\nlast".as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(result.to_string(), "[first, second, last, null]");
+
+    let result = crate::run_program_with_stdin(
+        "io.read_line()".to_string(),
+        "test.luma".to_string(),
+        std::io::empty(),
+    )
+    .unwrap();
+    assert_eq!(result, Value::Null);
+}
+
+#[test]
+fn test_io_read_line_reports_invalid_utf8() {
+    let mut input: &[u8] = &[0xff, b'\n'];
+    let err = native_io_read_line(&mut input).unwrap_err();
+    assert_eq!(err, "io.read_line: stdin is not valid UTF-8 text");
+}
+
+#[test]
+fn test_io_read_all_returns_remaining_input() {
+    let source = "let first = io.read_line()\n[first, io.read_all(), io.read_all()]";
+    let result = crate::run_program_with_stdin(
+        source.to_string(),
+        "test.luma".to_string(),
+        "a\nb\nc\n".as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(result.to_string(), "[a, b\nc\n, ]");
+}
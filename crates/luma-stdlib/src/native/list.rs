@@ -0,0 +1,165 @@
+//! List natives backing `List.sort`, `List.sort_by`, `List.with_capacity`,
+//! `List.reserve` and `List.push`.
+//!
+//! Sorting uses the ordering defined by [`compare_values`]: numbers compare
+//! by value (NaN after every other number), strings lexicographically by
+//! byte, booleans as `false < true`, and `null` equal to itself. Any other
+//! pairing — including values of different types — is an error rather than
+//! an arbitrary cross-type order.
+
+use super::helpers::value_type_name;
+use luma_core::vm::value::Value;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+/// Compare two values under the sorting order described in the module docs.
+pub fn compare_values(a: &Value, b: &Value) -> Result<Ordering, String> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => Ok(x.total_cmp(y)),
+        (Value::String(x), Value::String(y)) => Ok(x.cmp(y)),
+        (Value::Boolean(x), Value::Boolean(y)) => Ok(x.cmp(y)),
+        (Value::Null, Value::Null) => Ok(Ordering::Equal),
+        _ if value_type_name(a) == value_type_name(b) => Err(format!(
+            "cannot order values of type {}",
+            value_type_name(a)
+        )),
+        _ => Err(format!(
+            "cannot compare {} with {}",
+            value_type_name(a),
+            value_type_name(b)
+        )),
+    }
+}
+
+/// Stable sort that surfaces the first comparison error instead of panicking.
+fn sort_checked<T>(items: &mut [T], key: impl Fn(&T) -> &Value) -> Result<(), String> {
+    let mut error = None;
+    items.sort_by(|a, b| match compare_values(key(a), key(b)) {
+        Ok(ordering) => ordering,
+        Err(e) => {
+            error.get_or_insert(e);
+            Ordering::Equal
+        }
+    });
+    error.map_or(Ok(()), Err)
+}
+
+/// Native function: __list_sort(list: List) -> List
+/// Returns a new list with the elements in ascending order.
+pub fn native_list_sort(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!("sort() expects 1 argument, got {}", args.len()));
+    }
+
+    match &args[0] {
+        Value::List(list_rc) => {
+            let mut items = list_rc.borrow().clone();
+            sort_checked(&mut items, |v| v)?;
+            Ok(Value::List(Rc::new(RefCell::new(items))))
+        }
+        other => Err(format!(
+            "sort() requires a List, got {}",
+            value_type_name(other)
+        )),
+    }
+}
+
+/// Native function: __list_sort_by_keys(list: List, keys: List) -> List
+/// Returns a new list ordered by the parallel `keys` list. `List.sort_by`
+/// computes the keys by calling the user's key function on each element.
+pub fn native_list_sort_by_keys(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err(format!("sort_by() expects 2 arguments, got {}", args.len()));
+    }
+
+    match (&args[0], &args[1]) {
+        (Value::List(list_rc), Value::List(keys_rc)) => {
+            let items = list_rc.borrow();
+            let keys = keys_rc.borrow();
+            if items.len() != keys.len() {
+                return Err(format!(
+                    "sort_by() got {} keys for {} elements",
+                    keys.len(),
+                    items.len()
+                ));
+            }
+            let mut pairs: Vec<(Value, Value)> =
+                keys.iter().cloned().zip(items.iter().cloned()).collect();
+            sort_checked(&mut pairs, |(key, _)| key)?;
+            let sorted = pairs.into_iter().map(|(_, item)| item).collect();
+            Ok(Value::List(Rc::new(RefCell::new(sorted))))
+        }
+        _ => Err("sort_by() requires a List".to_string()),
+    }
+}
+
+/// Validate a capacity argument: a non-negative whole number.
+fn capacity_arg(name: &str, value: &Value) -> Result<usize, String> {
+    match value {
+        Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 && n.is_finite() => Ok(*n as usize),
+        Value::Number(n) => Err(format!(
+            "{name}() capacity must be a non-negative integer, got {n}"
+        )),
+        other => Err(format!(
+            "{name}() capacity must be a Number, got {}",
+            value_type_name(other)
+        )),
+    }
+}
+
+/// Native function: __list_with_capacity(n: Number) -> List
+/// Returns an empty list with room for `n` elements before it reallocates.
+pub fn native_list_with_capacity(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "with_capacity() expects 1 argument, got {}",
+            args.len()
+        ));
+    }
+    let capacity = capacity_arg("with_capacity", &args[0])?;
+    let mut items = Vec::new();
+    items
+        .try_reserve(capacity)
+        .map_err(|_| format!("with_capacity() cannot allocate room for {capacity} elements"))?;
+    Ok(Value::List(Rc::new(RefCell::new(items))))
+}
+
+/// Native function: __list_reserve(list: List, n: Number) -> List
+/// Reserves room for `n` more elements in place and returns the same list.
+pub fn native_list_reserve(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err(format!("reserve() expects 2 arguments, got {}", args.len()));
+    }
+    let additional = capacity_arg("reserve", &args[1])?;
+    match &args[0] {
+        Value::List(list_rc) => {
+            list_rc.borrow_mut().try_reserve(additional).map_err(|_| {
+                format!("reserve() cannot allocate room for {additional} more elements")
+            })?;
+            Ok(Value::List(list_rc.clone()))
+        }
+        other => Err(format!(
+            "reserve() requires a List, got {}",
+            value_type_name(other)
+        )),
+    }
+}
+
+/// Native function: __list_push(list: List, item: Any) -> List
+/// Appends `item` in place and returns the same list.
+pub fn native_list_push(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err(format!("push() expects 2 arguments, got {}", args.len()));
+    }
+    match &args[0] {
+        Value::List(list_rc) => {
+            list_rc.borrow_mut().push(args[1].clone());
+            Ok(Value::List(list_rc.clone()))
+        }
+        other => Err(format!(
+            "push() requires a List, got {}",
+            value_type_name(other)
+        )),
+    }
+}
@@ -0,0 +1,132 @@
+//! Tests for list natives
+
+use super::list::*;
+use luma_core::vm::value::Value;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+fn run(source: &str) -> Value {
+    crate::run_program(source.to_string(), "test.luma".to_string()).unwrap()
+}
+
+fn list(items: Vec<Value>) -> Value {
+    Value::List(Rc::new(RefCell::new(items)))
+}
+
+fn numbers(values: &[f64]) -> Value {
+    list(values.iter().map(|n| Value::Number(*n)).collect())
+}
+
+fn strings(values: &[&str]) -> Value {
+    list(
+        values
+            .iter()
+            .map(|s| Value::String(s.to_string()))
+            .collect(),
+    )
+}
+
+#[test]
+fn test_sort_numbers() {
+    let result = run("List.sort([3, 1, 2, -5])");
+    assert_eq!(result, numbers(&[-5.0, 1.0, 2.0, 3.0]));
+}
+
+#[test]
+fn test_sort_strings() {
+    let result = run(r#"List.sort(["pear", "apple", "fig"])"#);
+    assert_eq!(result, strings(&["apple", "fig", "pear"]));
+}
+
+#[test]
+fn test_sort_by_key_function() {
+    let source = r#"
+        let people = [{ name = "b", age = 30 }, { name = "a", age = 20 }, { name = "c", age = 30 }]
+        let sorted = List.sort_by(people, fn(p: Any): Number do return p.age end)
+        List.map(sorted, fn(p: Any): String do return p.name end)
+    "#;
+    assert_eq!(run(source), strings(&["a", "b", "c"]));
+}
+
+#[test]
+fn test_sort_mixed_types_is_an_error() {
+    let mixed = list(vec![Value::Number(1.0), Value::String("a".into())]);
+    let err = native_list_sort(&[mixed]).unwrap_err();
+    assert!(err.starts_with("cannot compare"), "{err}");
+
+    assert_eq!(
+        compare_values(&Value::Number(1.0), &Value::String("a".into())),
+        Err("cannot compare Number with String".to_string())
+    );
+    assert_eq!(
+        compare_values(&Value::Boolean(false), &Value::Boolean(true)),
+        Ok(Ordering::Less)
+    );
+}
+
+#[test]
+fn test_with_capacity_returns_empty_list() {
+    let Value::List(list_rc) = native_list_with_capacity(&[Value::Number(16.0)]).unwrap() else {
+        panic!("expected a list");
+    };
+    assert!(list_rc.borrow().is_empty());
+    assert!(list_rc.borrow().capacity() >= 16);
+
+    assert_eq!(run("List.with_capacity(8)"), list(vec![]));
+    let err = native_list_with_capacity(&[Value::Number(-1.0)]).unwrap_err();
+    assert!(err.contains("non-negative integer"), "{err}");
+    for bad in [0.5, f64::INFINITY, f64::NAN] {
+        let err = native_list_with_capacity(&[Value::Number(bad)]).unwrap_err();
+        assert!(err.contains("non-negative integer"), "{err}");
+    }
+}
+
+#[test]
+fn test_capacity_too_large_to_allocate_is_an_error() {
+    let err = native_list_with_capacity(&[Value::Number(1e18)]).unwrap_err();
+    assert!(err.contains("cannot allocate"), "{err}");
+    let err = native_list_reserve(&[numbers(&[1.0]), Value::Number(1e18)]).unwrap_err();
+    assert!(err.contains("cannot allocate"), "{err}");
+}
+
+#[test]
+fn test_reserve_then_push_builds_list() {
+    let source = r#"
+        let xs = List.reserve([0], 999)
+        var i = 1
+        while i < 1000 do
+            xs[i] = i
+            i = i + 1
+        end
+        xs
+    "#;
+    let expected: Vec<f64> = (0..1000).map(|n| n as f64).collect();
+    assert_eq!(run(source), numbers(&expected));
+
+    let existing = numbers(&[1.0]);
+    let Value::List(list_rc) =
+        native_list_reserve(&[existing.clone(), Value::Number(32.0)]).unwrap()
+    else {
+        panic!("expected a list");
+    };
+    let Value::List(original) = &existing else {
+        unreachable!()
+    };
+    assert!(Rc::ptr_eq(&list_rc, original));
+    assert!(list_rc.borrow().capacity() >= 33);
+}
+
+#[test]
+fn test_push_appends_in_place() {
+    let source = r#"
+        var xs = []
+        push(xs, 1)
+        let same = List.push(xs, 2)
+        same[2] = 3
+        xs
+    "#;
+    assert_eq!(run(source), numbers(&[1.0, 2.0, 3.0]));
+    let err = native_list_push(&[Value::Number(1.0), Value::Null]).unwrap_err();
+    assert!(err.contains("requires a List"), "{err}");
+}
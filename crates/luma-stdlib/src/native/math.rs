@@ -0,0 +1,197 @@
+//! Numeric helper native functions.
+//!
+//! This module provides the `math` global object which contains:
+//! - `math.approx_eq(a, b, epsilon)` - Tolerant comparison for floating-point results
+//! - `math.is_nan(x)` - True when `x` is NaN, the one number not equal to itself
+//! - `math.to_radix(n, base)` - Format an integer in base 2 to 36
+//! - `math.clamp(x, lo, hi)` / `math.lerp(a, b, t)` / `math.sign(x)` - Range helpers
+//! - `math.sin(x)` / `math.cos(x)` / `math.tan(x)` / `math.atan2(y, x)` - Trigonometry in radians
+//! - `math.nan` / `math.inf` - The IEEE 754 not-a-number and positive infinity values
+
+use luma_core::vm::value::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Native function: math.approx_eq(a: Number, b: Number, epsilon: Number) -> Boolean
+/// True when `a` and `b` differ by at most `epsilon`. NaN is never approximately
+/// equal to anything, and equal infinities compare equal.
+pub fn native_math_approx_eq(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 3 {
+        return Err(format!(
+            "math.approx_eq() expects 3 arguments, got {}",
+            args.len()
+        ));
+    }
+    match (&args[0], &args[1], &args[2]) {
+        (Value::Number(a), Value::Number(b), Value::Number(epsilon)) => {
+            if epsilon.is_nan() || *epsilon < 0.0 {
+                return Err(format!(
+                    "math.approx_eq() epsilon must be a non-negative number, got {epsilon}"
+                ));
+            }
+            Ok(Value::Boolean(a == b || (a - b).abs() <= *epsilon))
+        }
+        _ => Err("math.approx_eq() arguments must be numbers".to_string()),
+    }
+}
+
+/// Native function: math.is_nan(x: Number) -> Boolean
+pub fn native_math_is_nan(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "math.is_nan() expects 1 argument, got {}",
+            args.len()
+        ));
+    }
+    match &args[0] {
+        Value::Number(n) => Ok(Value::Boolean(n.is_nan())),
+        _ => Err("math.is_nan() argument must be a number".to_string()),
+    }
+}
+
+/// Native function: math.to_radix(n: Number, base: Number) -> String
+/// Lowercase digits, with a leading `-` for negative numbers:
+/// `math.to_radix(255, 16)` is `"ff"`.
+pub fn native_math_to_radix(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err(format!(
+            "math.to_radix() expects 2 arguments, got {}",
+            args.len()
+        ));
+    }
+    let (n, base) = match (&args[0], &args[1]) {
+        (Value::Number(n), Value::Number(base)) => (*n, *base),
+        _ => return Err("math.to_radix() arguments must be numbers".to_string()),
+    };
+    if base.fract() != 0.0 || !(2.0..=36.0).contains(&base) {
+        return Err(format!(
+            "math.to_radix() base must be an integer from 2 to 36, got {base}"
+        ));
+    }
+    // Beyond 2^53 not every integer is representable, so digits would be made up
+    if n.fract() != 0.0 || n.abs() > 9_007_199_254_740_992.0 {
+        return Err(format!(
+            "math.to_radix() requires an integer of at most 2^53 in magnitude, got {n}"
+        ));
+    }
+
+    let base = base as u64;
+    let mut rest = n.abs() as u64;
+    let mut digits = Vec::new();
+    loop {
+        digits.push(char::from_digit((rest % base) as u32, base as u32).unwrap());
+        rest /= base;
+        if rest == 0 {
+            break;
+        }
+    }
+    if n < 0.0 {
+        digits.push('-');
+    }
+    Ok(Value::String(digits.iter().rev().collect()))
+}
+
+/// Check that `args` are `N` numbers and return them
+fn numbers<const N: usize>(name: &str, args: &[Value]) -> Result<[f64; N], String> {
+    if args.len() != N {
+        let plural = if N == 1 { "argument" } else { "arguments" };
+        return Err(format!(
+            "math.{name}() expects {N} {plural}, got {}",
+            args.len()
+        ));
+    }
+    let mut out = [0.0; N];
+    for (slot, arg) in out.iter_mut().zip(args) {
+        match arg {
+            Value::Number(n) => *slot = *n,
+            _ if N == 1 => return Err(format!("math.{name}() argument must be a number")),
+            _ => return Err(format!("math.{name}() arguments must be numbers")),
+        }
+    }
+    Ok(out)
+}
+
+/// Native function: math.clamp(x: Number, lo: Number, hi: Number) -> Number
+/// `x` limited to the range `lo..=hi`. It is an error for `lo` to exceed `hi`
+/// or for either bound to be NaN; a NaN `x` is returned unchanged.
+pub fn native_math_clamp(args: &[Value]) -> Result<Value, String> {
+    let [x, lo, hi] = numbers("clamp", args)?;
+    if lo.is_nan() || hi.is_nan() || lo > hi {
+        return Err(format!(
+            "math.clamp() requires lo <= hi, got lo = {lo} and hi = {hi}"
+        ));
+    }
+    Ok(Value::Number(x.clamp(lo, hi)))
+}
+
+/// Native function: math.lerp(a: Number, b: Number, t: Number) -> Number
+/// Linear interpolation: `a` at `t = 0`, `b` at `t = 1`. `t` is not limited
+/// to `0..=1`, so values outside it extrapolate.
+pub fn native_math_lerp(args: &[Value]) -> Result<Value, String> {
+    let [a, b, t] = numbers("lerp", args)?;
+    Ok(Value::Number(a + (b - a) * t))
+}
+
+/// Native function: math.sign(x: Number) -> Number
+/// -1 for negative numbers, 1 for positive ones and 0 for zero; NaN stays NaN.
+pub fn native_math_sign(args: &[Value]) -> Result<Value, String> {
+    let [x] = numbers("sign", args)?;
+    // `signum` alone gives ±1 for ±0
+    Ok(Value::Number(if x == 0.0 { 0.0 } else { x.signum() }))
+}
+
+/// Native function: math.sin(x: Number) -> Number
+pub fn native_math_sin(args: &[Value]) -> Result<Value, String> {
+    let [x] = numbers("sin", args)?;
+    Ok(Value::Number(x.sin()))
+}
+
+/// Native function: math.cos(x: Number) -> Number
+pub fn native_math_cos(args: &[Value]) -> Result<Value, String> {
+    let [x] = numbers("cos", args)?;
+    Ok(Value::Number(x.cos()))
+}
+
+/// Native function: math.tan(x: Number) -> Number
+pub fn native_math_tan(args: &[Value]) -> Result<Value, String> {
+    let [x] = numbers("tan", args)?;
+    Ok(Value::Number(x.tan()))
+}
+
+/// Native function: math.atan2(y: Number, x: Number) -> Number
+/// The angle of the point `(x, y)` from the positive x axis, in `-pi..=pi`.
+pub fn native_math_atan2(args: &[Value]) -> Result<Value, String> {
+    let [y, x] = numbers("atan2", args)?;
+    Ok(Value::Number(y.atan2(x)))
+}
+
+/// Create the `math` module table.
+pub fn create_math_module() -> Value {
+    let mut math_table: HashMap<String, Value> = HashMap::new();
+
+    for (name, arity) in [
+        ("approx_eq", 3),
+        ("is_nan", 1),
+        ("to_radix", 2),
+        ("clamp", 3),
+        ("lerp", 3),
+        ("sign", 1),
+        ("sin", 1),
+        ("cos", 1),
+        ("tan", 1),
+        ("atan2", 2),
+    ] {
+        math_table.insert(
+            name.to_string(),
+            Value::NativeFunction {
+                name: format!("math.{name}"),
+                arity,
+            },
+        );
+    }
+    math_table.insert("nan".to_string(), Value::Number(f64::NAN));
+    math_table.insert("inf".to_string(), Value::Number(f64::INFINITY));
+
+    Value::Table(Rc::new(RefCell::new(math_table.into())))
+}
@@ -0,0 +1,151 @@
+//! Tests for math native functions
+
+use super::math::*;
+use luma_core::vm::value::Value;
+
+fn approx_eq(a: f64, b: f64, epsilon: f64) -> Value {
+    native_math_approx_eq(&[Value::Number(a), Value::Number(b), Value::Number(epsilon)]).unwrap()
+}
+
+#[test]
+fn test_math_approx_eq_tolerates_rounding() {
+    assert_ne!(0.1 + 0.2, 0.3);
+    assert_eq!(approx_eq(0.1 + 0.2, 0.3, 1e-9), Value::Boolean(true));
+    assert_eq!(approx_eq(1.0, 1.1, 1e-9), Value::Boolean(false));
+    assert_eq!(approx_eq(42.0, 42.0, 0.0), Value::Boolean(true));
+}
+
+#[test]
+fn test_math_approx_eq_nan_and_infinity() {
+    assert_eq!(approx_eq(f64::NAN, f64::NAN, 1.0), Value::Boolean(false));
+    assert_eq!(
+        approx_eq(f64::INFINITY, f64::INFINITY, 0.0),
+        Value::Boolean(true)
+    );
+    assert_eq!(
+        approx_eq(f64::INFINITY, f64::NEG_INFINITY, 1.0),
+        Value::Boolean(false)
+    );
+}
+
+#[test]
+fn test_math_approx_eq_rejects_bad_epsilon() {
+    let err = native_math_approx_eq(&[Value::Number(1.0), Value::Number(1.0), Value::Number(-1.0)])
+        .unwrap_err();
+    assert!(err.contains("non-negative"), "{err}");
+    assert!(native_math_approx_eq(&[Value::Number(1.0), Value::Null, Value::Number(1.0)]).is_err());
+}
+
+#[test]
+fn test_number_equality_follows_ieee() {
+    let source = "let nan = math.nan\n\
+                  [nan == nan, nan != nan, math.is_nan(nan), 42.0 == 42, 0.1 + 0.2 == 0.3, \
+                  math.approx_eq(0.1 + 0.2, 0.3, 1e-9)]";
+    let result = crate::run_program(source.to_string(), "test.luma".to_string()).unwrap();
+    let Value::List(items) = result else {
+        panic!("Expected list, got {result:?}");
+    };
+    assert_eq!(
+        *items.borrow(),
+        vec![
+            Value::Boolean(false),
+            Value::Boolean(true),
+            Value::Boolean(true),
+            Value::Boolean(true),
+            Value::Boolean(false),
+            Value::Boolean(true),
+        ]
+    );
+}
+
+#[test]
+fn test_math_to_radix_formats_digits() {
+    let to_radix =
+        |n: f64, base: f64| native_math_to_radix(&[Value::Number(n), Value::Number(base)]);
+    assert_eq!(
+        to_radix(255.0, 16.0).unwrap(),
+        Value::String("ff".to_string())
+    );
+    assert_eq!(
+        to_radix(10.0, 2.0).unwrap(),
+        Value::String("1010".to_string())
+    );
+    assert_eq!(to_radix(0.0, 8.0).unwrap(), Value::String("0".to_string()));
+    assert_eq!(
+        to_radix(-35.0, 36.0).unwrap(),
+        Value::String("-z".to_string())
+    );
+}
+
+#[test]
+fn test_math_to_radix_rejects_bad_input() {
+    let to_radix =
+        |n: f64, base: f64| native_math_to_radix(&[Value::Number(n), Value::Number(base)]);
+    assert!(to_radix(10.0, 1.0).unwrap_err().contains("from 2 to 36"));
+    assert!(to_radix(10.0, 37.0).is_err());
+    assert!(to_radix(1.5, 2.0).unwrap_err().contains("integer"));
+    assert!(to_radix(f64::NAN, 10.0).is_err());
+}
+
+#[test]
+fn test_math_to_radix_round_trips_literal() {
+    let result = crate::run_program(
+        "math.to_radix(0xFF, 16) + \":\" + math.to_radix(0b1010, 10)".to_string(),
+        "test.luma".to_string(),
+    )
+    .unwrap();
+    assert_eq!(result, Value::String("ff:10".to_string()));
+}
+
+fn call(f: fn(&[Value]) -> Result<Value, String>, args: &[f64]) -> f64 {
+    let args: Vec<Value> = args.iter().map(|n| Value::Number(*n)).collect();
+    match f(&args).unwrap() {
+        Value::Number(n) => n,
+        other => panic!("Expected number, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_math_clamp_limits_to_range() {
+    assert_eq!(call(native_math_clamp, &[-5.0, 0.0, 10.0]), 0.0);
+    assert_eq!(call(native_math_clamp, &[4.0, 0.0, 10.0]), 4.0);
+    assert_eq!(call(native_math_clamp, &[15.0, 0.0, 10.0]), 10.0);
+}
+
+#[test]
+fn test_math_clamp_rejects_inverted_range() {
+    let err = native_math_clamp(&[Value::Number(1.0), Value::Number(10.0), Value::Number(0.0)])
+        .unwrap_err();
+    assert!(err.contains("lo <= hi"), "{err}");
+    let err =
+        native_math_clamp(&[Value::Number(1.0), Value::Null, Value::Number(0.0)]).unwrap_err();
+    assert!(err.contains("must be numbers"), "{err}");
+}
+
+#[test]
+fn test_math_lerp_midpoint() {
+    assert_eq!(call(native_math_lerp, &[10.0, 20.0, 0.5]), 15.0);
+    assert_eq!(call(native_math_lerp, &[10.0, 20.0, 2.0]), 30.0);
+}
+
+#[test]
+fn test_math_sign() {
+    assert_eq!(call(native_math_sign, &[-3.5]), -1.0);
+    assert_eq!(call(native_math_sign, &[0.0]), 0.0);
+    assert_eq!(call(native_math_sign, &[-0.0]).to_bits(), 0.0f64.to_bits());
+    assert_eq!(call(native_math_sign, &[7.0]), 1.0);
+    assert!(call(native_math_sign, &[f64::NAN]).is_nan());
+}
+
+#[test]
+fn test_math_trig_known_values() {
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, FRAC_PI_6};
+    assert!((call(native_math_sin, &[FRAC_PI_6]) - 0.5).abs() < 1e-12);
+    assert!((call(native_math_cos, &[0.0]) - 1.0).abs() < 1e-12);
+    assert!((call(native_math_tan, &[FRAC_PI_4]) - 1.0).abs() < 1e-12);
+    assert!((call(native_math_atan2, &[1.0, 0.0]) - FRAC_PI_2).abs() < 1e-12);
+    let err = native_math_sin(&[Value::String("x".to_string())]).unwrap_err();
+    assert!(err.contains("must be a number"), "{err}");
+    let err = native_math_atan2(&[Value::Number(1.0)]).unwrap_err();
+    assert!(err.contains("expects 2 arguments, got 1"), "{err}");
+}
@@ -3,17 +3,29 @@
 //! This module contains all built-in functions that are implemented in Rust
 //! rather than in Luma bytecode. Functions are organized into submodules:
 //!
-//! - `core`: Core runtime functions (cast, isInstanceOf, into, typeof, iter)
-//! - `io`: Input/output functions (print, read_file, write_file, etc.)
+//! - `core`: Core runtime functions (cast, isInstanceOf, into, typeof, is_callable, iter, range, keys, values)
+//! - `io`: Input/output functions (print, read_file, write_file, etc.) and stdin reading
+//! - `list`: List sorting, capacity and push natives, and the value ordering sorting uses
+//! - `math`: Numeric helpers such as tolerant float comparison
+//! - `path`: Joining and resolving file paths
 //! - `ffi`: Foreign Function Interface for calling native C code
 //! - `process`: Process-related functions (os detection, exit, etc.)
+//! - `random`: Deterministic, seedable random number generation
+//! - `stats`: Summary statistics over lists of numbers
+//! - `string`: String formatting
 //! - `helpers`: Shared utilities for native function implementations
 
 pub mod core;
 pub mod ffi;
 pub mod helpers;
 pub mod io;
+pub mod list;
+pub mod math;
+pub mod path;
 pub mod process;
+pub mod random;
+pub mod stats;
+pub mod string;
 
 #[cfg(test)]
 mod core_tests;
@@ -21,16 +33,54 @@ mod core_tests;
 mod helpers_tests;
 #[cfg(test)]
 mod io_tests;
+#[cfg(test)]
+mod list_tests;
+#[cfg(test)]
+mod math_tests;
+#[cfg(test)]
+mod path_tests;
+#[cfg(test)]
+mod random_tests;
+#[cfg(test)]
+mod stats_tests;
+#[cfg(test)]
+mod string_tests;
 
 // Re-export all native functions for convenience
-pub use core::{native_cast, native_into, native_is_instance_of, native_iter, native_typeof};
+#[cfg(debug_assertions)]
+pub use core::native_inspect;
+pub use core::{
+    native_cast, native_deep_freeze, native_freeze, native_into, native_is_callable,
+    native_is_instance_of, native_iter, native_iter_keys, native_keys, native_len, native_query,
+    native_range, native_range_next, native_typeof, native_values,
+};
 pub use ffi::{
     create_ffi_module, native_ffi_call, native_ffi_def, native_ffi_dispatch, native_ffi_free,
     native_ffi_free_cstr, native_ffi_is_null, native_ffi_new, native_ffi_new_cstr,
     native_ffi_nullptr,
 };
 pub use io::{
-    native_file_exists, native_panic, native_print, native_read_file, native_write,
-    native_write_file,
+    capture_stdout, create_io_module, native_file_exists, native_io_read_all, native_io_read_line,
+    native_panic, native_pretty_print, native_print, native_read_file, native_write,
+    native_write_file, set_pretty_print,
+};
+pub use list::{
+    compare_values, native_list_push, native_list_reserve, native_list_sort,
+    native_list_sort_by_keys, native_list_with_capacity,
+};
+pub use math::{
+    create_math_module, native_math_approx_eq, native_math_atan2, native_math_clamp,
+    native_math_cos, native_math_is_nan, native_math_lerp, native_math_sign, native_math_sin,
+    native_math_tan, native_math_to_radix,
+};
+pub use path::{create_path_module, native_path_dirname, native_path_join, native_path_resolve};
+pub use process::{create_process_module, native_at_exit, native_process_exit};
+pub use random::{
+    create_random_module, native_random_choice, native_random_float, native_random_int,
+    native_random_seed, native_random_shuffle, seed_random,
+};
+pub use stats::{
+    create_stats_module, native_stats_max, native_stats_mean, native_stats_median,
+    native_stats_min, native_stats_stddev, native_stats_sum,
 };
-pub use process::{create_process_module, native_process_exit};
+pub use string::native_format;
@@ -0,0 +1,101 @@
+//! Path manipulation native functions.
+//!
+//! This module provides the `path` global object which contains:
+//! - `path.join(...parts)` - Joins path segments with the platform separator
+//! - `path.dirname(p)` - The directory part of a path (`"."` when there is none)
+//! - `path.resolve(...parts)` - Joins segments onto the working directory and
+//!   normalizes `.` and `..` into an absolute path
+//!
+//! Together with the `__file` global they let scripts locate files next to
+//! themselves: `path.join(path.dirname(__file), "data.txt")`.
+
+use luma_core::vm::value::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+/// Collect string arguments, naming the function in errors.
+fn string_args<'a>(name: &str, args: &'a [Value]) -> Result<Vec<&'a str>, String> {
+    args.iter()
+        .map(|arg| match arg {
+            Value::String(s) => Ok(s.as_str()),
+            other => Err(format!(
+                "path.{name}() arguments must be strings, got {other}"
+            )),
+        })
+        .collect()
+}
+
+fn path_value(path: &Path) -> Value {
+    Value::String(path.to_string_lossy().into_owned())
+}
+
+/// Native function: path.join(...parts: String) -> String
+/// An absolute segment replaces everything before it.
+pub fn native_path_join(args: &[Value]) -> Result<Value, String> {
+    let parts = string_args("join", args)?;
+    if parts.is_empty() {
+        return Err("path.join() expects at least 1 argument, got 0".to_string());
+    }
+    let joined: PathBuf = parts.iter().collect();
+    Ok(path_value(&joined))
+}
+
+/// Native function: path.dirname(p: String) -> String
+pub fn native_path_dirname(args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "path.dirname() expects 1 argument, got {}",
+            args.len()
+        ));
+    }
+    let [p] = string_args("dirname", args)?[..] else {
+        unreachable!("checked argument count");
+    };
+    let path = Path::new(p);
+    Ok(match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => path_value(parent),
+        // A root is its own directory
+        None if path.has_root() => path_value(path),
+        _ => Value::String(".".to_string()),
+    })
+}
+
+/// Native function: path.resolve(...parts: String) -> String
+/// Resolution is lexical: the path doesn't have to exist.
+pub fn native_path_resolve(args: &[Value]) -> Result<Value, String> {
+    let parts = string_args("resolve", args)?;
+    let cwd = std::env::current_dir()
+        .map_err(|e| format!("path.resolve() could not read the working directory: {e}"))?;
+    let joined = parts.iter().fold(cwd, |acc, part| acc.join(part));
+
+    let mut resolved = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    Ok(path_value(&resolved))
+}
+
+/// Create the `path` module table.
+pub fn create_path_module() -> Value {
+    let mut path_table: HashMap<String, Value> = HashMap::new();
+
+    for (name, arity) in [("join", 0), ("dirname", 1), ("resolve", 0)] {
+        path_table.insert(
+            name.to_string(),
+            Value::NativeFunction {
+                name: format!("path.{name}"),
+                arity,
+            },
+        );
+    }
+
+    Value::Table(Rc::new(RefCell::new(path_table.into())))
+}
@@ -0,0 +1,72 @@
+//! Tests for path native functions
+
+use super::path::*;
+use luma_core::vm::value::Value;
+use std::path::{MAIN_SEPARATOR_STR, Path};
+
+fn s(value: &str) -> Value {
+    Value::String(value.to_string())
+}
+
+fn expect_string(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        other => panic!("Expected string, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_path_join_uses_platform_separator() {
+    let joined = expect_string(native_path_join(&[s("a"), s("b"), s("c.txt")]).unwrap());
+    assert_eq!(joined, ["a", "b", "c.txt"].join(MAIN_SEPARATOR_STR));
+    assert_eq!(
+        expect_string(native_path_join(&[s("a")]).unwrap()),
+        "a".to_string()
+    );
+    assert!(native_path_join(&[]).is_err());
+    assert!(native_path_join(&[s("a"), Value::Number(1.0)]).is_err());
+}
+
+#[test]
+fn test_path_dirname() {
+    let nested = ["dir", "sub", "file.luma"].join(MAIN_SEPARATOR_STR);
+    assert_eq!(
+        expect_string(native_path_dirname(&[s(&nested)]).unwrap()),
+        ["dir", "sub"].join(MAIN_SEPARATOR_STR)
+    );
+    assert_eq!(
+        expect_string(native_path_dirname(&[s("file.luma")]).unwrap()),
+        "."
+    );
+    assert_eq!(
+        expect_string(native_path_dirname(&[s(MAIN_SEPARATOR_STR)]).unwrap()),
+        MAIN_SEPARATOR_STR
+    );
+}
+
+#[test]
+fn test_path_resolve_is_absolute_and_normalized() {
+    let resolved = expect_string(native_path_resolve(&[s("a"), s("."), s("b"), s("..")]).unwrap());
+    let expected = std::env::current_dir().unwrap().join("a");
+    assert!(Path::new(&resolved).is_absolute());
+    assert_eq!(Path::new(&resolved), expected);
+}
+
+#[test]
+fn test_file_global_reflects_running_file() {
+    let file = std::env::temp_dir().join(format!("luma_file_global_{}.luma", std::process::id()));
+    std::fs::write(&file, "").unwrap();
+    let canonical = file.canonicalize().unwrap();
+    let result = crate::run_program(
+        "[__file, path.dirname(__file)]".to_string(),
+        file.to_string_lossy().into_owned(),
+    );
+    let _ = std::fs::remove_file(&file);
+
+    let Value::List(items) = result.unwrap() else {
+        panic!("Expected list");
+    };
+    let items = items.borrow();
+    assert_eq!(items[0], s(&canonical.to_string_lossy()));
+    assert_eq!(items[1], s(&canonical.parent().unwrap().to_string_lossy()));
+}
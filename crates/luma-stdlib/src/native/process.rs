@@ -3,14 +3,21 @@
 //! This module provides the `process` global object which contains:
 //! - `process.os` - The current operating system ('windows', 'linux', or 'macos')
 //! - `process.exit(code)` - Terminates the program with the given exit code
+//!
+//! It also provides the global `at_exit(fn)`, which registers a hook to run
+//! before the program ends. The VM stores and runs hooks itself (including
+//! before `process.exit`), since natives cannot call back into Luma code.
 
 use luma_core::vm::value::Value;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::io::Write;
 use std::rc::Rc;
 
 /// Native function: process.exit(code: Number) -> Never
-/// Terminates the program with the given exit code.
+/// Terminates the program with the given exit code, flushing buffered
+/// output first (`std::process::exit` would otherwise drop it). While stdout
+/// is captured it raises an error instead.
 pub fn native_process_exit(args: &[Value]) -> Result<Value, String> {
     if args.len() != 1 {
         return Err(format!(
@@ -24,9 +31,22 @@ pub fn native_process_exit(args: &[Value]) -> Result<Value, String> {
         _ => return Err("process.exit() argument must be a number".to_string()),
     };
 
+    // A host capturing output runs scripts in-process, so exiting would end the host
+    if super::io::is_capturing_stdout() {
+        return Err(format!("process.exit({code}) called"));
+    }
+
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
     std::process::exit(code);
 }
 
+/// Native function: at_exit(hook: fn()) -> Null
+/// Intercepted by the VM, which owns the hook list; reaching this is a bug.
+pub fn native_at_exit(_args: &[Value]) -> Result<Value, String> {
+    Err("at_exit() can only be called from Luma code".to_string())
+}
+
 /// Returns the current operating system as a string.
 /// Returns 'windows', 'linux', or 'macos'.
 fn get_os_name() -> &'static str {
@@ -64,5 +84,5 @@ pub fn create_process_module() -> Value {
         },
     );
 
-    Value::Table(Rc::new(RefCell::new(process_table)))
+    Value::Table(Rc::new(RefCell::new(process_table.into())))
 }
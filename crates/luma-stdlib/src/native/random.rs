@@ -0,0 +1,199 @@
+//! Random number native functions backed by a deterministic PRNG.
+//!
+//! This module provides the `random` global object which contains:
+//! - `random.seed(n)` - Reseeds the generator so subsequent draws are reproducible
+//! - `random.int(min, max)` - Returns an integer in the inclusive range [min, max]
+//! - `random.float()` - Returns a number in the half-open range [0, 1)
+//! - `random.choice(list)` - Returns a random element of a non-empty list
+//! - `random.shuffle(list)` - Shuffles a list in place and returns it
+//!
+//! The generator is a xorshift64* implementation so results never depend on the
+//! host RNG: the same seed yields the same sequence on every platform.
+
+use luma_core::vm::NativeState;
+use luma_core::vm::value::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Seed used when a script never calls `random.seed` and no `--seed` was given.
+fn default_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0x2545_F491_4F6C_DD1D)
+}
+
+/// Generator state, kept in each VM's [`NativeState`]
+struct Rng(u64);
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng(scramble_seed(default_seed()))
+    }
+}
+
+/// Map an arbitrary seed to a valid (non-zero) xorshift state.
+fn scramble_seed(seed: u64) -> u64 {
+    // SplitMix64 finalizer spreads small seeds (0, 1, 2, ...) across the state space
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    if z == 0 { 0x2545_F491_4F6C_DD1D } else { z }
+}
+
+/// Reseed the generator of the VM owning `state`. `random.seed(n)` goes
+/// through [`seed_from_number`], so `seed_random(state, 42)` and
+/// `random.seed(42)` produce the same sequence.
+pub fn seed_random(state: &mut NativeState, seed: u64) {
+    state.insert(Rng(scramble_seed(seed)));
+}
+
+/// Seed for a script-supplied number: integers seed as themselves, so they
+/// agree with `luma run --seed`; any other number seeds by its bit pattern.
+pub fn seed_from_number(n: f64) -> u64 {
+    if n.fract() == 0.0 && n.abs() < 2f64.powi(63) {
+        n as i64 as u64
+    } else {
+        n.to_bits()
+    }
+}
+
+/// Advance the generator and return the next 64-bit output.
+fn next_u64(state: &mut NativeState) -> u64 {
+    let Rng(x) = state.get_or_default::<Rng>();
+    *x ^= *x >> 12;
+    *x ^= *x << 25;
+    *x ^= *x >> 27;
+    x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+}
+
+/// Next number in [0, 1) using the top 53 bits of output.
+fn next_f64(state: &mut NativeState) -> f64 {
+    (next_u64(state) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Uniform index in [0, n); n must be non-zero.
+fn next_index(state: &mut NativeState, n: usize) -> usize {
+    (next_f64(state) * n as f64) as usize
+}
+
+/// Native function: random.seed(n: Number) -> Null
+pub fn native_random_seed(state: &mut NativeState, args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "random.seed() expects 1 argument, got {}",
+            args.len()
+        ));
+    }
+    match &args[0] {
+        Value::Number(n) => {
+            seed_random(state, seed_from_number(*n));
+            Ok(Value::Null)
+        }
+        _ => Err("random.seed() argument must be a number".to_string()),
+    }
+}
+
+/// Native function: random.int(min: Number, max: Number) -> Number
+/// Returns an integer in the inclusive range [min, max].
+pub fn native_random_int(state: &mut NativeState, args: &[Value]) -> Result<Value, String> {
+    if args.len() != 2 {
+        return Err(format!(
+            "random.int() expects 2 arguments, got {}",
+            args.len()
+        ));
+    }
+    let (min, max) = match (&args[0], &args[1]) {
+        (Value::Number(a), Value::Number(b)) => (a.ceil(), b.floor()),
+        _ => return Err("random.int() arguments must be numbers".to_string()),
+    };
+    if !min.is_finite() || !max.is_finite() || min > max {
+        return Err(format!(
+            "random.int() requires min <= max, got {min} and {max}"
+        ));
+    }
+    let span = max - min + 1.0;
+    let offset = (next_f64(state) * span).floor().min(span - 1.0);
+    Ok(Value::Number(min + offset))
+}
+
+/// Native function: random.float() -> Number
+/// Returns a number in the half-open range [0, 1).
+pub fn native_random_float(state: &mut NativeState, args: &[Value]) -> Result<Value, String> {
+    if !args.is_empty() {
+        return Err(format!(
+            "random.float() expects 0 arguments, got {}",
+            args.len()
+        ));
+    }
+    Ok(Value::Number(next_f64(state)))
+}
+
+/// Native function: random.choice(list: List) -> Any
+pub fn native_random_choice(state: &mut NativeState, args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "random.choice() expects 1 argument, got {}",
+            args.len()
+        ));
+    }
+    match &args[0] {
+        Value::List(list) => {
+            let borrowed = list.borrow();
+            if borrowed.is_empty() {
+                return Err("random.choice() called on an empty list".to_string());
+            }
+            Ok(borrowed[next_index(state, borrowed.len())].clone())
+        }
+        _ => Err("random.choice() argument must be a list".to_string()),
+    }
+}
+
+/// Native function: random.shuffle(list: List) -> List
+/// Shuffles the list in place (Fisher-Yates) and returns it.
+pub fn native_random_shuffle(state: &mut NativeState, args: &[Value]) -> Result<Value, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "random.shuffle() expects 1 argument, got {}",
+            args.len()
+        ));
+    }
+    match &args[0] {
+        Value::List(list) => {
+            {
+                let mut borrowed = list.borrow_mut();
+                for i in (1..borrowed.len()).rev() {
+                    let j = next_index(state, i + 1);
+                    borrowed.swap(i, j);
+                }
+            }
+            Ok(Value::List(list.clone()))
+        }
+        _ => Err("random.shuffle() argument must be a list".to_string()),
+    }
+}
+
+/// Create the `random` module table.
+pub fn create_random_module() -> Value {
+    let mut random_table: HashMap<String, Value> = HashMap::new();
+
+    for (name, arity) in [
+        ("seed", 1),
+        ("int", 2),
+        ("float", 0),
+        ("choice", 1),
+        ("shuffle", 1),
+    ] {
+        random_table.insert(
+            name.to_string(),
+            Value::NativeFunction {
+                name: format!("random.{name}"),
+                arity,
+            },
+        );
+    }
+
+    Value::Table(Rc::new(RefCell::new(random_table.into())))
+}
@@ -0,0 +1,156 @@
+//! Tests for random native functions
+
+use super::random::*;
+use luma_core::vm::NativeState;
+use luma_core::vm::value::Value;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn draw_floats(state: &mut NativeState, n: usize) -> Vec<Value> {
+    (0..n)
+        .map(|_| native_random_float(state, &[]).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_random_seed_reproducible_sequence() {
+    let mut state = NativeState::default();
+    native_random_seed(&mut state, &[Value::Number(42.0)]).unwrap();
+    let first = draw_floats(&mut state, 5);
+    native_random_seed(&mut state, &[Value::Number(42.0)]).unwrap();
+    let second = draw_floats(&mut state, 5);
+    assert_eq!(first, second);
+
+    native_random_seed(&mut state, &[Value::Number(7.0)]).unwrap();
+    let other = draw_floats(&mut state, 5);
+    assert_ne!(first, other);
+}
+
+#[test]
+fn test_random_seed_matches_host_seed() {
+    let mut state = NativeState::default();
+    // `luma run --seed 42` calls seed_random(&mut state, 42); a script calling
+    // random.seed(42) must see the same numbers
+    for seed in [0u64, 1, 42, 1 << 40] {
+        seed_random(&mut state, seed);
+        let from_host = draw_floats(&mut state, 5);
+        native_random_seed(&mut state, &[Value::Number(seed as f64)]).unwrap();
+        assert_eq!(draw_floats(&mut state, 5), from_host, "seed {seed}");
+    }
+
+    native_random_seed(&mut state, &[Value::Number(-3.0)]).unwrap();
+    let negative = draw_floats(&mut state, 3);
+    native_random_seed(&mut state, &[Value::Number(0.5)]).unwrap();
+    assert_ne!(draw_floats(&mut state, 3), negative);
+}
+
+#[test]
+fn test_random_float_in_unit_range() {
+    let mut state = NativeState::default();
+    seed_random(&mut state, 1);
+    for _ in 0..1000 {
+        match native_random_float(&mut state, &[]).unwrap() {
+            Value::Number(n) => assert!((0.0..1.0).contains(&n)),
+            other => panic!("Expected number, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_random_int_stays_in_range() {
+    let mut state = NativeState::default();
+    seed_random(&mut state, 3);
+    for _ in 0..1000 {
+        match native_random_int(&mut state, &[Value::Number(-2.0), Value::Number(3.0)]).unwrap() {
+            Value::Number(n) => {
+                assert!((-2.0..=3.0).contains(&n));
+                assert_eq!(n.fract(), 0.0);
+            }
+            other => panic!("Expected number, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_random_int_rejects_inverted_range() {
+    let mut state = NativeState::default();
+    let result = native_random_int(&mut state, &[Value::Number(5.0), Value::Number(1.0)]);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_random_choice_single_element() {
+    let mut state = NativeState::default();
+    let list = Value::List(Rc::new(RefCell::new(vec![Value::String("only".into())])));
+    let result = native_random_choice(&mut state, &[list]).unwrap();
+    assert_eq!(result, Value::String("only".into()));
+}
+
+#[test]
+fn test_random_choice_empty_list() {
+    let mut state = NativeState::default();
+    let list = Value::List(Rc::new(RefCell::new(vec![])));
+    let result = native_random_choice(&mut state, &[list]);
+    assert!(result.unwrap_err().contains("empty list"));
+}
+
+#[test]
+fn test_random_shuffle_is_permutation() {
+    let mut state = NativeState::default();
+    seed_random(&mut state, 9);
+    let items: Vec<Value> = (0..10).map(|i| Value::Number(i as f64)).collect();
+    let list = Rc::new(RefCell::new(items.clone()));
+    native_random_shuffle(&mut state, &[Value::List(list.clone())]).unwrap();
+
+    let mut shuffled: Vec<f64> = list
+        .borrow()
+        .iter()
+        .map(|v| match v {
+            Value::Number(n) => *n,
+            _ => panic!("Expected number"),
+        })
+        .collect();
+    shuffled.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(shuffled, (0..10).map(|i| i as f64).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_random_module_exposes_functions() {
+    match create_random_module() {
+        Value::Table(table) => {
+            let borrowed = table.borrow();
+            for name in ["seed", "int", "float", "choice", "shuffle"] {
+                assert!(borrowed.contains_key(name), "missing random.{name}");
+            }
+        }
+        _ => panic!("Expected table"),
+    }
+}
+
+#[test]
+fn test_random_seed_stays_in_its_vm() {
+    let run = |stdlib: crate::StdlibBuilder, source: &str| {
+        let (result, output) = crate::capture_stdout(|| {
+            stdlib.run_program(source.to_string(), "test.luma".to_string())
+        });
+        assert!(result.is_ok(), "{result:?}");
+        output
+    };
+    let seeded = run(
+        crate::StdlibBuilder::new().with_seed(7),
+        "print(random.float())",
+    );
+
+    // Reseeding in one VM leaves the generator of the next one alone
+    assert_eq!(
+        run(
+            crate::StdlibBuilder::new(),
+            "random.seed(7)\nprint(random.float())"
+        ),
+        seeded
+    );
+    assert_ne!(
+        run(crate::StdlibBuilder::new(), "print(random.float())"),
+        seeded
+    );
+}
@@ -0,0 +1,121 @@
+//! Summary statistics over lists of numbers.
+//!
+//! This module provides the `stats` global object which contains:
+//! - `stats.sum(list)` - Total of the elements; `0` for an empty list
+//! - `stats.mean(list)` - Arithmetic mean
+//! - `stats.median(list)` - Middle element, or the mean of the two middle ones
+//! - `stats.min(list)` / `stats.max(list)` - Smallest and largest element
+//! - `stats.stddev(list)` - Population standard deviation
+//!
+//! Every function requires a list holding only numbers and reports the index
+//! of the first element that is not one. Apart from `sum`, they have no
+//! meaningful answer for an empty list and return an error.
+
+use luma_core::vm::value::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Check the single list argument of `stats.<name>()` and copy out its numbers.
+fn numbers(name: &str, args: &[Value]) -> Result<Vec<f64>, String> {
+    if args.len() != 1 {
+        return Err(format!(
+            "stats.{name}() expects 1 argument, got {}",
+            args.len()
+        ));
+    }
+    let Value::List(list) = &args[0] else {
+        return Err(format!("stats.{name}() argument must be a list"));
+    };
+    list.borrow()
+        .iter()
+        .enumerate()
+        .map(|(i, item)| match item {
+            Value::Number(n) => Ok(*n),
+            other => Err(format!(
+                "stats.{name}() expects a list of numbers, but element {i} is {}",
+                super::helpers::value_type_name(other)
+            )),
+        })
+        .collect()
+}
+
+/// Like [`numbers`], but rejects an empty list.
+fn non_empty(name: &str, args: &[Value]) -> Result<Vec<f64>, String> {
+    let values = numbers(name, args)?;
+    if values.is_empty() {
+        return Err(format!("stats.{name}() called on an empty list"));
+    }
+    Ok(values)
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Native function: stats.sum(list: List<Number>) -> Number
+pub fn native_stats_sum(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Number(numbers("sum", args)?.iter().sum()))
+}
+
+/// Native function: stats.mean(list: List<Number>) -> Number
+pub fn native_stats_mean(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Number(mean(&non_empty("mean", args)?)))
+}
+
+/// Native function: stats.median(list: List<Number>) -> Number
+/// Does not reorder the list it is given.
+pub fn native_stats_median(args: &[Value]) -> Result<Value, String> {
+    let mut values = non_empty("median", args)?;
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    let median = if values.len() % 2 == 0 {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    };
+    Ok(Value::Number(median))
+}
+
+/// Native function: stats.min(list: List<Number>) -> Number
+pub fn native_stats_min(args: &[Value]) -> Result<Value, String> {
+    let values = non_empty("min", args)?;
+    Ok(Value::Number(
+        values.into_iter().fold(f64::INFINITY, f64::min),
+    ))
+}
+
+/// Native function: stats.max(list: List<Number>) -> Number
+pub fn native_stats_max(args: &[Value]) -> Result<Value, String> {
+    let values = non_empty("max", args)?;
+    Ok(Value::Number(
+        values.into_iter().fold(f64::NEG_INFINITY, f64::max),
+    ))
+}
+
+/// Native function: stats.stddev(list: List<Number>) -> Number
+/// Population standard deviation: the mean squared distance from the mean
+/// is divided by the element count, not the count minus one.
+pub fn native_stats_stddev(args: &[Value]) -> Result<Value, String> {
+    let values = non_empty("stddev", args)?;
+    let mean = mean(&values);
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+    Ok(Value::Number(variance.sqrt()))
+}
+
+/// Create the `stats` module table.
+pub fn create_stats_module() -> Value {
+    let mut stats_table: HashMap<String, Value> = HashMap::new();
+
+    for name in ["sum", "mean", "median", "min", "max", "stddev"] {
+        stats_table.insert(
+            name.to_string(),
+            Value::NativeFunction {
+                name: format!("stats.{name}"),
+                arity: 1,
+            },
+        );
+    }
+
+    Value::Table(Rc::new(RefCell::new(stats_table.into())))
+}
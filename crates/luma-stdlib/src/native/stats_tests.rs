@@ -0,0 +1,84 @@
+//! Tests for stats native functions
+
+use super::stats::*;
+use luma_core::vm::value::Value;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn list(items: Vec<Value>) -> Value {
+    Value::List(Rc::new(RefCell::new(items)))
+}
+
+fn numbers(items: &[f64]) -> Value {
+    list(items.iter().map(|n| Value::Number(*n)).collect())
+}
+
+const DATA: [f64; 8] = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+
+#[test]
+fn test_stats_on_known_dataset() {
+    let data = [numbers(&DATA)];
+    assert_eq!(native_stats_sum(&data), Ok(Value::Number(40.0)));
+    assert_eq!(native_stats_mean(&data), Ok(Value::Number(5.0)));
+    assert_eq!(native_stats_median(&data), Ok(Value::Number(4.5)));
+    assert_eq!(native_stats_min(&data), Ok(Value::Number(2.0)));
+    assert_eq!(native_stats_max(&data), Ok(Value::Number(9.0)));
+    assert_eq!(native_stats_stddev(&data), Ok(Value::Number(2.0)));
+}
+
+#[test]
+fn test_stats_median_odd_length_and_unsorted_input() {
+    let data = numbers(&[9.0, 1.0, 5.0]);
+    assert_eq!(
+        native_stats_median(std::slice::from_ref(&data)),
+        Ok(Value::Number(5.0))
+    );
+    // The caller's list is left in its original order
+    assert_eq!(data, numbers(&[9.0, 1.0, 5.0]));
+}
+
+#[test]
+fn test_stats_empty_list() {
+    let empty = [numbers(&[])];
+    assert_eq!(native_stats_sum(&empty), Ok(Value::Number(0.0)));
+    for (name, f) in [
+        (
+            "mean",
+            native_stats_mean as fn(&[Value]) -> Result<Value, String>,
+        ),
+        ("median", native_stats_median),
+        ("min", native_stats_min),
+        ("max", native_stats_max),
+        ("stddev", native_stats_stddev),
+    ] {
+        assert_eq!(
+            f(&empty),
+            Err(format!("stats.{name}() called on an empty list"))
+        );
+    }
+}
+
+#[test]
+fn test_stats_rejects_non_numbers() {
+    let mixed = [list(vec![
+        Value::Number(1.0),
+        Value::Number(2.0),
+        Value::String("3".to_string()),
+    ])];
+    assert_eq!(
+        native_stats_mean(&mixed),
+        Err("stats.mean() expects a list of numbers, but element 2 is String".to_string())
+    );
+    assert!(native_stats_sum(&mixed).is_err());
+    assert!(native_stats_max(&[Value::Number(1.0)]).is_err());
+}
+
+#[test]
+fn test_stats_module_from_script() {
+    let result = crate::run_program(
+        "let xs = [3, 1, 2]\n[stats.sum(xs), stats.median(xs), stats.max(xs)]".to_string(),
+        "test.luma".to_string(),
+    )
+    .unwrap();
+    assert_eq!(result, numbers(&[6.0, 2.0, 3.0]));
+}
@@ -0,0 +1,62 @@
+//! String native functions.
+//!
+//! - `format(template, ...args)` - Fills `{}` placeholders in order with the
+//!   printed form of each argument; `{{` and `}}` stand for literal braces
+
+use luma_core::vm::value::Value;
+
+/// Native function: format(template: String, ...args: Any) -> String
+/// Every `{}` takes the next argument, printed the way `print` shows it.
+/// The number of placeholders must match the number of arguments.
+pub fn native_format(args: &[Value]) -> Result<Value, String> {
+    let Some((template, values)) = args.split_first() else {
+        return Err("format() expects at least 1 argument, got 0".to_string());
+    };
+    let Value::String(template) = template else {
+        return Err(format!(
+            "format() template must be a string, got {template}"
+        ));
+    };
+
+    let mut out = String::with_capacity(template.len());
+    let mut next = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                out.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                if let Some(value) = values.get(next) {
+                    out.push_str(&value.to_string());
+                }
+                next += 1;
+            }
+            ('{', _) => {
+                return Err(
+                    "format() template has an unclosed '{'; use '{{' for a literal brace"
+                        .to_string(),
+                );
+            }
+            ('}', _) => {
+                return Err(
+                    "format() template has an unmatched '}'; use '}}' for a literal brace"
+                        .to_string(),
+                );
+            }
+            _ => out.push(c),
+        }
+    }
+
+    if next != values.len() {
+        return Err(format!(
+            "format() template has {next} placeholder{} but got {} argument{}",
+            if next == 1 { "" } else { "s" },
+            values.len(),
+            if values.len() == 1 { "" } else { "s" },
+        ));
+    }
+    Ok(Value::String(out))
+}
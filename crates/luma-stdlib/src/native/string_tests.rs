@@ -0,0 +1,76 @@
+//! Tests for string native functions
+
+use super::string::*;
+use luma_core::vm::value::Value;
+
+fn s(value: &str) -> Value {
+    Value::String(value.to_string())
+}
+
+fn format(args: &[Value]) -> String {
+    match native_format(args).unwrap() {
+        Value::String(s) => s,
+        other => panic!("Expected string, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_format_substitutes_in_order() {
+    assert_eq!(
+        format(&[
+            s("{} + {} = {}"),
+            Value::Number(1.0),
+            Value::Number(2.0),
+            Value::Number(3.0)
+        ]),
+        "1 + 2 = 3"
+    );
+    assert_eq!(
+        format(&[s("{}, {}!"), s("hello"), s("world")]),
+        "hello, world!"
+    );
+    assert_eq!(format(&[s("no placeholders")]), "no placeholders");
+}
+
+#[test]
+fn test_format_brace_escapes() {
+    assert_eq!(format(&[s("{{}}")]), "{}");
+    assert_eq!(format(&[s("{{{}}}"), Value::Number(7.0)]), "{7}");
+    assert!(native_format(&[s("{ oops")]).is_err());
+    assert!(native_format(&[s("oops }")]).is_err());
+}
+
+#[test]
+fn test_format_argument_count_mismatch() {
+    let err = native_format(&[s("{} and {}"), Value::Number(1.0)]).unwrap_err();
+    assert_eq!(
+        err,
+        "format() template has 2 placeholders but got 1 argument"
+    );
+    let err = native_format(&[s("{}"), Value::Number(1.0), Value::Number(2.0)]).unwrap_err();
+    assert_eq!(
+        err,
+        "format() template has 1 placeholder but got 2 arguments"
+    );
+    assert!(native_format(&[]).is_err());
+    assert!(native_format(&[Value::Number(1.0)]).is_err());
+}
+
+#[test]
+fn test_format_without_template_is_rejected_by_the_vm() {
+    let err = crate::run_program("format()".to_string(), "test.luma".to_string()).unwrap_err();
+    assert!(
+        format!("{err:?}").contains("function `format` expected at least 1 argument, got 0"),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn test_format_prints_non_string_values() {
+    let result = crate::run_program(
+        r#"format("{} {} {} {}", [1, 2.5], { a = true }, null, "s")"#.to_string(),
+        "test.luma".to_string(),
+    )
+    .unwrap();
+    assert_eq!(result, s("[1, 2.5] {a: true} null s"));
+}